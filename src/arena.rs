@@ -0,0 +1,135 @@
+use crate::*;
+use std::ops::{Deref, DerefMut};
+
+/// Scratch buffers for temporary lists that only live for a frame, see EntitiesAndComponents::frame_alloc
+/// A ScratchVec goes back to the arena when it is dropped and the next one of the same type reuses its memory,
+/// so collecting entities every frame does not allocate once the buffers have grown
+/// The arena is shared, cloning it gives another handle to the same buffers
+#[derive(Clone, Default)]
+pub struct FrameArena {
+    pools: Arc<Mutex<FxHashMap<TypeId, ScratchPool>>>,
+}
+
+// the free buffers of one type
+#[derive(Default)]
+struct ScratchPool {
+    free: Vec<Box<dyn Any + Send>>,
+    in_use: usize,
+    // the most buffers that were in use at once since the last reset
+    most_in_use: usize,
+}
+
+impl FrameArena {
+    /// Gets an empty Vec that gives its memory back to the arena when it is dropped
+    pub fn vec<T: Send + 'static>(&self) -> ScratchVec<T> {
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let pool = pools.entry(TypeId::of::<Vec<T>>()).or_default();
+        pool.in_use += 1;
+        pool.most_in_use = pool.most_in_use.max(pool.in_use);
+
+        let vec = pool
+            .free
+            .pop()
+            .and_then(|vec| vec.downcast::<Vec<T>>().ok())
+            .unwrap_or_default();
+        ScratchVec {
+            vec: Some(vec),
+            pools: self.pools.clone(),
+        }
+    }
+
+    /// Frees the buffers that were not needed since the last reset, World::run calls this at the end of every run
+    /// Buffers that are still in use are kept
+    pub fn reset(&self) {
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        pools.retain(|_, pool| {
+            let unused = pool.most_in_use.saturating_sub(pool.in_use);
+            pool.free.truncate(unused);
+            pool.most_in_use = pool.in_use;
+            pool.in_use > 0 || !pool.free.is_empty()
+        });
+    }
+
+    /// Gets the number of buffers waiting to be reused
+    pub fn free_buffers(&self) -> usize {
+        let pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        pools.values().map(|pool| pool.free.len()).sum()
+    }
+}
+
+impl std::fmt::Debug for FrameArena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameArena")
+            .field("free_buffers", &self.free_buffers())
+            .finish()
+    }
+}
+
+/// A Vec from a FrameArena, it is cleared and given back to the arena when dropped
+pub struct ScratchVec<T: Send + 'static> {
+    // only None while being dropped
+    // boxed so it can go back into the pool without allocating a new box
+    #[allow(clippy::box_collection)]
+    vec: Option<Box<Vec<T>>>,
+    pools: Arc<Mutex<FxHashMap<TypeId, ScratchPool>>>,
+}
+
+impl<T: Send + 'static> Deref for ScratchVec<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        self.vec
+            .as_ref()
+            .unwrap_or_else(|| panic!("ScratchVec was used after it was given back to its arena"))
+    }
+}
+
+impl<T: Send + 'static> DerefMut for ScratchVec<T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        self.vec
+            .as_mut()
+            .unwrap_or_else(|| panic!("ScratchVec was used after it was given back to its arena"))
+    }
+}
+
+impl<T: Send + std::fmt::Debug + 'static> std::fmt::Debug for ScratchVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl<T: Send + 'static> Drop for ScratchVec<T> {
+    fn drop(&mut self) {
+        let Some(mut vec) = self.vec.take() else {
+            return;
+        };
+        vec.clear();
+
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let pool = pools.entry(TypeId::of::<Vec<T>>()).or_default();
+        pool.in_use = pool.in_use.saturating_sub(1);
+        pool.free.push(vec);
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Gets the arena for temporary lists that only live for a frame, like the entities a system is about to change
+    /// The handle can be kept while changing the world, the buffers it hands out are reused every frame
+    pub fn frame_alloc(&self) -> FrameArena {
+        self.frame_arena.clone()
+    }
+}
+
+impl EntitiesAndComponentsThreadSafe<'_> {
+    /// Gets the arena for temporary lists that only live for a frame, see EntitiesAndComponents::frame_alloc
+    pub fn frame_alloc(&self) -> FrameArena {
+        self.entities_and_components.frame_alloc()
+    }
+}
+
+impl World {
+    /// Gets the arena for temporary lists that only live for a frame, see EntitiesAndComponents::frame_alloc
+    pub fn frame_alloc(&self) -> FrameArena {
+        self.entities_and_components.frame_alloc()
+    }
+}
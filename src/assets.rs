@@ -0,0 +1,217 @@
+use crate::*;
+use std::marker::PhantomData;
+
+/// A reference to an asset stored in Assets<T>
+/// Handles are cheap to copy and can be stored in components
+/// A handle to a removed asset will never point at a different asset
+pub struct Handle<T> {
+    id: DefaultKey,
+    marker: PhantomData<fn() -> T>,
+}
+
+/// How far along an asset is
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum LoadState {
+    /// The asset is still being loaded
+    Loading,
+    /// The asset is loaded and can be used
+    Loaded,
+    /// The asset failed to load
+    Failed,
+}
+
+/// Something that happened to an asset, see Assets::events
+pub enum AssetEvent<T> {
+    /// The asset finished loading or was added
+    Loaded(Handle<T>),
+    /// The asset failed to load
+    Failed(Handle<T>),
+    /// The asset was removed
+    Removed(Handle<T>),
+}
+
+enum AssetSlot<T> {
+    Loading,
+    Loaded(T),
+    Failed,
+}
+
+/// A resource that stores every asset of one type, like textures or sounds
+/// Add it with init_resource::<Assets<T>>(), assets can be loaded in the background with EntitiesAndComponents::load_asset
+/// Events are kept for one frame so every system can see them
+pub struct Assets<T> {
+    assets: SlotMap<DefaultKey, AssetSlot<T>>,
+    events: Vec<AssetEvent<T>>,
+}
+
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Assets {
+            assets: SlotMap::new(),
+            events: vec![],
+        }
+    }
+}
+
+impl<T: 'static> Resource for Assets<T> {
+    fn update(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl<T: Send + Sync + 'static> Assets<T> {
+    /// Adds an asset that is already loaded
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        let handle = Handle::new(self.assets.insert(AssetSlot::Loaded(asset)));
+        self.events.push(AssetEvent::Loaded(handle));
+        handle
+    }
+
+    /// Makes a handle for an asset that will be loaded later with finish_loading or fail_loading
+    pub fn reserve(&mut self) -> Handle<T> {
+        Handle::new(self.assets.insert(AssetSlot::Loading))
+    }
+
+    /// Gives a reserved handle its asset
+    /// If the handle was removed the asset is dropped
+    pub fn finish_loading(&mut self, handle: Handle<T>, asset: T) {
+        if let Some(slot) = self.assets.get_mut(handle.id) {
+            *slot = AssetSlot::Loaded(asset);
+            self.events.push(AssetEvent::Loaded(handle));
+        }
+    }
+
+    /// Marks a reserved handle as failed
+    pub fn fail_loading(&mut self, handle: Handle<T>) {
+        if let Some(slot) = self.assets.get_mut(handle.id) {
+            *slot = AssetSlot::Failed;
+            self.events.push(AssetEvent::Failed(handle));
+        }
+    }
+
+    /// Gets a reference to an asset
+    /// returns None if the asset is not loaded or was removed
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        match self.assets.get(handle.id) {
+            Some(AssetSlot::Loaded(asset)) => Some(asset),
+            _ => None,
+        }
+    }
+
+    /// Gets a mutable reference to an asset
+    /// returns None if the asset is not loaded or was removed
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        match self.assets.get_mut(handle.id) {
+            Some(AssetSlot::Loaded(asset)) => Some(asset),
+            _ => None,
+        }
+    }
+
+    /// Gets how far along an asset is
+    /// returns None if the asset was removed
+    pub fn get_load_state(&self, handle: Handle<T>) -> Option<LoadState> {
+        self.assets.get(handle.id).map(|slot| match slot {
+            AssetSlot::Loading => LoadState::Loading,
+            AssetSlot::Loaded(_) => LoadState::Loaded,
+            AssetSlot::Failed => LoadState::Failed,
+        })
+    }
+
+    /// Removes an asset and returns it if it was loaded
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self.assets.remove(handle.id)?;
+        self.events.push(AssetEvent::Removed(handle));
+
+        match slot {
+            AssetSlot::Loaded(asset) => Some(asset),
+            _ => None,
+        }
+    }
+
+    /// Gets everything that happened to the assets since the start of this frame
+    pub fn events(&self) -> &[AssetEvent<T>] {
+        &self.events
+    }
+
+    /// Gets the number of assets, including the ones that are still loading
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    /// Checks if there are no assets
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+}
+
+impl<T> Handle<T> {
+    fn new(id: DefaultKey) -> Self {
+        Handle {
+            id,
+            marker: PhantomData,
+        }
+    }
+}
+
+// implemented by hand so that T doesn't need to implement these traits
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Handle")
+            .field("asset", &std::any::type_name::<T>())
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl<T> Clone for AssetEvent<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetEvent<T> {}
+
+impl<T> PartialEq for AssetEvent<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (AssetEvent::Loaded(a), AssetEvent::Loaded(b)) => a == b,
+            (AssetEvent::Failed(a), AssetEvent::Failed(b)) => a == b,
+            (AssetEvent::Removed(a), AssetEvent::Removed(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<T> Eq for AssetEvent<T> {}
+
+impl<T> std::fmt::Debug for AssetEvent<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetEvent::Loaded(handle) => f.debug_tuple("Loaded").field(handle).finish(),
+            AssetEvent::Failed(handle) => f.debug_tuple("Failed").field(handle).finish(),
+            AssetEvent::Removed(handle) => f.debug_tuple("Removed").field(handle).finish(),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Scenarios for measuring the performance of the crate, turned on with the bench feature
+//! Each scenario is split into a setup that is not measured and a routine that is,
+//! which lines up with criterion's iter_batched_ref:
+//! ```ignore
+//! for scenario in ABC_ECS::bench::scenarios() {
+//!     criterion.bench_function(scenario.name, |bencher| {
+//!         bencher.iter_batched_ref(
+//!             || scenario.setup(10_000),
+//!             |world| scenario.run(world, 10_000),
+//!             criterion::BatchSize::LargeInput,
+//!         )
+//!     });
+//! }
+//! ```
+//! Scenario::measure can be used instead when criterion is not available
+
+use crate::*;
+use std::time::{Duration, Instant};
+
+/// A position component used by the scenarios
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Position {
+    /// The x position
+    pub x: f32,
+    /// The y position
+    pub y: f32,
+}
+
+/// A velocity component used by the scenarios
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Velocity {
+    /// The x velocity
+    pub x: f32,
+    /// The y velocity
+    pub y: f32,
+}
+
+/// A health component used by the scenarios
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Health {
+    /// The amount of health left
+    pub health: f32,
+}
+
+/// One thing to measure, like spawning entities or running a system
+pub struct Scenario {
+    /// The name of the scenario, used as the benchmark id
+    pub name: &'static str,
+    setup: fn(usize) -> World,
+    routine: fn(&mut World, usize),
+}
+
+impl Scenario {
+    /// Makes the world the routine runs on, this part is not measured
+    pub fn setup(&self, entity_count: usize) -> World {
+        (self.setup)(entity_count)
+    }
+
+    /// Runs the part of the scenario that is measured
+    /// entity_count should be the same as the one given to setup
+    pub fn run(&self, world: &mut World, entity_count: usize) {
+        (self.routine)(world, entity_count)
+    }
+
+    /// Sets up a new world and runs the routine on it iterations times
+    /// Returns the average time a routine took
+    pub fn measure(&self, entity_count: usize, iterations: u32) -> Duration {
+        let mut total = Duration::ZERO;
+        for _ in 0..iterations {
+            let mut world = self.setup(entity_count);
+
+            let start_time = Instant::now();
+            self.run(&mut world, entity_count);
+            total += start_time.elapsed();
+        }
+        total / iterations.max(1)
+    }
+}
+
+/// Every scenario, in the order they should be reported
+pub fn scenarios() -> Vec<Scenario> {
+    vec![
+        spawn(),
+        iterate(),
+        for_each_mut(),
+        add_remove_churn(),
+        parallel_step(),
+    ]
+}
+
+/// Adds entity_count entities to an empty world, half with a position and health and half with a velocity
+pub fn spawn() -> Scenario {
+    Scenario {
+        name: "spawn",
+        setup: |_| World::new(),
+        routine: |world, entity_count| {
+            add_entities(&mut world.entities_and_components, entity_count);
+        },
+    }
+}
+
+/// Moves every entity with a position and velocity from inside of a run function
+pub fn iterate() -> Scenario {
+    Scenario {
+        name: "iterate",
+        setup: |entity_count| {
+            let mut world = World::new();
+            add_moving_entities(&mut world.entities_and_components, entity_count);
+            world.add_system(MovementSystem);
+            world
+        },
+        routine: |world, _| world.run(),
+    }
+}
+
+/// Moves every entity with a position with for_each_mut
+pub fn for_each_mut() -> Scenario {
+    Scenario {
+        name: "for_each_mut",
+        setup: |entity_count| {
+            let mut world = World::new();
+            add_moving_entities(&mut world.entities_and_components, entity_count);
+            world
+        },
+        routine: |world, _| {
+            world
+                .entities_and_components
+                .for_each_mut::<Position>(|_, position| {
+                    position.x += 1.0;
+                    position.y += 1.0;
+                });
+        },
+    }
+}
+
+/// Adds and removes a component on every entity, then removes and adds back half of the entities
+pub fn add_remove_churn() -> Scenario {
+    Scenario {
+        name: "add_remove_churn",
+        setup: |entity_count| {
+            let mut world = World::new();
+            add_entities(&mut world.entities_and_components, entity_count);
+            world
+        },
+        routine: |world, _| {
+            let entities_and_components = &mut world.entities_and_components;
+
+            let entities = entities_and_components.get_entities();
+            for entity in &entities {
+                entities_and_components.add_component_to(*entity, Velocity { x: 1.0, y: 1.0 });
+            }
+            for entity in &entities {
+                entities_and_components.remove_component_from::<Velocity>(*entity);
+            }
+
+            let removed = entities.len() / 2;
+            for entity in &entities[..removed] {
+                entities_and_components.remove_entity(*entity);
+            }
+            add_entities(entities_and_components, removed);
+        },
+    }
+}
+
+/// Moves every entity with a position and velocity from single_entity_step, which runs in parallel
+pub fn parallel_step() -> Scenario {
+    Scenario {
+        name: "parallel_step",
+        setup: |entity_count| {
+            let mut world = World::new();
+            add_moving_entities(&mut world.entities_and_components, entity_count);
+            world.add_system(ParallelMovementSystem);
+            world
+        },
+        routine: |world, _| world.run(),
+    }
+}
+
+fn add_entities(entities_and_components: &mut EntitiesAndComponents, entity_count: usize) {
+    for i in 0..entity_count {
+        if i % 2 == 0 {
+            entities_and_components
+                .add_entity_with((Position::default(), Health { health: 100.0 }));
+        } else {
+            entities_and_components.add_entity_with((Velocity::default(),));
+        }
+    }
+}
+
+fn add_moving_entities(entities_and_components: &mut EntitiesAndComponents, entity_count: usize) {
+    for i in 0..entity_count {
+        entities_and_components.add_entity_with((
+            Position::default(),
+            Velocity {
+                x: i as f32,
+                y: 1.0,
+            },
+        ));
+    }
+}
+
+struct MovementSystem;
+
+impl System for MovementSystem {
+    fn run(&mut self, engine: &mut EntitiesAndComponents) {
+        for entity in engine.get_entities_matching::<(Position, Velocity)>() {
+            let (position, velocity) = engine.get_components_mut::<(Position, Velocity)>(entity);
+            position.x += velocity.x;
+            position.y += velocity.y;
+        }
+    }
+}
+
+struct ParallelMovementSystem;
+
+impl System for ParallelMovementSystem {
+    fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+        if let (Some(position), Some(velocity)) =
+            single_entity.try_get_components_mut::<(Position, Velocity)>()
+        {
+            position.x += velocity.x;
+            position.y += velocity.y;
+        }
+    }
+
+    fn implements_single_entity_step(&self) -> bool {
+        true
+    }
+}
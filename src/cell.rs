@@ -0,0 +1,159 @@
+use crate::*;
+use std::sync::{MutexGuard, TryLockError};
+
+/// A component that can be changed from any entity's single_entity_step, like a score shared by every enemy
+/// The value is behind a lock that is shared by every handle made with share,
+/// so getting it from another entity with SingleMutEntity::get_cell never aliases a mutable reference
+/// Cloning the cell copies the value into a new cell, so a world copied with World::clone_for_simulation does not share it
+/// Keep the guard from lock short lived, only one thread can hold it at a time
+/// The type has to be registered with EntitiesAndComponents::register_cell_type before get_cell can find it
+pub struct EcsCell<T> {
+    value: Arc<Mutex<T>>,
+}
+
+impl<T: Send + 'static> EcsCell<T> {
+    /// Makes a new cell holding the value
+    pub fn new(value: T) -> Self {
+        EcsCell {
+            value: Arc::new(Mutex::new(value)),
+        }
+    }
+}
+
+impl<T> EcsCell<T> {
+    /// Makes another handle to the same value, changes made through either handle are seen by both
+    pub fn share(&self) -> Self {
+        EcsCell {
+            value: self.value.clone(),
+        }
+    }
+
+    /// Locks the cell and returns a guard to the value, this blocks until no other thread holds the lock
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.value.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the cell and returns a guard to the value, returns None if another thread holds the lock
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        match self.value.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(error)) => Some(error.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Clone for EcsCell<T> {
+    fn clone(&self) -> Self {
+        EcsCell::new(self.lock().clone())
+    }
+}
+
+impl<T: Default + Send + 'static> Default for EcsCell<T> {
+    fn default() -> Self {
+        EcsCell::new(T::default())
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for EcsCell<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.try_lock() {
+            Some(value) => f.debug_tuple("EcsCell").field(&*value).finish(),
+            None => f.write_str("EcsCell(<locked>)"),
+        }
+    }
+}
+
+// copies a handle to every EcsCell<T> in a world, keyed by entity
+pub(crate) type GatherCells =
+    fn(&EntitiesAndComponents) -> SecondaryMap<DefaultKey, Box<dyn Any + Send + Sync>>;
+
+fn gather_cells<T: Send + 'static>(
+    entities_and_components: &EntitiesAndComponents,
+) -> SecondaryMap<DefaultKey, Box<dyn Any + Send + Sync>> {
+    let mut cells = SecondaryMap::new();
+    for entity in entities_and_components.get_entities_with_component::<EcsCell<T>>() {
+        if let Some(cell) = entities_and_components.try_get_component::<EcsCell<T>>(entity) {
+            cells.insert(
+                entity.entity_id,
+                Box::new(cell.share()) as Box<dyn Any + Send + Sync>,
+            );
+        }
+    }
+    cells
+}
+
+// a handle to every cell in a world, gathered before single_entity_step runs in parallel
+// so get_cell never reads the components of an entity that another thread may be changing
+pub(crate) struct CellHandles {
+    cells: FxHashMap<TypeId, SecondaryMap<DefaultKey, Box<dyn Any + Send + Sync>>>,
+}
+
+impl CellHandles {
+    // only the registered types that some entity has are gathered, so nothing is allocated when there are no cells
+    pub(crate) fn gather(entities_and_components: &EntitiesAndComponents) -> Self {
+        let cells = entities_and_components
+            .cell_types
+            .iter()
+            .filter(|(type_id, _)| {
+                entities_and_components
+                    .entities_with_components
+                    .get(type_id)
+                    .is_some_and(|entities| entities.len() > 0)
+            })
+            .map(|(type_id, gather_cells)| (*type_id, gather_cells(entities_and_components)))
+            .collect();
+        CellHandles { cells }
+    }
+
+    fn get<T: Send + 'static>(&self, entity: Entity) -> Option<EcsCell<T>> {
+        self.cells
+            .get(&TypeId::of::<EcsCell<T>>())?
+            .get(entity.entity_id)?
+            .downcast_ref::<EcsCell<T>>()
+            .map(EcsCell::share)
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Registers EcsCell<T> so SingleMutEntity::get_cell can find the cells of that type on other entities
+    pub fn register_cell_type<T: Send + 'static>(&mut self) {
+        self.cell_types
+            .insert(TypeId::of::<EcsCell<T>>(), gather_cells::<T>);
+    }
+}
+
+impl SingleMutEntity<'_> {
+    /// Gets the EcsCell<T> on any entity, not just the one this step is running on
+    /// Other threads may be running single_entity_step on that entity, so the value can only be reached through the lock
+    /// Returns None if the entity does not exist or does not have the cell
+    /// The cells are found before the entities are stepped, so a cell added or replaced during single_entity_step
+    /// can only be gotten from the next frame on
+    /// panics if T was not registered with register_cell_type
+    pub fn get_cell<T: Send + 'static>(&self, entity: Entity) -> Option<EcsCell<T>> {
+        if !self
+            .entities_and_components
+            .cell_types
+            .contains_key(&TypeId::of::<EcsCell<T>>())
+        {
+            panic!(
+                "Cell of type {type:?} can not be found, was it registered with register_cell_type?",
+                type = std::any::type_name::<T>()
+            );
+        }
+        if let Some(cells) = self.cells {
+            return cells.get(entity);
+        }
+        // nothing else is running, so the other entity's components can be read directly
+        self.entities_and_components
+            .try_get_component::<EcsCell<T>>(entity)
+            .map(EcsCell::share)
+    }
+}
+
+impl World {
+    /// Registers EcsCell<T> so get_cell can find it, see EntitiesAndComponents::register_cell_type
+    pub fn register_cell_type<T: Send + 'static>(&mut self) {
+        self.entities_and_components.register_cell_type::<T>();
+    }
+}
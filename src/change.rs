@@ -0,0 +1,177 @@
+use crate::*;
+
+// how many changes undo_last_change can go back
+const UNDO_CAPACITY: usize = 1024;
+
+// a component from before it was changed, None if the entity did not have it
+struct UndoEntry {
+    entity: Entity,
+    type_id: TypeId,
+    component: Option<Box<dyn Any + Send + Sync>>,
+}
+
+#[derive(Default)]
+struct ChangeState {
+    // advanced at the start of every run
+    tick: u64,
+    // the tick each tracked component was last changed on
+    ticks: FxHashMap<TypeId, SecondaryMap<DefaultKey, u64>>,
+    undo_log: VecDeque<UndoEntry>,
+}
+
+// the components whose changes are tracked, see EntitiesAndComponents::track_changes
+#[derive(Default)]
+pub(crate) struct ChangeTracking {
+    // the tracked types, with the function that copies them if their old values go to the undo log
+    tracked: FxHashMap<TypeId, Option<versioned::CopyComponent>>,
+    // locked because single_entity_step can change components on several threads at once
+    state: Mutex<ChangeState>,
+}
+
+impl EntitiesAndComponents {
+    /// Starts keeping the tick each T was last changed on, see get_change_tick
+    /// A component counts as changed when it is added, removed, changed with modify or borrowed mutably
+    pub fn track_changes<T: Component>(&mut self) {
+        self.change_tracking
+            .tracked
+            .entry(TypeId::of::<T>())
+            .or_insert(None);
+    }
+
+    /// Tracks the changes of T like track_changes, and also keeps the value T had before it was changed
+    /// so undo_last_change can put it back
+    /// Only the value from before the first change in each tick is kept, and only the last 1024 changes
+    pub fn track_changes_with_undo<T: Component + Clone + Send + Sync>(&mut self) {
+        self.change_tracking
+            .tracked
+            .insert(TypeId::of::<T>(), Some(versioned::copy_component::<T>));
+    }
+
+    /// Gets the current change tick, it goes up by one at the start of every run
+    pub fn change_tick(&self) -> u64 {
+        self.change_tracking
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .tick
+    }
+
+    /// Gets the tick the component T on an entity was last changed on
+    /// Returns None if T is not tracked, the entity does not have T or it has not changed since it was tracked
+    pub fn get_change_tick<T: Component>(&self, entity: Entity) -> Option<u64> {
+        if !self.has_component_type(entity, TypeId::of::<T>()) {
+            return None;
+        }
+        self.change_tracking
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .ticks
+            .get(&TypeId::of::<T>())
+            .and_then(|ticks| ticks.get(entity.entity_id))
+            .copied()
+    }
+
+    /// Puts back the value a component had before its last change that is in the undo log, see track_changes_with_undo
+    /// The component is added or removed again if the change added or removed it
+    /// Returns false if there is nothing left to undo
+    pub fn undo_last_change(&mut self) -> bool {
+        let state = self
+            .change_tracking
+            .state
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some(entry) = state.undo_log.pop_back() else {
+            return false;
+        };
+        // the entity may have been removed since, the change is gone with it
+        if !self.does_entity_exist(entry.entity) {
+            return true;
+        }
+
+        // undoing is not recorded as another change
+        let tracked = std::mem::take(&mut self.change_tracking.tracked);
+        match entry.component {
+            Some(component) => self.add_component_dyn(entry.entity, component),
+            None => self.remove_component_by_id(entry.entity, entry.type_id),
+        }
+        self.change_tracking.tracked = tracked;
+        true
+    }
+
+    // checks if a mutable access has to be marked with mark_changed
+    #[inline]
+    pub(crate) fn is_marking_changes(&self) -> bool {
+        self.versioned_changes.is_some() || !self.change_tracking.tracked.is_empty()
+    }
+
+    // called before a component could be changed, by every mutable access, add and remove
+    #[inline]
+    pub(crate) fn mark_changed(&mut self, entity: Entity, type_id: TypeId) {
+        if self.versioned_changes.is_some() {
+            self.copy_versioned(entity, type_id);
+        }
+        if !self.change_tracking.tracked.is_empty() {
+            self.record_change(entity, type_id);
+        }
+    }
+
+    // marks every component of an entity, for when all of them are borrowed at once
+    pub(crate) fn mark_all_changed(&mut self, entity: Entity) {
+        if self.versioned_changes.is_some() {
+            self.copy_all_versioned(entity);
+        }
+        let type_ids = self
+            .change_tracking
+            .tracked
+            .keys()
+            .copied()
+            .collect::<Vec<TypeId>>();
+        for type_id in type_ids {
+            self.record_change(entity, type_id);
+        }
+    }
+
+    fn record_change(&self, entity: Entity, type_id: TypeId) {
+        let Some(copy) = self.change_tracking.tracked.get(&type_id) else {
+            return;
+        };
+        let Some(components) = self.components.get(entity.entity_id) else {
+            return;
+        };
+
+        let mut state = self
+            .change_tracking
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let tick = state.tick;
+        let ticks = state.ticks.entry(type_id).or_default();
+        let first_change = ticks.get(entity.entity_id) != Some(&tick);
+        ticks.insert(entity.entity_id, tick);
+
+        if let (Some(copy), true) = (copy, first_change) {
+            let component = components
+                .as_raw()
+                .get(&type_id)
+                .map(|component| copy(&**component));
+            if state.undo_log.len() == UNDO_CAPACITY {
+                state.undo_log.pop_front();
+            }
+            state.undo_log.push_back(UndoEntry {
+                entity,
+                type_id,
+                component,
+            });
+        }
+    }
+
+    // moves to the next tick, called at the start of every run
+    pub(crate) fn advance_change_tick(&mut self) {
+        self.change_tracking
+            .state
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .tick += 1;
+    }
+}
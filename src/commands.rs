@@ -0,0 +1,41 @@
+use crate::*;
+
+type Command = Box<dyn FnOnce(&mut EntitiesAndComponents) + Send>;
+
+/// Changes to the entities that are recorded while systems run in parallel
+/// They are applied in order once every single_entity_step has finished
+#[derive(Default)]
+pub(crate) struct CommandQueue {
+    // each command is kept with the system that queued it so recorded ops can name it
+    commands: Vec<(Option<&'static str>, Command)>,
+    system: Option<&'static str>,
+}
+
+impl CommandQueue {
+    pub(crate) fn push<F: FnOnce(&mut EntitiesAndComponents) + Send + 'static>(
+        &mut self,
+        command: F,
+    ) {
+        self.commands.push((self.system, Box::new(command)));
+    }
+
+    // sets the system that the commands pushed after this come from
+    pub(crate) fn set_system(&mut self, system: Option<&'static str>) {
+        self.system = system;
+    }
+
+    // the system that is running, None outside of systems
+    pub(crate) fn get_system(&self) -> Option<&'static str> {
+        self.system
+    }
+
+    pub(crate) fn apply(self, entities_and_components: &mut EntitiesAndComponents) {
+        // commands queued outside of a system belong to whatever system is applying them
+        let applying_system = entities_and_components.get_recording_system();
+        for (system, command) in self.commands {
+            entities_and_components.set_recording_system(system.or(applying_system));
+            command(entities_and_components);
+        }
+        entities_and_components.set_recording_system(applying_system);
+    }
+}
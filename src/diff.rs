@@ -0,0 +1,184 @@
+use crate::*;
+
+// checks if two components of the same type are equal without knowing the type
+pub(crate) type CompareComponent = fn(&dyn Any, &dyn Any) -> bool;
+
+/// How a component is different in the second world, see diff
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum ChangeKind {
+    /// The entity has the component only in the second world
+    Added,
+    /// The entity has the component only in the first world
+    Removed,
+    /// The entity has the component in both worlds with different values
+    Changed,
+}
+
+/// A component that is different on an entity in the second world, see diff
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub struct ComponentChange {
+    /// The entity in the first world
+    pub entity: Entity,
+    /// The type of the component
+    pub type_id: TypeId,
+    /// The type name of the component
+    pub component: &'static str,
+    /// How the component is different
+    pub kind: ChangeKind,
+}
+
+/// The differences between two worlds, made by diff
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct WorldDiff {
+    /// The entities of the second world that are not in the first
+    pub spawned: Vec<Entity>,
+    /// The entities of the first world that are not in the second
+    pub despawned: Vec<Entity>,
+    /// The components that are different on the entities in both worlds, sorted by entity then component name
+    pub components: Vec<ComponentChange>,
+}
+
+impl WorldDiff {
+    /// Checks if the worlds have the same entities with the same components
+    pub fn is_empty(&self) -> bool {
+        self.spawned.is_empty() && self.despawned.is_empty() && self.components.is_empty()
+    }
+
+    /// Gets the changes to the component T
+    pub fn changes_to<T: Component>(&self) -> impl Iterator<Item = &ComponentChange> {
+        self.components
+            .iter()
+            .filter(|change| change.type_id == TypeId::of::<T>())
+    }
+
+    /// Checks if nothing but the component T is different, like when testing that a system only changed Position
+    pub fn only_changes<T: Component>(&self) -> bool {
+        self.spawned.is_empty()
+            && self.despawned.is_empty()
+            && self
+                .components
+                .iter()
+                .all(|change| change.type_id == TypeId::of::<T>())
+    }
+}
+
+/// Finds the differences between two worlds whose entities are the same, like two worlds built by the same steps,
+/// see diff_mapped for worlds whose entities were moved like a copy made with World::clone_for_simulation
+/// Components registered with register_diff_component are compared by value, other components
+/// are only compared by which entities have them, replay components are compared by the text they save
+pub fn diff(a: &EntitiesAndComponents, b: &EntitiesAndComponents) -> WorldDiff {
+    diff_entities(a, b, |entity| entity)
+}
+
+/// Finds the differences between two worlds like diff, with the entities of a mapped to the entities of b
+/// Use the map returned by World::clone_for_simulation to compare the copy with the original
+pub fn diff_mapped(
+    a: &EntitiesAndComponents,
+    b: &EntitiesAndComponents,
+    map: &EntityMap,
+) -> WorldDiff {
+    diff_entities(a, b, |entity| map.map(entity))
+}
+
+fn diff_entities(
+    a: &EntitiesAndComponents,
+    b: &EntitiesAndComponents,
+    map: impl Fn(Entity) -> Entity,
+) -> WorldDiff {
+    let mut world_diff = WorldDiff::default();
+    let mut matched = SecondaryMap::new();
+
+    for entity in a.iter_entities() {
+        let other = map(entity);
+        if !b.does_entity_exist(other) || b.is_soft_despawned(other) {
+            world_diff.despawned.push(entity);
+            continue;
+        }
+        matched.insert(other.entity_id, ());
+
+        let components = a.components[entity.entity_id].as_raw();
+        let other_components = b.components[other.entity_id].as_raw();
+        let mut changes = vec![];
+        for (type_id, component) in components.iter() {
+            let kind = match other_components.get(type_id) {
+                None => ChangeKind::Removed,
+                Some(other_component) => {
+                    if components_equal(a, b, *type_id, &**component, &**other_component) {
+                        continue;
+                    }
+                    ChangeKind::Changed
+                }
+            };
+            changes.push(ComponentChange {
+                entity,
+                type_id: *type_id,
+                component: a.get_type_name(*type_id),
+                kind,
+            });
+        }
+        for type_id in other_components.keys() {
+            if !components.contains_key(type_id) {
+                changes.push(ComponentChange {
+                    entity,
+                    type_id: *type_id,
+                    component: b.get_type_name(*type_id),
+                    kind: ChangeKind::Added,
+                });
+            }
+        }
+        // the components of an entity are stored by hash, sorting keeps the diff the same every time
+        changes.sort_by(|a, b| a.component.cmp(b.component));
+        world_diff.components.extend(changes);
+    }
+
+    world_diff.spawned = b
+        .iter_entities()
+        .filter(|entity| !matched.contains_key(entity.entity_id))
+        .collect();
+    world_diff
+}
+
+// compares with the first registered way found, components with none are treated as equal
+fn components_equal(
+    a: &EntitiesAndComponents,
+    b: &EntitiesAndComponents,
+    type_id: TypeId,
+    component: &dyn Any,
+    other_component: &dyn Any,
+) -> bool {
+    let compare = a
+        .diff_components
+        .get(&type_id)
+        .or_else(|| b.diff_components.get(&type_id));
+    if let Some(compare) = compare {
+        return compare(component, other_component);
+    }
+
+    let codec = a
+        .replay_codecs
+        .get(&type_id)
+        .or_else(|| b.replay_codecs.get(&type_id));
+    if let Some(codec) = codec {
+        return (codec.save)(component) == (codec.save)(other_component);
+    }
+
+    true
+}
+
+impl EntitiesAndComponents {
+    /// Registers a component to be compared by value in diff, components that are not registered
+    /// are only compared by which entities have them
+    pub fn register_diff_component<T: Component + PartialEq>(&mut self) {
+        self.diff_components
+            .insert(TypeId::of::<T>(), |component, other_component| {
+                component.downcast_ref::<T>() == other_component.downcast_ref::<T>()
+            });
+    }
+}
+
+impl World {
+    /// Registers a component to be compared by value in diff, see EntitiesAndComponents::register_diff_component
+    pub fn register_diff_component<T: Component + PartialEq>(&mut self) {
+        self.entities_and_components.register_diff_component::<T>();
+    }
+}
@@ -0,0 +1,115 @@
+use crate::*;
+use std::marker::PhantomData;
+
+/// A component that stops single_entity_step of the system S from running on the entity,
+/// like a cutscene actor that should ignore the AI for a while without losing its components
+/// prestep and run still see the entity, see also World::exclude_from_system
+pub struct SkipSystem<S>(PhantomData<fn() -> S>);
+
+impl<S> SkipSystem<S> {
+    /// Makes the marker, add it to an entity to skip S and remove it to stop skipping
+    pub fn new() -> Self {
+        SkipSystem(PhantomData)
+    }
+}
+
+impl<S> Default for SkipSystem<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// what makes a system skip an entity in single_entity_step
+#[derive(Clone, Copy)]
+pub(crate) struct SystemSkips<'a> {
+    // the entities excluded with World::exclude_from_system
+    excluded: Option<&'a SecondaryMap<DefaultKey, ()>>,
+    // SkipSystem<S> of the system, None if no entity has ever had it
+    marker: Option<TypeId>,
+}
+
+impl<'a> SystemSkips<'a> {
+    // gets what the system skips, looked up once per run instead of once per entity
+    pub(crate) fn new(
+        system: &dyn SystemWrapper,
+        excluded: Option<&'a SecondaryMap<DefaultKey, ()>>,
+        entities_and_components: &EntitiesAndComponents,
+    ) -> Self {
+        let marker = system.skip_marker();
+        SystemSkips {
+            excluded: excluded.filter(|excluded| !excluded.is_empty()),
+            marker: entities_and_components
+                .entities_with_components
+                .contains_key(&marker)
+                .then_some(marker),
+        }
+    }
+
+    pub(crate) fn skips(
+        &self,
+        entities_and_components: &EntitiesAndComponents,
+        entity: Entity,
+    ) -> bool {
+        self.excluded
+            .is_some_and(|excluded| excluded.contains_key(entity.entity_id))
+            || self
+                .marker
+                .is_some_and(|marker| entities_and_components.has_component_type(entity, marker))
+    }
+}
+
+impl World {
+    /// Stops single_entity_step of the system from running on the entity until include_in_system is called
+    /// prestep and run still see the entity, to skip every system of a type add SkipSystem<S> to the entity instead
+    pub fn exclude_from_system(&mut self, system: &SystemHandle, entity: Entity) {
+        if !self.systems.contains_key(system.system_id) {
+            return;
+        }
+
+        match self.system_exclusions.get_mut(system.system_id) {
+            Some(excluded) => {
+                excluded.insert(entity.entity_id, ());
+            }
+            None => {
+                let mut excluded = SecondaryMap::new();
+                excluded.insert(entity.entity_id, ());
+                self.system_exclusions.insert(system.system_id, excluded);
+            }
+        }
+    }
+
+    /// Lets single_entity_step of the system run on an entity that was excluded with exclude_from_system again
+    pub fn include_in_system(&mut self, system: &SystemHandle, entity: Entity) {
+        if let Some(excluded) = self.system_exclusions.get_mut(system.system_id) {
+            excluded.remove(entity.entity_id);
+        }
+    }
+
+    /// Checks if the entity was excluded from the system with exclude_from_system
+    pub fn is_excluded_from_system(&self, system: &SystemHandle, entity: Entity) -> bool {
+        self.system_exclusions
+            .get(system.system_id)
+            .is_some_and(|excluded| excluded.contains_key(entity.entity_id))
+    }
+
+    // points the exclusions at the entities that replaced the old ones
+    pub(crate) fn remap_system_exclusions(&mut self, entity_map: &EntityMap) {
+        for excluded in self.system_exclusions.values_mut() {
+            *excluded = remap_excluded(excluded, entity_map);
+        }
+    }
+}
+
+// the exclusions with every moved entity swapped for its new id
+pub(crate) fn remap_excluded(
+    excluded: &SecondaryMap<DefaultKey, ()>,
+    entity_map: &EntityMap,
+) -> SecondaryMap<DefaultKey, ()> {
+    let mut remapped = SecondaryMap::new();
+    for entity_id in excluded.keys() {
+        let entity = Entity { entity_id };
+        let entity = entity_map.get(entity).unwrap_or(entity);
+        remapped.insert(entity.entity_id, ());
+    }
+    remapped
+}
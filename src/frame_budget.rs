@@ -0,0 +1,117 @@
+use crate::*;
+use std::time::{Duration, Instant};
+
+// see World::set_frame_budget
+#[derive(Clone, Copy)]
+pub(crate) struct FrameBudget {
+    budget: Duration,
+    // the index of the last entity that was stepped, the next frame starts at the first entity after it
+    // an index is used instead of a position in the entities so removing entities does not make the next frame skip any
+    last_stepped: Option<usize>,
+}
+
+impl World {
+    /// Limits how long the single_entity_step of a system can take each frame, for expensive per entity work like AI
+    /// Once the budget is used up the rest of the entities are left for the next frame, which starts where this one stopped,
+    /// so every entity is stepped once before any entity is stepped again
+    /// At least one entity is stepped every frame, the budget is checked after each one
+    /// The system steps its entities on one thread after the other systems have finished single_entity_step,
+    /// and parents_before_children is ignored for it
+    pub fn set_frame_budget(&mut self, system: &SystemHandle, budget: Duration) {
+        let last_stepped = self
+            .frame_budgets
+            .get(system.system_id)
+            .and_then(|frame_budget| frame_budget.last_stepped);
+        self.frame_budgets.insert(
+            system.system_id,
+            FrameBudget {
+                budget,
+                last_stepped,
+            },
+        );
+    }
+
+    /// Lets a system step every entity every frame again, see set_frame_budget
+    pub fn remove_frame_budget(&mut self, system: &SystemHandle) {
+        self.frame_budgets.remove(system.system_id);
+    }
+
+    // checks if a system was given a budget with set_frame_budget
+    pub(crate) fn has_frame_budget(&self, system_id: DefaultKey) -> bool {
+        self.frame_budgets.contains_key(system_id)
+    }
+
+    // runs single_entity_step of the systems with a budget until their budgets are used up
+    pub(crate) fn run_budgeted_single_entity_steps(
+        &mut self,
+        system_ids: &[DefaultKey],
+        frame_failures: &mut Vec<SystemFailure>,
+    ) {
+        for system_id in system_ids {
+            self.entities_buffer.clear();
+            self.entities_buffer
+                .extend(self.entities_and_components.iter_entities());
+            let entity_count = self.entities_buffer.len();
+            let Some(frame_budget) = self.frame_budgets.get_mut(*system_id) else {
+                continue;
+            };
+            if entity_count == 0 {
+                continue;
+            }
+
+            let system = &self.systems[*system_id];
+            let systems = [(
+                system,
+                &self.system_locals[*system_id],
+                self.system_groups.get(*system_id).copied(),
+                self.system_markers.get(*system_id).copied(),
+                SystemSkips::new(
+                    &**system,
+                    self.system_exclusions.get(*system_id),
+                    &self.entities_and_components,
+                ),
+            )];
+
+            let start = Instant::now();
+            let mut commands = CommandQueue::default();
+            // the entities are in the order of their index, entities may have been added or removed since the last frame
+            let mut index = frame_budget.last_stepped.map_or(0, |last_stepped| {
+                self.entities_buffer
+                    .partition_point(|entity| entity.index() <= last_stepped)
+                    % entity_count
+            });
+            let mut stepped = 0;
+            while stepped < entity_count {
+                single_entity_steps(
+                    &systems,
+                    &mut self.entities_and_components,
+                    self.entities_buffer[index],
+                    &mut commands,
+                    frame_failures,
+                    StepSettings {
+                        panic_policy: self.panic_policy,
+                        validators: self.component_validators.get_active(),
+                        cells: None,
+                    },
+                );
+                frame_budget.last_stepped = Some(self.entities_buffer[index].index());
+                stepped += 1;
+                index = (index + 1) % entity_count;
+                if start.elapsed() >= frame_budget.budget {
+                    break;
+                }
+            }
+
+            if let Some(trace_capture) = &self.trace_capture {
+                trace_capture.record(
+                    system.type_name(),
+                    "single_entity_step",
+                    start,
+                    Some(stepped),
+                );
+            }
+            commands.apply(&mut self.entities_and_components);
+            self.entities_and_components.spawn_taken_entities();
+        }
+    }
+}
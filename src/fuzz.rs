@@ -0,0 +1,418 @@
+//! Random sequences of world operations that check the invariants of the world after every step,
+//! turned on with the fuzz feature
+//! ```ignore
+//! for seed in 0..1000 {
+//!     if let Err(failure) = ABC_ECS::fuzz::fuzz_world(seed, 200) {
+//!         panic!("{failure}");
+//!     }
+//! }
+//! ```
+//! A failure holds every operation up to the one that failed, run_ops runs them again to debug the failure
+
+use crate::*;
+
+/// One operation on the world, entities are picked by their position in the list of live entities
+/// so a sequence of operations can be run again on a new world
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum FuzzOp {
+    /// Adds an entity with a FuzzValue
+    Spawn,
+    /// Removes an entity and its children
+    Despawn(usize),
+    /// Adds a FuzzValue to an entity, replacing the one it has
+    AddValue(usize, u32),
+    /// Removes the FuzzValue of an entity
+    RemoveValue(usize),
+    /// Adds a FuzzMarker to an entity
+    AddMarker(usize),
+    /// Removes the FuzzMarker of an entity
+    RemoveMarker(usize),
+    /// Sets the parent of the first entity to the second
+    SetParent(usize, usize),
+    /// Removes the parent of an entity
+    RemoveParent(usize),
+    /// Hides an entity and its children with despawn_soft
+    DespawnSoft(usize),
+    /// Brings back an entity hidden by DespawnSoft, picked by its position in the hidden entities
+    Resurrect(usize),
+    /// Moves every entity to a new id with compact
+    Compact,
+    /// Takes an entity with a FuzzValue out of the entity pool
+    Acquire,
+    /// Returns an entity to the entity pool
+    Release(usize),
+    /// Runs a frame with the fuzz systems whose bit is set, see FUZZ_SYSTEMS
+    RunFrame(u8),
+}
+
+/// The number of systems RunFrame picks from
+/// they change values in single_entity_step, spawn and remove entities through commands and change parents in run
+pub const FUZZ_SYSTEMS: u8 = 4;
+
+impl FuzzOp {
+    /// Picks a random operation, the entity positions are not limited to the number of live entities
+    pub fn random(rng: &mut RngStream) -> Self {
+        let entity = rng.range(0..64) as usize;
+        match rng.range(0..15) {
+            0 | 1 => FuzzOp::Spawn,
+            2 => FuzzOp::Despawn(entity),
+            3 => FuzzOp::AddValue(entity, rng.range(0..16) as u32),
+            4 => FuzzOp::RemoveValue(entity),
+            5 => FuzzOp::AddMarker(entity),
+            6 => FuzzOp::RemoveMarker(entity),
+            7 => FuzzOp::SetParent(entity, rng.range(0..64) as usize),
+            8 => FuzzOp::RemoveParent(entity),
+            9 => FuzzOp::DespawnSoft(entity),
+            10 => FuzzOp::Resurrect(entity),
+            11 => FuzzOp::Compact,
+            12 => FuzzOp::Acquire,
+            13 => FuzzOp::Release(entity),
+            _ => FuzzOp::RunFrame(rng.range(0..1 << FUZZ_SYSTEMS) as u8),
+        }
+    }
+}
+
+/// A component the fuzz operations and systems add, change and remove
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Default)]
+pub struct FuzzValue(pub u32);
+
+/// A marker component the fuzz operations add and remove
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Default)]
+pub struct FuzzMarker;
+
+/// The operations that broke an invariant or panicked, see fuzz_world
+#[derive(Clone, PartialEq, Debug)]
+pub struct FuzzFailure {
+    /// The seed the operations were made from, None if they were given to run_ops
+    pub seed: Option<u64>,
+    /// Every operation that was run, the last one is the one that failed
+    pub ops: Vec<FuzzOp>,
+    /// What was wrong with the world after the last operation, or the panic message
+    pub problems: Vec<String>,
+}
+
+impl std::fmt::Display for FuzzFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(seed) = self.seed {
+            write!(f, "seed {seed}: ")?;
+        }
+        write!(
+            f,
+            "{:?} failed after {} operations: {}",
+            self.ops.last(),
+            self.ops.len(),
+            self.problems.join(", ")
+        )
+    }
+}
+
+/// Runs steps random operations made from seed on a new world, checking the world after each one
+pub fn fuzz_world(seed: u64, steps: usize) -> Result<(), FuzzFailure> {
+    let mut rng = RngStream::new(seed);
+    let ops = (0..steps)
+        .map(|_| FuzzOp::random(&mut rng))
+        .collect::<Vec<FuzzOp>>();
+    run_ops(&ops).map_err(|failure| FuzzFailure {
+        seed: Some(seed),
+        ..failure
+    })
+}
+
+/// Runs operations on a new world, checking the world after each one
+/// Returns the operations up to the first one that broke an invariant or panicked
+pub fn run_ops(ops: &[FuzzOp]) -> Result<(), FuzzFailure> {
+    let mut fuzz_world = FuzzWorld::new();
+    for (index, op) in ops.iter().enumerate() {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| fuzz_world.apply(*op)));
+        let problems = match result {
+            Ok(()) => fuzz_world.check(),
+            Err(error) => vec![format!("panicked: {}", panic_message(&*error))],
+        };
+        if !problems.is_empty() {
+            return Err(FuzzFailure {
+                seed: None,
+                ops: ops[..=index].to_vec(),
+                problems,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks the invariants of a world that should hold between any two operations
+/// Returns a description of each one that is broken, this is slow so it is meant for tests
+pub fn check_invariants(world: &World) -> Vec<String> {
+    let engine = &world.entities_and_components;
+    let mut problems = engine
+        .validate()
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<String>>();
+
+    if engine.entities.len() != engine.components.len() {
+        problems.push(format!(
+            "there are {} entities but {} component maps",
+            engine.entities.len(),
+            engine.components.len()
+        ));
+    }
+    let iterated = engine.iter_entities().count();
+    if engine.get_entity_count() != iterated {
+        problems.push(format!(
+            "get_entity_count is {} but {iterated} entities are iterated",
+            engine.get_entity_count()
+        ));
+    }
+    for entity in engine.iter_entities() {
+        if engine.entity_from_bits(entity.to_bits()) != Some(entity) {
+            problems.push(format!("{entity:?} is not found from its bits"));
+        }
+        if engine.upgrade_entity(entity.downgrade()) != Some(entity) {
+            problems.push(format!("a weak handle to {entity:?} does not upgrade"));
+        }
+        if let Some(parent) = engine.get_parent(entity) {
+            if engine.get_depth(entity) != engine.get_depth(parent) + 1 {
+                problems.push(format!(
+                    "{entity:?} is not one level below its parent {parent:?}"
+                ));
+            }
+        }
+    }
+    problems
+}
+
+// the number of runs an entity hidden by DespawnSoft is kept for, low so some are removed for good
+const SOFT_DESPAWN_FRAMES: usize = 4;
+
+// a world and the entities the operations have removed or compacted away, which should never exist again
+struct FuzzWorld {
+    world: World,
+    removed: Vec<Entity>,
+    // the entities hidden by DespawnSoft, they may have been removed for good since
+    soft_despawned: Vec<Entity>,
+    pool: EntityPool<(FuzzValue,)>,
+}
+
+impl FuzzWorld {
+    fn new() -> Self {
+        let mut world = World::new();
+        world.set_soft_despawn_frames(SOFT_DESPAWN_FRAMES);
+        let pool = world.create_pool(
+            2,
+            || (FuzzValue(0),),
+            |entity, engine| engine.add_component_to(entity, FuzzValue(0)),
+        );
+        FuzzWorld {
+            world,
+            removed: vec![],
+            soft_despawned: vec![],
+            pool,
+        }
+    }
+
+    // the entity at a position in the live entities, wrapping around, None if there are none
+    fn pick(&self, index: usize) -> Option<Entity> {
+        let count = self.world.entities_and_components.get_entity_count();
+        if count == 0 {
+            return None;
+        }
+        self.world
+            .entities_and_components
+            .get_nth_entity(index % count)
+    }
+
+    fn apply(&mut self, op: FuzzOp) {
+        match op {
+            FuzzOp::Spawn => {
+                self.world
+                    .entities_and_components
+                    .add_entity_with((FuzzValue(0),));
+            }
+            FuzzOp::Despawn(index) => {
+                if let Some(entity) = self.pick(index) {
+                    self.world.entities_and_components.remove_entity(entity);
+                    self.removed.push(entity);
+                }
+            }
+            FuzzOp::AddValue(index, value) => {
+                if let Some(entity) = self.pick(index) {
+                    self.world
+                        .entities_and_components
+                        .add_component_to(entity, FuzzValue(value));
+                }
+            }
+            FuzzOp::RemoveValue(index) => {
+                if let Some(entity) = self.pick(index) {
+                    self.world
+                        .entities_and_components
+                        .remove_component_from::<FuzzValue>(entity);
+                }
+            }
+            FuzzOp::AddMarker(index) => {
+                if let Some(entity) = self.pick(index) {
+                    self.world
+                        .entities_and_components
+                        .add_component_to(entity, FuzzMarker);
+                }
+            }
+            FuzzOp::RemoveMarker(index) => {
+                if let Some(entity) = self.pick(index) {
+                    self.world
+                        .entities_and_components
+                        .remove_component_from::<FuzzMarker>(entity);
+                }
+            }
+            FuzzOp::SetParent(child, parent) => {
+                if let (Some(child), Some(parent)) = (self.pick(child), self.pick(parent)) {
+                    self.world.entities_and_components.set_parent(child, parent);
+                }
+            }
+            FuzzOp::RemoveParent(index) => {
+                if let Some(entity) = self.pick(index) {
+                    self.world.entities_and_components.remove_parent(entity);
+                }
+            }
+            FuzzOp::DespawnSoft(index) => {
+                if let Some(entity) = self.pick(index) {
+                    self.world.entities_and_components.despawn_soft(entity);
+                    self.soft_despawned.push(entity);
+                }
+            }
+            FuzzOp::Resurrect(index) => {
+                if !self.soft_despawned.is_empty() {
+                    let entity = self
+                        .soft_despawned
+                        .remove(index % self.soft_despawned.len());
+                    self.world.entities_and_components.resurrect(entity);
+                }
+            }
+            FuzzOp::Compact => {
+                // every handle from before compacting is stale, including the hidden entities it removes
+                let entity_map = self.world.compact();
+                self.removed
+                    .extend(entity_map.iter().map(|(old_entity, _)| old_entity));
+                self.removed.append(&mut self.soft_despawned);
+            }
+            FuzzOp::Acquire => {
+                self.pool.acquire(&mut self.world.entities_and_components);
+            }
+            FuzzOp::Release(index) => {
+                if let Some(entity) = self.pick(index) {
+                    self.pool
+                        .release(&mut self.world.entities_and_components, entity);
+                    self.removed.push(entity);
+                }
+            }
+            FuzzOp::RunFrame(systems) => {
+                self.world.remove_all_systems();
+                if systems & 1 != 0 {
+                    self.world.add_system(FuzzIncrement);
+                }
+                if systems & 2 != 0 {
+                    self.world.add_system(FuzzSpawner);
+                }
+                if systems & 4 != 0 {
+                    self.world.add_system(FuzzDespawner);
+                }
+                if systems & 8 != 0 {
+                    self.world.add_system(FuzzReparent);
+                }
+                self.world.run();
+            }
+        }
+    }
+
+    fn check(&self) -> Vec<String> {
+        let engine = &self.world.entities_and_components;
+        let mut problems = check_invariants(&self.world);
+        for entity in &self.removed {
+            if engine.does_entity_exist(*entity) {
+                problems.push(format!("{entity:?} exists after it was removed"));
+            }
+            // a stale handle must not find the entity that reused its index
+            if engine.upgrade_entity(entity.downgrade()).is_some() {
+                problems.push(format!(
+                    "a weak handle to {entity:?} upgrades after it was removed"
+                ));
+            }
+            if engine.entity_from_bits(entity.to_bits()).is_some() {
+                problems.push(format!(
+                    "{entity:?} is found from its bits after it was removed"
+                ));
+            }
+        }
+        problems
+    }
+}
+
+// adds one to every FuzzValue
+struct FuzzIncrement;
+
+impl System for FuzzIncrement {
+    fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+        if let Some(value) = single_entity.try_get_component_mut::<FuzzValue>() {
+            value.0 = value.0.wrapping_add(1);
+        }
+    }
+
+    fn implements_single_entity_step(&self) -> bool {
+        true
+    }
+}
+
+// entities with a marker and a value divisible by 3 spawn a new entity
+struct FuzzSpawner;
+
+impl System for FuzzSpawner {
+    fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+        let spawns = single_entity.has_component::<FuzzMarker>()
+            && single_entity
+                .try_get_component::<FuzzValue>()
+                .is_some_and(|value| value.0 % 3 == 0);
+        if spawns {
+            single_entity.queue_spawn((FuzzValue(1),));
+        }
+    }
+
+    fn implements_single_entity_step(&self) -> bool {
+        true
+    }
+}
+
+// entities with a value of 4 more than a multiple of 5 remove themselves
+struct FuzzDespawner;
+
+impl System for FuzzDespawner {
+    fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+        let despawns = single_entity
+            .try_get_component::<FuzzValue>()
+            .is_some_and(|value| value.0 % 5 == 4);
+        if despawns {
+            single_entity.remove_entity();
+        }
+    }
+
+    fn implements_single_entity_step(&self) -> bool {
+        true
+    }
+}
+
+// moves every entity with a marker under the entity with the largest value
+struct FuzzReparent;
+
+impl System for FuzzReparent {
+    fn run(&mut self, engine: &mut EntitiesAndComponents) {
+        let Some(parent) = engine
+            .get_entities_with_component::<FuzzValue>()
+            .max_by_key(|entity| engine.get_components::<(FuzzValue,)>(*entity).0 .0)
+        else {
+            return;
+        };
+        let marked = engine
+            .get_entities_with_component::<FuzzMarker>()
+            .collect::<Vec<Entity>>();
+        for entity in marked {
+            engine.set_parent(entity, parent);
+        }
+    }
+}
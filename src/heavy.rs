@@ -0,0 +1,217 @@
+use crate::*;
+use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+// the slots of an arena, slot i is in chunk log2(i + 1) and each chunk is twice as big as the one before it
+// chunks are never moved or freed while the arena is alive, so a value stays at the same address
+// they are kept as raw pointers so writing one slot never borrows the values in the other slots
+struct HeavySlots<T> {
+    chunks: Vec<*mut [MaybeUninit<T>]>,
+    // the slots whose value was dropped, used again before new slots are made
+    free: Vec<usize>,
+    // the number of slots that have been handed out at least once
+    used: usize,
+}
+
+impl<T> HeavySlots<T> {
+    fn location(slot: usize) -> (usize, usize) {
+        let chunk = (usize::BITS - 1 - (slot + 1).leading_zeros()) as usize;
+        (chunk, slot + 1 - (1 << chunk))
+    }
+
+    // finds a free slot and returns it with a pointer to where its value goes
+    fn take_slot(&mut self) -> (usize, *mut T) {
+        let slot = self.free.pop().unwrap_or_else(|| {
+            self.used += 1;
+            self.used - 1
+        });
+        let (chunk, offset) = Self::location(slot);
+        if chunk == self.chunks.len() {
+            let values = (0..1usize << chunk)
+                .map(|_| MaybeUninit::uninit())
+                .collect::<Box<[MaybeUninit<T>]>>();
+            self.chunks.push(Box::into_raw(values));
+        }
+        // SAFETY: offset is less than the length of the chunk, 1 << chunk
+        let value = unsafe { (self.chunks[chunk] as *mut MaybeUninit<T>).add(offset) };
+        (slot, value.cast())
+    }
+}
+
+impl<T> Drop for HeavySlots<T> {
+    fn drop(&mut self) {
+        // every value was dropped by its handle, the handles keep the slots alive until then
+        for chunk in self.chunks.drain(..) {
+            // SAFETY: the chunk was made with Box::into_raw and is only freed here
+            drop(unsafe { Box::from_raw(chunk) });
+        }
+    }
+}
+
+// SAFETY: the chunks are owned by the slots like a Vec<Box<[T]>> would be
+unsafe impl<T: Send> Send for HeavySlots<T> {}
+
+/// Where Heavy values are stored, one arena holds values of one type
+/// Values are kept in a few big blocks that are reused as values are dropped,
+/// so making and dropping big components does not go back to the allocator every time
+/// Clones of an arena share the same blocks, the blocks are freed once the arena and every value in it are dropped
+pub struct HeavyArena<T> {
+    slots: Arc<Mutex<HeavySlots<T>>>,
+}
+
+impl<T> HeavyArena<T> {
+    /// Makes an empty arena, nothing is allocated until the first value is added
+    pub fn new() -> Self {
+        HeavyArena {
+            slots: Arc::new(Mutex::new(HeavySlots {
+                chunks: vec![],
+                free: vec![],
+                used: 0,
+            })),
+        }
+    }
+
+    /// Moves a value into the arena and returns the handle that owns it
+    pub fn alloc(&self, value: T) -> Heavy<T> {
+        let (slot, pointer) = self
+            .slots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take_slot();
+        // SAFETY: the slot is free, so nothing else points at it
+        unsafe { pointer.write(value) };
+        Heavy {
+            arena: self.slots.clone(),
+            slot,
+            // SAFETY: pointers into a chunk are never null
+            value: unsafe { NonNull::new_unchecked(pointer) },
+        }
+    }
+
+    /// Gets the number of values in the arena
+    pub fn len(&self) -> usize {
+        let slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        slots.used - slots.free.len()
+    }
+
+    /// Checks if the arena has no values in it
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Default for HeavyArena<T> {
+    fn default() -> Self {
+        HeavyArena::new()
+    }
+}
+
+impl<T> Clone for HeavyArena<T> {
+    fn clone(&self) -> Self {
+        HeavyArena {
+            slots: self.slots.clone(),
+        }
+    }
+}
+
+impl<T: 'static> Resource for HeavyArena<T> {}
+
+/// A handle to a big component like a mesh or a pathfinding grid that is stored in a HeavyArena
+/// Only the handle is moved when the component is added to an entity or the entity is moved to another world,
+/// the value itself stays where the arena put it
+/// Heavy<T> derefs to T, so a query for Heavy<Mesh> can be used like a query for Mesh
+/// Dropping the handle drops the value and gives its slot back to the arena
+pub struct Heavy<T> {
+    arena: Arc<Mutex<HeavySlots<T>>>,
+    slot: usize,
+    value: NonNull<T>,
+}
+
+// SAFETY: a Heavy owns its value like a Box does, the arena it points into is only used behind its mutex
+unsafe impl<T: Send> Send for Heavy<T> {}
+// SAFETY: a shared Heavy only gives out shared references to its value
+unsafe impl<T: Sync> Sync for Heavy<T> {}
+
+impl<T> Heavy<T> {
+    /// Gets the slot the value is stored in, it is only reused after this value is dropped
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+
+    /// Gets the arena the value is stored in, values cloned from this one are stored in it too
+    pub fn arena(&self) -> HeavyArena<T> {
+        HeavyArena {
+            slots: self.arena.clone(),
+        }
+    }
+}
+
+impl<T> Deref for Heavy<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the value was written when the handle was made and is only dropped with the handle,
+        // the chunk holding it is kept alive by the arena the handle holds
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T> DerefMut for Heavy<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: as in deref, and the handle is the only thing pointing at its slot
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T> Drop for Heavy<T> {
+    fn drop(&mut self) {
+        // SAFETY: the value is alive until now and nothing can reach it after the handle is gone
+        unsafe { std::ptr::drop_in_place(self.value.as_ptr()) };
+        self.arena
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .free
+            .push(self.slot);
+    }
+}
+
+impl<T: Clone> Clone for Heavy<T> {
+    /// Clones the value into a new slot of the same arena
+    fn clone(&self) -> Self {
+        self.arena().alloc((**self).clone())
+    }
+}
+
+impl<T: PartialEq> PartialEq for Heavy<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Heavy<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Heavy").field(&**self).finish()
+    }
+}
+
+impl<T> AsRef<T> for Heavy<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsMut<T> for Heavy<T> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Moves a big value into the HeavyArena<T> resource, adding the resource if it does not exist yet
+    /// The returned handle is added to entities like any other component
+    pub fn alloc_heavy<T: 'static>(&mut self, value: T) -> Heavy<T> {
+        self.get_resource_or_insert_with(HeavyArena::<T>::new)
+            .alloc(value)
+    }
+}
@@ -0,0 +1,212 @@
+use crate::*;
+
+type EntityHook = Arc<dyn Fn(&mut SingleMutEntity) + Send + Sync>;
+
+// the functions that are called whenever an entity is spawned or despawned, see EntitiesAndComponents::on_spawn
+#[derive(Default, Clone)]
+pub(crate) struct EntityHooks {
+    spawn: Vec<EntityHook>,
+    despawn: Vec<EntityHook>,
+}
+
+impl EntitiesAndComponents {
+    /// Adds a function that is called for every new entity, after the entity has all of the components it was spawned with
+    /// This is called for entities added with add_entity, add_entity_with and queue_spawn, taken from an EntityPool
+    /// after it is reset, moved in from another world by World::merge, or loaded by World::load_scene,
+    /// but not for entities moved by compact or copied by World::clone_for_simulation
+    /// Components added with the SingleMutEntity are added right after the function returns,
+    /// which makes this a good place to add components every entity needs
+    pub fn on_spawn(&mut self, hook: impl Fn(&mut SingleMutEntity) + Send + Sync + 'static) {
+        self.entity_hooks.spawn.push(Arc::new(hook));
+    }
+
+    /// Adds a function that is called for every entity that is removed, before any of its components are removed
+    /// The children of a removed entity are removed after it, so the function is called for the parent first
+    /// This is also called for entities released to an EntityPool and for every entity removed by clear_entities
+    pub fn on_despawn(&mut self, hook: impl Fn(&mut SingleMutEntity) + Send + Sync + 'static) {
+        self.entity_hooks.despawn.push(Arc::new(hook));
+    }
+
+    // calls every on_spawn function on a new entity
+    pub(crate) fn run_spawn_hooks(&mut self, entity: Entity) {
+        if !self.entity_hooks.spawn.is_empty() {
+            self.run_entity_hooks(self.entity_hooks.spawn.clone(), entity);
+        }
+    }
+
+    // calls every on_despawn function on an entity that is about to be removed
+    pub(crate) fn run_despawn_hooks(&mut self, entity: Entity) {
+        if !self.entity_hooks.despawn.is_empty() {
+            self.run_entity_hooks(self.entity_hooks.despawn.clone(), entity);
+        }
+    }
+
+    // removes an entity and its children without calling the on_despawn functions,
+    // used for entities that were never spawned, so the on_spawn functions were never called for them either
+    pub(crate) fn remove_unspawned_entity(&mut self, entity: Entity) {
+        let despawn = std::mem::take(&mut self.entity_hooks.despawn);
+        self.remove_entity(entity);
+        self.entity_hooks.despawn = despawn;
+    }
+
+    fn run_entity_hooks(&mut self, hooks: Vec<EntityHook>, entity: Entity) {
+        let mut commands = CommandQueue::default();
+        for hook in hooks {
+            let mut single_entity = SingleMutEntity {
+                entity,
+                entities_and_components: &mut *self,
+                system_locals: None,
+                commands: &mut commands,
+                // hooks run one at a time so nothing else can be changing the parent
+                parent_done: true,
+                cells: None,
+            };
+            hook(&mut single_entity);
+        }
+        commands.apply(self);
+    }
+}
+
+impl World {
+    /// Adds a function that is called for every new entity, see EntitiesAndComponents::on_spawn
+    pub fn on_spawn(&mut self, hook: impl Fn(&mut SingleMutEntity) + Send + Sync + 'static) {
+        self.entities_and_components.on_spawn(hook);
+    }
+
+    /// Adds a function that is called for every entity that is removed, see EntitiesAndComponents::on_despawn
+    pub fn on_despawn(&mut self, hook: impl Fn(&mut SingleMutEntity) + Send + Sync + 'static) {
+        self.entities_and_components.on_despawn(hook);
+    }
+}
+
+type ResourceHook = Arc<dyn Fn(&mut EntitiesAndComponents) + Send + Sync>;
+
+// the functions that are called when a resource is added or removed, see EntitiesAndComponents::on_resource_added
+#[derive(Default, Clone)]
+pub(crate) struct ResourceHooks {
+    added: FxHashMap<TypeId, Vec<ResourceHook>>,
+    removed: FxHashMap<TypeId, Vec<ResourceHook>>,
+}
+
+impl EntitiesAndComponents {
+    /// Adds a function that is called right after the resource T is added when it did not exist before
+    /// Replacing a resource that already exists does not call it
+    /// Useful for setting up entities for an optional resource, like a NetworkSession that only exists in multiplayer
+    pub fn on_resource_added<T: Resource>(
+        &mut self,
+        hook: impl Fn(&mut EntitiesAndComponents) + Send + Sync + 'static,
+    ) {
+        self.resource_hooks
+            .added
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(Arc::new(hook));
+    }
+
+    /// Adds a function that is called right after the resource T is removed
+    /// This is also called for resources removed with clear_resources
+    pub fn on_resource_removed<T: Resource>(
+        &mut self,
+        hook: impl Fn(&mut EntitiesAndComponents) + Send + Sync + 'static,
+    ) {
+        self.resource_hooks
+            .removed
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(Arc::new(hook));
+    }
+
+    // calls every on_resource_added function for a resource
+    pub(crate) fn run_resource_added_hooks(&mut self, type_id: TypeId) {
+        if let Some(hooks) = self.resource_hooks.added.get(&type_id).cloned() {
+            for hook in hooks {
+                hook(self);
+            }
+        }
+    }
+
+    // calls every on_resource_removed function for a resource
+    pub(crate) fn run_resource_removed_hooks(&mut self, type_id: TypeId) {
+        if let Some(hooks) = self.resource_hooks.removed.get(&type_id).cloned() {
+            for hook in hooks {
+                hook(self);
+            }
+        }
+    }
+}
+
+impl World {
+    /// Adds a function that is called when a resource is added, see EntitiesAndComponents::on_resource_added
+    pub fn on_resource_added<T: Resource>(
+        &mut self,
+        hook: impl Fn(&mut EntitiesAndComponents) + Send + Sync + 'static,
+    ) {
+        self.entities_and_components.on_resource_added::<T>(hook);
+    }
+
+    /// Adds a function that is called when a resource is removed, see EntitiesAndComponents::on_resource_removed
+    pub fn on_resource_removed<T: Resource>(
+        &mut self,
+        hook: impl Fn(&mut EntitiesAndComponents) + Send + Sync + 'static,
+    ) {
+        self.entities_and_components.on_resource_removed::<T>(hook);
+    }
+}
+
+/// A point in World::run where the functions added with World::on_frame are called
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub enum FrameStage {
+    /// Before the resources are updated
+    Start,
+    /// After every prestep has finished and the resource commands have been applied
+    AfterPrestep,
+    /// After every single_entity_step has finished and their commands have been applied
+    AfterSingleEntityStep,
+    /// After every run function has finished
+    AfterRun,
+    /// At the end of the frame, after the extractor
+    End,
+}
+
+type FrameHook = Box<dyn FnMut(&mut EntitiesAndComponents) + Send + Sync>;
+
+// the functions that are called at each stage of World::run, see World::on_frame
+#[derive(Default)]
+pub(crate) struct FrameHooks {
+    hooks: Vec<(FrameStage, FrameHook)>,
+}
+
+impl FrameHooks {
+    // calls every function for the stage in the order they were added
+    pub(crate) fn run(
+        &mut self,
+        stage: FrameStage,
+        entities_and_components: &mut EntitiesAndComponents,
+    ) {
+        for (hook_stage, hook) in self.hooks.iter_mut() {
+            if *hook_stage == stage {
+                hook(entities_and_components);
+            }
+        }
+    }
+}
+
+impl World {
+    /// Adds a function that is called at a stage of every run, for things like pumping input at the start of the frame
+    /// or handing the world to a renderer at the end, without making a system for it
+    /// Functions for the same stage are called in the order they were added
+    /// While the world is paused only Start and End are called, see World::pause
+    pub fn on_frame(
+        &mut self,
+        stage: FrameStage,
+        hook: impl FnMut(&mut EntitiesAndComponents) + Send + Sync + 'static,
+    ) {
+        self.frame_hooks.hooks.push((stage, Box::new(hook)));
+    }
+
+    // calls the on_frame functions for a stage
+    pub(crate) fn run_frame_hooks(&mut self, stage: FrameStage) {
+        self.frame_hooks
+            .run(stage, &mut self.entities_and_components);
+    }
+}
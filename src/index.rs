@@ -0,0 +1,203 @@
+use crate::*;
+use std::hash::Hash;
+
+// a type erased ValueIndex, see EntitiesAndComponents::add_index
+pub(crate) trait ComponentIndex: Send + Sync {
+    // updates the key of an entity after its component was added or changed
+    fn update(&mut self, entity: Entity, component: &dyn Any);
+    // removes an entity after its component was removed
+    fn remove(&mut self, entity: Entity);
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    // an index with the same key that has no entities yet, used by World::clone_for_simulation
+    fn empty_copy(&self) -> Box<dyn ComponentIndex>;
+}
+
+// maps a key made from a component to the entities with that key
+pub(crate) struct ValueIndex<T, K> {
+    key_of: Arc<dyn Fn(&T) -> K + Send + Sync>,
+    entities: FxHashMap<K, Vec<Entity>>,
+    keys: SecondaryMap<DefaultKey, K>,
+}
+
+impl<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static> ValueIndex<T, K> {
+    pub(crate) fn new<F: Fn(&T) -> K + Send + Sync + 'static>(key_of: F) -> Self {
+        ValueIndex {
+            key_of: Arc::new(key_of),
+            entities: FxHashMap::default(),
+            keys: SecondaryMap::new(),
+        }
+    }
+
+    pub(crate) fn get(&self, key: &K) -> &[Entity] {
+        match self.entities.get(key) {
+            Some(entities) => entities,
+            None => &[],
+        }
+    }
+}
+
+impl<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static> ComponentIndex
+    for ValueIndex<T, K>
+{
+    fn update(&mut self, entity: Entity, component: &dyn Any) {
+        let Some(component) = component.downcast_ref::<T>() else {
+            return;
+        };
+
+        let key = (self.key_of)(component);
+        if self.keys.get(entity.entity_id) == Some(&key) {
+            return;
+        }
+
+        self.remove(entity);
+        self.entities.entry(key.clone()).or_default().push(entity);
+        self.keys.insert(entity.entity_id, key);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let Some(key) = self.keys.remove(entity.entity_id) else {
+            return;
+        };
+
+        if let Some(entities) = self.entities.get_mut(&key) {
+            entities.retain(|indexed_entity| *indexed_entity != entity);
+            if entities.is_empty() {
+                self.entities.remove(&key);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entities.clear();
+        self.keys.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn empty_copy(&self) -> Box<dyn ComponentIndex> {
+        Box::new(ValueIndex::<T, K> {
+            key_of: self.key_of.clone(),
+            entities: FxHashMap::default(),
+            keys: SecondaryMap::new(),
+        })
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Adds an index that finds entities by a key made from one of their components, like a network id
+    /// The key is updated when the component is added, removed or changed with modify,
+    /// changes made any other way are picked up at the end of World::run or when sync_indexes is called
+    /// Adding an index with the same component and key type replaces the old one
+    pub fn add_index<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static>(
+        &mut self,
+        key_of: impl Fn(&T) -> K + Send + Sync + 'static,
+    ) {
+        let mut index = ValueIndex::<T, K>::new(key_of);
+        for entity in self.get_entities_with_component::<T>() {
+            if let Some(component) = self
+                .components
+                .get(entity.entity_id)
+                .and_then(|components| components.get::<T>())
+            {
+                index.update(entity, component);
+            }
+        }
+
+        let indexes = self.value_indexes.entry(TypeId::of::<T>()).or_default();
+        indexes.retain(|index| !index.as_any().is::<ValueIndex<T, K>>());
+        indexes.push(Box::new(index));
+    }
+
+    /// Gets the entities whose component T has the key, using the index added with add_index::<T, K>
+    /// panics if there is no index for T and K
+    pub fn get_entities_by_key<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static>(
+        &self,
+        key: &K,
+    ) -> &[Entity] {
+        self.value_indexes
+            .get(&TypeId::of::<T>())
+            .and_then(|indexes| {
+                indexes
+                    .iter()
+                    .find_map(|index| index.as_any().downcast_ref::<ValueIndex<T, K>>())
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "There is no index for {type:?} with keys of type {key_type:?}, was it added with add_index?",
+                    type = std::any::type_name::<T>(),
+                    key_type = std::any::type_name::<K>()
+                );
+            })
+            .get(key)
+    }
+
+    /// Updates every index with the current value of the components
+    /// This is needed after changing an indexed component with get_components_mut or a query
+    /// World::run calls this after all the systems have run
+    pub fn sync_indexes(&mut self) {
+        for (type_id, indexes) in self.value_indexes.iter_mut() {
+            if let Some(entities) = self.entities_with_components.get(type_id) {
+                for entity in entities.values() {
+                    if let Some(component) = self
+                        .components
+                        .get(entity.entity_id)
+                        .and_then(|components| components.as_raw().get(type_id))
+                    {
+                        for index in indexes.iter_mut() {
+                            index.update(*entity, &**component);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // updates the indexes of a component type on an entity after it was added or changed
+    pub(crate) fn update_value_indexes(&mut self, entity: Entity, type_id: TypeId) {
+        let Some(indexes) = self.value_indexes.get_mut(&type_id) else {
+            return;
+        };
+
+        if let Some(component) = self
+            .components
+            .get(entity.entity_id)
+            .and_then(|components| components.as_raw().get(&type_id))
+        {
+            for index in indexes.iter_mut() {
+                index.update(entity, &**component);
+            }
+        }
+    }
+
+    // empties every index without removing them, used when every entity is removed or moved
+    // the same indexes with no entities, they are filled as entities are added to the world they are put in
+    pub(crate) fn copy_value_indexes(&self) -> FxHashMap<TypeId, Vec<Box<dyn ComponentIndex>>> {
+        self.value_indexes
+            .iter()
+            .map(|(type_id, indexes)| {
+                let copies = indexes.iter().map(|index| index.empty_copy()).collect();
+                (*type_id, copies)
+            })
+            .collect()
+    }
+
+    pub(crate) fn clear_value_indexes(&mut self) {
+        for indexes in self.value_indexes.values_mut() {
+            for index in indexes.iter_mut() {
+                index.clear();
+            }
+        }
+    }
+
+    // removes an entity from the indexes of a component type
+    pub(crate) fn remove_from_value_indexes(&mut self, entity: Entity, type_id: TypeId) {
+        if let Some(indexes) = self.value_indexes.get_mut(&type_id) {
+            for index in indexes.iter_mut() {
+                index.remove(entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::*;
+
+// for each component type, the entity each entity inherits it from, None if no parent has it
+// cleared whenever the hierarchy changes and for a type whenever that component is added
+pub(crate) type InheritedOwners = FxHashMap<TypeId, SecondaryMap<DefaultKey, Option<Entity>>>;
+
+impl EntitiesAndComponents {
+    /// Gets the component T of an entity, or of the closest parent above it that has T
+    /// Useful for settings that are given to a whole group at once, like a team color, render layer or physics material
+    /// The entity each entity inherits from is cached, so most calls do not walk up the hierarchy
+    /// Returns None if neither the entity nor any of its parents have T
+    /// panics if the entity does not exist
+    pub fn effective<T: Component>(&self, entity: Entity) -> Option<&T> {
+        let owner = self.get_effective_owner::<T>(entity)?;
+        self.components.get(owner.entity_id)?.get::<T>()
+    }
+
+    /// Gets the entity that effective::<T> takes the component from, the entity itself if it has T
+    /// panics if the entity does not exist
+    pub fn get_effective_owner<T: Component>(&self, entity: Entity) -> Option<Entity> {
+        if !self.does_entity_exist(entity) {
+            panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+        }
+        let has_component = |entity: Entity| {
+            self.components
+                .get(entity.entity_id)
+                .is_some_and(|components| components.contains::<T>())
+        };
+        if has_component(entity) {
+            return Some(entity);
+        }
+
+        let mut inherited_owners = self
+            .inherited_owners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let owners = inherited_owners.entry(TypeId::of::<T>()).or_default();
+        // a cached owner can lose the component without the cache being cleared
+        let cached_owner = |entity: Entity| {
+            owners
+                .get(entity.entity_id)
+                .copied()
+                .filter(|owner| owner.is_none_or(has_component))
+        };
+        if let Some(owner) = cached_owner(entity) {
+            return owner;
+        }
+
+        // walks up until an entity with the component or a cached entity is found
+        let mut visited = vec![entity];
+        let mut current = entity;
+        let owner = loop {
+            let Some(parent) = self.get_parent(current) else {
+                break None;
+            };
+            if has_component(parent) {
+                break Some(parent);
+            }
+            if let Some(owner) = cached_owner(parent) {
+                break owner;
+            }
+            visited.push(parent);
+            current = parent;
+        };
+
+        for visited_entity in visited {
+            owners.insert(visited_entity.entity_id, owner);
+        }
+        owner
+    }
+
+    // forgets which entities inherit the component, called when it is added to an entity
+    pub(crate) fn invalidate_inherited_owners(&mut self, type_id: TypeId) {
+        if let Some(owners) = self
+            .inherited_owners
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(&type_id)
+        {
+            owners.clear();
+        }
+    }
+}
+
+impl EntitiesAndComponentsThreadSafe<'_> {
+    /// Gets the component T of an entity or its closest parent that has it, see EntitiesAndComponents::effective
+    pub fn effective<T: Component + Send + Sync>(&self, entity: Entity) -> Option<&T> {
+        self.entities_and_components.effective::<T>(entity)
+    }
+}
+
+impl World {
+    /// Gets the component T of an entity or its closest parent that has it, see EntitiesAndComponents::effective
+    pub fn effective<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.entities_and_components.effective::<T>(entity)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::*;
+
+/// A snapshot of the world that external tools like editors can read without knowing the component types
+/// It only holds plain data, so it can be sent to another thread or serialized by the tool
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct WorldInspection {
+    /// Every entity in the world with the names of its components
+    pub entities: Vec<EntityInspection>,
+    /// Every parent child link in the world as (parent, child)
+    pub hierarchy: Vec<(Entity, Entity)>,
+    /// The type names of all the resources in the world
+    pub resources: Vec<&'static str>,
+}
+
+/// A single entity and the type names of its components, sorted by name
+#[derive(Clone, PartialEq, Debug)]
+pub struct EntityInspection {
+    /// The entity being inspected
+    pub entity: Entity,
+    /// The type names of the entity's components
+    pub components: Vec<&'static str>,
+}
+
+impl WorldInspection {
+    /// Gets the inspection of a single entity if it exists
+    pub fn get_entity(&self, entity: Entity) -> Option<&EntityInspection> {
+        self.entities
+            .iter()
+            .find(|entity_inspection| entity_inspection.entity == entity)
+    }
+
+    /// Gets the children of an entity in the order they were added
+    pub fn get_children(&self, parent: Entity) -> Vec<Entity> {
+        self.hierarchy
+            .iter()
+            .filter(|(edge_parent, _)| *edge_parent == parent)
+            .map(|(_, child)| *child)
+            .collect()
+    }
+}
+
+/// A report of everything the world knows about one entity, made with debug_entity
+/// It is meant to be printed, use Display to get a readable report
+#[derive(Clone, PartialEq, Debug)]
+pub struct EntityDebug {
+    /// The entity being debugged
+    pub entity: Entity,
+    /// Whether the entity exists, nothing else is filled in if it does not
+    pub exists: bool,
+    /// The type names of the entity's components, sorted by name
+    pub components: Vec<&'static str>,
+    /// The parent of the entity
+    pub parent: Option<Entity>,
+    /// The children of the entity
+    pub children: Vec<Entity>,
+    /// The group the entity is in
+    pub group: Option<GroupId>,
+    /// The type names of the component indexes that list the entity, sorted by name
+    /// these should be the same as the components, if they are not the world is out of sync
+    pub indexed_by: Vec<&'static str>,
+}
+
+impl std::fmt::Display for EntityDebug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.exists {
+            return writeln!(f, "{:?} does not exist", self.entity);
+        }
+
+        writeln!(f, "{:?}", self.entity)?;
+        writeln!(f, "    components: {}", self.components.join(", "))?;
+        writeln!(f, "    parent: {:?}", self.parent)?;
+        writeln!(f, "    children: {:?}", self.children)?;
+        writeln!(f, "    group: {:?}", self.group)?;
+        writeln!(f, "    indexed by: {}", self.indexed_by.join(", "))
+    }
+}
+
+// the hierarchy and groups are stored as components, but they are not shown as components
+pub(crate) fn is_internal_component(type_id: TypeId) -> bool {
+    type_id == TypeId::of::<Children>()
+        || type_id == TypeId::of::<Parent>()
+        || type_id == TypeId::of::<InGroup>()
+        || type_id == TypeId::of::<Tags>()
+}
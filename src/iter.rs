@@ -0,0 +1,65 @@
+use crate::*;
+
+/// An iterator over entities, returned by iter_entities, get_entities_with_component and the other functions that list entities
+/// What it iterates over is private so the way entities are stored can change without breaking code that uses it
+pub struct EntityIter<'a> {
+    inner: EntityIterInner<'a>,
+}
+
+enum EntityIterInner<'a> {
+    // skips the entities hidden with despawn_soft and the reserved entities that have not been handed out and spawned
+    All(
+        slotmap::basic::Values<'a, DefaultKey, Entity>,
+        &'a SecondaryMap<DefaultKey, usize>,
+        &'a SecondaryMap<DefaultKey, ()>,
+    ),
+    // None when no entity has ever had the component
+    WithComponent(Option<storage::ComponentEntitiesIter<'a>>),
+}
+
+impl<'a> EntityIter<'a> {
+    pub(crate) fn all(
+        entities: &'a SlotMap<DefaultKey, Entity>,
+        hidden: &'a SecondaryMap<DefaultKey, usize>,
+        reserved: &'a SecondaryMap<DefaultKey, ()>,
+    ) -> Self {
+        EntityIter {
+            inner: EntityIterInner::All(entities.values(), hidden, reserved),
+        }
+    }
+
+    pub(crate) fn with_component(entities: Option<&'a ComponentEntities>) -> Self {
+        EntityIter {
+            inner: EntityIterInner::WithComponent(entities.map(|entities| entities.values())),
+        }
+    }
+}
+
+impl Iterator for EntityIter<'_> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Entity> {
+        match &mut self.inner {
+            EntityIterInner::All(entities, hidden, reserved) => entities
+                .find(|entity| {
+                    !hidden.contains_key(entity.entity_id)
+                        && !reserved.contains_key(entity.entity_id)
+                })
+                .copied(),
+            EntityIterInner::WithComponent(entities) => entities.as_mut()?.next().copied(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            EntityIterInner::All(entities, hidden, reserved)
+                if hidden.is_empty() && reserved.is_empty() =>
+            {
+                entities.size_hint()
+            }
+            EntityIterInner::All(entities, _, _) => (0, entities.size_hint().1),
+            EntityIterInner::WithComponent(Some(entities)) => entities.size_hint(),
+            EntityIterInner::WithComponent(None) => (0, Some(0)),
+        }
+    }
+}
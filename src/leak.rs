@@ -0,0 +1,174 @@
+use crate::*;
+
+/// The memory used by one component type and how its count changed, see World::leak_report
+#[derive(Clone, PartialEq, Debug)]
+pub struct ComponentMemory {
+    /// The type name of the component
+    pub name: &'static str,
+    /// The size of one component in bytes, 0 if none have been seen at the end of a run
+    pub size: usize,
+    /// The alignment of the component in bytes, 0 if none have been seen at the end of a run
+    pub align: usize,
+    /// The number of entities with the component at the end of the last run
+    pub live: usize,
+    /// The number of times the component was added since leak detection was turned on, replacing one counts as adding it
+    pub added: usize,
+    /// The number of times the component was removed since leak detection was turned on, including by removing its entity
+    pub removed: usize,
+    /// The number of runs the live count went up in since it last went down
+    pub growing_frames: usize,
+    /// True if growing_frames is at least the number of frames given to enable_leak_detection
+    pub suspected_leak: bool,
+}
+
+impl ComponentMemory {
+    /// The bytes taken by the live components, not counting memory they point to like the contents of a Vec
+    pub fn live_bytes(&self) -> usize {
+        self.live * self.size
+    }
+}
+
+/// What World::leak_report found, the components are sorted by live_bytes with the largest first
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct LeakReport {
+    /// Every component type that existed when leak detection was turned on or has been added since
+    pub components: Vec<ComponentMemory>,
+}
+
+impl LeakReport {
+    /// Gets the components whose count has been growing for too long, likely from a system that spawns and never despawns
+    pub fn suspected_leaks(&self) -> impl Iterator<Item = &ComponentMemory> {
+        self.components
+            .iter()
+            .filter(|component| component.suspected_leak)
+    }
+}
+
+// the counts for one component type
+#[derive(Default)]
+struct TypeCounts {
+    added: usize,
+    removed: usize,
+    live: usize,
+    growing_frames: usize,
+    size: usize,
+    align: usize,
+}
+
+// counts components as they are added and removed, see World::enable_leak_detection
+pub(crate) struct LeakTracker {
+    // the number of growing runs after which a type is reported
+    frames: usize,
+    types: FxHashMap<TypeId, TypeCounts>,
+}
+
+impl LeakTracker {
+    pub(crate) fn count_removed(&mut self, type_id: TypeId) {
+        self.types.entry(type_id).or_default().removed += 1;
+    }
+}
+
+impl EntitiesAndComponents {
+    // counts a component that was added if leak detection is on
+    #[inline]
+    pub(crate) fn count_component_added(&mut self, type_id: TypeId) {
+        if let Some(leak_tracker) = &mut self.leak_tracker {
+            leak_tracker.types.entry(type_id).or_default().added += 1;
+        }
+    }
+
+    // counts a component that was removed if leak detection is on
+    #[inline]
+    pub(crate) fn count_component_removed(&mut self, type_id: TypeId) {
+        if let Some(leak_tracker) = &mut self.leak_tracker {
+            leak_tracker.count_removed(type_id);
+        }
+    }
+
+    // updates the live counts at the end of a run
+    pub(crate) fn sample_leak_tracker(&mut self) {
+        let Some(leak_tracker) = &mut self.leak_tracker else {
+            return;
+        };
+
+        for (type_id, counts) in leak_tracker.types.iter_mut() {
+            let entities = self.entities_with_components.get(type_id);
+            let live = entities.map_or(0, |entities| entities.len());
+            if live < counts.live {
+                counts.growing_frames = 0;
+            } else if live > counts.live {
+                counts.growing_frames += 1;
+            }
+            counts.live = live;
+
+            // the size is read from a live component since the type is not known here
+            let component = entities
+                .and_then(|entities| entities.values().next())
+                .and_then(|entity| self.components.get(entity.entity_id))
+                .and_then(|components| components.as_raw().get(type_id));
+            if let Some(component) = component {
+                counts.size = std::mem::size_of_val(&**component);
+                counts.align = std::mem::align_of_val(&**component);
+            }
+        }
+    }
+}
+
+impl World {
+    /// Starts counting how many of each component type are added and removed, see leak_report
+    /// A component type is reported as a suspected leak once its count has gone up in frames runs without ever going down,
+    /// which usually means a system spawns entities or adds components and nothing removes them
+    /// Counting has a small cost on every add and remove, so it is meant for debug builds
+    pub fn enable_leak_detection(&mut self, frames: usize) {
+        let engine = &mut self.entities_and_components;
+        // the components that already exist are counted from here
+        let types = engine
+            .entities_with_components
+            .iter()
+            .map(|(type_id, entities)| {
+                let counts = TypeCounts {
+                    live: entities.len(),
+                    ..Default::default()
+                };
+                (*type_id, counts)
+            })
+            .collect();
+        engine.leak_tracker = Some(LeakTracker { frames, types });
+        engine.sample_leak_tracker();
+    }
+
+    /// Stops counting components and forgets the counts, see enable_leak_detection
+    pub fn disable_leak_detection(&mut self) {
+        self.entities_and_components.leak_tracker = None;
+    }
+
+    /// Reports the memory used by each component type and the types that are likely leaking
+    /// Returns None if enable_leak_detection has not been called
+    pub fn leak_report(&self) -> Option<LeakReport> {
+        let engine = &self.entities_and_components;
+        let leak_tracker = engine.leak_tracker.as_ref()?;
+
+        let mut components = leak_tracker
+            .types
+            .iter()
+            .map(|(type_id, counts)| ComponentMemory {
+                name: engine.get_type_name(*type_id),
+                size: counts.size,
+                align: counts.align,
+                live: counts.live,
+                added: counts.added,
+                removed: counts.removed,
+                growing_frames: counts.growing_frames,
+                suspected_leak: leak_tracker.frames > 0
+                    && counts.growing_frames >= leak_tracker.frames,
+            })
+            .collect::<Vec<ComponentMemory>>();
+        components.sort_by(|a, b| {
+            b.live_bytes()
+                .cmp(&a.live_bytes())
+                .then_with(|| a.name.cmp(b.name))
+        });
+
+        Some(LeakReport { components })
+    }
+}
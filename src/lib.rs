@@ -4,20 +4,168 @@
 
 #[doc = include_str!("../README.md")]
 use anymap::Map;
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
-use rustc_hash::FxHashMap;
-use slotmap::{DefaultKey, SecondaryMap, SlotMap};
+use rayon::iter::{
+    IntoParallelIterator, IntoParallelRefMutIterator, ParallelExtend, ParallelIterator,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use slotmap::{DefaultKey, Key, KeyData, SecondaryMap, SlotMap};
 use std::any::{Any, TypeId};
+use std::collections::VecDeque;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, PoisonError};
+mod arena;
+mod assets;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod cell;
+mod change;
+mod commands;
+mod diff;
+mod exclude;
+mod frame_budget;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+mod heavy;
+mod hooks;
+mod index;
+mod inherited;
+mod inspect;
+mod iter;
+mod leak;
+mod local;
 mod macros;
+mod maintenance;
+mod map_entities;
+mod mask;
+mod multi_world;
+mod op_log;
+mod pool;
+mod prev;
+mod query_plan;
+mod query_str;
+mod read_only;
+mod replay;
+mod replay_encoder;
+mod report;
+mod reserve;
+mod rng;
+mod scene;
+mod schedule;
+mod signature;
+mod small_vec;
+mod snapshot;
+mod soft_despawn;
+mod sorted_query;
+mod spatial;
+mod static_schedule;
+mod stats;
+mod storage;
+mod system_list;
+mod system_set;
+mod tags;
+mod tasks;
+#[cfg(feature = "test_utils")]
+pub mod test_utils;
+mod trace;
+mod trait_query;
+mod transform;
+mod typed_entity;
+mod unique_system;
+mod validate;
+mod validator;
+mod versioned;
+mod view;
+mod visibility;
+mod warm_up;
+pub use arena::{FrameArena, ScratchVec};
+pub use assets::{AssetEvent, Assets, Handle, LoadState};
+use cell::CellHandles;
+pub use cell::EcsCell;
+use commands::CommandQueue;
+pub use diff::{diff, diff_mapped, ChangeKind, ComponentChange, WorldDiff};
+pub use exclude::SkipSystem;
+use exclude::SystemSkips;
+use frame_budget::FrameBudget;
+pub use heavy::{Heavy, HeavyArena};
+pub use hooks::FrameStage;
+use hooks::{EntityHooks, FrameHooks, ResourceHooks};
+use index::ComponentIndex;
+pub use inspect::{EntityDebug, EntityInspection, WorldInspection};
+pub use iter::EntityIter;
+use leak::LeakTracker;
+pub use leak::{ComponentMemory, LeakReport};
+pub use local::Local;
+use local::SystemLocals;
 pub use macros::*;
-use rayon::prelude::ParallelSliceMut;
-
+pub use map_entities::MapEntities;
+use mask::ComponentMask;
+pub use multi_world::{CrossWorldSystem, MultiWorld, WorldId};
+use op_log::OpLog;
+pub use op_log::{RecordedOp, StructuralOp};
+pub use pool::EntityPool;
+pub use prev::{Lerp, Prev};
+pub use query_plan::IndexStats;
+pub use query_str::QueryStrError;
+use rayon::prelude::{ParallelSlice, ParallelSliceMut};
+use read_only::ReadOnlyRunner;
+pub use read_only::ReadOnlySystem;
+pub use replay::ReplayComponent;
+use replay::{Recorder, ReplayCodec};
+pub use replay_encoder::{FieldDelta, ReplayEncoder, TextDelta};
+pub use report::{FrameReport, SystemError, SystemFailure};
+use reserve::EntityReserve;
+pub use rng::{Rng, RngStream};
+pub use scene::SceneError;
+pub use schedule::ComponentAccess;
+use schedule::{Schedule, ScheduleNode};
+use small_vec::SmallVec;
+pub use snapshot::ResourceSnapshot;
+use soft_despawn::SoftDespawns;
+pub use sorted_query::SortedQuery;
+use spatial::SpatialGridSystem;
+pub use spatial::{SpatialGrid, SpatialPosition};
+pub use static_schedule::{StaticSchedule, SystemTuple, SystemVisitor};
+use stats::FrameCounts;
+pub use stats::WorldStats;
+use storage::ComponentEntities;
+pub use storage::{DenseEntitySet, EntityStorage};
+pub use system_list::{IntoSystemList, SystemList};
+pub use system_set::SystemSet;
+use tags::{TagId, Tags};
+pub use tasks::AsyncTasks;
+pub use transform::HierarchyTransform;
+pub use typed_entity::{Marker, TypedEntity};
+pub use unique_system::DuplicateSystemPolicy;
+pub use validate::ValidationError;
+use validator::ComponentValidators;
+pub use versioned::MergePolicy;
+use versioned::{Versioned, VersionedColumn};
+use view::CopyViewColumn;
+pub use view::WorldView;
+pub use visibility::{InheritedVisibility, Visible, VisibleOnly};
+
+// most entities have less than 4 children, so they are stored inline up to that
 struct Children {
-    children: Vec<Entity>,
+    children: SmallVec<Entity, 4>,
 }
 
 struct Parent(Entity);
 
+// where an entity is in the hierarchy, entities without a parent are not stored and have a depth of 0
+#[derive(Clone, Copy)]
+struct HierarchyLevel {
+    depth: usize,
+    root: Entity,
+}
+
+// the group an entity has been assigned to
+struct InGroup(GroupId);
+
+/// A label that can be given to entities and systems so one world can hold multiple isolated scenes
+/// Systems added to a group will only run single_entity_step on entities in the same group
+#[derive(Clone, Copy, PartialEq, Debug, PartialOrd, Eq, Ord, Hash)]
+pub struct GroupId(pub u32);
+
 // The Entity will just be an ID that can be
 // indexed into arrays of components for now...
 /// An entity is a unique identifier for an object in the game engine
@@ -27,6 +175,111 @@ pub struct Entity {
     pub(crate) entity_id: DefaultKey,
 }
 
+impl Entity {
+    /// Creates a weak handle to this entity
+    /// Weak handles can be stored across frames and checked before they are used
+    pub fn downgrade(&self) -> WeakEntity {
+        WeakEntity { entity: *self }
+    }
+
+    /// Packs the index and generation of the entity into a u64, for sending it over the network or saving it
+    /// The bits are only meaningful to the world the entity came from
+    pub fn to_bits(&self) -> u64 {
+        self.entity_id.data().as_ffi()
+    }
+
+    /// Makes an entity from bits made by to_bits
+    /// The entity is not checked, use EntitiesAndComponents::entity_from_bits to check that it still exists
+    pub fn from_bits(bits: u64) -> Entity {
+        Entity {
+            entity_id: KeyData::from_ffi(bits).into(),
+        }
+    }
+
+    /// Gets the slot the entity is stored in, for EntityStorage implementations
+    /// The index of a removed entity is reused by a later entity, so it does not identify the entity on its own
+    pub fn index(&self) -> usize {
+        (self.to_bits() & u32::MAX as u64) as usize
+    }
+}
+
+/// A handle to an entity that might have been removed
+/// Use this when storing references to other entities in components,
+/// then upgrade it to get the entity back only if it still exists
+#[derive(Clone, Copy, PartialEq, Debug, PartialOrd, Eq, Ord)]
+pub struct WeakEntity {
+    entity: Entity,
+}
+
+impl WeakEntity {
+    /// Gets the entity if it still exists in the world
+    /// Returns None if the entity has been removed
+    pub fn upgrade(&self, entities_and_components: &EntitiesAndComponents) -> Option<Entity> {
+        if self.is_alive(entities_and_components) {
+            Some(self.entity)
+        } else {
+            None
+        }
+    }
+
+    /// Checks if the entity still exists in the world
+    pub fn is_alive(&self, entities_and_components: &EntitiesAndComponents) -> bool {
+        entities_and_components.does_entity_exist(self.entity)
+    }
+}
+
+impl From<Entity> for WeakEntity {
+    fn from(entity: Entity) -> Self {
+        entity.downgrade()
+    }
+}
+
+/// Maps the entities of one world to the entities that replaced them in another world
+/// This is returned when entities are moved between worlds, for example by World::merge
+pub struct EntityMap {
+    map: SecondaryMap<DefaultKey, Entity>,
+}
+
+impl EntityMap {
+    fn new() -> Self {
+        EntityMap {
+            map: SecondaryMap::new(),
+        }
+    }
+
+    fn insert(&mut self, old_entity: Entity, new_entity: Entity) {
+        self.map.insert(old_entity.entity_id, new_entity);
+    }
+
+    /// Gets the new entity that replaced the old entity
+    /// Returns None if the old entity was not moved
+    pub fn get(&self, old_entity: Entity) -> Option<Entity> {
+        self.map.get(old_entity.entity_id).copied()
+    }
+
+    /// Gets the new entity that replaced the old entity, or the old entity if it was not moved
+    pub fn map(&self, entity: Entity) -> Entity {
+        self.get(entity).unwrap_or(entity)
+    }
+
+    /// Gets the number of entities that were moved
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Checks if no entities were moved
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns an iterator over pairs of (old entity, new entity)
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.map
+            .iter()
+            .map(|(entity_id, new_entity)| (Entity { entity_id }, *new_entity))
+    }
+}
+
 /// Resources are objects that are not components and do not have any relation to entities
 /// They are a sort of blend between an entity and a system,
 /// they have their own update method that is called every frame like a system
@@ -46,6 +299,7 @@ pub trait Resource: 'static + Sized {
 
 trait ResourceWrapper {
     fn update(&mut self);
+    fn type_name(&self) -> &'static str;
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
@@ -54,6 +308,9 @@ impl<T: Resource> ResourceWrapper for T {
     fn update(&mut self) {
         self.update();
     }
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -62,18 +319,131 @@ impl<T: Resource> ResourceWrapper for T {
     }
 }
 
+/// Which entities are removed by EntitiesAndComponents::cleanup
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum CleanupPolicy {
+    /// Nothing is removed, the entities are only counted
+    ReportOnly,
+    /// Entities with no components are removed
+    RemoveEmpty,
+    /// Entities with no components and entities with only a parent or group are removed
+    /// Entities with children are never removed, because that would remove the children too
+    RemoveEmptyAndHierarchyOnly,
+}
+
+/// What a cleanup found, entities are counted even if the policy did not remove them
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Default)]
+pub struct CleanupStats {
+    /// The number of entities that were checked
+    pub checked: usize,
+    /// The number of entities with no components
+    pub empty: usize,
+    /// The number of entities with only a parent or group and no children
+    pub hierarchy_only: usize,
+    /// The number of entities that were removed
+    pub removed: usize,
+}
+
 /// This struct holds all the entities and components in the game engine
 /// It is the main way to interact with the game engine, it is seperate from systems for safety reasons
 pub struct EntitiesAndComponents {
     entities: SlotMap<DefaultKey, Entity>,
     pub(crate) components: SlotMap<DefaultKey, Map<dyn Any + 'static>>, // where components[entity_id][component_id]
-    entities_with_components: FxHashMap<TypeId, SecondaryMap<DefaultKey, Entity>>,
+    entities_with_components: FxHashMap<TypeId, ComponentEntities>,
     /// resources holds all the resources that are not components and do not have any relation to entities
     /// they are read only and can be accessed by any system
     /// Resources have their own trait, Resource, which has an update method that is called every frame
     pub(crate) resources: FxHashMap<TypeId, Box<dyn ResourceWrapper>>,
+    // the descendants of entities that have been queried with query_in_subtree
+    // cleared whenever the hierarchy changes
+    subtree_cache: Mutex<SecondaryMap<DefaultKey, Vec<Entity>>>,
+    // the entities that effective takes each component from, also cleared whenever the hierarchy changes
+    inherited_owners: Mutex<inherited::InheritedOwners>,
+    // the depth and root of every entity with a parent, kept up to date by set_parent and remove_parent
+    hierarchy_levels: SecondaryMap<DefaultKey, HierarchyLevel>,
+    // components that are added automatically when another component is added, see register_required_component
+    required_components: FxHashMap<TypeId, Vec<AddRequiredComponent>>,
+    // the type names of every component type that has been added, used by inspect
+    component_names: FxHashMap<TypeId, &'static str>,
+    // the components that are copied into a WorldView, see register_view_component
+    view_columns: FxHashMap<TypeId, CopyViewColumn>,
+    // the cells that get_cell can find on other entities, see register_cell_type
+    cell_types: FxHashMap<TypeId, cell::GatherCells>,
+    // when strict, every component has to be registered with register_component before it is used
+    strict_components: bool,
+    registered_components: FxHashMap<TypeId, &'static str>,
+    // the bit each registered component type has in the component masks
+    component_bits: FxHashMap<TypeId, usize>,
+    // which registered components each entity has, see mask.rs
+    component_masks: SecondaryMap<DefaultKey, ComponentMask>,
+    // the entities with each tag, see add_tag
+    tagged_entities: FxHashMap<TagId, SecondaryMap<DefaultKey, Entity>>,
+    // the indexes of each component type, see add_index
+    value_indexes: FxHashMap<TypeId, Vec<Box<dyn ComponentIndex>>>,
+    // the recent structural changes, None unless World::start_recording_ops was called
+    op_log: Option<OpLog>,
+    // the components that are written to replay files, see World::register_replay_component
+    replay_codecs: FxHashMap<TypeId, ReplayCodec>,
+    // the replay file being written, see World::record_to
+    recorder: Option<Recorder>,
+    // see on_spawn and on_despawn
+    entity_hooks: EntityHooks,
+    // see on_resource_added and on_resource_removed
+    resource_hooks: ResourceHooks,
+    // where cleanup_incremental left off
+    cleanup_cursor: usize,
+    // resources that can be updated in parallel, see set_resource_independent
+    independent_resources: FxHashSet<TypeId>,
+    // the order the other resources are updated in, see set_resource_order
+    resource_orders: FxHashMap<TypeId, i32>,
+    // resources that are still updated while the world is paused, see set_resource_always_run
+    always_run_resources: FxHashSet<TypeId>,
+    // the spawns and despawns for WorldStats
+    frame_counts: FrameCounts,
+    // scratch buffers that are reused every frame, see frame_alloc
+    frame_arena: FrameArena,
+    // the components implementing each trait, see register_trait_impl
+    trait_impls: FxHashMap<TypeId, Box<dyn trait_query::AnyTraitImpls>>,
+    // the components holding entities, see register_map_entities
+    entity_mappers: FxHashMap<TypeId, map_entities::MapComponentEntities>,
+    // the resources copied by snapshot_resources
+    clone_resources: FxHashMap<TypeId, snapshot::CloneResource>,
+    // the number of entities warm_up makes room for
+    warm_up_capacity: usize,
+    // the component types warm_up makes room for, with how many entities each will have
+    warm_up_components: FxHashMap<TypeId, usize>,
+    // entities that systems running in parallel can take, see set_entity_reserve
+    entity_reserve: Mutex<EntityReserve>,
+    // the entities in the reserve, hidden from iter_entities until they are handed out and spawned
+    reserved_entities: SecondaryMap<DefaultKey, ()>,
+    // the encoders replay components are written with when they change, see World::register_replay_encoder
+    replay_encoders: FxHashMap<TypeId, Arc<dyn ReplayEncoder>>,
+    // entities hidden from queries and systems until they are removed or resurrected, see despawn_soft
+    soft_despawns: SoftDespawns,
+    // the components copied by World::clone_for_simulation
+    clone_components: FxHashMap<TypeId, snapshot::CloneComponent>,
+    // counts the components that are added and removed, see World::enable_leak_detection
+    leak_tracker: Option<LeakTracker>,
+    // the changes made at the end of each run, see World::run
+    maintenance: maintenance::Maintenance,
+    // the component each query is pinned to go through, see pin_query_driver
+    query_drivers: FxHashMap<TypeId, TypeId>,
+    // the components compared by value in diff, see register_diff_component
+    diff_components: FxHashMap<TypeId, diff::CompareComponent>,
+    // the originals of the versioned components the running system changed, see World::run_versioned
+    versioned_changes: Option<versioned::VersionedChanges>,
+    // the change ticks and undo log of the components whose changes are tracked, see track_changes
+    change_tracking: change::ChangeTracking,
 }
 
+// a pointer to a resource that was marked as independent
+// it can be sent to another thread because set_resource_independent requires the resource to be Send
+struct IndependentResource(*mut dyn ResourceWrapper);
+
+unsafe impl Send for IndependentResource {}
+
+type AddRequiredComponent = Arc<dyn Fn(&mut EntitiesAndComponents, Entity) + Send + Sync>;
+
 impl EntitiesAndComponents {
     /// Creates a new EntitiesAndComponents struct
     pub fn new() -> Self {
@@ -83,77 +453,366 @@ impl EntitiesAndComponents {
             components: SlotMap::with_capacity(100),
             entities_with_components: FxHashMap::with_capacity_and_hasher(3, Default::default()),
             resources: FxHashMap::default(),
+            subtree_cache: Mutex::new(SecondaryMap::new()),
+            inherited_owners: Mutex::new(FxHashMap::default()),
+            hierarchy_levels: SecondaryMap::new(),
+            required_components: FxHashMap::default(),
+            component_names: FxHashMap::default(),
+            view_columns: FxHashMap::default(),
+            cell_types: FxHashMap::default(),
+            strict_components: false,
+            registered_components: FxHashMap::default(),
+            component_bits: FxHashMap::default(),
+            component_masks: SecondaryMap::new(),
+            tagged_entities: FxHashMap::default(),
+            value_indexes: FxHashMap::default(),
+            op_log: None,
+            replay_codecs: FxHashMap::default(),
+            recorder: None,
+            entity_hooks: EntityHooks::default(),
+            resource_hooks: ResourceHooks::default(),
+            cleanup_cursor: 0,
+            independent_resources: FxHashSet::default(),
+            resource_orders: FxHashMap::default(),
+            always_run_resources: FxHashSet::default(),
+            frame_counts: FrameCounts::default(),
+            frame_arena: FrameArena::default(),
+            trait_impls: FxHashMap::default(),
+            entity_mappers: FxHashMap::default(),
+            clone_resources: FxHashMap::default(),
+            warm_up_capacity: 100,
+            warm_up_components: FxHashMap::default(),
+            entity_reserve: Mutex::new(EntityReserve::default()),
+            reserved_entities: SecondaryMap::new(),
+            replay_encoders: FxHashMap::default(),
+            soft_despawns: SoftDespawns::default(),
+            clone_components: FxHashMap::default(),
+            leak_tracker: None,
+            maintenance: maintenance::Maintenance::default(),
+            query_drivers: FxHashMap::default(),
+            diff_components: FxHashMap::default(),
+            versioned_changes: None,
+            change_tracking: change::ChangeTracking::default(),
         }
     }
 
     /// Adds an entity to the game engine
     /// Returns the entity
     pub fn add_entity(&mut self) -> Entity {
+        let entity = self.insert_entity();
+        self.run_spawn_hooks(entity);
+        entity
+    }
+
+    // adds an entity without calling the on_spawn functions, they are called once the entity has its components
+    pub(crate) fn insert_entity(&mut self) -> Entity {
         let entity_id = self.components.insert(Map::new());
         self.entities.insert(Entity { entity_id });
+        self.component_masks
+            .insert(entity_id, ComponentMask::default());
+        self.record_op(StructuralOp::Spawn(Entity { entity_id }));
 
         Entity { entity_id }
     }
 
+    // adds an entity that already has all of its components, used when moving entities between worlds
+    // the names of the components have to be known already, the on_spawn functions are called by the caller
+    // with run_spawn_hooks once the entity is ready, like after its parent has been set
+    pub(crate) fn add_entity_with_component_map(
+        &mut self,
+        components: Map<dyn Any + 'static>,
+    ) -> Entity {
+        let type_ids = components.as_raw().keys().copied().collect::<Vec<TypeId>>();
+        let entity = self.insert_component_map(components);
+        self.record_op(StructuralOp::Spawn(entity));
+        for type_id in type_ids {
+            self.record_component_op(entity, type_id, true);
+            self.count_component_added(type_id);
+        }
+        entity
+    }
+
+    // adds an entity with its components without recording a spawn or counting them for leak detection,
+    // used by compact which only moves entities
+    fn insert_component_map(&mut self, components: Map<dyn Any + 'static>) -> Entity {
+        let type_ids = components.as_raw().keys().copied().collect::<Vec<TypeId>>();
+
+        let entity_id = self.components.insert(components);
+        self.entities.insert(Entity { entity_id });
+        self.component_masks
+            .insert(entity_id, ComponentMask::default());
+        let entity = Entity { entity_id };
+
+        for type_id in type_ids {
+            self.entities_with_components
+                .entry(type_id)
+                .or_default()
+                .insert(entity);
+            self.set_component_bit(entity, type_id);
+            self.update_value_indexes(entity, type_id);
+        }
+        self.index_tags(entity);
+
+        entity
+    }
+
+    // removes an entity from the game engine without dropping its components, the opposite of add_entity_with_component_map
+    // the entity is unlinked from its parent and children first
+    pub(crate) fn detach_entity(&mut self, entity: Entity) -> Map<dyn Any + 'static> {
+        if self.does_entity_exist(entity) {
+            self.run_despawn_hooks(entity);
+        }
+        self.remove_parent(entity);
+        self.remove_all_children(entity);
+        self.remove_component_from::<Children>(entity);
+        self.unindex_tags(entity);
+
+        let components = self.components.remove(entity.entity_id).unwrap_or_else(|| {
+            panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+        });
+        for type_id in components.as_raw().keys() {
+            if let Some(entities) = self.entities_with_components.get_mut(type_id) {
+                entities.remove(entity);
+            }
+            self.remove_from_value_indexes(entity, *type_id);
+            self.count_component_removed(*type_id);
+        }
+        self.entities.remove(entity.entity_id);
+        self.soft_despawns.remaining.remove(entity.entity_id);
+        self.component_masks.remove(entity.entity_id);
+        self.hierarchy_levels.remove(entity.entity_id);
+        self.record_op(StructuralOp::Despawn(entity));
+
+        components
+    }
+
     /// Adds an entity to the game engine with components
     pub fn add_entity_with<T: OwnedComponents<Input = T>>(&mut self, components: T) -> Entity {
         let entity = <T>::make_entity_with_components(self, components);
         entity
     }
 
+    /// Adds an entity with a marker and all of the marker's components
+    /// Returns a TypedEntity that can get the components without naming them
+    pub fn add_typed_entity<M: Marker>(
+        &mut self,
+        marker: M,
+        components: M::Components,
+    ) -> TypedEntity<M> {
+        let entity = self.add_entity_with(components);
+        self.add_component_to(entity, marker);
+
+        TypedEntity::new(entity)
+    }
+
+    /// Gets a TypedEntity for an entity if it has the marker M and all of the marker's components
+    /// panics if the entity does not exist
+    pub fn try_get_typed_entity<M: Marker>(&self, entity: Entity) -> Option<TypedEntity<M>> {
+        if self.try_get_component::<M>(entity).is_some()
+            && M::Components::has_components(self, entity)
+        {
+            Some(TypedEntity::new(entity))
+        } else {
+            None
+        }
+    }
+
     /// Removes an entity from the game engine
     /// This will also remove all children of the entity
     pub fn remove_entity(&mut self, entity: Entity) {
+        if self.does_entity_exist(entity) {
+            self.run_despawn_hooks(entity);
+        }
         self.remove_parent(entity);
-        let children = self
-            .try_get_components::<(Children,)>(entity)
-            .0
-            .unwrap_or(&Children { children: vec![] })
-            .children
-            .clone();
+        let children = self.get_children(entity);
 
         for child in children {
             self.remove_entity(child);
         }
+        self.unindex_tags(entity);
 
         match self.components.get(entity.entity_id) {
             Some(components) => {
                 for type_id in components.as_raw().keys() {
                     match self.entities_with_components.get_mut(&type_id) {
                         Some(entities) => {
-                            entities.remove(entity.entity_id);
+                            entities.remove(entity);
                         }
                         None => {}
                     }
+                    if let Some(indexes) = self.value_indexes.get_mut(type_id) {
+                        for index in indexes.iter_mut() {
+                            index.remove(entity);
+                        }
+                    }
+                    if let Some(leak_tracker) = &mut self.leak_tracker {
+                        leak_tracker.count_removed(*type_id);
+                    }
                 }
             }
             None => {}
         }
 
-        self.components.remove(entity.entity_id);
+        if self.components.remove(entity.entity_id).is_some() {
+            self.record_op(StructuralOp::Despawn(entity));
+        }
         self.entities.remove(entity.entity_id);
+        self.soft_despawns.remaining.remove(entity.entity_id);
+        self.reserved_entities.remove(entity.entity_id);
+        self.component_masks.remove(entity.entity_id);
+        self.hierarchy_levels.remove(entity.entity_id);
     }
 
     /// Gets a reference to all the entities in the game engine
     /// Should rarely if ever be used
     pub fn get_entities(&self) -> Vec<Entity> {
         // clone the entities vector
-        self.entities.values().cloned().collect::<Vec<Entity>>()
+        self.iter_entities().collect::<Vec<Entity>>()
+    }
+
+    /// Returns an iterator over all the entities in the game engine
+    /// Unlike get_entities this does not allocate
+    pub fn iter_entities(&self) -> EntityIter<'_> {
+        EntityIter::all(
+            &self.entities,
+            &self.soft_despawns.remaining,
+            &self.reserved_entities,
+        )
     }
 
     /// Gets a copy of an entity at a certain index
     pub fn get_nth_entity(&self, index: usize) -> Option<Entity> {
         // get the nth entity
-        if let Some(entity) = self.entities.values().nth(index) {
-            Some(entity.clone())
-        } else {
-            None
-        }
+        self.iter_entities().nth(index)
     }
 
     /// Gets the number of entities in the game engine
     pub fn get_entity_count(&self) -> usize {
-        self.entities.len()
+        // entities hidden with despawn_soft and reserved entities that were not spawned yet are not counted
+        self.entities.len() - self.soft_despawns.remaining.len() - self.reserved_entities.len()
+    }
+
+    /// Takes a snapshot of all the entities, their component names, the hierarchy and the resources
+    /// This is meant for external tools like editors, it is too slow to call every frame
+    /// Like iter_entities, entities hidden with despawn_soft and reserved entities that were not spawned yet are left out
+    pub fn inspect(&self) -> WorldInspection {
+        let mut entity_components: SecondaryMap<DefaultKey, Vec<&'static str>> =
+            SecondaryMap::new();
+        for entity in self.iter_entities() {
+            entity_components.insert(entity.entity_id, vec![]);
+        }
+        for (type_id, entities) in self.entities_with_components.iter() {
+            if inspect::is_internal_component(*type_id) {
+                continue;
+            }
+            let name = self
+                .component_names
+                .get(type_id)
+                .copied()
+                .unwrap_or("unknown");
+            for entity in entities.values() {
+                if let Some(components) = entity_components.get_mut(entity.entity_id) {
+                    components.push(name);
+                }
+            }
+        }
+
+        let entities = self
+            .iter_entities()
+            .map(|entity| {
+                let mut components = entity_components
+                    .remove(entity.entity_id)
+                    .unwrap_or_default();
+                components.sort_unstable();
+                EntityInspection { entity, components }
+            })
+            .collect();
+
+        let mut hierarchy = vec![];
+        for parent in self.iter_entities() {
+            if let Some(children) = self.try_get_component::<Children>(parent) {
+                for child in children.children.iter() {
+                    if !self.is_hidden_entity(*child) {
+                        hierarchy.push((parent, *child));
+                    }
+                }
+            }
+        }
+
+        let mut resources = self
+            .resources
+            .values()
+            .map(|resource| resource.type_name())
+            .collect::<Vec<&'static str>>();
+        resources.sort_unstable();
+
+        WorldInspection {
+            entities,
+            hierarchy,
+            resources,
+        }
+    }
+
+    /// Finds entities that have no components or only a parent or group and removes them based on the policy
+    /// This checks every entity, use cleanup_incremental to spread the work over multiple frames
+    /// Like iter_entities, entities hidden with despawn_soft and reserved entities that were not spawned yet are not checked
+    pub fn cleanup(&mut self, policy: CleanupPolicy) -> CleanupStats {
+        self.cleanup_cursor = 0;
+        self.cleanup_incremental(policy, usize::MAX)
+    }
+
+    /// Like cleanup but only checks up to max_entities entities
+    /// The next call continues where the last one stopped and it starts over once every entity has been checked
+    pub fn cleanup_incremental(
+        &mut self,
+        policy: CleanupPolicy,
+        max_entities: usize,
+    ) -> CleanupStats {
+        if self.cleanup_cursor >= self.get_entity_count() {
+            self.cleanup_cursor = 0;
+        }
+
+        let to_check = self
+            .iter_entities()
+            .skip(self.cleanup_cursor)
+            .take(max_entities)
+            .collect::<Vec<Entity>>();
+        self.cleanup_cursor += to_check.len();
+
+        let mut stats = CleanupStats {
+            checked: to_check.len(),
+            ..Default::default()
+        };
+        let mut to_remove = vec![];
+
+        for entity in to_check {
+            let components = self.get_all_components(entity).as_raw();
+
+            if components.is_empty() {
+                stats.empty += 1;
+                if policy != CleanupPolicy::ReportOnly {
+                    to_remove.push(entity);
+                }
+            } else if components
+                .keys()
+                .all(|type_id| inspect::is_internal_component(*type_id))
+                && self.get_children(entity).is_empty()
+            {
+                stats.hierarchy_only += 1;
+                if policy == CleanupPolicy::RemoveEmptyAndHierarchyOnly {
+                    to_remove.push(entity);
+                }
+            }
+        }
+
+        stats.removed = to_remove.len();
+        for entity in to_remove {
+            self.remove_entity(entity);
+        }
+        // the removed entities were all before the cursor
+        self.cleanup_cursor -= stats.removed;
+
+        stats
     }
 
     /// Gets a reference to all the components on an entity
@@ -172,6 +831,7 @@ impl EntitiesAndComponents {
         &mut self,
         entity: Entity,
     ) -> &mut anymap::Map<(dyn Any + 'static)> {
+        self.mark_all_changed(entity);
         self.components
             .get_mut(entity.entity_id)
             .unwrap_or_else(|| {
@@ -182,25 +842,50 @@ impl EntitiesAndComponents {
     /// Gets a reference to a component on an entity
     /// If the component does not exist on the entity, it will return None
     /// panics if the entity does not exist
-    pub fn try_get_component<T: Component>(&self, entity: Entity) -> Option<&Box<T>> {
+    pub fn try_get_component<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.check_registered::<T>();
         self.components
             .get(entity.entity_id)
             .unwrap_or_else(|| {
                 panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
             })
-            .get::<Box<T>>()
+            .get::<T>()
     }
 
     /// Gets a mutable reference to a component on an entity
     /// If the component does not exist on the entity, it will return None
     /// panics if the entity does not exist
-    pub fn try_get_component_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut Box<T>> {
+    pub fn try_get_component_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.check_registered::<T>();
+        self.mark_changed(entity, TypeId::of::<T>());
         self.components
             .get_mut(entity.entity_id)
             .unwrap_or_else(|| {
                 panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
             })
-            .get_mut::<Box<T>>()
+            .get_mut::<T>()
+    }
+
+    /// Changes a component on an entity with a closure and returns what the closure returns
+    /// This is the preferred way to change a component, anything that has to happen
+    /// whenever a component changes will be done here
+    /// The change tick is set and the old value goes to the undo log if T is tracked, see track_changes
+    /// panics if the entity does not exist or does not have the component
+    pub fn modify<T: Component, R, F: FnOnce(&mut T) -> R>(
+        &mut self,
+        entity: Entity,
+        modify: F,
+    ) -> R {
+        let component = self.try_get_component_mut::<T>(entity).unwrap_or_else(|| {
+            panic!(
+                "Component of type {type:?} does not exist on entity {entity:?}",
+                type = std::any::type_name::<T>()
+            );
+        });
+
+        let result = modify(component);
+        self.update_value_indexes(entity, TypeId::of::<T>());
+        result
     }
 
     /// Gets a tuple of references to components on an entity
@@ -243,10 +928,99 @@ impl EntitiesAndComponents {
         <T>::try_get_components_mut(self, entity)
     }
 
+    /// Gets a mutable reference to the component A on entity1 and the component B on entity2 at the same time
+    /// Useful for interactions like collisions where two entities change each other
+    /// panics if the entities are the same, if either entity does not exist, or if a component is missing
+    pub fn get_components_mut_pair<A: Component, B: Component>(
+        &mut self,
+        entity1: Entity,
+        entity2: Entity,
+    ) -> (&mut A, &mut B) {
+        match self.try_get_components_mut_pair::<A, B>(entity1, entity2) {
+            (Some(a), Some(b)) => (a, b),
+            (None, _) => panic!(
+                "Component {type_name} does not exist on entity {entity1:?}, was the Component added to the entity?",
+                type_name = std::any::type_name::<A>()
+            ),
+            (_, None) => panic!(
+                "Component {type_name} does not exist on entity {entity2:?}, was the Component added to the entity?",
+                type_name = std::any::type_name::<B>()
+            ),
+        }
+    }
+
+    /// Gets a mutable reference to the component A on entity1 and the component B on entity2 at the same time
+    /// If a component does not exist on its entity, it will be None
+    /// panics if the entities are the same or if either entity does not exist
+    pub fn try_get_components_mut_pair<A: Component, B: Component>(
+        &mut self,
+        entity1: Entity,
+        entity2: Entity,
+    ) -> (Option<&mut A>, Option<&mut B>) {
+        self.check_registered::<A>();
+        self.check_registered::<B>();
+        assert_ne!(
+            entity1, entity2,
+            "You cannot borrow the same entity mutably more than once!"
+        );
+        self.mark_changed(entity1, TypeId::of::<A>());
+        self.mark_changed(entity2, TypeId::of::<B>());
+
+        let [components1, components2] = self
+            .components
+            .get_disjoint_mut([entity1.entity_id, entity2.entity_id])
+            .unwrap_or_else(|| {
+                panic!("Entity ID {entity1:?} or {entity2:?} does not exist, was the Entity ID edited?");
+            });
+
+        (components1.get_mut::<A>(), components2.get_mut::<B>())
+    }
+
+    /// Registers a component type, this is needed when strict components are turned on
+    /// Registered components are also tracked with a bit per entity,
+    /// which makes checking for several of them at once faster
+    pub fn register_component<T: Component>(&mut self) {
+        self.registered_components
+            .insert(TypeId::of::<T>(), std::any::type_name::<T>());
+        self.assign_component_bit(TypeId::of::<T>());
+    }
+
+    /// Turns strict components on or off, they are off by default
+    /// When they are on, adding or getting a component that was not registered with register_component panics
+    /// and lists the registered components, which catches using the wrong type (like Box<T> instead of T) early
+    pub fn set_strict_components(&mut self, strict: bool) {
+        self.strict_components = strict;
+    }
+
+    // panics if strict components are on and T was not registered
+    #[inline]
+    pub(crate) fn check_registered<T: Component>(&self) {
+        if self.strict_components
+            && !self.registered_components.contains_key(&TypeId::of::<T>())
+            && !inspect::is_internal_component(TypeId::of::<T>())
+        {
+            let mut registered = self
+                .registered_components
+                .values()
+                .copied()
+                .collect::<Vec<&'static str>>();
+            registered.sort_unstable();
+
+            panic!(
+                "Component {type_name} is not registered, was it registered with register_component? The registered components are: {registered}",
+                type_name = std::any::type_name::<T>(),
+                registered = registered.join(", ")
+            );
+        }
+    }
+
     /// Adds a component to an entity
     /// If the component already exists on the entity, it will be overwritten
     /// panics if the entity does not exist
     pub fn add_component_to<T: Component>(&mut self, entity: Entity, component: T) {
+        self.check_registered::<T>();
+        self.mark_changed(entity, TypeId::of::<T>());
+
         // add the component to the entity
         let components = self
             .components
@@ -254,25 +1028,175 @@ impl EntitiesAndComponents {
             .unwrap_or_else(|| {
                 panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
             });
-        components.insert(Box::new(component));
+        components.insert(component);
 
-        // add the entity to the list of entities with the component
-        match self.entities_with_components.entry(TypeId::of::<Box<T>>()) {
-            std::collections::hash_map::Entry::Occupied(mut entry) => {
-                entry.get_mut().insert(entity.entity_id, entity);
-            }
-            std::collections::hash_map::Entry::Vacant(entry) => {
-                let mut new_map = SecondaryMap::new();
-                new_map.insert(entity.entity_id, entity);
-                entry.insert(new_map);
+        self.component_names
+            .entry(TypeId::of::<T>())
+            .or_insert(std::any::type_name::<T>());
+        self.on_component_added(entity, TypeId::of::<T>());
+    }
+
+    /// Adds a component to an entity only if the entity does not have one already
+    /// Returns true if the component was added, otherwise the existing component is left as it was
+    /// panics if the entity does not exist
+    pub fn add_component_if_absent<T: Component>(&mut self, entity: Entity, component: T) -> bool {
+        self.insert_component_if_absent(entity, || component)
+    }
+
+    /// Gets a mutable reference to a component on an entity, adding the component made by create first if it is missing
+    /// create is only called when the component is added, the indexes and hooks only run then as well
+    /// panics if the entity does not exist
+    pub fn get_or_insert_component_with<T: Component>(
+        &mut self,
+        entity: Entity,
+        create: impl FnOnce() -> T,
+    ) -> &mut T {
+        self.insert_component_if_absent(entity, create);
+        self.try_get_component_mut::<T>(entity).unwrap_or_else(|| {
+            panic!(
+                "Component of type {type:?} does not exist on entity {entity:?}",
+                type = std::any::type_name::<T>()
+            );
+        })
+    }
+
+    // adds the component made by create if the entity does not have one, returns true if it was added
+    fn insert_component_if_absent<T: Component>(
+        &mut self,
+        entity: Entity,
+        create: impl FnOnce() -> T,
+    ) -> bool {
+        self.check_registered::<T>();
+
+        let components = self
+            .components
+            .get_mut(entity.entity_id)
+            .unwrap_or_else(|| {
+                panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+            });
+        if components.contains::<T>() {
+            return false;
+        }
+        self.mark_changed(entity, TypeId::of::<T>());
+        let components = self
+            .components
+            .get_mut(entity.entity_id)
+            .unwrap_or_else(|| {
+                panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+            });
+        components.insert(create());
+
+        self.component_names
+            .entry(TypeId::of::<T>())
+            .or_insert(std::any::type_name::<T>());
+        self.on_component_added(entity, TypeId::of::<T>());
+        true
+    }
+
+    /// Adds a component to an entity without knowing its type at compile time, for tools and scripting layers
+    /// If the component already exists on the entity, it will be overwritten
+    /// panics if the entity does not exist
+    pub fn add_component_dyn(&mut self, entity: Entity, component: Box<dyn Any>) {
+        let type_id = (*component).type_id();
+        if self.strict_components
+            && !self.registered_components.contains_key(&type_id)
+            && !inspect::is_internal_component(type_id)
+        {
+            panic!("Component with {type_id:?} is not registered, was it registered with register_component?");
+        }
+        self.mark_changed(entity, type_id);
+
+        let components = self
+            .components
+            .get_mut(entity.entity_id)
+            .unwrap_or_else(|| {
+                panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+            });
+        // safe because the component is stored under its own TypeId
+        unsafe {
+            components.as_raw_mut().insert(type_id, component);
+        }
+
+        self.on_component_added(entity, type_id);
+    }
+
+    /// Gets a reference to a component on an entity without knowing its type at compile time
+    /// If the component does not exist on the entity, it will return None
+    /// panics if the entity does not exist
+    pub fn get_component_dyn(&self, entity: Entity, type_id: TypeId) -> Option<&dyn Any> {
+        self.components
+            .get(entity.entity_id)
+            .unwrap_or_else(|| {
+                panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+            })
+            .as_raw()
+            .get(&type_id)
+            .map(|component| &**component)
+    }
+
+    // updates everything that keeps track of which entities have a component after it was added
+    fn on_component_added(&mut self, entity: Entity, type_id: TypeId) {
+        self.count_component_added(type_id);
+        // entities hidden with despawn_soft are added to the lists when they are resurrected
+        if !self.soft_despawns.remaining.contains_key(entity.entity_id) {
+            // add the entity to the list of entities with the component
+            self.entities_with_components
+                .entry(type_id)
+                .or_default()
+                .insert(entity);
+            self.set_component_bit(entity, type_id);
+            self.update_value_indexes(entity, type_id);
+            self.invalidate_inherited_owners(type_id);
+        }
+        self.record_component_op(entity, type_id, true);
+
+        // add any components that this component requires and the entity is missing
+        if let Some(required_components) = self.required_components.get(&type_id) {
+            for add_required_component in required_components.clone() {
+                add_required_component(self, entity);
             }
         }
     }
 
+    /// Registers that component A requires component B
+    /// Whenever A is added to an entity that does not have B, B::default() will be added as well
+    pub fn register_required_component<A: Component, B: Component + Default + Send + Sync>(
+        &mut self,
+    ) {
+        self.register_required_component_with::<A, B>(B::default);
+    }
+
+    /// Registers that component A requires component B
+    /// Whenever A is added to an entity that does not have B, B will be made with create and added as well
+    pub fn register_required_component_with<A: Component, B: Component + Send + Sync>(
+        &mut self,
+        create: impl Fn() -> B + Send + Sync + 'static,
+    ) {
+        self.required_components
+            .entry(TypeId::of::<A>())
+            .or_default()
+            .push(Arc::new(
+                move |entities_and_components: &mut EntitiesAndComponents, entity: Entity| {
+                    if entities_and_components
+                        .try_get_component::<B>(entity)
+                        .is_none()
+                    {
+                        entities_and_components.add_component_to(entity, create());
+                    }
+                },
+            ));
+    }
+
     /// Removes a component from an entity
     /// If the component does not exist on the entity, it will do nothing
     /// panics if the entity does not exist
     pub fn remove_component_from<T: Component>(&mut self, entity: Entity) {
+        self.mark_changed(entity, TypeId::of::<T>());
+        self.remove_component_by_id(entity, TypeId::of::<T>());
+    }
+
+    // removes a component from an entity from its TypeId, see remove_component_from
+    pub(crate) fn remove_component_by_id(&mut self, entity: Entity, type_id: TypeId) {
         // remove the component from the entity
         let components = self
             .components
@@ -280,34 +1204,92 @@ impl EntitiesAndComponents {
             .unwrap_or_else(|| {
                 panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
             });
-        components.remove::<Box<T>>();
+        // safe because removing a component can not put it under the wrong TypeId
+        let removed = unsafe { components.as_raw_mut().remove(&type_id).is_some() };
 
         // remove the entity from the list of entities with the component
-        match self
-            .entities_with_components
-            .get_mut(&TypeId::of::<Box<T>>())
-        {
-            Some(entities) => {
-                entities.remove(entity.entity_id);
-            }
-            None => {}
+        if let Some(entities) = self.entities_with_components.get_mut(&type_id) {
+            entities.remove(entity);
+        }
+        self.unset_component_bit(entity, type_id);
+        self.remove_from_value_indexes(entity, type_id);
+        if removed {
+            self.count_component_removed(type_id);
+            self.record_component_op(entity, type_id, false);
         }
     }
 
     /// returns an iterator over all entities with a certain component
-    pub fn get_entities_with_component<T: Component>(
+    pub fn get_entities_with_component<T: Component>(&self) -> EntityIter<'_> {
+        EntityIter::with_component(self.entities_with_components.get(&TypeId::of::<T>()))
+    }
+
+    // checks if an entity has a component from its TypeId
+    pub(crate) fn has_component_type(&self, entity: Entity, type_id: TypeId) -> bool {
+        self.entities_with_components
+            .get(&type_id)
+            .is_some_and(|entities| entities.contains(entity))
+    }
+
+    // adds every entity with at least one of the components to entities, each entity is only added once
+    pub(crate) fn get_entities_with_any_type(
         &self,
-    ) -> std::iter::Flatten<std::option::IntoIter<slotmap::secondary::Values<'_, DefaultKey, Entity>>>
-    {
-        match self.entities_with_components.get(&TypeId::of::<Box<T>>()) {
-            Some(entities) => Some(entities.values()).into_iter().flatten(),
-            None => None.into_iter().flatten(), // this is a hack so that it returns an empty iterator
+        type_ids: &[TypeId],
+        entities: &mut Vec<Entity>,
+    ) {
+        let mut added = SecondaryMap::new();
+        for type_id in type_ids {
+            let Some(entities_with_type) = self.entities_with_components.get(type_id) else {
+                continue;
+            };
+            for entity in entities_with_type.values() {
+                if !added.contains_key(entity.entity_id) {
+                    added.insert(entity.entity_id, ());
+                    entities.push(*entity);
+                }
+            }
+        }
+        // keep the same order as iter_entities so commands are applied in the same order
+        entities.sort();
+    }
+
+    /// Calls f with every entity that has the component T and a mutable reference to the component
+    /// This is the fastest way to change one component type since it skips the query and the checks for the other types
+    pub fn for_each_mut<T: Component>(&mut self, mut f: impl FnMut(Entity, &mut T)) {
+        self.check_registered::<T>();
+        if self.is_marking_changes() {
+            for entity in self
+                .get_entities_with_component::<T>()
+                .collect::<Vec<Entity>>()
+            {
+                self.mark_changed(entity, TypeId::of::<T>());
+            }
+        }
+        let Some(entities) = self.entities_with_components.get(&TypeId::of::<T>()) else {
+            return;
+        };
+
+        for entity in entities.values() {
+            if let Some(component) = self
+                .components
+                .get_mut(entity.entity_id)
+                .and_then(|components| components.get_mut::<T>())
+            {
+                f(*entity, component);
+            }
         }
     }
 
+    /// gets every entity that matches the filter T
+    /// T can be a tuple of components to get the entities with all of them,
+    /// or an Or of a tuple to get the entities with any of them
+    pub fn get_entities_matching<T: HasComponents>(&self) -> Vec<Entity> {
+        T::get_matching_entities(self)
+    }
+
     /// gets the number of entities with a certain component
     pub fn get_entity_count_with_component<T: Component>(&self) -> usize {
-        match self.entities_with_components.get(&TypeId::of::<Box<T>>()) {
+        match self.entities_with_components.get(&TypeId::of::<T>()) {
             Some(entities) => entities.len(),
             None => 0,
         }
@@ -316,7 +1298,7 @@ impl EntitiesAndComponents {
     /// gets the nth entity with a certain component
     /// O(n) use get_entities_with_component if you need to iterate over all entities with a certain component
     pub fn get_entity_with_component<T: Component>(&self, index: usize) -> Option<Entity> {
-        match self.entities_with_components.get(&TypeId::of::<Box<T>>()) {
+        match self.entities_with_components.get(&TypeId::of::<T>()) {
             Some(entities) => {
                 if let Some(entity) = entities.values().nth(index) {
                     Some(entity.clone())
@@ -328,12 +1310,57 @@ impl EntitiesAndComponents {
         }
     }
 
-    /// Gets a resource from the game engine
-    pub fn get_resource<T: Resource>(&self) -> Option<&T> {
-        match self.resources.get(&TypeId::of::<T>()) {
-            Some(resource) => {
-                let resource = (&**resource)
-                    .as_any()
+    /// Copies every instance of a component out of the game engine along with the entity it is on
+    /// Useful for tools that need all of the data for a component at once
+    pub fn export_column<T: Component + Clone>(&self) -> Vec<(Entity, T)> {
+        let mut column = Vec::with_capacity(self.get_entity_count_with_component::<T>());
+        for entity in self.get_entities_with_component::<T>() {
+            if let Some(component) = self.try_get_component::<T>(entity) {
+                column.push((entity, component.clone()));
+            }
+        }
+        column
+    }
+
+    /// Registers a component to be copied into every WorldView made with read_view
+    pub fn register_view_component<T: Component + Clone + Send + Sync>(&mut self) {
+        self.view_columns
+            .insert(TypeId::of::<T>(), view::copy_view_column::<T>);
+    }
+
+    /// Makes a read only copy of the entities and the registered components that can be sent to other threads
+    /// Only components registered with register_view_component are copied
+    /// Like iter_entities, entities hidden with despawn_soft and reserved entities that were not spawned yet are left out
+    pub fn read_view(&self) -> WorldView {
+        let mut entities = SecondaryMap::new();
+        for entity in self.iter_entities() {
+            entities.insert(entity.entity_id, entity);
+        }
+
+        let columns = self
+            .view_columns
+            .iter()
+            .map(|(type_id, copy_view_column)| (*type_id, copy_view_column(self)))
+            .collect();
+
+        WorldView::new(entities, columns)
+    }
+
+    /// Adds every component in the column to the entity it is paired with
+    /// If an entity already has the component, it will be overwritten
+    /// panics if any of the entities do not exist
+    pub fn import_column<T: Component>(&mut self, column: Vec<(Entity, T)>) {
+        for (entity, component) in column {
+            self.add_component_to(entity, component);
+        }
+    }
+
+    /// Gets a resource from the game engine
+    pub fn get_resource<T: Resource>(&self) -> Option<&T> {
+        match self.resources.get(&TypeId::of::<T>()) {
+            Some(resource) => {
+                let resource = (&**resource)
+                    .as_any()
                     .downcast_ref::<T>()
                     .unwrap_or_else(|| {
                         panic!(
@@ -349,12 +1376,267 @@ impl EntitiesAndComponents {
 
     /// Adds a resource to the game engine
     pub fn add_resource<T: Resource>(&mut self, resource: T) {
-        self.resources.insert(TypeId::of::<T>(), Box::new(resource));
+        let type_id = TypeId::of::<T>();
+        if self.resources.insert(type_id, Box::new(resource)).is_none() {
+            self.run_resource_added_hooks(type_id);
+        }
     }
 
     /// Removes a resource from the game engine
     pub fn remove_resource<T: Resource>(&mut self) {
-        self.resources.remove(&TypeId::of::<T>());
+        let type_id = TypeId::of::<T>();
+        if self.resources.remove(&type_id).is_some() {
+            self.run_resource_removed_hooks(type_id);
+        }
+    }
+
+    /// Checks if a resource exists, useful for optional resources that only exist some of the time
+    pub fn has_resource<T: Resource>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Moves every entity into a dense range of ids so iterating over them touches less memory
+    /// Useful after a lot of entities have been added and removed
+    /// Returns a map from the old entities to the new ones, entities stored in resources
+    /// or in components that are not registered with register_map_entities have to be updated with it
+    /// Old entities and weak handles stop existing, they never point at one of the moved entities
+    /// Entities hidden with despawn_soft are removed for good first
+    pub fn compact(&mut self) -> EntityMap {
+        let mut entity_map = EntityMap::new();
+        self.remove_soft_despawned();
+
+        // the reserved entities that were not spawned yet are moved too, they are remapped in the reserve below
+        let old_entities = self.entities.values().copied().collect::<Vec<Entity>>();
+        let mut old_components = self.empty_entity_slots();
+        for entities in self.entities_with_components.values_mut() {
+            entities.clear();
+        }
+        self.component_masks.clear();
+        self.tagged_entities.clear();
+        self.clear_value_indexes();
+        self.hierarchy_levels.clear();
+
+        for old_entity in old_entities {
+            let components = old_components
+                .remove(old_entity.entity_id)
+                .unwrap_or_else(|| {
+                    panic!("Entity ID {old_entity:?} does not exist, was the Entity ID edited?");
+                });
+
+            let new_entity = self.insert_component_map(components);
+            entity_map.insert(old_entity, new_entity);
+        }
+
+        self.remap_hierarchy(&entity_map);
+        self.map_moved_entities(&entity_map);
+        self.record_remap_to_file(&entity_map);
+        self.entity_reserve
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remap(&entity_map);
+        let reserved_entities = std::mem::take(&mut self.reserved_entities);
+        for (entity_id, _) in reserved_entities.iter() {
+            if let Some(new_entity) = entity_map.get(Entity { entity_id }) {
+                self.reserved_entities.insert(new_entity.entity_id, ());
+            }
+        }
+        self.invalidate_hierarchy_caches();
+        self.cleanup_cursor = 0;
+
+        entity_map
+    }
+
+    // removes every entity from the slot maps and returns their components by their old ids
+    // the slots are kept instead of making new slot maps, so every slot gets a newer version than any entity
+    // handed out before and a stale Entity or WeakEntity can never point at one of the entities added next
+    fn empty_entity_slots(&mut self) -> SecondaryMap<DefaultKey, Map<dyn Any + 'static>> {
+        let mut old_components = SecondaryMap::with_capacity(self.components.len());
+        let mut entity_ids = self.components.keys().collect::<Vec<DefaultKey>>();
+
+        // fill the free slots until a new slot is made, a new slot starts at the first version
+        loop {
+            let entity_id = self.components.insert(Map::new());
+            let entity = Entity { entity_id };
+            self.entities.insert(entity);
+            entity_ids.push(entity_id);
+            if entity.to_bits() >> 32 <= 1 {
+                break;
+            }
+        }
+
+        // the slot removed last is the first to be used again, so the lowest slots are removed last
+        // if the slots were used in another order the entities would be less dense, but still never reuse an id
+        entity_ids.sort_by_key(|entity_id| {
+            std::cmp::Reverse(
+                Entity {
+                    entity_id: *entity_id,
+                }
+                .index(),
+            )
+        });
+        for entity_id in entity_ids {
+            self.entities.remove(entity_id);
+            if let Some(components) = self.components.remove(entity_id) {
+                old_components.insert(entity_id, components);
+            }
+        }
+        old_components
+    }
+
+    // points the parents and children of the moved entities at the entities that replaced them
+    pub(crate) fn remap_hierarchy(&mut self, entity_map: &EntityMap) {
+        for (_, new_entity) in entity_map.iter() {
+            if let Some(children) = self.try_get_component_mut::<Children>(new_entity) {
+                for child in children.children.iter_mut() {
+                    if let Some(new_child) = entity_map.get(*child) {
+                        *child = new_child;
+                    }
+                }
+            }
+
+            if let Some(parent) = self.try_get_component_mut::<Parent>(new_entity) {
+                if let Some(new_parent) = entity_map.get(parent.0) {
+                    parent.0 = new_parent;
+                }
+            }
+        }
+
+        // the moved roots update the depths of everything below them
+        for (_, new_entity) in entity_map.iter() {
+            if self.get_parent(new_entity).is_none() {
+                self.update_hierarchy_levels(new_entity);
+            }
+        }
+    }
+
+    /// Removes every entity and component, resources and registered types are kept
+    pub fn clear_entities(&mut self) {
+        let entities = self.entities.values().copied().collect::<Vec<Entity>>();
+        for entity in entities {
+            // an on_despawn function may have removed another entity
+            if self.does_entity_exist(entity) {
+                self.run_despawn_hooks(entity);
+            }
+        }
+        // the same bookkeeping as remove_entity, without updating the lists that are cleared below
+        let entities = self.entities.values().copied().collect::<Vec<Entity>>();
+        for entity in entities {
+            if let Some(leak_tracker) = &mut self.leak_tracker {
+                if let Some(components) = self.components.get(entity.entity_id) {
+                    for type_id in components.as_raw().keys() {
+                        leak_tracker.count_removed(*type_id);
+                    }
+                }
+            }
+            self.record_op(StructuralOp::Despawn(entity));
+        }
+        self.entities.clear();
+        self.components.clear();
+        self.soft_despawns.remaining.clear();
+        // the sets are cleared in place so the storages set with set_component_storage are kept
+        for entities in self.entities_with_components.values_mut() {
+            entities.clear();
+        }
+        self.component_masks.clear();
+        self.tagged_entities.clear();
+        self.clear_value_indexes();
+        self.hierarchy_levels.clear();
+        self.entity_reserve
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.reserved_entities.clear();
+        self.invalidate_hierarchy_caches();
+        self.cleanup_cursor = 0;
+    }
+
+    /// Removes every resource, entities and components are kept
+    pub fn clear_resources(&mut self) {
+        let type_ids = self.resources.keys().copied().collect::<Vec<TypeId>>();
+        self.resources.clear();
+        for type_id in type_ids {
+            self.run_resource_removed_hooks(type_id);
+        }
+    }
+
+    /// Marks a resource as independent, its update method can then run at the same time as other independent resources
+    /// Only mark resources that do not share any state with other resources
+    /// Independent resources are updated before all the other resources
+    pub fn set_resource_independent<T: Resource + Send>(&mut self) {
+        self.independent_resources.insert(TypeId::of::<T>());
+    }
+
+    /// Sets when a resource that is not independent is updated, lower orders are updated first
+    /// Resources have an order of 0 by default
+    pub fn set_resource_order<T: Resource>(&mut self, order: i32) {
+        self.resource_orders.insert(TypeId::of::<T>(), order);
+    }
+
+    /// Keeps updating a resource while the world is paused, like an input or audio resource, see World::pause
+    pub fn set_resource_always_run<T: Resource>(&mut self) {
+        self.always_run_resources.insert(TypeId::of::<T>());
+    }
+
+    // calls update on every resource, the independent ones in parallel and then the rest in order
+    // while paused only the resources marked with set_resource_always_run are updated
+    fn update_resources(&mut self, paused: bool) {
+        let mut independent = vec![];
+        let mut ordered = vec![];
+        for (type_id, resource) in self.resources.iter_mut() {
+            if paused && !self.always_run_resources.contains(type_id) {
+                continue;
+            }
+            if self.independent_resources.contains(type_id) {
+                independent.push(IndependentResource(
+                    &mut **resource as *mut dyn ResourceWrapper,
+                ));
+            } else {
+                let order = self.resource_orders.get(type_id).copied().unwrap_or(0);
+                ordered.push((order, resource));
+            }
+        }
+
+        independent.into_par_iter().for_each(|resource| {
+            // SAFETY: every pointer is to a different resource and the resources are not used anywhere else until this returns
+            unsafe { (*resource.0).update() }
+        });
+
+        ordered.sort_by_key(|(order, _)| *order);
+        for (_, resource) in ordered {
+            resource.update();
+        }
+    }
+
+    /// Loads an asset in the background with the AsyncTasks resource
+    /// The asset can be used once the task has finished, if the task returns an error the asset is marked as failed
+    /// panics if the Assets<T> or AsyncTasks resource has not been added
+    pub fn load_asset<T: Send + Sync + 'static, E: Send + 'static>(
+        &mut self,
+        future: impl std::future::Future<Output = Result<T, E>> + Send + 'static,
+    ) -> Handle<T> {
+        let handle = self
+            .get_resource_mut::<Assets<T>>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "Resource of type {type:?} does not exist, was the resource added?",
+                    type = std::any::type_name::<Assets<T>>()
+                );
+            })
+            .reserve();
+
+        let tasks = self.get_resource::<AsyncTasks>().unwrap_or_else(|| {
+            panic!("Resource of type AsyncTasks does not exist, was the resource added?");
+        });
+        tasks.spawn(future, move |result, entities_and_components| {
+            if let Some(assets) = entities_and_components.get_resource_mut::<Assets<T>>() {
+                match result {
+                    Ok(asset) => assets.finish_loading(handle, asset),
+                    Err(_) => assets.fail_loading(handle),
+                }
+            }
+        });
+
+        handle
     }
 
     /// Gets a resource from the game engine mutably, panics if the resource does not exist
@@ -376,11 +1658,68 @@ impl EntitiesAndComponents {
         }
     }
 
+    /// Gets a resource from the game engine mutably
+    /// If the resource does not exist, it will be created with the given function first
+    pub fn get_resource_or_insert_with<T: Resource, F: FnOnce() -> T>(
+        &mut self,
+        create_resource: F,
+    ) -> &mut T {
+        if !self.has_resource::<T>() {
+            // added first so the on_resource_added functions see it
+            self.add_resource(create_resource());
+            return self.get_resource_mut::<T>().unwrap_or_else(|| {
+                panic!(
+                    "Resource of type {type:?} was removed by on_resource_added",
+                    type = std::any::type_name::<T>()
+                );
+            });
+        }
+
+        let resource = self
+            .resources
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(create_resource()));
+
+        resource
+            .as_any_mut()
+            .downcast_mut::<T>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "Resource of type {type:?} does not exist, was the type edited?",
+                    type = std::any::type_name::<T>()
+                );
+            })
+    }
+
+    /// Adds the default value of a resource to the game engine
+    /// If the resource already exists, it will not be overwritten
+    pub fn init_resource<T: Resource + Default>(&mut self) {
+        self.get_resource_or_insert_with(T::default);
+    }
+
     /// Checks if an entity exists in the world
     pub fn does_entity_exist(&self, entity: Entity) -> bool {
         self.entities.contains_key(entity.entity_id)
     }
 
+    /// Gets the entity a weak handle points to if it still exists in the world
+    pub fn upgrade_entity(&self, weak_entity: WeakEntity) -> Option<Entity> {
+        weak_entity.upgrade(self)
+    }
+
+    // checks if an entity exists but is left out of queries and systems,
+    // like one hidden with despawn_soft or a reserved entity that was not spawned yet
+    pub(crate) fn is_hidden_entity(&self, entity: Entity) -> bool {
+        self.is_soft_despawned(entity) || self.reserved_entities.contains_key(entity.entity_id)
+    }
+
+    /// Makes an entity from bits made by Entity::to_bits, returns None if the entity does not exist in this world
+    /// An entity that was removed is never returned, even if its index has been reused
+    pub fn entity_from_bits(&self, bits: u64) -> Option<Entity> {
+        let entity = Entity::from_bits(bits);
+        self.does_entity_exist(entity).then_some(entity)
+    }
+
     /// This function is used to help debug entities and components
     /// It will print out all the entities and components in the game engine
     /// it prints the type id of the components, not the actual type because that is not possible
@@ -406,17 +1745,78 @@ impl EntitiesAndComponents {
         }
     }
 
+    /// Makes a report of an entity's components, parent, children, group and the indexes that list it
+    /// Print it to see why an entity or component is missing, it does not panic if the entity does not exist
+    pub fn debug_entity(&self, entity: Entity) -> EntityDebug {
+        let Some(components) = self.components.get(entity.entity_id) else {
+            return EntityDebug {
+                entity,
+                exists: false,
+                components: vec![],
+                parent: None,
+                children: vec![],
+                group: None,
+                indexed_by: vec![],
+            };
+        };
+
+        let component_name = |type_id: &TypeId| {
+            self.component_names
+                .get(type_id)
+                .copied()
+                .unwrap_or("unknown")
+        };
+
+        let mut component_names = components
+            .as_raw()
+            .keys()
+            .filter(|type_id| !inspect::is_internal_component(**type_id))
+            .map(component_name)
+            .collect::<Vec<&'static str>>();
+        component_names.sort_unstable();
+
+        let mut indexed_by = self
+            .entities_with_components
+            .iter()
+            .filter(|(type_id, entities)| {
+                !inspect::is_internal_component(**type_id) && entities.contains(entity)
+            })
+            .map(|(type_id, _)| component_name(type_id))
+            .collect::<Vec<&'static str>>();
+        indexed_by.sort_unstable();
+
+        EntityDebug {
+            entity,
+            exists: true,
+            components: component_names,
+            parent: self.get_parent(entity),
+            children: self.get_children(entity),
+            group: self.get_group(entity),
+            indexed_by,
+        }
+    }
+
     /// gets the children of an entity
     pub fn get_children(&self, entity: Entity) -> Vec<Entity> {
         let (children,) = self.try_get_components::<(Children,)>(entity);
+        children.map_or_else(Vec::new, |children| children.children.to_vec())
+    }
 
-        if let Some(children) = children {
-            return children.children.clone();
-        } else {
-            return vec![];
+    /// gets the number of children an entity has without copying them like get_children does
+    pub fn children_count(&self, entity: Entity) -> usize {
+        match self.try_get_component::<Children>(entity) {
+            Some(children) => children.children.len(),
+            None => 0,
         }
     }
 
+    /// gets the child at an index in the order the children were added
+    /// returns None if the entity has less children than that
+    pub fn nth_child(&self, entity: Entity, index: usize) -> Option<Entity> {
+        self.try_get_component::<Children>(entity)
+            .and_then(|children| children.children.get(index).copied())
+    }
+
     /// gets the parent of an entity
     /// returns None if the entity is a root entity
     pub fn get_parent(&self, entity: Entity) -> Option<Entity> {
@@ -429,6 +1829,58 @@ impl EntitiesAndComponents {
         }
     }
 
+    /// gets how many parents are above an entity, 0 for root entities
+    /// this is cached so it does not walk up the hierarchy
+    /// panics if the entity does not exist
+    pub fn get_depth(&self, entity: Entity) -> usize {
+        self.get_hierarchy_level(entity).depth
+    }
+
+    /// gets the entity at the top of the hierarchy an entity is in, the entity itself for root entities
+    /// this is cached so it does not walk up the hierarchy
+    /// panics if the entity does not exist
+    pub fn get_root(&self, entity: Entity) -> Entity {
+        self.get_hierarchy_level(entity).root
+    }
+
+    fn get_hierarchy_level(&self, entity: Entity) -> HierarchyLevel {
+        if !self.does_entity_exist(entity) {
+            panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+        }
+
+        self.hierarchy_levels
+            .get(entity.entity_id)
+            .copied()
+            .unwrap_or(HierarchyLevel {
+                depth: 0,
+                root: entity,
+            })
+    }
+
+    // recalculates the depth and root of an entity whose parent changed and of everything below it
+    fn update_hierarchy_levels(&mut self, entity: Entity) {
+        let mut to_visit = vec![entity];
+        while let Some(entity) = to_visit.pop() {
+            match self.get_parent(entity) {
+                Some(parent) => {
+                    let parent_level = self.get_hierarchy_level(parent);
+                    self.hierarchy_levels.insert(
+                        entity.entity_id,
+                        HierarchyLevel {
+                            depth: parent_level.depth + 1,
+                            root: parent_level.root,
+                        },
+                    );
+                }
+                None => {
+                    self.hierarchy_levels.remove(entity.entity_id);
+                }
+            }
+
+            to_visit.extend(self.get_children(entity));
+        }
+    }
+
     /// sets the parent of an entity
     /// if the entity already has a parent it will be changed
     /// returns true if the parent was set, false if the parent was not set (inverse relationship detected)
@@ -462,7 +1914,7 @@ impl EntitiesAndComponents {
             children.children.push(child_entity);
         } else {
             let children = Children {
-                children: vec![child_entity],
+                children: SmallVec::from_slice(&[child_entity]),
             };
 
             self.add_component_to(parent_entity, children);
@@ -476,9 +1928,61 @@ impl EntitiesAndComponents {
             self.add_component_to(child_entity, parent);
         }
 
+        self.invalidate_hierarchy_caches();
+        self.update_hierarchy_levels(child_entity);
+        self.record_op(StructuralOp::SetParent {
+            child: child_entity,
+            parent: parent_entity,
+        });
+
         true
     }
 
+    /// Gets the transform of an entity relative to the world by combining it with the transforms of its parents
+    /// Parents without the transform are skipped
+    /// returns None if the entity does not have the transform
+    pub fn get_world_transform<T: HierarchyTransform>(&self, entity: Entity) -> Option<T> {
+        let mut world_transform = self.try_get_component::<T>(entity)?.clone();
+
+        let mut current = entity;
+        while let Some(parent) = self.get_parent(current) {
+            if let Some(parent_transform) = self.try_get_component::<T>(parent) {
+                world_transform = parent_transform.combine(&world_transform);
+            }
+            current = parent;
+        }
+
+        Some(world_transform)
+    }
+
+    /// Like set_parent, but changes the transform of the child so it stays in the same place in the world
+    /// returns the same thing as set_parent
+    pub fn set_parent_keep_world_transform<T: HierarchyTransform>(
+        &mut self,
+        child_entity: Entity,
+        parent_entity: Entity,
+    ) -> bool {
+        let world_transform = self.get_world_transform::<T>(child_entity);
+        let parent_set = self.set_parent(child_entity, parent_entity);
+
+        // set_parent can remove the old parent even if it fails, so use whatever parent the child ends up with
+        if let Some(world_transform) = world_transform {
+            let parent_world_transform = self
+                .get_parent(child_entity)
+                .and_then(|parent| self.get_world_transform::<T>(parent));
+
+            let local_transform = match parent_world_transform {
+                Some(parent_world_transform) => {
+                    parent_world_transform.inverse().combine(&world_transform)
+                }
+                None => world_transform,
+            };
+            self.add_component_to(child_entity, local_transform);
+        }
+
+        parent_set
+    }
+
     /// this function removes the link between a parent and a child making the child a root entity
     pub fn remove_parent(&mut self, child_entity: Entity) {
         if let (Some(parent),) = self.try_get_components::<(Parent,)>(child_entity) {
@@ -495,6 +1999,10 @@ impl EntitiesAndComponents {
 
             // remove the parent from the child
             self.remove_component_from::<Parent>(child_entity);
+
+            self.invalidate_hierarchy_caches();
+            self.update_hierarchy_levels(child_entity);
+            self.record_op(StructuralOp::RemoveParent(child_entity));
         }
     }
 
@@ -507,36 +2015,218 @@ impl EntitiesAndComponents {
     }
 
     /// gets the entities with children
-    pub fn get_entities_with_children(
-        &self,
-    ) -> std::iter::Flatten<std::option::IntoIter<slotmap::secondary::Values<'_, DefaultKey, Entity>>>
-    {
+    pub fn get_entities_with_children(&self) -> EntityIter<'_> {
         self.get_entities_with_component::<Children>()
     }
 
     /// gets the entities with parents
-    pub fn get_entities_with_parent(
-        &self,
-    ) -> std::iter::Flatten<std::option::IntoIter<slotmap::secondary::Values<'_, DefaultKey, Entity>>>
-    {
+    pub fn get_entities_with_parent(&self) -> EntityIter<'_> {
         self.get_entities_with_component::<Parent>()
     }
+
+    /// assigns an entity to a group, if the entity was already in a group it will be moved
+    pub fn assign_group(&mut self, entity: Entity, group: GroupId) {
+        self.add_component_to(entity, InGroup(group));
+    }
+
+    /// removes an entity from its group
+    pub fn remove_from_group(&mut self, entity: Entity) {
+        self.remove_component_from::<InGroup>(entity);
+    }
+
+    /// gets the group an entity is in
+    /// returns None if the entity is not in a group
+    pub fn get_group(&self, entity: Entity) -> Option<GroupId> {
+        self.try_get_component::<InGroup>(entity)
+            .map(|in_group| in_group.0)
+    }
+
+    /// gets all the entities in a group
+    pub fn get_entities_in_group(&self, group: GroupId) -> Vec<Entity> {
+        self.get_entities_with_component::<InGroup>()
+            .filter(|entity| self.get_group(*entity) == Some(group))
+            .collect()
+    }
+
+    /// gets all the descendants of an entity (children, grandchildren, etc.) that have all of the components in T
+    /// the root entity itself is not included
+    /// the descendants of the root are cached until the hierarchy changes, so repeated queries are cheap
+    pub fn query_in_subtree<T: HasComponents>(&self, root: Entity) -> Vec<Entity> {
+        let mut subtree_cache = self
+            .subtree_cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if !subtree_cache.contains_key(root.entity_id) {
+            let mut descendants = vec![];
+            let mut to_visit = self.get_children(root);
+            while let Some(entity) = to_visit.pop() {
+                descendants.push(entity);
+                to_visit.extend(self.get_children(entity));
+            }
+
+            subtree_cache.insert(root.entity_id, descendants);
+        }
+
+        subtree_cache[root.entity_id]
+            .iter()
+            .copied()
+            .filter(|entity| T::has_components(self, *entity))
+            .collect()
+    }
+
+    /// Calls f for every parent and child where the parent has all of the components in P and the child has all of the components in C
+    /// f is given the parent, references to its components, the child and mutable references to its components
+    /// Parents are always visited before their children, so values can be passed down the hierarchy (like transforms)
+    pub fn query_child_pairs<P, C, F>(&mut self, mut f: F)
+    where
+        P: for<'a> ComponentsRef<'a> + HasComponents + 'static,
+        C: for<'a> ComponentsMut<'a> + HasComponents + 'static,
+        F: for<'a> FnMut(
+            Entity,
+            <P as ComponentsRef<'a>>::Result,
+            Entity,
+            <C as ComponentsMut<'a>>::Result,
+        ),
+    {
+        // start at the top of every hierarchy and work down
+        let mut to_visit = self
+            .get_entities_with_component::<Children>()
+            .filter(|entity| self.get_parent(*entity).is_none())
+            .collect::<VecDeque<Entity>>();
+
+        while let Some(parent) = to_visit.pop_front() {
+            let children = self.get_children(parent);
+            let parent_matches = P::has_components(self, parent);
+
+            for child in children {
+                if parent_matches && C::has_components(self, child) {
+                    P::check_registered(self);
+                    C::prepare_mut(self, child);
+
+                    // a parent is never its own child, so the two component maps can be borrowed at once
+                    let [parent_map, child_map] = self
+                        .components
+                        .get_disjoint_mut([parent.entity_id, child.entity_id])
+                        .unwrap_or_else(|| {
+                            panic!("Entity ID {parent:?} or {child:?} does not exist, was the Entity ID edited?");
+                        });
+
+                    f(
+                        parent,
+                        P::get_from_map(parent_map),
+                        child,
+                        C::get_from_map_mut(child_map),
+                    );
+                }
+
+                to_visit.push_back(child);
+            }
+        }
+    }
+
+    /// Gets every entity with all of the components in C whose parent has all of the components in P,
+    /// like query_with_parent::<(Collider,), (RigidBody,)>() for the colliders attached to a rigid body
+    /// Yields the child, references to its components, the parent and references to the parent's components
+    pub fn query_with_parent<'a, C, P>(
+        &'a self,
+    ) -> impl Iterator<Item = (Entity, C::Result, Entity, P::Result)> + 'a
+    where
+        C: ComponentsRef<'a> + HasComponents + 'static,
+        P: ComponentsRef<'a> + HasComponents + 'static,
+    {
+        C::get_matching_entities(self)
+            .into_iter()
+            .filter_map(move |child| {
+                let parent = self.get_parent(child)?;
+                if !P::has_components(self, parent) {
+                    return None;
+                }
+                Some((
+                    child,
+                    C::get_components(self, child),
+                    parent,
+                    P::get_components(self, parent),
+                ))
+            })
+    }
+
+    fn invalidate_hierarchy_caches(&mut self) {
+        self.subtree_cache
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.inherited_owners
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
 }
 
+// the number of entities folded together on one thread in par_fold
+// this has to stay the same no matter how many threads there are to keep par_fold deterministic
+const PAR_FOLD_CHUNK_SIZE: usize = 64;
+
 /// This struct is a thread safe version of the EntitiesAndComponents struct
 /// It is used to allow systems to access the entities and components in parallel
 /// It will not allow any non send sync components to be accessed or added
+///
+/// This is checked at compile time, none of these will compile:
+/// ```compile_fail,E0277
+/// use ABC_ECS::{EntitiesAndComponentsThreadSafe, Entity};
+/// use std::rc::Rc;
+///
+/// fn add_component(engine: &mut EntitiesAndComponentsThreadSafe, entity: Entity) {
+///     engine.add_component_to(entity, Rc::new(0));
+/// }
+/// ```
+/// ```compile_fail,E0277
+/// use ABC_ECS::EntitiesAndComponentsThreadSafe;
+/// use std::rc::Rc;
+///
+/// fn add_entity(engine: &mut EntitiesAndComponentsThreadSafe) {
+///     engine.add_entity_with((0.0f32, Rc::new(0)));
+/// }
+/// ```
+/// ```compile_fail,E0277
+/// use ABC_ECS::{EntitiesAndComponentsThreadSafe, Entity};
+/// use std::cell::Cell;
+///
+/// fn get_components(engine: &EntitiesAndComponentsThreadSafe, entity: Entity) {
+///     let (cell,) = engine.get_components::<(Cell<i32>,)>(entity);
+/// }
+/// ```
+/// ```compile_fail,E0277
+/// use ABC_ECS::{EntitiesAndComponentsThreadSafe, Resource};
+/// use std::rc::Rc;
+///
+/// struct NonSendResource(Rc<i32>);
+/// impl Resource for NonSendResource {}
+///
+/// fn add_resource(engine: &EntitiesAndComponentsThreadSafe) {
+///     engine.queue_insert_resource(NonSendResource(Rc::new(0)));
+/// }
+/// ```
 pub struct EntitiesAndComponentsThreadSafe<'a> {
     entities_and_components: &'a mut EntitiesAndComponents,
+    resource_commands: Mutex<CommandQueue>,
 }
 
 impl<'b> EntitiesAndComponentsThreadSafe<'b> {
     fn new(entities_and_components: &'b mut EntitiesAndComponents) -> Self {
         EntitiesAndComponentsThreadSafe {
             entities_and_components: entities_and_components,
+            resource_commands: Mutex::new(CommandQueue::default()),
         }
     }
 
+    // the resource changes queued while the view was shared, to apply once it is dropped
+    fn take_resource_commands(self) -> CommandQueue {
+        self.resource_commands
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Adds an entity to the game engine
     /// Returns the entity
     pub fn add_entity(&mut self) -> Entity {
@@ -562,6 +2252,12 @@ impl<'b> EntitiesAndComponentsThreadSafe<'b> {
         self.entities_and_components.get_entities()
     }
 
+    /// Returns an iterator over all the entities in the game engine
+    /// Unlike get_entities this does not allocate
+    pub fn iter_entities(&self) -> EntityIter<'_> {
+        self.entities_and_components.iter_entities()
+    }
+
     /// Gets a copy of an entity at a certain index
     pub fn get_nth_entity(&self, index: usize) -> Option<Entity> {
         self.entities_and_components.get_nth_entity(index)
@@ -576,7 +2272,7 @@ impl<'b> EntitiesAndComponentsThreadSafe<'b> {
 
     /// Gets a reference to a component on an entity
     /// If the component does not exist on the entity, it will return None
-    pub fn try_get_component<T: Component + Send + Sync>(&self, entity: Entity) -> Option<&Box<T>> {
+    pub fn try_get_component<T: Component + Send + Sync>(&self, entity: Entity) -> Option<&T> {
         self.entities_and_components.try_get_component(entity)
     }
 
@@ -585,10 +2281,20 @@ impl<'b> EntitiesAndComponentsThreadSafe<'b> {
     pub fn try_get_component_mut<T: Component + Send + Sync>(
         &mut self,
         entity: Entity,
-    ) -> Option<&mut Box<T>> {
+    ) -> Option<&mut T> {
         self.entities_and_components.try_get_component_mut(entity)
     }
 
+    /// Changes a component on an entity with a closure, see EntitiesAndComponents::modify
+    /// panics if the entity does not exist or does not have the component
+    pub fn modify<T: Component + Send + Sync, R, F: FnOnce(&mut T) -> R>(
+        &mut self,
+        entity: Entity,
+        modify: F,
+    ) -> R {
+        self.entities_and_components.modify(entity, modify)
+    }
+
     /// Gets a tuple of references to components on an entity
     /// If the component does not exist on the entity, it will panic
     pub fn get_components<'a, T: ComponentsRef<'a> + Send + Sync + 'static>(
@@ -631,6 +2337,26 @@ impl<'b> EntitiesAndComponentsThreadSafe<'b> {
             .add_component_to(entity, component)
     }
 
+    /// Adds a component to an entity only if the entity does not have one already, see EntitiesAndComponents::add_component_if_absent
+    pub fn add_component_if_absent<T: Component + Send + Sync>(
+        &mut self,
+        entity: Entity,
+        component: T,
+    ) -> bool {
+        self.entities_and_components
+            .add_component_if_absent(entity, component)
+    }
+
+    /// Gets a component on an entity, adding it first if it is missing, see EntitiesAndComponents::get_or_insert_component_with
+    pub fn get_or_insert_component_with<T: Component + Send + Sync>(
+        &mut self,
+        entity: Entity,
+        create: impl FnOnce() -> T,
+    ) -> &mut T {
+        self.entities_and_components
+            .get_or_insert_component_with(entity, create)
+    }
+
     /// Removes a component from an entity
     pub fn remove_component_from<T: Component + Send + Sync>(&mut self, entity: Entity) {
         self.entities_and_components
@@ -638,14 +2364,26 @@ impl<'b> EntitiesAndComponentsThreadSafe<'b> {
     }
 
     /// returns an iterator over all entities with a certain component
-    pub fn get_entities_with_component<T: Component + Send + Sync>(
-        &self,
-    ) -> std::iter::Flatten<std::option::IntoIter<slotmap::secondary::Values<'_, DefaultKey, Entity>>>
-    {
+    pub fn get_entities_with_component<T: Component + Send + Sync>(&self) -> EntityIter<'_> {
         self.entities_and_components
             .get_entities_with_component::<T>()
     }
 
+    /// gets every entity that matches the filter T, see EntitiesAndComponents::get_entities_matching
+    pub fn get_entities_matching<T: HasComponents + Send + Sync>(&self) -> Vec<Entity> {
+        self.entities_and_components.get_entities_matching::<T>()
+    }
+
+    /// Gets every entity with a tag, see EntitiesAndComponents::add_tag
+    pub fn get_entities_with_tag(&self, tag: &str) -> Vec<Entity> {
+        self.entities_and_components.get_entities_with_tag(tag)
+    }
+
+    /// Checks if an entity has a tag
+    pub fn has_tag(&self, entity: Entity, tag: &str) -> bool {
+        self.entities_and_components.has_tag(entity, tag)
+    }
+
     /// gets the number of entities with a certain component
     pub fn get_entity_count_with_component<T: Component + Send + Sync>(&self) -> usize {
         self.entities_and_components
@@ -662,19 +2400,76 @@ impl<'b> EntitiesAndComponentsThreadSafe<'b> {
             .get_entity_with_component::<T>(index)
     }
 
+    /// Folds every entity with a certain component on multiple threads, then merges the results
+    /// The entities are split into chunks of a fixed size and the chunks are merged in order,
+    /// so the result does not depend on the number of threads
+    /// init is called once per chunk and once more to start the merge
+    pub fn par_fold<T, Acc, I, F, M>(&self, init: I, fold: F, merge: M) -> Acc
+    where
+        T: Component + Send + Sync,
+        Acc: Send,
+        I: Fn() -> Acc + Send + Sync,
+        F: Fn(Acc, Entity, &T) -> Acc + Send + Sync,
+        M: Fn(Acc, Acc) -> Acc,
+    {
+        let column = self
+            .entities_and_components
+            .get_entities_with_component::<T>()
+            .map(|entity| {
+                let (component,) = self.get_components::<(T,)>(entity);
+                (entity, component)
+            })
+            .collect::<Vec<(Entity, &T)>>();
+
+        let chunk_results = column
+            .par_chunks(PAR_FOLD_CHUNK_SIZE)
+            .map(|chunk| {
+                chunk.iter().fold(init(), |acc, (entity, component)| {
+                    fold(acc, *entity, component)
+                })
+            })
+            .collect::<Vec<Acc>>();
+
+        chunk_results.into_iter().fold(init(), merge)
+    }
+
+    /// Copies every instance of a component out of the game engine along with the entity it is on
+    pub fn export_column<T: Component + Clone + Send + Sync>(&self) -> Vec<(Entity, T)> {
+        self.entities_and_components.export_column::<T>()
+    }
+
+    /// Adds every component in the column to the entity it is paired with
+    /// If an entity already has the component, it will be overwritten
+    pub fn import_column<T: Component + Send + Sync>(&mut self, column: Vec<(Entity, T)>) {
+        self.entities_and_components.import_column(column)
+    }
+
     /// Gets a resource from the game engine
     pub fn get_resource<T: Resource + Send + Sync>(&self) -> Option<&T> {
         self.entities_and_components.get_resource::<T>()
     }
 
-    /// Adds a resource to the game engine
-    pub fn add_resource<T: Resource + Send + Sync>(&mut self, resource: T) {
-        self.entities_and_components.add_resource(resource)
+    /// Checks if a resource exists
+    pub fn has_resource<T: Resource + Send + Sync>(&self) -> bool {
+        self.entities_and_components.has_resource::<T>()
     }
 
-    /// Removes a resource from the game engine
-    pub fn remove_resource<T: Resource + Send + Sync>(&mut self) {
-        self.entities_and_components.remove_resource::<T>()
+    /// Queues a resource to be added to the game engine, overwriting the old one if it exists
+    /// The resource is added once every prestep has finished
+    pub fn queue_insert_resource<T: Resource + Send + Sync>(&self, resource: T) {
+        self.resource_commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(move |entities_and_components| entities_and_components.add_resource(resource));
+    }
+
+    /// Queues a resource to be removed from the game engine
+    /// The resource is removed once every prestep has finished
+    pub fn queue_remove_resource<T: Resource + Send + Sync>(&self) {
+        self.resource_commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(|entities_and_components| entities_and_components.remove_resource::<T>());
     }
 
     /// Gets a resource from the game engine mutably, panics if the resource does not exist
@@ -687,11 +2482,27 @@ impl<'b> EntitiesAndComponentsThreadSafe<'b> {
         self.entities_and_components.does_entity_exist(entity)
     }
 
+    /// Gets the entity a weak handle points to if it still exists in the world
+    pub fn upgrade_entity(&self, weak_entity: WeakEntity) -> Option<Entity> {
+        weak_entity.upgrade(self.entities_and_components)
+    }
+
     /// gets the children of an entity
     pub fn get_children(&self, entity: Entity) -> Vec<Entity> {
         self.entities_and_components.get_children(entity)
     }
 
+    /// gets the number of children an entity has without copying them like get_children does
+    pub fn children_count(&self, entity: Entity) -> usize {
+        self.entities_and_components.children_count(entity)
+    }
+
+    /// gets the child at an index in the order the children were added
+    /// returns None if the entity has less children than that
+    pub fn nth_child(&self, entity: Entity, index: usize) -> Option<Entity> {
+        self.entities_and_components.nth_child(entity, index)
+    }
+
     /// gets the parent of an entity
     /// returns None if the entity is a root entity
     pub fn get_parent(&self, entity: Entity) -> Option<Entity> {
@@ -706,33 +2517,126 @@ impl<'b> EntitiesAndComponentsThreadSafe<'b> {
             .set_parent(child_entity, parent_entity)
     }
 
-    /// this function removes the link between a parent and a child making the child a root entity
-    pub fn remove_parent(&mut self, child_entity: Entity) {
-        self.entities_and_components.remove_parent(child_entity)
+    /// gets how many parents are above an entity, 0 for root entities
+    pub fn get_depth(&self, entity: Entity) -> usize {
+        self.entities_and_components.get_depth(entity)
     }
 
-    /// gets the entities with children
-    pub fn get_entities_with_children(
-        &self,
-    ) -> std::iter::Flatten<std::option::IntoIter<slotmap::secondary::Values<'_, DefaultKey, Entity>>>
-    {
-        self.entities_and_components.get_entities_with_children()
+    /// gets the entity at the top of the hierarchy an entity is in, the entity itself for root entities
+    pub fn get_root(&self, entity: Entity) -> Entity {
+        self.entities_and_components.get_root(entity)
     }
 
-    /// gets the entities with parents
-    pub fn get_entities_with_parent(
+    /// Gets the transform of an entity relative to the world, see EntitiesAndComponents::get_world_transform
+    pub fn get_world_transform<T: HierarchyTransform + Send + Sync>(
         &self,
-    ) -> std::iter::Flatten<std::option::IntoIter<slotmap::secondary::Values<'_, DefaultKey, Entity>>>
-    {
-        self.entities_and_components.get_entities_with_parent()
+        entity: Entity,
+    ) -> Option<T> {
+        self.entities_and_components
+            .get_world_transform::<T>(entity)
     }
-}
+
+    /// Like set_parent, but changes the transform of the child so it stays in the same place in the world
+    pub fn set_parent_keep_world_transform<T: HierarchyTransform + Send + Sync>(
+        &mut self,
+        child_entity: Entity,
+        parent_entity: Entity,
+    ) -> bool {
+        self.entities_and_components
+            .set_parent_keep_world_transform::<T>(child_entity, parent_entity)
+    }
+
+    /// this function removes the link between a parent and a child making the child a root entity
+    pub fn remove_parent(&mut self, child_entity: Entity) {
+        self.entities_and_components.remove_parent(child_entity)
+    }
+
+    /// gets the entities with children
+    pub fn get_entities_with_children(&self) -> EntityIter<'_> {
+        self.entities_and_components.get_entities_with_children()
+    }
+
+    /// gets the entities with parents
+    pub fn get_entities_with_parent(&self) -> EntityIter<'_> {
+        self.entities_and_components.get_entities_with_parent()
+    }
+
+    /// assigns an entity to a group, if the entity was already in a group it will be moved
+    pub fn assign_group(&mut self, entity: Entity, group: GroupId) {
+        self.entities_and_components.assign_group(entity, group)
+    }
+
+    /// removes an entity from its group
+    pub fn remove_from_group(&mut self, entity: Entity) {
+        self.entities_and_components.remove_from_group(entity)
+    }
+
+    /// gets the group an entity is in
+    /// returns None if the entity is not in a group
+    pub fn get_group(&self, entity: Entity) -> Option<GroupId> {
+        self.entities_and_components.get_group(entity)
+    }
+
+    /// gets all the entities in a group
+    pub fn get_entities_in_group(&self, group: GroupId) -> Vec<Entity> {
+        self.entities_and_components.get_entities_in_group(group)
+    }
+
+    /// gets all the descendants of an entity (children, grandchildren, etc.) that have all of the components in T
+    /// the root entity itself is not included
+    pub fn query_in_subtree<T: HasComponents + Send + Sync>(&self, root: Entity) -> Vec<Entity> {
+        self.entities_and_components.query_in_subtree::<T>(root)
+    }
+
+    /// Gets every entity with the components in C whose parent has the components in P, see EntitiesAndComponents::query_with_parent
+    pub fn query_with_parent<'a, C, P>(
+        &'a self,
+    ) -> impl Iterator<Item = (Entity, C::Result, Entity, P::Result)> + 'a
+    where
+        C: ComponentsRef<'a> + HasComponents + Send + Sync + 'static,
+        P: ComponentsRef<'a> + HasComponents + Send + Sync + 'static,
+    {
+        self.entities_and_components.query_with_parent::<C, P>()
+    }
+}
 
 /// This struct is very similar to the EntitiesAndComponents struct but
 /// it only allows access to components on a single entity for safety reasons
+/// Like EntitiesAndComponentsThreadSafe, it will not allow any non send sync components to be accessed or added
+/// ```compile_fail,E0277
+/// use ABC_ECS::SingleMutEntity;
+/// use std::rc::Rc;
+///
+/// fn add_component(single_entity: &mut SingleMutEntity) {
+///     single_entity.add_component(Rc::new(0));
+/// }
+/// ```
+/// ```compile_fail,E0277
+/// use ABC_ECS::SingleMutEntity;
+/// use std::cell::Cell;
+///
+/// fn get_components_mut(single_entity: &mut SingleMutEntity) {
+///     let (cell,) = single_entity.get_components_mut::<(Cell<i32>,)>();
+/// }
+/// ```
+/// ```compile_fail,E0277
+/// use ABC_ECS::SingleMutEntity;
+/// use std::rc::Rc;
+///
+/// fn get_local(single_entity: &SingleMutEntity) {
+///     single_entity.get_local::<Option<Rc<i32>>>();
+/// }
+/// ```
 pub struct SingleMutEntity<'a> {
     entity: Entity,
     entities_and_components: &'a mut EntitiesAndComponents,
+    system_locals: Option<&'a SystemLocals>,
+    // structural changes are queued and applied after the parallel step so other threads never see them
+    commands: &'a mut CommandQueue,
+    // true if the parent of the entity has finished its step and nothing else is changing it, see get_parent_component
+    parent_done: bool,
+    // the cells of every entity when other threads may be stepping them, see get_cell
+    cells: Option<&'a CellHandles>,
 }
 
 // for safety reasons, we need to make sure we only access data pertaining to this entity
@@ -763,8 +2667,14 @@ impl<'a> SingleMutEntity<'a> {
             })
     }
 
+    /// Gets a reference to a resource, returns None if it does not exist
+    /// Useful for optional resources, like a NetworkSession that only exists in multiplayer
+    pub fn try_get_resource<T: Resource + Send + Sync>(&self) -> Option<&T> {
+        self.entities_and_components.get_resource::<T>()
+    }
+
     /// Gets a mutable reference to a component on an entity
-    pub fn try_get_component<T: Component + Send + Sync>(&self) -> Option<&Box<T>> {
+    pub fn try_get_component<T: Component + Send + Sync>(&self) -> Option<&T> {
         self.entities_and_components
             .try_get_component::<T>(self.entity)
     }
@@ -783,11 +2693,20 @@ impl<'a> SingleMutEntity<'a> {
     }
 
     /// Gets a mutable reference to a component on an entity
-    pub fn try_get_component_mut<T: Component + Send + Sync>(&mut self) -> Option<&mut Box<T>> {
+    pub fn try_get_component_mut<T: Component + Send + Sync>(&mut self) -> Option<&mut T> {
         self.entities_and_components
             .try_get_component_mut::<T>(self.entity)
     }
 
+    /// Changes a component on the entity with a closure, see EntitiesAndComponents::modify
+    /// panics if the entity does not have the component
+    pub fn modify<T: Component + Send + Sync, R, F: FnOnce(&mut T) -> R>(
+        &mut self,
+        modify: F,
+    ) -> R {
+        modify(self.get_component_mut::<T>())
+    }
+
     /// Gets a tuple of references to components on an entity
     pub fn get_components<'b, T: ComponentsRef<'b> + Send + Sync + 'static>(&'b self) -> T::Result {
         <T>::get_components(self.entities_and_components, self.entity)
@@ -819,16 +2738,37 @@ impl<'a> SingleMutEntity<'a> {
 
     /// Removes a component from an entity
     /// If the component does not exist on the entity, it will do nothing
+    /// The component is removed after every single_entity_step has finished
     pub fn remove_component<T: Component + Send + Sync>(&mut self) {
-        self.entities_and_components
-            .remove_component_from::<T>(self.entity);
+        let entity = self.entity;
+        self.commands.push(move |entities_and_components| {
+            if entities_and_components.does_entity_exist(entity) {
+                entities_and_components.remove_component_from::<T>(entity);
+            }
+        });
     }
 
     /// Adds a component to an entity
     /// If the component already exists on the entity, it will be overwritten
+    /// The component is added after every single_entity_step has finished
     pub fn add_component<T: Component + Send + Sync>(&mut self, component: T) {
-        self.entities_and_components
-            .add_component_to(self.entity, component);
+        let entity = self.entity;
+        self.commands.push(move |entities_and_components| {
+            if entities_and_components.does_entity_exist(entity) {
+                entities_and_components.add_component_to(entity, component);
+            }
+        });
+    }
+
+    /// Adds a component to the entity only if it does not have one already
+    /// Whether it is missing is checked when the component would be added, after every single_entity_step has finished
+    pub fn add_component_if_absent<T: Component + Send + Sync>(&mut self, component: T) {
+        let entity = self.entity;
+        self.commands.push(move |entities_and_components| {
+            if entities_and_components.does_entity_exist(entity) {
+                entities_and_components.add_component_if_absent(entity, component);
+            }
+        });
     }
 
     /// Checks if an entity has a certain component
@@ -840,9 +2780,23 @@ impl<'a> SingleMutEntity<'a> {
     }
 
     /// Removes the entity from the game engine
-    /// If you call this function, the struct will be useless and will panic if you try to use it
+    /// The entity is removed after every single_entity_step has finished,
+    /// until then it can still be used by this and other systems
     pub fn remove_entity(&mut self) {
-        self.entities_and_components.remove_entity(self.entity);
+        let entity = self.entity;
+        self.commands.push(move |entities_and_components| {
+            if entities_and_components.does_entity_exist(entity) {
+                entities_and_components.remove_entity(entity);
+            }
+        });
+    }
+
+    /// Adds a new entity with components to the game engine
+    /// The entity is added after every single_entity_step has finished
+    pub fn queue_spawn<T: OwnedComponents<Input = T> + Send + 'static>(&mut self, components: T) {
+        self.commands.push(move |entities_and_components| {
+            entities_and_components.add_entity_with(components);
+        });
     }
 
     /// Gets the entity that this struct is referencing
@@ -850,6 +2804,180 @@ impl<'a> SingleMutEntity<'a> {
     pub fn get_entity(&self) -> Entity {
         self.entity
     }
+
+    /// Gets the group this entity is in
+    /// returns None if the entity is not in a group
+    pub fn get_group(&self) -> Option<GroupId> {
+        self.entities_and_components.get_group(self.entity)
+    }
+
+    /// Gets a component on the parent of this entity, for things like adding the parent's transform to this one
+    /// Returns None if the entity has no parent or the parent does not have the component
+    /// panics if the system does not return true from System::parents_before_children,
+    /// since otherwise another thread could be changing the parent at the same time
+    pub fn get_parent_component<T: Component + Send + Sync>(&self) -> Option<&T> {
+        if !self.parent_done {
+            panic!(
+                "Component of type {type:?} can only be read from the parent if the system returns true from parents_before_children",
+                type = std::any::type_name::<T>()
+            );
+        }
+
+        let parent = self.entities_and_components.get_parent(self.entity)?;
+        self.entities_and_components.try_get_component::<T>(parent)
+    }
+
+    /// Gets the Local storage of the system that is currently running
+    /// If the local does not exist yet, it will be created with its default value
+    /// panics if this is not called from inside of a system
+    pub fn get_local<T: Default + Send + 'static>(&self) -> &Local<T> {
+        self.system_locals
+            .unwrap_or_else(|| {
+                panic!(
+                    "Local of type {type:?} can only be accessed from inside of a system",
+                    type = std::any::type_name::<T>()
+                );
+            })
+            .get_or_init::<T>()
+    }
+}
+
+// a system that implements single_entity_step, with its locals, the group and marker it is limited to
+// and the entities it skips
+type SingleEntityStepSystem<'a> = (
+    &'a Box<dyn SystemWrapper + Sync + Send>,
+    &'a SystemLocals,
+    Option<GroupId>,
+    Option<TypeId>,
+    SystemSkips<'a>,
+);
+
+// how single_entity_steps runs the systems, the same for every entity of a frame
+#[derive(Clone, Copy)]
+struct StepSettings<'a> {
+    panic_policy: PanicPolicy,
+    validators: Option<&'a ComponentValidators>,
+    // the cells gathered before the entities are stepped in parallel, None when they are stepped one at a time
+    cells: Option<&'a CellHandles>,
+}
+
+// runs single_entity_step of every system on one entity
+// structural changes are recorded into commands and errors are recorded into failures
+fn single_entity_steps(
+    systems: &[SingleEntityStepSystem],
+    entities_and_components: &mut EntitiesAndComponents,
+    entity: Entity,
+    commands: &mut CommandQueue,
+    failures: &mut Vec<SystemFailure>,
+    settings: StepSettings,
+) {
+    for (system, system_locals, system_group, system_marker, system_skips) in systems {
+        if system_group.is_some() && entities_and_components.get_group(entity) != *system_group {
+            // grouped systems only run on the entities in their group
+            continue;
+        }
+        if let Some(marker) = system_marker {
+            if !entities_and_components.has_component_type(entity, *marker) {
+                // systems added with add_system_for only run on the entities with their marker
+                continue;
+            }
+        }
+        if system_skips.skips(entities_and_components, entity) {
+            // the entity was excluded from the system or has SkipSystem
+            continue;
+        }
+
+        single_entity_step(
+            &***system,
+            system_locals,
+            entities_and_components,
+            entity,
+            commands,
+            failures,
+            settings,
+        );
+    }
+}
+
+// runs single_entity_step of one system on one entity, catching a panic if the panic policy says so
+// and checking the validators afterwards, used by single_entity_steps and World::run_schedule
+fn single_entity_step<S: SystemWrapper + ?Sized>(
+    system: &S,
+    system_locals: &SystemLocals,
+    entities_and_components: &mut EntitiesAndComponents,
+    entity: Entity,
+    commands: &mut CommandQueue,
+    failures: &mut Vec<SystemFailure>,
+    settings: StepSettings,
+) {
+    commands.set_system(Some(system.type_name()));
+    let mut single_entity = SingleMutEntity {
+        entity,
+        entities_and_components: &mut *entities_and_components,
+        system_locals: Some(system_locals),
+        commands: &mut *commands,
+        parent_done: system.parents_before_children(),
+        cells: settings.cells,
+    };
+
+    let result = match settings.panic_policy {
+        PanicPolicy::Panic => system.try_single_entity_step(&mut single_entity),
+        PanicPolicy::LogAndSkip => {
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                system.try_single_entity_step(&mut single_entity)
+            }));
+            result.unwrap_or_else(|error| {
+                let message = panic_message(&*error);
+                Err(SystemError::new(format!(
+                    "single_entity_step panicked: {message}"
+                )))
+            })
+        }
+    };
+
+    if let Err(error) = result {
+        failures.push(SystemFailure {
+            system: system.type_name(),
+            entity: Some(entity),
+            error,
+        });
+    }
+
+    if let Some(validators) = settings.validators {
+        validators.check_entity(
+            entities_and_components,
+            entity,
+            system.type_name(),
+            failures,
+        );
+    }
+}
+
+// gets the message out of a caught panic, panics made with a format string hold a String
+fn panic_message(error: &(dyn Any + Send)) -> &str {
+    if let Some(message) = error.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = error.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+// writes a string as a json string with its quotes, used by the trace and schedule exporters
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
 }
 
 #[derive(Clone)]
@@ -884,13 +3012,74 @@ pub struct SystemHandle {
     system_id: DefaultKey,
 }
 
+/// What happens when a system panics in single_entity_step, for example because a component is missing
+/// Set it with World::set_panic_policy
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Default)]
+pub enum PanicPolicy {
+    /// The panic is not caught and the game stops, this is the default
+    #[default]
+    Panic,
+    /// The error is added to the FrameReport and passed to the function set with set_error_handler,
+    /// and the system is skipped for that entity, every other entity still runs
+    /// Changes the system made to the entity before it panicked are kept
+    /// This does nothing if the game is built with panic = "abort"
+    LogAndSkip,
+}
+
 /// This struct is the main struct for the game engine
 pub struct World {
     /// This struct holds all the entities and components in the game engine
     pub entities_and_components: EntitiesAndComponents,
     //systems: Vec<Box<dyn System + Sync + Send>>,
     systems: SlotMap<DefaultKey, Box<dyn SystemWrapper + Send + Sync>>,
+    system_locals: SecondaryMap<DefaultKey, SystemLocals>,
+    system_groups: SecondaryMap<DefaultKey, GroupId>,
+    // the marker component that each system added with add_system_for is limited to
+    system_markers: SecondaryMap<DefaultKey, TypeId>,
+    // how often each system added with add_system_with_interval runs
+    system_intervals: SecondaryMap<DefaultKey, SystemInterval>,
+    // the entities each system skips in single_entity_step, see exclude_from_system
+    system_exclusions: SecondaryMap<DefaultKey, SecondaryMap<DefaultKey, ()>>,
+    system_sets: SecondaryMap<DefaultKey, &'static str>,
+    // the systems added with add_read_only_system
+    read_only_systems: SecondaryMap<DefaultKey, ReadOnlyRunner>,
+    set_configs: FxHashMap<&'static str, SystemSet>,
+    // reused every frame to hold the entities that single_entity_step runs on
+    entities_buffer: Vec<Entity>,
+    panic_policy: PanicPolicy,
+    frame_report: FrameReport,
+    error_handler: Option<ErrorHandler>,
+    // the components that each system gets its own copy of in run_versioned
+    versioned_components: FxHashMap<TypeId, Box<dyn VersionedColumn>>,
+    extractor: Option<Extractor>,
+    // what the extractor made on the last run
+    render_queue: Option<Box<dyn Any + Send + Sync>>,
+    // see pause and step
+    paused: bool,
+    frames_to_step: usize,
+    frame_hooks: FrameHooks,
+    component_validators: ComponentValidators,
+    // the components that are copied into Prev<T> every run, see register_previous
+    previous_components: FxHashMap<TypeId, fn(&mut EntitiesAndComponents)>,
+    // see add_world_stats
+    profile_systems: bool,
+    system_timings: Vec<(&'static str, std::time::Duration)>,
+    // the timeline being recorded, see start_capture
+    trace_capture: Option<trace::TraceCapture>,
+    // how long the single_entity_step of each system can take each frame, see set_frame_budget
+    frame_budgets: SecondaryMap<DefaultKey, FrameBudget>,
+}
+
+type ErrorHandler = Box<dyn Fn(&SystemFailure) + Send + Sync>;
+
+// see World::add_system_with_interval
+#[derive(Clone, Copy)]
+struct SystemInterval {
+    every_n_frames: usize,
+    // the frames to skip before the system runs again
+    frames_left: usize,
 }
+type Extractor = Box<dyn FnMut(&EntitiesAndComponents) -> Box<dyn Any + Send + Sync> + Send + Sync>;
 
 impl World {
     /// Creates a new world
@@ -898,19 +3087,262 @@ impl World {
         World {
             entities_and_components: EntitiesAndComponents::new(),
             systems: SlotMap::with_capacity(10),
+            system_locals: SecondaryMap::new(),
+            system_groups: SecondaryMap::new(),
+            system_markers: SecondaryMap::new(),
+            system_exclusions: SecondaryMap::new(),
+            system_intervals: SecondaryMap::new(),
+            system_sets: SecondaryMap::new(),
+            read_only_systems: SecondaryMap::new(),
+            set_configs: FxHashMap::default(),
+            entities_buffer: Vec::new(),
+            panic_policy: PanicPolicy::default(),
+            frame_report: FrameReport::default(),
+            error_handler: None,
+            versioned_components: FxHashMap::default(),
+            extractor: None,
+            render_queue: None,
+            paused: false,
+            frames_to_step: 0,
+            frame_hooks: FrameHooks::default(),
+            component_validators: ComponentValidators::default(),
+            previous_components: FxHashMap::default(),
+            profile_systems: false,
+            system_timings: vec![],
+            trace_capture: None,
+            frame_budgets: SecondaryMap::new(),
         }
     }
 
+    /// Makes a read only copy of the world that can be sent to other threads, see EntitiesAndComponents::read_view
+    pub fn read_view(&self) -> WorldView {
+        self.entities_and_components.read_view()
+    }
+
+    /// Makes a report of everything the world knows about an entity, see EntitiesAndComponents::debug_entity
+    pub fn debug_entity(&self, entity: Entity) -> EntityDebug {
+        self.entities_and_components.debug_entity(entity)
+    }
+
+    /// Takes a snapshot of the world for external tools, see EntitiesAndComponents::inspect
+    pub fn inspect(&self) -> WorldInspection {
+        self.entities_and_components.inspect()
+    }
+
+    /// Registers a component type, see EntitiesAndComponents::register_component
+    pub fn register_component<T: Component>(&mut self) {
+        self.entities_and_components.register_component::<T>();
+    }
+
+    /// Turns strict components on or off, see EntitiesAndComponents::set_strict_components
+    pub fn set_strict_components(&mut self, strict: bool) {
+        self.entities_and_components.set_strict_components(strict);
+    }
+
+    /// Finds and removes empty entities, see EntitiesAndComponents::cleanup
+    pub fn cleanup(&mut self, policy: CleanupPolicy) -> CleanupStats {
+        self.entities_and_components.cleanup(policy)
+    }
+
+    /// Sets what happens when a system panics in single_entity_step
+    pub fn set_panic_policy(&mut self, panic_policy: PanicPolicy) {
+        self.panic_policy = panic_policy;
+    }
+
+    /// Gets what happens when a system panics in single_entity_step
+    pub fn get_panic_policy(&self) -> PanicPolicy {
+        self.panic_policy
+    }
+
     /// Adds a system to the world
     pub fn add_system<T: System + Send + Sync + 'static>(&mut self, system: T) -> SystemHandle {
-        SystemHandle {
-            system_id: self.systems.insert(Box::new(system)),
+        self.add_boxed_system(Box::new(system))
+    }
+
+    fn add_boxed_system(&mut self, system: Box<dyn SystemWrapper + Send + Sync>) -> SystemHandle {
+        let system_id = self.systems.insert(system);
+        self.system_locals
+            .insert(system_id, SystemLocals::default());
+
+        SystemHandle { system_id }
+    }
+
+    /// Adds a SpatialGrid resource for entities with the component P and a system that keeps it up to date
+    /// The grid is updated when the system's run function is called,
+    /// so systems added before this one will see the positions from the last frame
+    pub fn add_spatial_grid<P: SpatialPosition + Send + Sync>(
+        &mut self,
+        cell_size: f32,
+    ) -> SystemHandle {
+        self.entities_and_components
+            .add_resource(SpatialGrid::<P>::new(cell_size));
+        self.add_system(SpatialGridSystem::<P>::new())
+    }
+
+    /// Adds a system to the world that only runs for a group
+    /// single_entity_step will only be called for entities in the group,
+    /// prestep and run are still given the whole world
+    pub fn add_system_to_group<T: System + Send + Sync + 'static>(
+        &mut self,
+        system: T,
+        group: GroupId,
+    ) -> SystemHandle {
+        let system_handle = self.add_system(system);
+        self.system_groups.insert(system_handle.system_id, group);
+
+        system_handle
+    }
+
+    /// Adds a system to the world whose single_entity_step only runs for entities with the component M
+    /// If every system with single_entity_step is limited like this,
+    /// only the entities with one of their markers are visited instead of every entity
+    /// prestep and run are still given the whole world
+    pub fn add_system_for<M: Component, T: System + Send + Sync + 'static>(
+        &mut self,
+        system: T,
+    ) -> SystemHandle {
+        let system_handle = self.add_system(system);
+        self.system_markers
+            .insert(system_handle.system_id, TypeId::of::<M>());
+
+        system_handle
+    }
+
+    /// Adds a system to the world that only runs every few frames, for expensive systems like refreshing paths
+    /// The system runs on the first frame and then once every every_n_frames frames,
+    /// on the other frames none of its functions are called
+    /// panics if every_n_frames is 0
+    pub fn add_system_with_interval<T: System + Send + Sync + 'static>(
+        &mut self,
+        system: T,
+        every_n_frames: usize,
+    ) -> SystemHandle {
+        if every_n_frames == 0 {
+            panic!("A system can not run every 0 frames, use add_system to run it every frame");
+        }
+
+        let system_handle = self.add_system(system);
+        self.system_intervals.insert(
+            system_handle.system_id,
+            SystemInterval {
+                every_n_frames,
+                frames_left: 0,
+            },
+        );
+
+        system_handle
+    }
+
+    /// Adds a system to the world as part of a set
+    /// All the systems in a set can be controlled together with configure_set and remove_set
+    pub fn add_system_to_set<T: System + Send + Sync + 'static>(
+        &mut self,
+        system: T,
+        set: &'static str,
+    ) -> SystemHandle {
+        let system_handle = self.add_system(system);
+        self.system_sets.insert(system_handle.system_id, set);
+        self.set_configs.entry(set).or_insert_with(SystemSet::new);
+
+        system_handle
+    }
+
+    /// Gets the settings of a set so they can be changed
+    /// The set is created if it does not exist yet
+    pub fn configure_set(&mut self, set: &'static str) -> &mut SystemSet {
+        self.set_configs.entry(set).or_insert_with(SystemSet::new)
+    }
+
+    /// Removes all the systems in a set from the world along with the set's settings
+    pub fn remove_set(&mut self, set: &'static str) {
+        let systems_to_remove = self
+            .system_sets
+            .iter()
+            .filter(|(_, system_set)| **system_set == set)
+            .map(|(key, _)| key)
+            .collect::<Vec<DefaultKey>>();
+
+        for key in systems_to_remove {
+            self.remove_system(SystemHandle { system_id: key });
         }
+
+        self.set_configs.remove(set);
+    }
+
+    /// Exports the systems as a graphviz dot graph, to see the order they run in and what they access
+    /// Each system lists its stages and the components it declared in System::component_access,
+    /// an edge means the run function of one system is called before the other because of their set orders
+    pub fn export_schedule_graphviz(&self) -> String {
+        self.get_schedule().to_graphviz()
+    }
+
+    /// Exports the same graph as export_schedule_graphviz as json
+    /// It has a list of systems with an id, name, set, group, order, stages, reads and writes,
+    /// and a list of edges with the id of the system that runs first and the id of the system that runs after it
+    pub fn export_schedule_json(&self) -> String {
+        self.get_schedule().to_json()
+    }
+
+    fn get_schedule(&self) -> Schedule {
+        let nodes = self
+            .systems
+            .iter()
+            .map(|(key, system)| {
+                let set = self.system_sets.get(key).copied();
+
+                let mut stages = vec![];
+                if system.implements_prestep() {
+                    stages.push("prestep");
+                }
+                if system.implements_single_entity_step() {
+                    stages.push("single_entity_step");
+                }
+                stages.push("run");
+
+                let mut access = ComponentAccess::default();
+                system.component_access(&mut access);
+
+                ScheduleNode {
+                    name: system.type_name(),
+                    set,
+                    group: self.system_groups.get(key).copied(),
+                    order: set
+                        .and_then(|set| self.set_configs.get(set))
+                        .map_or(0, |set_config| set_config.get_order()),
+                    stages,
+                    access,
+                }
+            })
+            .collect();
+
+        Schedule::new(nodes)
     }
 
     /// Removes a system from the world based on the SystemHandle
     pub fn remove_system(&mut self, system: SystemHandle) {
         self.systems.remove(system.system_id);
+        self.system_locals.remove(system.system_id);
+        self.system_groups.remove(system.system_id);
+        self.system_markers.remove(system.system_id);
+        self.system_exclusions.remove(system.system_id);
+        self.system_intervals.remove(system.system_id);
+        self.system_sets.remove(system.system_id);
+        self.read_only_systems.remove(system.system_id);
+        self.frame_budgets.remove(system.system_id);
+    }
+
+    /// Gets a Local of a system mutably
+    /// Returns None if the system does not exist or has not used the local yet
+    pub fn get_local_mut<T: Send + 'static>(&mut self, system: &SystemHandle) -> Option<&mut T> {
+        self.system_locals.get_mut(system.system_id)?.get_mut::<T>()
+    }
+
+    /// Resets all the Locals of a system,
+    /// they will be recreated with their default value the next time the system uses them
+    pub fn reset_locals(&mut self, system: &SystemHandle) {
+        if let Some(system_locals) = self.system_locals.get_mut(system.system_id) {
+            system_locals.clear();
+        }
     }
 
     /// Removes all systems of a certain type from the world
@@ -925,147 +3357,836 @@ impl World {
 
         for key in systems_to_remove {
             self.systems.remove(key);
+            self.system_locals.remove(key);
+            self.system_groups.remove(key);
+            self.system_markers.remove(key);
+            self.system_exclusions.remove(key);
+            self.system_intervals.remove(key);
+            self.system_sets.remove(key);
+            self.read_only_systems.remove(key);
+            self.frame_budgets.remove(key);
         }
     }
 
     /// Removes all systems from the world
     pub fn remove_all_systems(&mut self) {
         self.systems.clear();
+        self.system_locals.clear();
+        self.system_groups.clear();
+        self.system_markers.clear();
+        self.system_exclusions.clear();
+        self.system_intervals.clear();
+        self.system_sets.clear();
+        self.read_only_systems.clear();
+        self.frame_budgets.clear();
     }
 
-    /// Runs the world
-    /// This will run all the systems in the world and update all the resources
-    pub fn run(&mut self) {
-        for resource in self.entities_and_components.resources.values_mut() {
-            resource.update();
-        }
+    /// Moves every entity into a dense range of ids, see EntitiesAndComponents::compact
+    pub fn compact(&mut self) -> EntityMap {
+        let entity_map = self.entities_and_components.compact();
+        self.remap_system_exclusions(&entity_map);
+        entity_map
+    }
 
-        if self.systems.is_empty() {
-            return;
-        }
+    /// Adds an index that finds entities by a key made from one of their components, see EntitiesAndComponents::add_index
+    pub fn add_index<T: Component, K: std::hash::Hash + Eq + Clone + Send + Sync + 'static>(
+        &mut self,
+        key_of: impl Fn(&T) -> K + Send + Sync + 'static,
+    ) {
+        self.entities_and_components.add_index(key_of);
+    }
 
-        // run the prestep function for each systems in parallel
-        {
-            let thread_safe_entities_and_components =
-                EntitiesAndComponentsThreadSafe::new(&mut self.entities_and_components);
+    /// Removes every entity and component, systems and resources are kept
+    /// Useful for moving to a new level without making a new world
+    pub fn clear_entities(&mut self) {
+        self.entities_and_components.clear_entities();
+    }
 
-            // check which systems implement the prestep function and collect mutable references to them
-            let mut systems_with_prestep = self
-                .systems
-                .values_mut()
-                .filter(|system| system.implements_prestep())
-                .collect::<Vec<&mut Box<dyn SystemWrapper + Sync + Send>>>();
+    /// Removes every resource, systems, entities and components are kept
+    pub fn clear_resources(&mut self) {
+        self.entities_and_components.clear_resources();
+    }
 
-            systems_with_prestep
-                .par_iter_mut()
-                .for_each(|system| system.prestep(&thread_safe_entities_and_components));
-        }
+    /// Removes every entity, component, resource and system
+    /// Settings like the panic policy, error handler and registered types are kept
+    pub fn clear_all(&mut self) {
+        self.clear_entities();
+        self.clear_resources();
+        self.remove_all_systems();
+        self.set_configs.clear();
+        self.frame_report.clear();
+    }
 
-        {
-            // check which systems implement the single_entity_step function and collect mutable references to them
-            let systems_with_single_entity_step = self
-                .systems
-                .values()
-                .filter(|system| system.implements_single_entity_step())
-                .collect::<Vec<&Box<dyn SystemWrapper + Sync + Send>>>();
+    /// Stops systems from running and resources from updating until resume is called
+    /// run still updates the resources marked with set_resource_always_run, finishes async tasks and calls the extractor
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
 
-            if !systems_with_single_entity_step.is_empty() {
-                let entities_and_components_ptr = &mut self.entities_and_components as *mut _;
-                let entities_and_components_ptr = EntitiesAndComponentPtr {
-                    entities_and_components: entities_and_components_ptr,
-                };
+    /// Lets systems run again after pause, any frames left from step are dropped
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.frames_to_step = 0;
+    }
 
-                /*let chunk_size = ((self.entities_and_components.get_entity_count())
-                / (self.num_cpus * 2))
-                .max(20);*/
-                let chunk_size = 5;
+    /// Checks if the world is paused, it is still paused while stepping
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
 
-                // run the single_entity_step function for each entity in parallel
-                let entities = &mut self.entities_and_components.get_entities();
-                let entity_len;
-                {
-                    entity_len = entities.len();
-                }
-                let par_chunks = entities.par_chunks_mut(chunk_size);
-                let entities_and_components_ptr_iter =
-                    std::iter::repeat(entities_and_components_ptr)
-                        .take(entity_len)
-                        .collect::<Vec<EntitiesAndComponentPtr>>();
-
-                par_chunks.zip(entities_and_components_ptr_iter).for_each(
-                    |(entity_chunk, mut entities_and_components_ptr)| {
-                        for entity in entity_chunk {
-                            for system in systems_with_single_entity_step.as_slice() {
-                                let entities_and_components =
-                                    unsafe { entities_and_components_ptr.as_mut() };
-
-                                if !entities_and_components.does_entity_exist(*entity) {
-                                    // don't run any other systems on this entity it no longer exists
-                                    // this means the entity was removed in the single entity step function of a previous system
-                                    break;
-                                }
-
-                                let mut single_entity = SingleMutEntity {
-                                    entity: *entity,
-                                    entities_and_components,
-                                };
-
-                                system.single_entity_step(&mut single_entity);
-                            }
-                        }
-                    },
-                );
-            }
+    /// Lets the next few calls to run on a paused world run the systems, for stepping through frames while debugging
+    /// Does nothing if the world is not paused
+    pub fn step(&mut self, frames: usize) {
+        if self.paused {
+            self.frames_to_step += frames;
         }
+    }
 
-        for system in &mut self.systems.values_mut() {
-            system.run(&mut self.entities_and_components);
+    // checks if a run should run the systems, using up one stepped frame if the world is paused
+    fn take_frame(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        if self.frames_to_step == 0 {
+            return false;
         }
+        self.frames_to_step -= 1;
+        true
     }
-}
 
-impl Default for World {
-    fn default() -> Self {
-        Self::new()
+    /// Runs the world
+    /// This will run all the systems in the world and update all the resources
+    /// A run happens in this order:
+    /// 1. the Start frame hooks, then the resources and the finished tasks are updated
+    /// 2. prestep, single_entity_step and run of the systems, with the AfterPrestep, AfterSingleEntityStep and AfterRun frame hooks
+    /// 3. the changes queued with EntitiesAndComponents::defer are made
+    /// 4. the entities queued with EntitiesAndComponents::queue_despawn are removed
+    /// 5. the entities hidden with despawn_soft that have no runs left are removed
+    /// 6. the components registered with register_transient are removed
+    /// 7. the indexes are synced, then WorldStats and the leak report are updated
+    /// 8. the extractor, then the End frame hooks
+    ///
+    /// Steps 5 and 6 are skipped while the world is paused
+    pub fn run(&mut self) {
+        self.run_frame(false);
     }
-}
 
-/// Components are the data that is stored on entities
-/// no need to implement this trait, it is implemented for all 'static types
-pub trait Component: 'static {}
+    /// Runs the world like run, but every system's run function gets its own copy of the versioned components
+    /// Systems do not see each other's changes to them, once every system has run the changes are merged
+    /// in the order the systems ran with the MergePolicy of each component, see register_versioned_component
+    /// A component is only copied when a system first changes it, and put back without hooks or op log entries
+    /// prestep and single_entity_step are not versioned, the rest of the run happens in the same order as run
+    pub fn run_versioned(&mut self) {
+        self.run_frame(true);
+    }
 
-impl<T: 'static> Component for T {}
+    // the body of run and run_versioned, versioned is passed on to run_systems
+    fn run_frame(&mut self, versioned: bool) {
+        self.frame_report.clear();
+        let paused = !self.take_frame();
+        let frame_start = self.start_span();
+        self.run_frame_hooks(FrameStage::Start);
+        if !paused {
+            self.store_previous_components();
+        }
+        self.update_resources_and_tasks(paused);
+        if !paused {
+            self.run_systems(None, versioned);
+        }
+        self.run_maintenance(paused);
+        self.extract();
+        self.run_frame_hooks(FrameStage::End);
+        self.finish_span("frame", "frame", frame_start);
+        self.entities_and_components.frame_arena.reset();
+    }
 
-/// Systems access and change components on objects
-/// Be careful to implement get_allow_entity_based_multithreading as true if you want to use the single_entity_step function
-/// If you don't it will still work but, it will be slower (in most cases)
-pub trait System: 'static + Sized {
-    /// This function can collect data that will be used in the single_entity_step function
-    /// This allows both functions to be called in parallel, without a data race
-    /// If you implement this function, make sure to implement implements_prestep as true
-    fn prestep(&mut self, engine: &EntitiesAndComponentsThreadSafe) {}
-    /// Should just return true or false based on whether or not the system implements the prestep function
-    fn implements_prestep(&self) -> bool {
-        false
+    /// Sets a function that is called at the end of every run, after all the systems,
+    /// to fill a render queue with what the renderer needs from the world
+    /// The queue starts as RenderQueue::default() every run, get it with get_render_queue or take_render_queue
+    /// This replaces any extractor that was set before
+    pub fn set_extractor<
+        RenderQueue: Default + Send + Sync + 'static,
+        F: FnMut(&EntitiesAndComponents, &mut RenderQueue) + Send + Sync + 'static,
+    >(
+        &mut self,
+        mut extractor: F,
+    ) {
+        self.extractor = Some(Box::new(move |entities_and_components| {
+            let mut render_queue = RenderQueue::default();
+            extractor(entities_and_components, &mut render_queue);
+            Box::new(render_queue)
+        }));
+        self.render_queue = None;
     }
-    /// If you implement this function, it will be called for each entity in parallel, but make sure to implement get_allow_single_entity_step as true
-    fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {}
-    /// Should just return true or false based on whether or not the system implements the single_entity_step function
-    fn implements_single_entity_step(&self) -> bool {
-        false
+
+    /// Removes the extractor and the render queue it made
+    pub fn remove_extractor(&mut self) {
+        self.extractor = None;
+        self.render_queue = None;
     }
-    /// This function is called after the single_entity_step function is called for all entities
-    fn run(&mut self, engine: &mut EntitiesAndComponents) {}
 
-    /// This function is used to downcast the system to an Any trait object
-    /// Should be automatically implemented
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
+    /// Gets the render queue the extractor made on the last run
+    /// Returns None if there is no extractor, it has not run yet, the queue was taken, or RenderQueue is the wrong type
+    pub fn get_render_queue<RenderQueue: 'static>(&self) -> Option<&RenderQueue> {
+        self.render_queue
+            .as_ref()
+            .and_then(|render_queue| render_queue.downcast_ref::<RenderQueue>())
     }
 
-    /// This function is used to downcast the system to an Any trait object
-    /// Should be automatically implemented
-    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+    /// Takes the render queue the extractor made on the last run, so it can be sent to a render thread
+    /// Returns None if there is no extractor, it has not run yet, the queue was taken, or RenderQueue is the wrong type
+    pub fn take_render_queue<RenderQueue: 'static>(&mut self) -> Option<RenderQueue> {
+        if !self.render_queue.as_ref()?.is::<RenderQueue>() {
+            return None;
+        }
+
+        self.render_queue
+            .take()
+            .and_then(|render_queue| render_queue.downcast::<RenderQueue>().ok())
+            .map(|render_queue| *render_queue)
+    }
+
+    fn extract(&mut self) {
+        if let Some(extractor) = &mut self.extractor {
+            self.render_queue = Some(extractor(&self.entities_and_components));
+        }
+    }
+
+    /// Makes every system get its own copy of a component in run_versioned
+    /// policy decides which change is kept when several systems change the same component
+    pub fn register_versioned_component<T: Component + Clone + PartialEq + Send + Sync>(
+        &mut self,
+        policy: MergePolicy<T>,
+    ) {
+        self.versioned_components
+            .insert(TypeId::of::<T>(), Box::new(Versioned::new(policy)));
+    }
+
+    fn update_resources_and_tasks(&mut self, paused: bool) {
+        self.entities_and_components.update_resources(paused);
+
+        // give the results of any finished tasks back to the world before the systems run
+        let completions = match self.entities_and_components.get_resource::<AsyncTasks>() {
+            Some(tasks) => tasks.poll(),
+            None => vec![],
+        };
+        for completion in completions {
+            completion(&mut self.entities_and_components);
+        }
+    }
+
+    /// Runs only the systems that were added to the group
+    /// single_entity_step will only be called for entities in the group, resources are not updated
+    pub fn run_systems_for_group(&mut self, group: GroupId) {
+        self.frame_report.clear();
+        self.run_systems(Some(group), false);
+    }
+
+    /// Runs single_entity_step of every system on one entity right away
+    /// Useful for setting up an entity that was just added without waiting for the next run
+    /// prestep and run are not called, so the systems use the data from their last prestep
+    /// Nothing is run on entities hidden with despawn_soft or reserved entities that were not spawned yet,
+    /// since systems never see them
+    /// Entities taken with SingleMutEntity::reserve_entities are spawned once the queued components were added, like in run
+    /// panics if the entity does not exist
+    pub fn run_systems_on(&mut self, entity: Entity) {
+        if !self.entities_and_components.does_entity_exist(entity) {
+            panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+        }
+        self.frame_report.clear();
+        if self.entities_and_components.is_hidden_entity(entity) {
+            return;
+        }
+
+        let skipped_sets = self.get_skipped_sets();
+        let systems_with_single_entity_step = self
+            .systems
+            .iter()
+            .filter(|(key, system)| {
+                let set_skipped = match self.system_sets.get(*key) {
+                    Some(set) => skipped_sets.contains(set),
+                    None => false,
+                };
+                system.implements_single_entity_step() && !set_skipped
+            })
+            .map(|(key, system)| {
+                (
+                    system,
+                    &self.system_locals[key],
+                    self.system_groups.get(key).copied(),
+                    self.system_markers.get(key).copied(),
+                    SystemSkips::new(
+                        &**system,
+                        self.system_exclusions.get(key),
+                        &self.entities_and_components,
+                    ),
+                )
+            })
+            .collect::<Vec<SingleEntityStepSystem>>();
+
+        let mut commands = CommandQueue::default();
+        let mut failures = vec![];
+        single_entity_steps(
+            &systems_with_single_entity_step,
+            &mut self.entities_and_components,
+            entity,
+            &mut commands,
+            &mut failures,
+            StepSettings {
+                panic_policy: self.panic_policy,
+                validators: self.component_validators.get_active(),
+                cells: None,
+            },
+        );
+        commands.apply(&mut self.entities_and_components);
+        self.entities_and_components.spawn_taken_entities();
+        self.report_failures(failures);
+    }
+
+    /// Gets every system failure from the last run, run_systems_for_group or run_systems_on
+    /// The report is cleared at the start of each of them, so it is empty after a paused run
+    pub fn get_frame_report(&self) -> &FrameReport {
+        &self.frame_report
+    }
+
+    /// Sets a function that is called every time a system returns an error
+    /// Failures are still added to the FrameReport
+    pub fn set_error_handler<F: Fn(&SystemFailure) + Send + Sync + 'static>(&mut self, handler: F) {
+        self.error_handler = Some(Box::new(handler));
+    }
+
+    fn report_failures(&mut self, failures: Vec<SystemFailure>) {
+        for failure in failures {
+            if let Some(error_handler) = &self.error_handler {
+                error_handler(&failure);
+            }
+            self.frame_report.push(failure);
+        }
+    }
+
+    // sets that are disabled or whose run conditions failed are skipped for the whole frame
+    fn get_skipped_sets(&self) -> Vec<&'static str> {
+        self.set_configs
+            .iter()
+            .filter(|(_, set_config)| !set_config.should_run(&self.entities_and_components))
+            .map(|(set, _)| *set)
+            .collect()
+    }
+
+    // counts down the frames of the systems added with add_system_with_interval
+    // returns the systems that are waiting and should not run this frame
+    fn advance_intervals(&mut self, group: Option<GroupId>) -> SecondaryMap<DefaultKey, ()> {
+        let mut waiting_systems = SecondaryMap::new();
+        for (system_id, interval) in self.system_intervals.iter_mut() {
+            if group.is_some() && self.system_groups.get(system_id) != group.as_ref() {
+                // systems that are not being run this time do not count the frame
+                continue;
+            }
+
+            if interval.frames_left == 0 {
+                interval.frames_left = interval.every_n_frames - 1;
+            } else {
+                interval.frames_left -= 1;
+                waiting_systems.insert(system_id, ());
+            }
+        }
+        waiting_systems
+    }
+
+    // runs every system if group is None, otherwise only the systems in the group
+    // if versioned is true each run function gets its own copy of the versioned components
+    fn run_systems(&mut self, group: Option<GroupId>, versioned: bool) {
+        self.entities_and_components.next_recording_frame();
+        self.entities_and_components.advance_change_tick();
+
+        if self.systems.is_empty() {
+            // the stages still happen without systems so the frame hooks are called the same way
+            self.entities_and_components.spawn_taken_entities();
+            self.run_frame_hooks(FrameStage::AfterPrestep);
+            self.run_frame_hooks(FrameStage::AfterSingleEntityStep);
+            self.run_frame_hooks(FrameStage::AfterRun);
+            return;
+        }
+
+        self.entities_and_components.refill_entity_reserve();
+        let skipped_sets = self.get_skipped_sets();
+        let waiting_systems = self.advance_intervals(group);
+        let mut frame_failures = vec![];
+
+        let system_groups = &self.system_groups;
+        let system_sets = &self.system_sets;
+        let is_selected = |system_id: DefaultKey| {
+            let in_group = match group {
+                Some(group) => system_groups.get(system_id) == Some(&group),
+                None => true,
+            };
+            let set_skipped = match system_sets.get(system_id) {
+                Some(set) => skipped_sets.contains(set),
+                None => false,
+            };
+
+            in_group && !set_skipped && !waiting_systems.contains_key(system_id)
+        };
+
+        let trace_capture = self.trace_capture.as_ref();
+
+        // run the prestep function for each systems in parallel
+        let resource_commands = {
+            let thread_safe_entities_and_components =
+                EntitiesAndComponentsThreadSafe::new(&mut self.entities_and_components);
+
+            // check which systems implement the prestep function and collect mutable references to them
+            let mut systems_with_prestep = self
+                .systems
+                .iter_mut()
+                .filter(|(key, system)| is_selected(*key) && system.implements_prestep())
+                .map(|(_, system)| system)
+                .collect::<Vec<&mut Box<dyn SystemWrapper + Sync + Send>>>();
+
+            systems_with_prestep.par_iter_mut().for_each(|system| {
+                let start = trace_capture.map(|_| std::time::Instant::now());
+                system.prestep(&thread_safe_entities_and_components);
+                if let (Some(trace_capture), Some(start)) = (trace_capture, start) {
+                    trace_capture.record(system.type_name(), "prestep", start, None);
+                }
+            });
+
+            thread_safe_entities_and_components
+                .resource_commands
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner)
+        };
+        resource_commands.apply(&mut self.entities_and_components);
+        self.entities_and_components.spawn_taken_entities();
+        self.frame_hooks
+            .run(FrameStage::AfterPrestep, &mut self.entities_and_components);
+
+        {
+            // check which systems implement the single_entity_step function and collect mutable references to them
+            // the list borrows the systems so it can't come from the frame arena, it is only a few systems long
+            let systems_with_single_entity_step = self
+                .systems
+                .iter()
+                .filter(|(key, system)| {
+                    is_selected(*key)
+                        && system.implements_single_entity_step()
+                        && !self.has_frame_budget(*key)
+                })
+                .map(|(key, system)| {
+                    (
+                        system,
+                        &self.system_locals[key],
+                        self.system_groups.get(key).copied(),
+                        self.system_markers.get(key).copied(),
+                        SystemSkips::new(
+                            &**system,
+                            self.system_exclusions.get(key),
+                            &self.entities_and_components,
+                        ),
+                    )
+                })
+                .collect::<Vec<SingleEntityStepSystem>>();
+
+            if !systems_with_single_entity_step.is_empty() {
+                /*let chunk_size = ((self.entities_and_components.get_entity_count())
+                / (self.num_cpus * 2))
+                .max(20);*/
+                let chunk_size = 5;
+
+                // run the single_entity_step function for each entity in parallel
+                // the buffer is reused every frame so that we don't allocate a new list of entities
+                self.entities_buffer.clear();
+                let frame_arena = self.entities_and_components.frame_alloc();
+                let mut markers = frame_arena.vec::<TypeId>();
+                if systems_with_single_entity_step
+                    .iter()
+                    .all(|(_, _, _, marker, _)| marker.map(|marker| markers.push(marker)).is_some())
+                {
+                    // every system is limited to a marker, so only the entities with one of them are visited
+                    self.entities_and_components
+                        .get_entities_with_any_type(&markers, &mut self.entities_buffer);
+                } else {
+                    self.entities_buffer
+                        .extend(self.entities_and_components.iter_entities());
+                }
+
+                // each level of the hierarchy is run after the one above it has finished,
+                // without any system that needs it every entity is in one level
+                let mut level_ends = frame_arena.vec::<usize>();
+                if systems_with_single_entity_step
+                    .iter()
+                    .any(|(system, _, _, _, _)| system.parents_before_children())
+                {
+                    let entities_and_components = &self.entities_and_components;
+                    self.entities_buffer
+                        .sort_by_cached_key(|entity| entities_and_components.get_depth(*entity));
+                    for (index, entity) in self.entities_buffer.iter().enumerate().skip(1) {
+                        let previous = self.entities_buffer[index - 1];
+                        if entities_and_components.get_depth(*entity)
+                            != entities_and_components.get_depth(previous)
+                        {
+                            level_ends.push(index);
+                        }
+                    }
+                }
+                level_ends.push(self.entities_buffer.len());
+
+                let entities_and_components_ptr = &mut self.entities_and_components as *mut _;
+                let entities_and_components_ptr = EntitiesAndComponentPtr {
+                    entities_and_components: entities_and_components_ptr,
+                };
+
+                // other entities' cells are read from here, their components may be changing on another thread
+                let cells = CellHandles::gather(&self.entities_and_components);
+                let settings = StepSettings {
+                    panic_policy: self.panic_policy,
+                    validators: self.component_validators.get_active(),
+                    cells: Some(&cells),
+                };
+
+                // each chunk records its own commands and failures, they are applied in the order of the chunks
+                let run_chunk = |entity_chunk: &mut [Entity]| {
+                    let start = trace_capture.map(|_| std::time::Instant::now());
+                    let chunk_len = entity_chunk.len();
+                    let mut entities_and_components_ptr = entities_and_components_ptr.clone();
+                    let mut commands = CommandQueue::default();
+                    let mut failures = vec![];
+                    for entity in entity_chunk {
+                        let entities_and_components =
+                            unsafe { entities_and_components_ptr.as_mut() };
+
+                        single_entity_steps(
+                            &systems_with_single_entity_step,
+                            entities_and_components,
+                            *entity,
+                            &mut commands,
+                            &mut failures,
+                            settings,
+                        );
+                    }
+                    if let (Some(trace_capture), Some(start)) = (trace_capture, start) {
+                        trace_capture.record(
+                            "single_entity_step",
+                            "single_entity_step",
+                            start,
+                            Some(chunk_len),
+                        );
+                    }
+                    (commands, failures)
+                };
+
+                let mut chunk_results = frame_arena.vec::<(CommandQueue, Vec<SystemFailure>)>();
+                let mut level_start = 0;
+                for level_end in level_ends.drain(..) {
+                    chunk_results.par_extend(
+                        self.entities_buffer[level_start..level_end]
+                            .par_chunks_mut(chunk_size)
+                            .map(run_chunk),
+                    );
+                    level_start = level_end;
+                }
+
+                for (commands, failures) in chunk_results.drain(..) {
+                    commands.apply(&mut self.entities_and_components);
+                    frame_failures.extend(failures);
+                }
+                self.entities_and_components.spawn_taken_entities();
+            }
+        }
+
+        let mut systems_to_run = self
+            .systems
+            .keys()
+            .filter(|key| is_selected(*key))
+            .collect::<Vec<DefaultKey>>();
+
+        // systems with a frame budget step their entities one at a time until the budget is used up
+        let budgeted_systems = systems_to_run
+            .iter()
+            .copied()
+            .filter(|key| {
+                self.systems[*key].implements_single_entity_step() && self.has_frame_budget(*key)
+            })
+            .collect::<Vec<DefaultKey>>();
+        self.run_budgeted_single_entity_steps(&budgeted_systems, &mut frame_failures);
+        self.frame_hooks.run(
+            FrameStage::AfterSingleEntityStep,
+            &mut self.entities_and_components,
+        );
+
+        // the run functions are called in the order of their sets
+        systems_to_run.sort_by_key(|key| {
+            self.system_sets
+                .get(*key)
+                .and_then(|set| self.set_configs.get(set))
+                .map_or(0, |set_config| set_config.get_order())
+        });
+
+        if versioned {
+            let mut copies = FxHashMap::default();
+            for (type_id, versioned_component) in self.versioned_components.iter_mut() {
+                versioned_component.begin();
+                copies.insert(*type_id, versioned_component.copy_function());
+            }
+            self.entities_and_components.start_versioned_changes(copies);
+        }
+
+        // read only systems that run one after another are run at the same time
+        let mut read_only_batch = vec![];
+        for key in systems_to_run {
+            if self.is_read_only_system(key) {
+                read_only_batch.push(key);
+                continue;
+            }
+            self.run_read_only_systems(&read_only_batch, &mut frame_failures);
+            read_only_batch.clear();
+
+            let start = self.start_timing();
+            let span_start = self.start_span();
+            let system = &mut self.systems[key];
+            let system_name = system.type_name();
+            self.entities_and_components
+                .set_recording_system(Some(system_name));
+            let result = system.try_run(&mut self.entities_and_components);
+            self.entities_and_components.set_recording_system(None);
+            self.finish_timing(system_name, start);
+            self.finish_span(system_name, "run", span_start);
+            if let Err(error) = result {
+                frame_failures.push(SystemFailure {
+                    system: system_name,
+                    entity: None,
+                    error,
+                });
+            }
+            if self.component_validators.is_active() {
+                self.component_validators.check_all(
+                    &self.entities_and_components,
+                    system_name,
+                    &mut frame_failures,
+                );
+            }
+
+            if versioned {
+                let changes = self.entities_and_components.take_versioned_changes();
+                for (type_id, originals) in changes {
+                    if let Some(versioned_component) = self.versioned_components.get_mut(&type_id) {
+                        versioned_component.collect(&mut self.entities_and_components, originals);
+                    }
+                }
+            }
+        }
+        self.run_read_only_systems(&read_only_batch, &mut frame_failures);
+
+        if versioned {
+            self.entities_and_components.stop_versioned_changes();
+            for versioned_component in self.versioned_components.values_mut() {
+                versioned_component.finish(&mut self.entities_and_components);
+            }
+        }
+        self.run_frame_hooks(FrameStage::AfterRun);
+
+        self.report_failures(frame_failures);
+    }
+
+    /// Creates a pool of entities that can be reused instead of being removed and added again
+    /// capacity entities are made up front with create, reset is called on an entity every time it is reused
+    pub fn create_pool<T: OwnedComponents<Input = T>>(
+        &mut self,
+        capacity: usize,
+        create: impl Fn() -> T + 'static,
+        reset: impl Fn(Entity, &mut EntitiesAndComponents) + 'static,
+    ) -> EntityPool<T> {
+        EntityPool::new(
+            &mut self.entities_and_components,
+            capacity,
+            Box::new(create),
+            Box::new(reset),
+        )
+    }
+
+    /// Moves all the entities, components and parent/child links from another world into this one
+    /// Only the component types registered with register_component in either world, or with register_map_entities,
+    /// are moved, the rest are dropped
+    /// If include_systems is true the systems of the other world (and their Locals) are moved over as well
+    /// Resources are not moved, entities hidden with despawn_soft in the other world are removed for good first
+    /// Returns a map from the entities in the other world to the new entities in this world
+    pub fn merge(&mut self, mut other: World, include_systems: bool) -> EntityMap {
+        let mut entity_map = EntityMap::new();
+        // a moved parent would otherwise keep the old id of a hidden child in its Children
+        other.entities_and_components.remove_soft_despawned();
+
+        for (type_id, name) in other.entities_and_components.component_names.drain() {
+            self.entities_and_components
+                .component_names
+                .entry(type_id)
+                .or_insert(name);
+        }
+        for (type_id, map_entities) in other.entities_and_components.entity_mappers.drain() {
+            self.entities_and_components
+                .entity_mappers
+                .entry(type_id)
+                .or_insert(map_entities);
+        }
+
+        let movable = self
+            .entities_and_components
+            .registered_components
+            .keys()
+            .chain(other.entities_and_components.registered_components.keys())
+            .chain(self.entities_and_components.entity_mappers.keys())
+            .copied()
+            .collect::<FxHashSet<TypeId>>();
+        let other_entities = other.entities_and_components.get_entities();
+        for old_entity in other_entities {
+            let mut components = other
+                .entities_and_components
+                .components
+                .remove(old_entity.entity_id)
+                .unwrap_or_else(|| {
+                    panic!("Entity ID {old_entity:?} does not exist, was the Entity ID edited?");
+                });
+            // safe because components are only removed, the rest stay under their own TypeId
+            unsafe {
+                components.as_raw_mut().retain(|type_id, _| {
+                    movable.contains(type_id) || inspect::is_internal_component(*type_id)
+                });
+            }
+
+            let new_entity = self
+                .entities_and_components
+                .add_entity_with_component_map(components);
+            entity_map.insert(old_entity, new_entity);
+        }
+
+        // the parent/child links and the registered components still point at the entities in the other world
+        self.entities_and_components.remap_hierarchy(&entity_map);
+        self.entities_and_components.map_moved_entities(&entity_map);
+        // the links came with the components, so they are recorded here instead of by set_parent
+        for (_, new_entity) in entity_map.iter() {
+            if let Some(parent) = self.entities_and_components.get_parent(new_entity) {
+                self.entities_and_components
+                    .record_op(StructuralOp::SetParent {
+                        child: new_entity,
+                        parent,
+                    });
+            }
+        }
+        for (_, new_entity) in entity_map.iter() {
+            self.entities_and_components.run_spawn_hooks(new_entity);
+        }
+
+        if include_systems {
+            let other_system_ids = other.systems.iter().map(|(key, _)| key).collect::<Vec<_>>();
+            for other_system_id in other_system_ids {
+                let system = other.systems.remove(other_system_id).unwrap();
+                let system_locals = other
+                    .system_locals
+                    .remove(other_system_id)
+                    .unwrap_or_default();
+
+                let system_id = self.systems.insert(system);
+                self.system_locals.insert(system_id, system_locals);
+                if let Some(group) = other.system_groups.remove(other_system_id) {
+                    self.system_groups.insert(system_id, group);
+                }
+                if let Some(marker) = other.system_markers.remove(other_system_id) {
+                    self.system_markers.insert(system_id, marker);
+                }
+                if let Some(excluded) = other.system_exclusions.remove(other_system_id) {
+                    self.system_exclusions
+                        .insert(system_id, exclude::remap_excluded(&excluded, &entity_map));
+                }
+                if let Some(interval) = other.system_intervals.remove(other_system_id) {
+                    self.system_intervals.insert(system_id, interval);
+                }
+                if let Some(set) = other.system_sets.remove(other_system_id) {
+                    self.system_sets.insert(system_id, set);
+                }
+                if let Some(runner) = other.read_only_systems.remove(other_system_id) {
+                    self.read_only_systems.insert(system_id, runner);
+                }
+                if let Some(frame_budget) = other.frame_budgets.remove(other_system_id) {
+                    self.frame_budgets.insert(system_id, frame_budget);
+                }
+            }
+
+            // sets that already exist in this world keep their settings
+            for (set, set_config) in other.set_configs.drain() {
+                self.set_configs.entry(set).or_insert(set_config);
+            }
+        }
+
+        entity_map
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Components are the data that is stored on entities
+/// no need to implement this trait, it is implemented for all 'static types
+pub trait Component: 'static {}
+
+impl<T: 'static> Component for T {}
+
+/// Systems access and change components on objects
+/// Be careful to implement get_allow_entity_based_multithreading as true if you want to use the single_entity_step function
+/// If you don't it will still work but, it will be slower (in most cases)
+pub trait System: 'static + Sized {
+    /// This function can collect data that will be used in the single_entity_step function
+    /// This allows both functions to be called in parallel, without a data race
+    /// If you implement this function, make sure to implement implements_prestep as true
+    fn prestep(&mut self, engine: &EntitiesAndComponentsThreadSafe) {}
+    /// Should just return true or false based on whether or not the system implements the prestep function
+    fn implements_prestep(&self) -> bool {
+        false
+    }
+    /// If you implement this function, it will be called for each entity in parallel, but make sure to implement get_allow_single_entity_step as true
+    fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {}
+    /// Like single_entity_step but can return an error, the error is added to the FrameReport
+    /// By default this calls single_entity_step, implement one or the other
+    /// implements_single_entity_step still has to return true for this to be called
+    fn try_single_entity_step(
+        &self,
+        single_entity: &mut SingleMutEntity,
+    ) -> Result<(), SystemError> {
+        self.single_entity_step(single_entity);
+        Ok(())
+    }
+    /// Should just return true or false based on whether or not the system implements the single_entity_step function
+    fn implements_single_entity_step(&self) -> bool {
+        false
+    }
+    /// Return true to run single_entity_step on parents before their children,
+    /// this lets the step read the parent's components with SingleMutEntity::get_parent_component
+    /// Entities are then run one hierarchy level at a time, which is slower for deep hierarchies
+    fn parents_before_children(&self) -> bool {
+        false
+    }
+    /// This function is called after the single_entity_step function is called for all entities
+    fn run(&mut self, engine: &mut EntitiesAndComponents) {}
+    /// Like run but can return an error, the error is added to the FrameReport
+    /// By default this calls run, implement one or the other
+    fn try_run(&mut self, engine: &mut EntitiesAndComponents) -> Result<(), SystemError> {
+        self.run(engine);
+        Ok(())
+    }
+    /// Declares the components the system reads and writes, it is only used by World::export_schedule_graphviz
+    /// and World::export_schedule_json, so it does not have to be implemented
+    fn component_access(&self, _access: &mut ComponentAccess) {}
+
+    /// This function is used to downcast the system to an Any trait object
+    /// Should be automatically implemented
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    /// This function is used to downcast the system to an Any trait object
+    /// Should be automatically implemented
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
 }
@@ -1073,9 +4194,17 @@ pub trait System: 'static + Sized {
 trait SystemWrapper {
     fn prestep(&mut self, engine: &EntitiesAndComponentsThreadSafe);
     fn implements_prestep(&self) -> bool;
-    fn single_entity_step(&self, single_entity: &mut SingleMutEntity);
+    fn try_single_entity_step(
+        &self,
+        single_entity: &mut SingleMutEntity,
+    ) -> Result<(), SystemError>;
     fn implements_single_entity_step(&self) -> bool;
-    fn run(&mut self, engine: &mut EntitiesAndComponents);
+    fn parents_before_children(&self) -> bool;
+    // the type of SkipSystem<Self>
+    fn skip_marker(&self) -> TypeId;
+    fn try_run(&mut self, engine: &mut EntitiesAndComponents) -> Result<(), SystemError>;
+    fn type_name(&self) -> &'static str;
+    fn component_access(&self, access: &mut ComponentAccess);
     fn as_any(&self) -> &dyn std::any::Any;
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
 }
@@ -1087,14 +4216,29 @@ impl<T: System> SystemWrapper for T {
     fn implements_prestep(&self) -> bool {
         System::implements_prestep(self)
     }
-    fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
-        System::single_entity_step(self, single_entity);
+    fn try_single_entity_step(
+        &self,
+        single_entity: &mut SingleMutEntity,
+    ) -> Result<(), SystemError> {
+        System::try_single_entity_step(self, single_entity)
     }
     fn implements_single_entity_step(&self) -> bool {
         System::implements_single_entity_step(self)
     }
-    fn run(&mut self, engine: &mut EntitiesAndComponents) {
-        System::run(self, engine);
+    fn parents_before_children(&self) -> bool {
+        System::parents_before_children(self)
+    }
+    fn skip_marker(&self) -> TypeId {
+        TypeId::of::<SkipSystem<T>>()
+    }
+    fn try_run(&mut self, engine: &mut EntitiesAndComponents) -> Result<(), SystemError> {
+        System::try_run(self, engine)
+    }
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+    fn component_access(&self, access: &mut ComponentAccess) {
+        System::component_access(self, access);
     }
     fn as_any(&self) -> &dyn std::any::Any {
         System::as_any(self)
@@ -1296,48 +4440,215 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn test_generation_values() {
+    fn test_weak_entities() {
         let mut engine = World::new();
         let entities_and_components = &mut engine.entities_and_components;
 
-        let entity_1 = entities_and_components.add_entity();
-        let entity_2 = entities_and_components.add_entity();
+        let entity = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let weak_entity = entity.downgrade();
 
-        entities_and_components.add_component_to(entity_1, Position { x: 0.0, y: 0.0 });
-        entities_and_components.add_component_to(entity_1, Velocity { x: 1.0, y: 1.0 });
+        assert!(weak_entity.is_alive(entities_and_components));
+        assert_eq!(weak_entity.upgrade(entities_and_components), Some(entity));
 
-        entities_and_components.add_component_to(entity_2, Position { x: 0.0, y: 0.0 });
-        entities_and_components.add_component_to(entity_2, Velocity { x: 1.0, y: 1.0 });
+        entities_and_components.remove_entity(entity);
 
-        // remove the first entity
-        entities_and_components.remove_entity(entity_1);
+        // the new entity may reuse the slot but the generation will be different
+        entities_and_components.add_entity();
 
-        // add a new entity
-        let entity_3 = entities_and_components.add_entity();
+        assert!(!weak_entity.is_alive(entities_and_components));
+        assert_eq!(entities_and_components.upgrade_entity(weak_entity), None);
+    }
 
-        // make sure the new entity doesn't have the old entity's components
-        let (position, velocity) =
-            entities_and_components.try_get_components::<(Position, Velocity)>(entity_3);
+    #[test]
+    fn test_required_components() {
+        #[derive(Default, Debug, PartialEq)]
+        struct Mass(f32);
 
-        assert_eq!(position, None);
-        assert_eq!(velocity, None);
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
 
-        // this line should panic, there is no entity with the id of entity_1 because the generation value should be different
-        let (position, velocity) =
-            entities_and_components.try_get_components::<(Position, Velocity)>(entity_1);
+        entities_and_components
+            .register_required_component_with::<Velocity, Position>(|| Position { x: 5.0, y: 5.0 });
+        entities_and_components.register_required_component::<Position, Mass>();
+
+        let entity = entities_and_components.add_entity();
+        entities_and_components.add_component_to(entity, Velocity { x: 1.0, y: 1.0 });
+
+        // the requirements should be followed all the way down
+        let (position, mass) = entities_and_components.get_components::<(Position, Mass)>(entity);
+        assert_eq!(position.x, 5.0);
+        assert_eq!(*mass, Mass(0.0));
+
+        // existing components should not be overwritten
+        let entity = entities_and_components.add_entity_with((Position { x: 1.0, y: 1.0 },));
+        entities_and_components.add_component_to(entity, Velocity { x: 1.0, y: 1.0 });
+        let (position,) = entities_and_components.get_components::<(Position,)>(entity);
+        assert_eq!(position.x, 1.0);
     }
 
     #[test]
-    fn test_resources() {
-        struct TestResource {
-            value: i32,
+    fn test_typed_entities() {
+        struct Player;
+
+        impl Marker for Player {
+            type Components = (Position, Velocity);
         }
 
-        impl Resource for TestResource {
-            fn update(&mut self) {
-                self.value += 1;
-            }
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let player = entities_and_components.add_typed_entity(
+            Player,
+            (Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 2.0 }),
+        );
+
+        let (position, velocity) = player.get_mut(entities_and_components);
+        position.x += velocity.x;
+        position.y += velocity.y;
+
+        let (position, _) = player.get(entities_and_components);
+        assert_eq!(*position, Position { x: 1.0, y: 2.0 });
+
+        let untyped = player.entity();
+        assert_eq!(
+            entities_and_components.try_get_typed_entity::<Player>(untyped),
+            Some(player)
+        );
+
+        let not_a_player = entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 2.0 }));
+        assert_eq!(
+            entities_and_components.try_get_typed_entity::<Player>(not_a_player),
+            None
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let entity = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let entity_2 = entities_and_components.add_entity_with((Velocity { x: 1.0, y: 1.0 },));
+
+        let mut column = entities_and_components.export_column::<Position>();
+        assert_eq!(column, vec![(entity, Position { x: 0.0, y: 0.0 })]);
+
+        column[0].1.x = 5.0;
+        column.push((entity_2, Position { x: 2.0, y: 2.0 }));
+        entities_and_components.import_column(column);
+
+        let (position,) = entities_and_components.get_components::<(Position,)>(entity);
+        assert_eq!(position.x, 5.0);
+        let (position, velocity) =
+            entities_and_components.get_components::<(Position, Velocity)>(entity_2);
+        assert_eq!(position.x, 2.0);
+        assert_eq!(velocity.x, 1.0);
+    }
+
+    #[test]
+    fn test_world_merge() {
+        struct NotMovable;
+
+        let mut world = World::new();
+        world.register_component::<Position>();
+        world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+
+        let mut other = World::new();
+        other.register_component::<Velocity>();
+        let other_entities_and_components = &mut other.entities_and_components;
+        let parent = other_entities_and_components.add_entity_with((
+            Position { x: 1.0, y: 1.0 },
+            Velocity { x: 1.0, y: 1.0 },
+            NotMovable,
+        ));
+        let child = other_entities_and_components.add_entity_with((Velocity { x: 2.0, y: 2.0 },));
+        other_entities_and_components.set_parent(child, parent);
+        other.add_system(MovementSystem {});
+
+        let entity_map = world.merge(other, true);
+        assert_eq!(entity_map.len(), 2);
+
+        let new_parent = entity_map.get(parent).unwrap();
+        let new_child = entity_map.get(child).unwrap();
+
+        let entities_and_components = &world.entities_and_components;
+        assert_eq!(entities_and_components.get_entity_count(), 3);
+        assert_eq!(
+            entities_and_components.get_entity_count_with_component::<Position>(),
+            2
+        );
+        assert_eq!(
+            entities_and_components.get_entity_count_with_component::<Velocity>(),
+            2
+        );
+        // the type that is not registered in either world is left behind
+        assert_eq!(
+            entities_and_components.get_entity_count_with_component::<NotMovable>(),
+            0
+        );
+        assert!(entities_and_components
+            .try_get_component::<NotMovable>(new_parent)
+            .is_none());
+        assert_eq!(
+            entities_and_components.get_children(new_parent),
+            vec![new_child]
+        );
+        assert_eq!(
+            entities_and_components.get_parent(new_child),
+            Some(new_parent)
+        );
+
+        // the system from the other world should have been moved over as well
+        assert_eq!(world.systems.len(), 1);
+        assert_eq!(world.system_locals.len(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_generation_values() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let entity_1 = entities_and_components.add_entity();
+        let entity_2 = entities_and_components.add_entity();
+
+        entities_and_components.add_component_to(entity_1, Position { x: 0.0, y: 0.0 });
+        entities_and_components.add_component_to(entity_1, Velocity { x: 1.0, y: 1.0 });
+
+        entities_and_components.add_component_to(entity_2, Position { x: 0.0, y: 0.0 });
+        entities_and_components.add_component_to(entity_2, Velocity { x: 1.0, y: 1.0 });
+
+        // remove the first entity
+        entities_and_components.remove_entity(entity_1);
+
+        // add a new entity
+        let entity_3 = entities_and_components.add_entity();
+
+        // make sure the new entity doesn't have the old entity's components
+        let (position, velocity) =
+            entities_and_components.try_get_components::<(Position, Velocity)>(entity_3);
+
+        assert_eq!(position, None);
+        assert_eq!(velocity, None);
+
+        // this line should panic, there is no entity with the id of entity_1 because the generation value should be different
+        let (position, velocity) =
+            entities_and_components.try_get_components::<(Position, Velocity)>(entity_1);
+    }
+
+    #[test]
+    fn test_resources() {
+        struct TestResource {
+            value: i32,
+        }
+
+        impl Resource for TestResource {
+            fn update(&mut self) {
+                self.value += 1;
+            }
 
             fn as_any(&self) -> &dyn Any {
                 self
@@ -1378,6 +4689,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resource_initialization() {
+        #[derive(Default)]
+        struct Score {
+            value: i32,
+        }
+
+        impl Resource for Score {}
+
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        assert!(entities_and_components.get_resource::<Score>().is_none());
+
+        entities_and_components.init_resource::<Score>();
+        assert_eq!(
+            entities_and_components
+                .get_resource::<Score>()
+                .unwrap()
+                .value,
+            0
+        );
+
+        entities_and_components
+            .get_resource_or_insert_with(|| Score { value: 10 })
+            .value += 1;
+
+        // the resource already existed so it should not have been replaced
+        assert_eq!(
+            entities_and_components
+                .get_resource::<Score>()
+                .unwrap()
+                .value,
+            1
+        );
+
+        entities_and_components.init_resource::<Score>();
+        assert_eq!(
+            entities_and_components
+                .get_resource::<Score>()
+                .unwrap()
+                .value,
+            1
+        );
+    }
+
     #[test]
     fn test_parallel_systems() {
         let mut engine = World::new();
@@ -1414,6 +4771,44 @@ mod tests {
         }
     }
 
+    struct CountingSystem {}
+
+    impl System for CountingSystem {
+        fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+            *single_entity.get_local::<usize>().lock() += 1;
+        }
+        fn implements_single_entity_step(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_system_locals() {
+        let mut engine = World::new();
+
+        for _ in 0..20 {
+            engine
+                .entities_and_components
+                .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        }
+
+        let system = engine.add_system(CountingSystem {});
+
+        assert_eq!(engine.get_local_mut::<usize>(&system), None);
+
+        for _ in 0..3 {
+            engine.run();
+        }
+
+        assert_eq!(engine.get_local_mut::<usize>(&system), Some(&mut 60));
+
+        engine.reset_locals(&system);
+        assert_eq!(engine.get_local_mut::<usize>(&system), None);
+
+        engine.run();
+        assert_eq!(engine.get_local_mut::<usize>(&system), Some(&mut 20));
+    }
+
     struct PrestepSystem {
         postions: Vec<Position>,
     }
@@ -1423,7 +4818,7 @@ mod tests {
             self.postions.clear();
 
             for entity in engine.get_entities_with_component::<Position>() {
-                let (position,) = engine.get_components::<(Position,)>(*entity);
+                let (position,) = engine.get_components::<(Position,)>(entity);
                 self.postions.push(position.clone());
             }
         }
@@ -1623,7 +5018,7 @@ mod tests {
         entities_and_components.add_component_to(
             parent,
             Children {
-                children: vec![child],
+                children: SmallVec::from_slice(&[child]),
             },
         );
         entities_and_components.add_component_to(child, Parent(parent));
@@ -1644,6 +5039,5133 @@ mod tests {
         assert_eq!(parent, None);
     }
 
+    #[test]
+    fn test_groups() {
+        const MENU: GroupId = GroupId(0);
+        const LEVEL: GroupId = GroupId(1);
+
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let menu_entity = entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+        let level_entity = entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+        let ungrouped_entity = entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+
+        entities_and_components.assign_group(menu_entity, MENU);
+        entities_and_components.assign_group(level_entity, LEVEL);
+
+        assert_eq!(entities_and_components.get_group(menu_entity), Some(MENU));
+        assert_eq!(entities_and_components.get_group(ungrouped_entity), None);
+        assert_eq!(
+            entities_and_components.get_entities_in_group(LEVEL),
+            vec![level_entity]
+        );
+
+        engine.add_system_to_group(ParallelMovementSystem {}, LEVEL);
+
+        // only the level systems should run and only on the level entities
+        engine.run_systems_for_group(LEVEL);
+        engine.run_systems_for_group(MENU);
+
+        let entities_and_components = &mut engine.entities_and_components;
+        let (position,) = entities_and_components.get_components::<(Position,)>(level_entity);
+        assert_eq!(position.x, 1.0);
+        let (position,) = entities_and_components.get_components::<(Position,)>(menu_entity);
+        assert_eq!(position.x, 0.0);
+        let (position,) = entities_and_components.get_components::<(Position,)>(ungrouped_entity);
+        assert_eq!(position.x, 0.0);
+
+        // a normal run still keeps grouped systems to their group
+        engine.run();
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(level_entity);
+        assert_eq!(position.x, 2.0);
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(ungrouped_entity);
+        assert_eq!(position.x, 0.0);
+    }
+
+    #[test]
+    fn test_inspect() {
+        #[derive(Default)]
+        struct Score {}
+
+        impl Resource for Score {}
+
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        entities_and_components.init_resource::<Score>();
+        let parent = entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+        let child = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        entities_and_components.set_parent(child, parent);
+
+        let inspection = engine.inspect();
+
+        assert_eq!(inspection.entities.len(), 2);
+        assert_eq!(
+            inspection.get_entity(parent).unwrap().components,
+            vec![
+                std::any::type_name::<Position>(),
+                std::any::type_name::<Velocity>()
+            ]
+        );
+        // the hierarchy is not shown as components
+        assert_eq!(
+            inspection.get_entity(child).unwrap().components,
+            vec![std::any::type_name::<Position>()]
+        );
+        assert_eq!(inspection.hierarchy, vec![(parent, child)]);
+        assert_eq!(inspection.get_children(parent), vec![child]);
+        assert_eq!(inspection.resources, vec![std::any::type_name::<Score>()]);
+    }
+
+    #[test]
+    fn test_modify() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let entity = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+
+        let x = entities_and_components.modify(entity, |position: &mut Position| {
+            position.x += 2.0;
+            position.x
+        });
+        assert_eq!(x, 2.0);
+
+        let (position,) = entities_and_components.get_components::<(Position,)>(entity);
+        assert_eq!(position.x, 2.0);
+    }
+
+    #[test]
+    fn test_modify_change_tracking() {
+        let mut engine = World::new();
+        engine
+            .entities_and_components
+            .track_changes_with_undo::<Position>();
+        let entity = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let empty = engine.entities_and_components.add_entity();
+        engine.run();
+
+        let entities_and_components = &mut engine.entities_and_components;
+        assert_eq!(entities_and_components.change_tick(), 1);
+        // adding the component was a change too
+        assert_eq!(
+            entities_and_components.get_change_tick::<Position>(entity),
+            Some(0)
+        );
+
+        // both changes happen in the same tick so only the value from before the first one is kept
+        entities_and_components.modify(entity, |position: &mut Position| position.x = 1.0);
+        entities_and_components.modify(entity, |position: &mut Position| position.x = 2.0);
+        assert_eq!(
+            entities_and_components.get_change_tick::<Position>(entity),
+            Some(1)
+        );
+        entities_and_components.add_component_to(empty, Position { x: 5.0, y: 0.0 });
+
+        // undoing the add removes the component again
+        assert!(entities_and_components.undo_last_change());
+        assert!(entities_and_components
+            .try_get_component::<Position>(empty)
+            .is_none());
+        assert!(entities_and_components.undo_last_change());
+        assert_eq!(
+            *entities_and_components
+                .get_components::<(Position,)>(entity)
+                .0,
+            Position { x: 0.0, y: 0.0 }
+        );
+        assert!(entities_and_components.undo_last_change());
+        assert!(entities_and_components
+            .try_get_component::<Position>(entity)
+            .is_none());
+        assert!(!entities_and_components.undo_last_change());
+    }
+
+    #[test]
+    fn test_par_fold() {
+        let mut engine = World::new();
+
+        for i in 0..200 {
+            engine.entities_and_components.add_entity_with((Position {
+                x: i as f32,
+                y: -(i as f32),
+            },));
+        }
+        engine.entities_and_components.add_entity();
+
+        let thread_safe = EntitiesAndComponentsThreadSafe::new(&mut engine.entities_and_components);
+
+        // the bounding box of all the positions as (min x, max x, min y, max y)
+        let bounds = thread_safe.par_fold::<Position, _, _, _, _>(
+            || (f32::MAX, f32::MIN, f32::MAX, f32::MIN),
+            |bounds, _, position| {
+                (
+                    bounds.0.min(position.x),
+                    bounds.1.max(position.x),
+                    bounds.2.min(position.y),
+                    bounds.3.max(position.y),
+                )
+            },
+            |a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)),
+        );
+        assert_eq!(bounds, (0.0, 199.0, -199.0, 0.0));
+
+        let count = thread_safe.par_fold::<Position, _, _, _, _>(
+            || 0,
+            |count, _, _| count + 1,
+            |a, b| a + b,
+        );
+        assert_eq!(count, 200);
+    }
+
+    impl SpatialPosition for Position {
+        fn spatial_position(&self) -> (f32, f32) {
+            (self.x, self.y)
+        }
+    }
+
+    #[test]
+    fn test_spatial_grid() {
+        let mut engine = World::new();
+        engine.add_spatial_grid::<Position>(10.0);
+
+        let near = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 1.0, y: 1.0 },));
+        let far = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 25.0, y: -5.0 },));
+
+        engine.run();
+        let grid = engine
+            .entities_and_components
+            .get_resource::<SpatialGrid<Position>>()
+            .unwrap();
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid.entities_in_aabb((0.0, 0.0), (5.0, 5.0)), vec![near]);
+        assert_eq!(grid.entities_in_aabb((20.0, -10.0), (30.0, 0.0)), vec![far]);
+
+        // moved and removed entities are picked up on the next run
+        engine
+            .entities_and_components
+            .modify(near, |position: &mut Position| position.x = 22.0);
+        engine.entities_and_components.remove_entity(far);
+        engine.run();
+        let grid = engine
+            .entities_and_components
+            .get_resource::<SpatialGrid<Position>>()
+            .unwrap();
+        assert_eq!(grid.len(), 1);
+        assert!(grid.entities_in_aabb((0.0, 0.0), (5.0, 5.0)).is_empty());
+        assert_eq!(grid.entities_in_aabb((20.0, 0.0), (30.0, 5.0)), vec![near]);
+    }
+
+    #[test]
+    fn test_queued_commands() {
+        struct SplitSystem {}
+
+        impl System for SplitSystem {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                let Some(position) = single_entity.try_get_component::<Position>() else {
+                    return;
+                };
+
+                if position.x > 0.0 {
+                    // split the entity into two new ones
+                    let x = position.x;
+                    single_entity.queue_spawn((Position { x: x / 2.0, y: 0.0 },));
+                    single_entity.queue_spawn((Position {
+                        x: -x / 2.0,
+                        y: 0.0,
+                    },));
+                    single_entity.remove_entity();
+                } else {
+                    single_entity.add_component(Velocity { x: 0.0, y: 0.0 });
+                }
+
+                // the changes are not applied until the parallel step is over
+                assert!(!single_entity.has_component::<Velocity>());
+            }
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let mut engine = World::new();
+        engine
+            .entities_and_components
+            .add_entity_with((Position { x: 4.0, y: 0.0 },));
+        engine.add_system(SplitSystem {});
+
+        engine.run();
+
+        let entities_and_components = &engine.entities_and_components;
+        assert_eq!(entities_and_components.get_entity_count(), 2);
+        assert_eq!(
+            entities_and_components.get_entity_count_with_component::<Velocity>(),
+            0
+        );
+
+        engine.run();
+        assert_eq!(engine.entities_and_components.get_entity_count(), 3);
+        assert_eq!(
+            engine
+                .entities_and_components
+                .get_entity_count_with_component::<Velocity>(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_read_view() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        entities_and_components.register_view_component::<Position>();
+        let entity = entities_and_components
+            .add_entity_with((Position { x: 1.0, y: 2.0 }, Velocity { x: 0.0, y: 0.0 }));
+        // entities hidden with despawn_soft are not in the view
+        let hidden = entities_and_components.add_entity_with((Position { x: 3.0, y: 4.0 },));
+        entities_and_components.despawn_soft(hidden);
+
+        let view = engine.read_view();
+
+        // the live world can keep changing without affecting the view
+        engine
+            .entities_and_components
+            .modify(entity, |position: &mut Position| position.x = 5.0);
+        engine.entities_and_components.add_entity();
+
+        let handle = std::thread::spawn(move || {
+            assert_eq!(view.get_entity_count(), 1);
+            assert_eq!(view.iter_entities().collect::<Vec<_>>(), vec![entity]);
+            assert!(view.does_entity_exist(entity));
+            assert!(!view.does_entity_exist(hidden));
+            assert_eq!(
+                view.try_get_component::<Position>(entity),
+                Some(&Position { x: 1.0, y: 2.0 })
+            );
+            assert_eq!(view.iter_component::<Position>().count(), 1);
+        });
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_async_tasks() {
+        // a future that is pending the first time it is polled
+        struct YieldOnce {
+            yielded: bool,
+        }
+
+        impl std::future::Future for YieldOnce {
+            type Output = ();
+
+            fn poll(
+                mut self: std::pin::Pin<&mut Self>,
+                _: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<()> {
+                if self.yielded {
+                    std::task::Poll::Ready(())
+                } else {
+                    self.yielded = true;
+                    std::task::Poll::Pending
+                }
+            }
+        }
+
+        let mut engine = World::new();
+        engine.entities_and_components.init_resource::<AsyncTasks>();
+        let entity = engine.entities_and_components.add_entity();
+
+        let tasks = engine
+            .entities_and_components
+            .get_resource::<AsyncTasks>()
+            .unwrap();
+        tasks.spawn_component(entity, async {
+            YieldOnce { yielded: false }.await;
+            Position { x: 3.0, y: 4.0 }
+        });
+        tasks.spawn(async { 7 }, |value, entities_and_components| {
+            entities_and_components.add_entity_with((Velocity {
+                x: value as f32,
+                y: 0.0,
+            },));
+        });
+
+        engine.run();
+        assert_eq!(
+            engine
+                .entities_and_components
+                .get_entity_count_with_component::<Velocity>(),
+            1
+        );
+        assert!(engine
+            .entities_and_components
+            .try_get_component::<Position>(entity)
+            .is_none());
+
+        engine.run();
+        assert_eq!(
+            engine
+                .entities_and_components
+                .try_get_component::<Position>(entity),
+            Some(&Position { x: 3.0, y: 4.0 })
+        );
+        assert!(engine
+            .entities_and_components
+            .get_resource::<AsyncTasks>()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_assets() {
+        struct Texture {
+            width: u32,
+        }
+
+        let mut engine = World::new();
+        engine.entities_and_components.init_resource::<AsyncTasks>();
+        engine
+            .entities_and_components
+            .init_resource::<Assets<Texture>>();
+
+        let entities_and_components = &mut engine.entities_and_components;
+        let loaded =
+            entities_and_components.load_asset(async { Ok::<_, ()>(Texture { width: 2 }) });
+        let failed =
+            entities_and_components.load_asset(async { Err::<Texture, _>("missing file") });
+
+        let assets = entities_and_components
+            .get_resource_mut::<Assets<Texture>>()
+            .unwrap();
+        let added = assets.add(Texture { width: 1 });
+
+        assert_eq!(assets.get(added).unwrap().width, 1);
+        assert_eq!(assets.get_load_state(loaded), Some(LoadState::Loading));
+        assert!(assets.get(loaded).is_none());
+        assert_eq!(assets.events(), &[AssetEvent::Loaded(added)]);
+
+        engine.run();
+        let assets = engine
+            .entities_and_components
+            .get_resource_mut::<Assets<Texture>>()
+            .unwrap();
+        assert_eq!(assets.get(loaded).unwrap().width, 2);
+        assert_eq!(assets.get_load_state(failed), Some(LoadState::Failed));
+        assert_eq!(
+            assets.events(),
+            &[AssetEvent::Loaded(loaded), AssetEvent::Failed(failed)]
+        );
+
+        assert_eq!(assets.remove(added).unwrap().width, 1);
+        assert_eq!(assets.get_load_state(added), None);
+        assert_eq!(assets.len(), 2);
+    }
+
+    #[test]
+    fn test_debug_entity() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let parent = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let child = entities_and_components.add_entity();
+        entities_and_components.set_parent(child, parent);
+
+        let report = engine.debug_entity(parent);
+        assert!(report.exists);
+        assert_eq!(report.components, vec![std::any::type_name::<Position>()]);
+        assert_eq!(report.indexed_by, report.components);
+        assert_eq!(report.parent, None);
+        assert_eq!(report.children, vec![child]);
+        assert!(report
+            .to_string()
+            .contains(&format!("children: [{:?}]", child)));
+
+        engine.entities_and_components.remove_entity(parent);
+        let report = engine.debug_entity(child);
+        assert!(!report.exists);
+        assert_eq!(report.to_string(), format!("{:?} does not exist\n", child));
+    }
+
+    #[test]
+    fn test_panic_policy() {
+        let mut engine = World::new();
+
+        let moving = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+        // this entity is missing its velocity so ParallelMovementSystem will panic on it
+        let missing_velocity = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+
+        engine.add_system(ParallelMovementSystem {});
+        engine.set_panic_policy(PanicPolicy::LogAndSkip);
+        assert_eq!(engine.get_panic_policy(), PanicPolicy::LogAndSkip);
+
+        engine.run();
+
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(moving);
+        assert_eq!(position.x, 1.0);
+
+        // the skipped panic is in the report
+        let failures = engine.get_frame_report().failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].entity, Some(missing_velocity));
+        assert!(failures[0].error.message().contains("panicked"));
+    }
+
+    #[test]
+    fn test_query_child_pairs() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let root = entities_and_components.add_entity_with((Position { x: 1.0, y: 0.0 },));
+        let child = entities_and_components.add_entity_with((Position { x: 1.0, y: 0.0 },));
+        let grandchild = entities_and_components.add_entity_with((Position { x: 1.0, y: 0.0 },));
+        // this child does not have a position so it is skipped, but its own children are still visited
+        let no_position = entities_and_components.add_entity();
+        entities_and_components.set_parent(child, root);
+        entities_and_components.set_parent(grandchild, child);
+        entities_and_components.set_parent(no_position, root);
+        entities_and_components.track_changes::<Position>();
+        entities_and_components.advance_change_tick();
+
+        // add the position of the parent to the position of the child, top down
+        let mut pairs = vec![];
+        entities_and_components.query_child_pairs::<(Position,), (Position,), _>(
+            |parent, (parent_position,), child, (child_position,)| {
+                child_position.x += parent_position.x;
+                pairs.push((parent, child));
+            },
+        );
+
+        assert_eq!(pairs, vec![(root, child), (child, grandchild)]);
+        let (position,) = entities_and_components.get_components::<(Position,)>(grandchild);
+        assert_eq!(position.x, 3.0);
+        // only the children are borrowed mutably, so only they are marked as changed
+        let tick = entities_and_components.change_tick();
+        assert_eq!(
+            entities_and_components.get_change_tick::<Position>(root),
+            None
+        );
+        assert_eq!(
+            entities_and_components.get_change_tick::<Position>(child),
+            Some(tick)
+        );
+        assert_eq!(
+            entities_and_components.get_change_tick::<Position>(grandchild),
+            Some(tick)
+        );
+    }
+
+    #[test]
+    fn test_run_systems_on() {
+        let mut engine = World::new();
+        engine.add_system(ParallelMovementSystem {});
+
+        let entity = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 2.0 }));
+        let other_entity = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 2.0 }));
+
+        engine.run_systems_on(entity);
+
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(entity);
+        assert_eq!(*position, Position { x: 1.0, y: 2.0 });
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(other_entity);
+        assert_eq!(*position, Position { x: 0.0, y: 0.0 });
+
+        // hidden entities are skipped
+        engine.entities_and_components.despawn_soft(entity);
+        engine.run_systems_on(entity);
+        engine.entities_and_components.resurrect(entity);
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(entity);
+        assert_eq!(*position, Position { x: 1.0, y: 2.0 });
+    }
+
+    #[test]
+    fn test_run_systems_on_reserved() {
+        struct CountSteps(Arc<Mutex<usize>>);
+        impl System for CountSteps {
+            fn single_entity_step(&self, _: &mut SingleMutEntity) {
+                *self.0.lock().unwrap() += 1;
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let steps = Arc::new(Mutex::new(0));
+        let mut engine = World::new();
+        engine.add_system(CountSteps(steps.clone()));
+        engine.set_entity_reserve(1);
+        engine.entities_and_components.refill_entity_reserve();
+        let reserved = engine
+            .entities_and_components
+            .take_reserved_entities(1)
+            .unwrap()[0];
+
+        // the reserved entity is not seen until it is spawned
+        engine.run_systems_on(reserved);
+        assert_eq!(*steps.lock().unwrap(), 0);
+        engine.entities_and_components.spawn_taken_entities();
+        engine.run_systems_on(reserved);
+        assert_eq!(*steps.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_run_systems_on_spawns_reserved() {
+        struct SpawnBullet;
+        impl System for SpawnBullet {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if single_entity.has_component::<Velocity>() {
+                    let bullet = single_entity.reserve_entities(1).unwrap()[0];
+                    single_entity.queue_add_component_to(bullet, Position { x: 5.0, y: 0.0 });
+                }
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let spawned = Arc::new(Mutex::new(vec![]));
+        let spawned_by_hook = spawned.clone();
+        let mut engine = World::new();
+        engine.add_system(SpawnBullet);
+        engine
+            .entities_and_components
+            .on_spawn(move |single_entity| {
+                let has_position = single_entity.has_component::<Position>();
+                spawned_by_hook.lock().unwrap().push(has_position);
+            });
+        let gun = engine
+            .entities_and_components
+            .add_entity_with((Velocity { x: 1.0, y: 0.0 },));
+        spawned.lock().unwrap().clear();
+        engine.set_entity_reserve(1);
+        engine.entities_and_components.refill_entity_reserve();
+
+        // the bullet is spawned right away instead of on the next run
+        engine.run_systems_on(gun);
+        assert_eq!(*spawned.lock().unwrap(), vec![true]);
+        assert_eq!(engine.entities_and_components.get_entity_count(), 2);
+        assert_eq!(
+            engine
+                .entities_and_components
+                .get_entity_count_with_component::<Position>(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_strict_components() {
+        let mut engine = World::new();
+        engine.register_component::<Position>();
+        engine.set_strict_components(true);
+
+        let entities_and_components = &mut engine.entities_and_components;
+        let parent = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let child = entities_and_components.add_entity();
+        // the components used for the hierarchy do not need to be registered
+        entities_and_components.set_parent(child, parent);
+
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            entities_and_components.get_components::<(Box<Position>,)>(parent);
+        }));
+        let error = result.unwrap_err();
+        let message = panic_message(&*error);
+        assert!(message.contains(std::any::type_name::<Box<Position>>()));
+        assert!(message.contains(std::any::type_name::<Position>()));
+    }
+
+    #[test]
+    fn test_or_filter() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let both = entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 0.0, y: 0.0 }));
+        let position = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let velocity = entities_and_components.add_entity_with((Velocity { x: 0.0, y: 0.0 },));
+        entities_and_components.add_entity();
+
+        let mut any = entities_and_components.get_entities_matching::<Or<(Position, Velocity)>>();
+        any.sort();
+        assert_eq!(any, vec![both, position, velocity]);
+
+        assert_eq!(
+            entities_and_components.get_entities_matching::<(Position, Velocity)>(),
+            vec![both]
+        );
+
+        // Or can be used anywhere a filter is taken
+        entities_and_components.set_parent(velocity, both);
+        assert_eq!(
+            entities_and_components.query_in_subtree::<Or<(Position, Velocity)>>(both),
+            vec![velocity]
+        );
+    }
+
+    #[test]
+    fn test_component_masks() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        // registering after the components were added sets the bits on the existing entities
+        let both = entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 0.0, y: 0.0 }));
+        entities_and_components.register_component::<Position>();
+        entities_and_components.register_component::<Velocity>();
+
+        let position = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let moved = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        entities_and_components.add_component_to(moved, Velocity { x: 1.0, y: 1.0 });
+
+        let mut matching = entities_and_components.get_entities_matching::<(Position, Velocity)>();
+        matching.sort();
+        assert_eq!(matching, vec![both, moved]);
+
+        entities_and_components.remove_component_from::<Velocity>(moved);
+        entities_and_components.remove_entity(both);
+        assert_eq!(
+            entities_and_components.get_entities_matching::<(Position, Velocity)>(),
+            vec![]
+        );
+
+        // unregistered components still work alongside registered ones
+        entities_and_components.add_component_to(position, String::from("player"));
+        assert_eq!(
+            entities_and_components.get_entities_matching::<(Position, String)>(),
+            vec![position]
+        );
+    }
+
+    #[test]
+    fn test_cleanup() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let parent = entities_and_components.add_entity();
+        let child = entities_and_components.add_entity();
+        entities_and_components.set_parent(child, parent);
+        entities_and_components.add_entity();
+        entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+
+        let stats = engine.cleanup(CleanupPolicy::ReportOnly);
+        assert_eq!(
+            stats,
+            CleanupStats {
+                checked: 4,
+                empty: 1,
+                hierarchy_only: 1,
+                removed: 0,
+            }
+        );
+
+        let stats = engine
+            .entities_and_components
+            .cleanup_incremental(CleanupPolicy::RemoveEmpty, 2);
+        assert_eq!(stats.checked, 2);
+        assert_eq!(stats.removed, 0);
+        let stats = engine
+            .entities_and_components
+            .cleanup_incremental(CleanupPolicy::RemoveEmpty, 2);
+        assert_eq!(stats.removed, 1);
+        assert_eq!(engine.entities_and_components.get_entity_count(), 3);
+
+        // the child only has a parent, the parent keeps its children so it is not removed
+        let stats = engine.cleanup(CleanupPolicy::RemoveEmptyAndHierarchyOnly);
+        assert_eq!(stats.removed, 1);
+        assert!(!engine.entities_and_components.does_entity_exist(child));
+        assert!(engine.entities_and_components.does_entity_exist(parent));
+    }
+
+    #[test]
+    fn test_resource_update_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // counts how many resources have been updated so far this frame
+        static UPDATES: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Default)]
+        struct First {
+            updated_at: usize,
+        }
+        impl Resource for First {
+            fn update(&mut self) {
+                self.updated_at = UPDATES.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[derive(Default)]
+        struct Second {
+            updated_at: usize,
+        }
+        impl Resource for Second {
+            fn update(&mut self) {
+                self.updated_at = UPDATES.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[derive(Default)]
+        struct Independent {
+            updates: usize,
+        }
+        impl Resource for Independent {
+            fn update(&mut self) {
+                self.updates += 1;
+            }
+        }
+
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+        entities_and_components.init_resource::<Second>();
+        entities_and_components.init_resource::<First>();
+        entities_and_components.init_resource::<Independent>();
+        entities_and_components.set_resource_order::<First>(-1);
+        entities_and_components.set_resource_order::<Second>(1);
+        entities_and_components.set_resource_independent::<Independent>();
+
+        engine.run();
+
+        let entities_and_components = &engine.entities_and_components;
+        let first = entities_and_components.get_resource::<First>().unwrap();
+        let second = entities_and_components.get_resource::<Second>().unwrap();
+        assert!(first.updated_at < second.updated_at);
+        assert_eq!(
+            entities_and_components
+                .get_resource::<Independent>()
+                .unwrap()
+                .updates,
+            1
+        );
+    }
+
+    #[test]
+    fn test_system_errors() {
+        struct FailingSystem {}
+
+        impl System for FailingSystem {
+            fn try_single_entity_step(
+                &self,
+                single_entity: &mut SingleMutEntity,
+            ) -> Result<(), SystemError> {
+                single_entity
+                    .try_get_component::<Velocity>()
+                    .map(|_| ())
+                    .ok_or_else(|| SystemError::new("missing velocity"))
+            }
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+            fn try_run(&mut self, _: &mut EntitiesAndComponents) -> Result<(), SystemError> {
+                Err("run failed".into())
+            }
+        }
+
+        let mut engine = World::new();
+        let entity = engine.entities_and_components.add_entity();
+        engine
+            .entities_and_components
+            .add_entity_with((Velocity { x: 0.0, y: 0.0 },));
+        engine.add_system(FailingSystem {});
+
+        let handled = Arc::new(Mutex::new(0));
+        let handled_in_handler = handled.clone();
+        engine.set_error_handler(move |_| *handled_in_handler.lock().unwrap() += 1);
+
+        engine.run();
+
+        let report = engine.get_frame_report();
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.failures(),
+            &[
+                SystemFailure {
+                    system: std::any::type_name::<FailingSystem>(),
+                    entity: Some(entity),
+                    error: SystemError::new("missing velocity"),
+                },
+                SystemFailure {
+                    system: std::any::type_name::<FailingSystem>(),
+                    entity: None,
+                    error: SystemError::new("run failed"),
+                },
+            ]
+        );
+        assert_eq!(*handled.lock().unwrap(), 2);
+
+        // run_systems_on starts a new report every time
+        engine.run_systems_on(entity);
+        engine.run_systems_on(entity);
+        assert_eq!(engine.get_frame_report().failures().len(), 1);
+
+        // a paused run does not keep the failures from before
+        engine.pause();
+        engine.run();
+        assert!(engine.get_frame_report().is_ok());
+        engine.resume();
+
+        // the report only holds the failures from the last run
+        engine.remove_all_systems();
+        engine.run();
+        assert!(engine.get_frame_report().is_ok());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut engine = World::new();
+        engine.add_system(ParallelMovementSystem {});
+        engine.entities_and_components.init_resource::<AsyncTasks>();
+
+        let entities_and_components = &mut engine.entities_and_components;
+        let parent = entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+        let child = entities_and_components.add_entity();
+        entities_and_components.set_parent(child, parent);
+        assert_eq!(entities_and_components.get_children(parent), vec![child]);
+
+        engine.clear_entities();
+        let entities_and_components = &mut engine.entities_and_components;
+        assert_eq!(entities_and_components.get_entity_count(), 0);
+        assert_eq!(
+            entities_and_components.get_entity_count_with_component::<Position>(),
+            0
+        );
+        assert!(entities_and_components
+            .get_resource::<AsyncTasks>()
+            .is_some());
+        assert_eq!(engine.systems.len(), 1);
+
+        // the world can be used again like normal
+        let entity = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+        engine.run();
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(entity);
+        assert_eq!(position.x, 1.0);
+
+        engine.clear_all();
+        assert_eq!(engine.entities_and_components.get_entity_count(), 0);
+        assert!(engine
+            .entities_and_components
+            .get_resource::<AsyncTasks>()
+            .is_none());
+        assert_eq!(engine.systems.len(), 0);
+    }
+
+    #[test]
+    fn test_queue_resource_from_prestep() {
+        struct PositionCount(usize);
+        impl Resource for PositionCount {}
+
+        struct Stale;
+        impl Resource for Stale {}
+
+        struct CountSystem;
+
+        impl System for CountSystem {
+            fn prestep(&mut self, engine: &EntitiesAndComponentsThreadSafe) {
+                let count = engine.get_entities_with_component::<Position>().count();
+                engine.queue_insert_resource(PositionCount(count));
+                engine.queue_remove_resource::<Stale>();
+
+                // nothing is applied until every prestep has finished
+                assert!(engine.get_resource::<PositionCount>().is_none());
+            }
+
+            fn implements_prestep(&self) -> bool {
+                true
+            }
+
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                assert!(engine.get_resource::<Stale>().is_none());
+            }
+        }
+
+        let mut engine = World::new();
+        engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        engine
+            .entities_and_components
+            .add_entity_with((Position { x: 1.0, y: 0.0 },));
+        engine.entities_and_components.add_resource(Stale);
+        engine.add_system(CountSystem);
+
+        engine.run();
+
+        let count = engine
+            .entities_and_components
+            .get_resource::<PositionCount>();
+        assert_eq!(count.map(|count| count.0), Some(2));
+        assert!(engine
+            .entities_and_components
+            .get_resource::<Stale>()
+            .is_none());
+    }
+
+    #[test]
+    fn test_run_versioned() {
+        struct MoveRight;
+
+        impl System for MoveRight {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine.get_entities_matching::<(Position, Velocity)>() {
+                    let (position, velocity) =
+                        engine.get_components_mut::<(Position, Velocity)>(entity);
+                    position.x += 1.0;
+                    velocity.x = 1.0;
+                }
+            }
+        }
+
+        struct MoveUp;
+
+        impl System for MoveUp {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine.get_entities_matching::<(Position, Velocity)>() {
+                    let (position, velocity) =
+                        engine.get_components_mut::<(Position, Velocity)>(entity);
+                    // MoveRight's changes are not visible here
+                    assert_eq!(position.x, 0.0);
+                    position.y += 1.0;
+                    velocity.y = 1.0;
+                }
+            }
+        }
+
+        let mut engine = World::new();
+        let entity = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 0.0, y: 0.0 }));
+
+        // add up how much each system moved the position
+        engine.register_versioned_component::<Position>(MergePolicy::Custom(Box::new(
+            |base, merged, version| Position {
+                x: merged.x + version.x - base.x,
+                y: merged.y + version.y - base.y,
+            },
+        )));
+        engine.register_versioned_component::<Velocity>(MergePolicy::LastWrite);
+
+        engine.add_system(MoveRight);
+        engine.add_system(MoveUp);
+        engine.run_versioned();
+
+        let (position, velocity) = engine
+            .entities_and_components
+            .get_components::<(Position, Velocity)>(entity);
+        assert_eq!(*position, Position { x: 1.0, y: 1.0 });
+        // only MoveUp's version of the velocity is kept
+        assert_eq!(*velocity, Velocity { x: 0.0, y: 1.0 });
+    }
+
+    #[test]
+    fn test_run_versioned_restore_has_no_side_effects() {
+        struct RemovePosition;
+
+        impl System for RemovePosition {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine.get_entities_matching::<(Position,)>() {
+                    engine.remove_component_from::<Position>(entity);
+                }
+            }
+        }
+
+        struct CheckRestored;
+
+        impl System for CheckRestored {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine.iter_entities().collect::<Vec<Entity>>() {
+                    // the position is back, but putting it back is not a real add
+                    let (position, velocity) =
+                        engine.try_get_components::<(Position, Velocity)>(entity);
+                    assert!(position.is_some());
+                    assert!(velocity.is_none());
+                }
+            }
+        }
+
+        let mut engine = World::new();
+        let entity = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        engine
+            .entities_and_components
+            .register_required_component_with::<Position, Velocity>(|| Velocity { x: 0.0, y: 0.0 });
+        engine.register_versioned_component::<Position>(MergePolicy::LastWrite);
+
+        engine.add_system(RemovePosition);
+        engine.add_system(CheckRestored);
+        engine.start_recording_ops(16);
+        engine.run_versioned();
+
+        assert!(engine
+            .entities_and_components
+            .try_get_component::<Position>(entity)
+            .is_none());
+        assert!(engine
+            .entities_and_components
+            .try_get_component::<Velocity>(entity)
+            .is_none());
+        assert!(engine
+            .recent_ops()
+            .iter()
+            .all(|op| matches!(op.op, StructuralOp::RemoveComponent { .. })));
+    }
+
+    #[test]
+    fn test_export_schedule() {
+        struct Physics;
+
+        impl System for Physics {
+            fn component_access(&self, access: &mut ComponentAccess) {
+                access.read::<Velocity>().write::<Position>();
+            }
+        }
+
+        struct Render;
+
+        impl System for Render {
+            fn component_access(&self, access: &mut ComponentAccess) {
+                access.read::<Position>();
+            }
+        }
+
+        let mut engine = World::new();
+        engine.add_system_to_set(Render, "render");
+        engine.add_system_to_set(Physics, "physics");
+        engine.configure_set("render").order(1);
+
+        // physics runs first so it comes first and has an edge to render
+        let json = engine.export_schedule_json();
+        let physics = format!(
+            "{{\"id\":0,\"name\":\"{}\",\"set\":\"physics\",\"group\":null,\"order\":0,\"stages\":[\"run\"],\"reads\":[\"{}\"],\"writes\":[\"{}\"]}}",
+            std::any::type_name::<Physics>(),
+            std::any::type_name::<Velocity>(),
+            std::any::type_name::<Position>()
+        );
+        assert!(json.starts_with(&format!("{{\"systems\":[{physics},")));
+        assert!(json.ends_with("\"edges\":[{\"from\":0,\"to\":1}]}"));
+
+        let graphviz = engine.export_schedule_graphviz();
+        assert!(graphviz.starts_with("digraph schedule {"));
+        assert!(graphviz.contains("label=\"physics\";"));
+        assert!(graphviz.contains("system_0 -> system_1;"));
+        assert!(graphviz.contains(&format!("writes {}", std::any::type_name::<Position>())));
+
+        // control characters in names are escaped so the json stays valid
+        let mut engine = World::new();
+        engine.add_system_to_set(Render, "late\tset");
+        assert!(engine
+            .export_schedule_json()
+            .contains("\"set\":\"late\\u0009set\""));
+    }
+
+    #[cfg(feature = "bench")]
+    #[test]
+    fn test_bench_scenarios() {
+        for scenario in bench::scenarios() {
+            let mut world = scenario.setup(100);
+            scenario.run(&mut world, 100);
+            assert!(world.entities_and_components.get_entity_count() >= 50);
+        }
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let entities = (0..10)
+            .map(|i| {
+                entities_and_components.add_entity_with((Position {
+                    x: i as f32,
+                    y: 0.0,
+                },))
+            })
+            .collect::<Vec<Entity>>();
+        for entity in entities.iter().step_by(2) {
+            entities_and_components.remove_entity(*entity);
+        }
+        let parent = entities[9];
+        let child = entities[1];
+        entities_and_components.set_parent(child, parent);
+        entities_and_components.add_component_to(child, Velocity { x: 1.0, y: 1.0 });
+
+        let entity_map = engine.compact();
+        let entities_and_components = &mut engine.entities_and_components;
+        assert_eq!(entity_map.len(), 5);
+
+        let new_parent = entity_map.get(parent).unwrap();
+        let new_child = entity_map.get(child).unwrap();
+        assert_eq!(
+            entities_and_components.get_children(new_parent),
+            vec![new_child]
+        );
+        assert_eq!(
+            entities_and_components.get_parent(new_child),
+            Some(new_parent)
+        );
+        assert_eq!(
+            entities_and_components.get_entities_matching::<(Position, Velocity)>(),
+            vec![new_child]
+        );
+
+        // the entities keep their order and components
+        for (i, entity) in entities_and_components.iter_entities().enumerate() {
+            let (position,) = entities_and_components.get_components::<(Position,)>(entity);
+            assert_eq!(position.x, (i * 2 + 1) as f32);
+        }
+    }
+
+    #[test]
+    fn test_compact_stale_handles() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let a = entities_and_components.add_entity();
+        let b = entities_and_components.add_entity();
+        let weak_a = a.downgrade();
+        entities_and_components.remove_entity(a);
+
+        let entity_map = engine.compact();
+        let entities_and_components = &mut engine.entities_and_components;
+        let new_b = entity_map.get(b).unwrap();
+        // b moved into the slot a was in
+        assert_eq!(new_b.index(), a.index());
+        assert!(!entities_and_components.does_entity_exist(b));
+
+        let spawned = entities_and_components.add_entity();
+        assert_eq!(entities_and_components.upgrade_entity(weak_a), None);
+        assert_eq!(entities_and_components.upgrade_entity(b.downgrade()), None);
+        assert_eq!(entities_and_components.entity_from_bits(a.to_bits()), None);
+        assert_eq!(entities_and_components.entity_from_bits(b.to_bits()), None);
+        assert_eq!(
+            entities_and_components.entity_from_bits(spawned.to_bits()),
+            Some(spawned)
+        );
+        assert_eq!(entities_and_components.get_entity_count(), 2);
+    }
+
+    #[test]
+    fn test_compact_entity_reserve() {
+        struct SpawnBullets(Arc<Mutex<usize>>);
+        impl System for SpawnBullets {
+            fn prestep(&mut self, engine: &EntitiesAndComponentsThreadSafe) {
+                let count = *self.0.lock().unwrap();
+                for entity in engine.reserve_entities(count).unwrap() {
+                    engine.queue_add_component_to(entity, Position { x: 0.0, y: 0.0 });
+                }
+            }
+
+            fn implements_prestep(&self) -> bool {
+                true
+            }
+        }
+
+        let count = Arc::new(Mutex::new(1));
+        let mut world = World::new();
+        world.set_entity_reserve(2);
+        world.add_system(SpawnBullets(count.clone()));
+        world.run();
+
+        // the ready entity that was not handed out is moved with the rest
+        assert_eq!(world.compact().len(), 2);
+        assert_eq!(world.entities_and_components.get_entity_count(), 1);
+        *count.lock().unwrap() = 2;
+        world.run();
+        let engine = &world.entities_and_components;
+        assert_eq!(engine.get_entity_count(), 3);
+        assert_eq!(engine.get_entity_count_with_component::<Position>(), 3);
+        assert!(engine.validate().is_empty());
+    }
+
+    #[test]
+    fn test_multi_world() {
+        // copies the positions from the simulation into sprites in the render world
+        struct ExtractSprites;
+
+        impl CrossWorldSystem for ExtractSprites {
+            fn run(&mut self, source: &EntitiesAndComponents, target: &mut EntitiesAndComponents) {
+                target.clear_entities();
+                for (_, position) in source.export_column::<Position>() {
+                    target.add_entity_with((position,));
+                }
+            }
+        }
+
+        let mut multi_world = MultiWorld::new();
+        // the render world is added first but still runs after the simulation
+        let render = multi_world.add_world(World::new());
+        let simulation = multi_world.add_world(World::new());
+        multi_world.add_cross_world_system(ExtractSprites, simulation, render);
+
+        let simulation_world = multi_world.get_world_mut(simulation);
+        simulation_world.add_system(MovementSystem {});
+        simulation_world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 2.0 }));
+
+        multi_world.run();
+        multi_world.run();
+
+        let render_world = &multi_world.get_world(render).entities_and_components;
+        assert_eq!(
+            render_world.export_column::<Position>(),
+            vec![(
+                render_world.get_nth_entity(0).unwrap(),
+                Position { x: 2.0, y: 4.0 }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_extractor() {
+        #[derive(Default)]
+        struct RenderQueue {
+            sprites: Vec<(f32, f32)>,
+        }
+
+        let mut engine = World::new();
+        engine.add_system(MovementSystem {});
+        engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+
+        assert!(engine.get_render_queue::<RenderQueue>().is_none());
+
+        engine.set_extractor(|engine: &EntitiesAndComponents, queue: &mut RenderQueue| {
+            for (_, position) in engine.export_column::<Position>() {
+                queue.sprites.push((position.x, position.y));
+            }
+        });
+
+        // the extractor runs after the systems and the queue starts empty every run
+        engine.run();
+        engine.run();
+        let queue = engine.get_render_queue::<RenderQueue>().unwrap();
+        assert_eq!(queue.sprites, vec![(2.0, 2.0)]);
+
+        assert!(engine.get_render_queue::<Position>().is_none());
+        assert!(engine.take_render_queue::<Position>().is_none());
+        assert!(engine.take_render_queue::<RenderQueue>().is_some());
+        assert!(engine.get_render_queue::<RenderQueue>().is_none());
+    }
+
+    #[test]
+    fn test_children_count() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let parent = entities_and_components.add_entity();
+        assert_eq!(entities_and_components.children_count(parent), 0);
+        assert_eq!(entities_and_components.nth_child(parent, 0), None);
+
+        // more children than fit inline
+        let children = (0..6)
+            .map(|_| {
+                let child = entities_and_components.add_entity();
+                entities_and_components.set_parent(child, parent);
+                child
+            })
+            .collect::<Vec<Entity>>();
+        assert_eq!(entities_and_components.children_count(parent), 6);
+        assert_eq!(
+            entities_and_components.nth_child(parent, 5),
+            Some(children[5])
+        );
+        assert_eq!(entities_and_components.get_children(parent), children);
+
+        entities_and_components.remove_parent(children[0]);
+        entities_and_components.remove_entity(children[3]);
+        assert_eq!(entities_and_components.children_count(parent), 4);
+        assert_eq!(
+            entities_and_components.nth_child(parent, 0),
+            Some(children[1])
+        );
+        assert_eq!(
+            entities_and_components.nth_child(parent, 2),
+            Some(children[4])
+        );
+        assert_eq!(entities_and_components.nth_child(parent, 4), None);
+    }
+
+    #[test]
+    fn test_set_parent_keep_world_transform() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Transform {
+            x: f32,
+            y: f32,
+            scale: f32,
+        }
+
+        impl HierarchyTransform for Transform {
+            fn combine(&self, child: &Self) -> Self {
+                Transform {
+                    x: self.x + child.x * self.scale,
+                    y: self.y + child.y * self.scale,
+                    scale: self.scale * child.scale,
+                }
+            }
+
+            fn inverse(&self) -> Self {
+                Transform {
+                    x: -self.x / self.scale,
+                    y: -self.y / self.scale,
+                    scale: 1.0 / self.scale,
+                }
+            }
+        }
+
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let parent = entities_and_components.add_entity_with((Transform {
+            x: 10.0,
+            y: 0.0,
+            scale: 2.0,
+        },));
+        let child = entities_and_components.add_entity_with((Transform {
+            x: 4.0,
+            y: 4.0,
+            scale: 1.0,
+        },));
+
+        assert!(entities_and_components.set_parent_keep_world_transform::<Transform>(child, parent));
+        assert_eq!(
+            entities_and_components
+                .get_components::<(Transform,)>(child)
+                .0,
+            &Transform {
+                x: -3.0,
+                y: 2.0,
+                scale: 0.5
+            }
+        );
+        assert_eq!(
+            entities_and_components.get_world_transform::<Transform>(child),
+            Some(Transform {
+                x: 4.0,
+                y: 4.0,
+                scale: 1.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let enemy = entities_and_components.add_entity();
+        let boss = entities_and_components.add_entity();
+        entities_and_components.add_tag(enemy, "enemy");
+        entities_and_components.add_tag(boss, "enemy");
+        entities_and_components.add_tag(boss, "boss");
+        entities_and_components.add_tag(boss, "boss");
+
+        let mut enemies = entities_and_components.get_entities_with_tag("enemy");
+        enemies.sort();
+        assert_eq!(enemies, vec![enemy, boss]);
+        assert_eq!(
+            entities_and_components.get_tags(boss),
+            vec!["enemy", "boss"]
+        );
+        assert!(entities_and_components.has_tag(boss, "boss"));
+        assert!(!entities_and_components.has_tag(enemy, "boss"));
+        assert!(entities_and_components
+            .get_entities_with_tag("unused")
+            .is_empty());
+
+        entities_and_components.remove_tag(boss, "enemy");
+        entities_and_components.remove_entity(enemy);
+        assert!(entities_and_components
+            .get_entities_with_tag("enemy")
+            .is_empty());
+        assert_eq!(entities_and_components.get_tags(boss), vec!["boss"]);
+
+        // tags move with the entity to another world
+        let mut other = World::new();
+        let entity_map = other.merge(engine, false);
+        let boss = entity_map.get(boss).unwrap();
+        assert_eq!(
+            other.entities_and_components.get_entities_with_tag("boss"),
+            vec![boss]
+        );
+    }
+
+    #[test]
+    fn test_value_index() {
+        struct NetworkId(u64);
+
+        struct ChangeIds;
+        impl System for ChangeIds {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine
+                    .get_entities_with_component::<NetworkId>()
+                    .collect::<Vec<_>>()
+                {
+                    let (network_id,) = engine.get_components_mut::<(NetworkId,)>(entity);
+                    network_id.0 += 100;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        let first = world
+            .entities_and_components
+            .add_entity_with((NetworkId(1),));
+        world.add_index::<NetworkId, u64>(|network_id| network_id.0);
+
+        let engine = &mut world.entities_and_components;
+        let second = engine.add_entity_with((NetworkId(2),));
+        assert_eq!(engine.get_entities_by_key::<NetworkId, u64>(&1), &[first]);
+        assert_eq!(engine.get_entities_by_key::<NetworkId, u64>(&2), &[second]);
+
+        engine.modify::<NetworkId, _, _>(second, |network_id| network_id.0 = 3);
+        assert!(engine.get_entities_by_key::<NetworkId, u64>(&2).is_empty());
+        assert_eq!(engine.get_entities_by_key::<NetworkId, u64>(&3), &[second]);
+
+        engine.remove_component_from::<NetworkId>(first);
+        assert!(engine.get_entities_by_key::<NetworkId, u64>(&1).is_empty());
+        engine.add_component_to(first, NetworkId(3));
+        let mut with_three = engine.get_entities_by_key::<NetworkId, u64>(&3).to_vec();
+        with_three.sort();
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(with_three, expected);
+
+        engine.remove_entity(first);
+        assert_eq!(engine.get_entities_by_key::<NetworkId, u64>(&3), &[second]);
+
+        world.add_system(ChangeIds);
+        world.run();
+        let engine = &world.entities_and_components;
+        assert!(engine.get_entities_by_key::<NetworkId, u64>(&3).is_empty());
+        assert_eq!(
+            engine.get_entities_by_key::<NetworkId, u64>(&103),
+            &[second]
+        );
+    }
+
+    #[test]
+    fn test_recent_ops() {
+        struct Health;
+
+        struct Despawner;
+        impl System for Despawner {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                let dead = engine
+                    .get_entities_with_component::<Health>()
+                    .collect::<Vec<_>>();
+                for entity in dead {
+                    // children are removed with their parents
+                    if engine.does_entity_exist(entity) {
+                        engine.remove_entity(entity);
+                    }
+                }
+            }
+        }
+
+        struct Wounder;
+        impl System for Wounder {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if !single_entity.has_component::<Health>() {
+                    single_entity.add_component(Health);
+                }
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let mut world = World::new();
+        let untracked = world.entities_and_components.add_entity();
+        assert!(world.recent_ops().is_empty());
+
+        world.start_recording_ops(4);
+        let parent = world.entities_and_components.add_entity();
+        world.entities_and_components.set_parent(untracked, parent);
+        assert_eq!(
+            world.recent_ops(),
+            vec![
+                RecordedOp {
+                    op: StructuralOp::Spawn(parent),
+                    system: None,
+                    frame: 0,
+                },
+                RecordedOp {
+                    op: StructuralOp::SetParent {
+                        child: untracked,
+                        parent,
+                    },
+                    system: None,
+                    frame: 0,
+                },
+            ]
+        );
+
+        world.add_system(Wounder);
+        world.add_system(Despawner);
+        world.run();
+
+        // only the last 4 are kept
+        let ops = world.recent_ops();
+        assert_eq!(ops.len(), 4);
+        assert!(matches!(
+            ops[0].op,
+            StructuralOp::AddComponent { component, .. } if component == std::any::type_name::<Health>()
+        ));
+        assert_eq!(ops[0].system, Some(std::any::type_name::<Wounder>()));
+        assert_eq!(
+            ops[1..],
+            [
+                RecordedOp {
+                    op: StructuralOp::RemoveParent(untracked),
+                    system: Some(std::any::type_name::<Despawner>()),
+                    frame: 1,
+                },
+                RecordedOp {
+                    op: StructuralOp::Despawn(untracked),
+                    system: Some(std::any::type_name::<Despawner>()),
+                    frame: 1,
+                },
+                RecordedOp {
+                    op: StructuralOp::Despawn(parent),
+                    system: Some(std::any::type_name::<Despawner>()),
+                    frame: 1,
+                },
+            ]
+        );
+
+        world.stop_recording_ops();
+        assert!(world.recent_ops().is_empty());
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        #[derive(Debug, PartialEq)]
+        struct Name(String);
+        impl ReplayComponent for Name {
+            fn save(&self) -> String {
+                self.0.clone()
+            }
+            fn load(data: &str) -> Option<Self> {
+                Some(Name(data.to_string()))
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Health(i32);
+        impl ReplayComponent for Health {
+            fn save(&self) -> String {
+                self.0.to_string()
+            }
+            fn load(data: &str) -> Option<Self> {
+                data.parse().ok().map(Health)
+            }
+        }
+
+        struct Poison;
+        impl System for Poison {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine
+                    .get_entities_with_component::<Health>()
+                    .collect::<Vec<_>>()
+                {
+                    let (health,) = engine.get_components_mut::<(Health,)>(entity);
+                    health.0 -= 40;
+                    if health.0 <= 0 {
+                        engine.remove_entity(entity);
+                    }
+                }
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("abc_ecs_replay_{}.txt", std::process::id()));
+
+        let mut world = World::new();
+        world.register_replay_component::<Name>();
+        world.register_replay_component::<Health>();
+        world.add_system(Poison);
+        let engine = &mut world.entities_and_components;
+        let player = engine.add_entity_with((Name("player\tone\n".to_string()), Health(100)));
+        let sword = engine.add_entity_with((Name("sword".to_string()),));
+        engine.set_parent(sword, player);
+
+        world.record_to(&path).unwrap();
+        let goblin = world
+            .entities_and_components
+            .add_entity_with((Name("goblin".to_string()), Health(50)));
+        world.run();
+        // the changes after compacting are written with the new ids
+        let sword = world.compact().map(sword);
+        world.run();
+        world
+            .entities_and_components
+            .remove_component_from::<Name>(sword);
+
+        // the hierarchy of merged entities is written too
+        let mut other = World::new();
+        other.register_component::<Name>();
+        let shield = other
+            .entities_and_components
+            .add_entity_with((Name("shield".to_string()),));
+        let strap = other
+            .entities_and_components
+            .add_entity_with((Name("strap".to_string()),));
+        other.entities_and_components.set_parent(strap, shield);
+        world.merge(other, false);
+        world.finish_recording().unwrap();
+        assert!(!world.entities_and_components.does_entity_exist(goblin));
+
+        let mut replayed = World::new();
+        replayed.register_replay_component::<Name>();
+        replayed.register_replay_component::<Health>();
+        replayed.replay_from(&path).unwrap();
+
+        let engine = &replayed.entities_and_components;
+        assert_eq!(engine.get_entity_count(), 4);
+        let player = engine
+            .get_entities_with_component::<Health>()
+            .next()
+            .unwrap();
+        let (name, health) = engine.get_components::<(Name, Health)>(player);
+        assert_eq!(name, &Name("player\tone\n".to_string()));
+        assert_eq!(health, &Health(20));
+        let children = engine.get_children(player);
+        assert_eq!(children.len(), 1);
+        assert!(engine.try_get_component::<Name>(children[0]).is_none());
+        let shield = engine
+            .get_entities_with_component::<Name>()
+            .find(|entity| engine.get_components::<(Name,)>(*entity).0 .0 == "shield")
+            .unwrap();
+        let children = engine.get_children(shield);
+        assert_eq!(children.len(), 1);
+        assert_eq!(
+            engine.get_components::<(Name,)>(children[0]).0,
+            &Name("strap".to_string())
+        );
+
+        // every replay component has to be registered
+        let mut unregistered = World::new();
+        assert!(unregistered.replay_from(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replay_corrupt() {
+        let path =
+            std::env::temp_dir().join(format!("abc_ecs_replay_corrupt_{}.txt", std::process::id()));
+
+        // lines that use an entity after it was despawned, directly or with its parent
+        for (lines, line_number) in [
+            ("spawn\t1\nspawn\t2\ndespawn\t1\nparent\t2\t1\n", 5),
+            (
+                "spawn\t1\nspawn\t2\nparent\t2\t1\ndespawn\t1\nunparent\t2\n",
+                6,
+            ),
+            ("spawn\t1\ndespawn\t1\nhide\t1\n", 4),
+            ("spawn\t1\ndespawn\t1\ndespawn\t1\n", 4),
+        ] {
+            std::fs::write(&path, format!("abc-ecs replay 1\n{lines}")).unwrap();
+            let error = World::new().replay_from(&path).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+            assert!(error
+                .to_string()
+                .contains(&format!("line {line_number} of the replay: unknown entity")));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_dyn_components() {
+        #[derive(Debug, PartialEq)]
+        struct NetworkId(u64);
+
+        let mut engine = EntitiesAndComponents::new();
+        engine.add_index::<NetworkId, u64>(|network_id| network_id.0);
+        let entity = engine.add_entity();
+        assert!(engine
+            .get_component_dyn(entity, TypeId::of::<NetworkId>())
+            .is_none());
+
+        engine.add_component_dyn(entity, Box::new(NetworkId(7)));
+        let component = engine
+            .get_component_dyn(entity, TypeId::of::<NetworkId>())
+            .unwrap();
+        assert_eq!(component.downcast_ref::<NetworkId>(), Some(&NetworkId(7)));
+        assert_eq!(
+            engine.get_components::<(NetworkId,)>(entity).0,
+            &NetworkId(7)
+        );
+        assert_eq!(
+            engine
+                .get_entities_with_component::<NetworkId>()
+                .collect::<Vec<_>>(),
+            vec![entity]
+        );
+        assert_eq!(engine.get_entities_by_key::<NetworkId, u64>(&7), &[entity]);
+
+        engine.remove_component_from::<NetworkId>(entity);
+        assert!(engine
+            .get_component_dyn(entity, TypeId::of::<NetworkId>())
+            .is_none());
+        assert!(engine.get_entities_by_key::<NetworkId, u64>(&7).is_empty());
+    }
+
+    #[test]
+    fn test_for_each_mut() {
+        struct Counter(u32);
+        struct Other;
+
+        let mut engine = EntitiesAndComponents::new();
+        let first = engine.add_entity_with((Counter(0),));
+        let second = engine.add_entity_with((Counter(10), Other));
+        engine.add_entity_with((Other,));
+
+        let mut visited = vec![];
+        engine.for_each_mut::<Counter>(|entity, counter| {
+            counter.0 += 1;
+            visited.push(entity);
+        });
+        visited.sort();
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(visited, expected);
+        assert_eq!(engine.get_components::<(Counter,)>(first).0 .0, 1);
+        assert_eq!(engine.get_components::<(Counter,)>(second).0 .0, 11);
+
+        // nothing has the component yet
+        engine.for_each_mut::<u8>(|_, _| panic!("no entity has a u8"));
+    }
+
+    #[test]
+    fn test_systems_macro() {
+        struct Order(Vec<u32>);
+        impl Resource for Order {}
+
+        struct Push(u32);
+        impl System for Push {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                engine.get_resource_mut::<Order>().unwrap().0.push(self.0);
+            }
+        }
+
+        fn first_systems() -> SystemList {
+            systems![Push(1), Push(2)]
+        }
+
+        let systems = systems![first_systems(), Push(3), systems![Push(4)],];
+        assert_eq!(systems.len(), 4);
+
+        let mut world = World::with_registered_systems(systems);
+        world.entities_and_components.add_resource(Order(vec![]));
+        world.run();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_resource::<Order>()
+                .unwrap()
+                .0,
+            vec![1, 2, 3, 4]
+        );
+        assert!(systems![].is_empty());
+    }
+
+    #[test]
+    fn test_spawn_hooks() {
+        #[derive(Debug, PartialEq)]
+        struct Transform(i32);
+        #[derive(Debug, PartialEq)]
+        struct GlobalTransform(i32);
+
+        struct Spawner;
+        impl System for Spawner {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if single_entity.has_component::<Transform>() {
+                    single_entity.queue_spawn((Transform(5),));
+                    single_entity.remove_entity();
+                }
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let despawned = Arc::new(Mutex::new(vec![]));
+        let mut world = World::new();
+        world.on_spawn(|single_entity| {
+            let global = single_entity
+                .try_get_component::<Transform>()
+                .map_or(0, |transform| transform.0);
+            single_entity.add_component(GlobalTransform(global));
+        });
+        let despawned_clone = despawned.clone();
+        world.on_despawn(move |single_entity| {
+            assert!(single_entity.has_component::<GlobalTransform>());
+            despawned_clone
+                .lock()
+                .unwrap()
+                .push(single_entity.get_entity());
+        });
+
+        let engine = &mut world.entities_and_components;
+        let empty = engine.add_entity();
+        let moving = engine.add_entity_with((Transform(3),));
+        engine.set_parent(empty, moving);
+        assert_eq!(
+            engine.get_components::<(GlobalTransform,)>(empty).0,
+            &GlobalTransform(0)
+        );
+        assert_eq!(
+            engine.get_components::<(GlobalTransform,)>(moving).0,
+            &GlobalTransform(3)
+        );
+
+        // entities spawned and removed by commands fire the hooks too
+        world.add_system(Spawner);
+        world.run();
+        let engine = &world.entities_and_components;
+        assert_eq!(*despawned.lock().unwrap(), vec![moving, empty]);
+        let spawned = engine
+            .get_entities_with_component::<Transform>()
+            .collect::<Vec<_>>();
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(
+            engine.get_components::<(GlobalTransform,)>(spawned[0]).0,
+            &GlobalTransform(5)
+        );
+    }
+
+    #[test]
+    fn test_entity_hooks_moved_entities() {
+        struct Bullet;
+
+        let spawned = Arc::new(Mutex::new(0));
+        let despawned = Arc::new(Mutex::new(0));
+        let mut world = World::new();
+        let spawned_clone = spawned.clone();
+        world.on_spawn(move |_| *spawned_clone.lock().unwrap() += 1);
+        let despawned_clone = despawned.clone();
+        world.on_despawn(move |_| *despawned_clone.lock().unwrap() += 1);
+        let counts = || (*spawned.lock().unwrap(), *despawned.lock().unwrap());
+
+        let mut pool = world.create_pool(1, || (Bullet,), |_, _| {});
+        assert_eq!(counts(), (1, 1));
+        let bullet = pool.acquire(&mut world.entities_and_components);
+        pool.release(&mut world.entities_and_components, bullet);
+        assert_eq!(counts(), (2, 2));
+
+        let mut other = World::new();
+        other.entities_and_components.add_entity_with((Bullet,));
+        other.entities_and_components.add_entity_with((Bullet,));
+        world.merge(other, false);
+        assert_eq!(counts(), (4, 2));
+
+        // compact only moves the entities
+        world.compact();
+        assert_eq!(counts(), (4, 2));
+
+        world.clear_entities();
+        assert_eq!(counts(), (4, 4));
+    }
+
+    #[test]
+    fn test_query_sorted() {
+        struct Depth(f32);
+        struct Sprite;
+
+        let mut engine = EntitiesAndComponents::new();
+        let middle = engine.add_entity_with((Depth(0.5), Sprite));
+        let back = engine.add_entity_with((Depth(-1.0), Sprite));
+        let front = engine.add_entity_with((Depth(2.0), Sprite));
+        engine.add_entity_with((Depth(0.0),));
+
+        let sorted = engine.query_sorted::<(Depth, Sprite), _>(|_, (depth, _)| depth.0);
+        assert_eq!(sorted, vec![back, middle, front]);
+
+        let mut sorted_query = SortedQuery::new();
+        let order = sorted_query.update::<(Depth, Sprite)>(&engine, |_, (depth, _)| depth.0);
+        assert_eq!(order, &[back, middle, front]);
+
+        // entities with equal keys keep their order from the last update
+        engine.get_components_mut::<(Depth,)>(front).0 .0 = -1.0;
+        let new = engine.add_entity_with((Depth(-1.0), Sprite));
+        engine.remove_entity(middle);
+        let order = sorted_query.update::<(Depth, Sprite)>(&engine, |_, (depth, _)| depth.0);
+        assert_eq!(order, &[back, front, new]);
+        assert_eq!(sorted_query.get_entities(), &[back, front, new]);
+    }
+
+    #[test]
+    fn test_query_sorted_nan() {
+        struct Depth(f32);
+
+        let mut engine = EntitiesAndComponents::new();
+        let depths = [3.0, f32::NAN, 1.0, 0.5, f32::NAN, -2.0, 7.0, 1.5];
+        let entities = depths
+            .iter()
+            .map(|depth| engine.add_entity_with((Depth(*depth),)))
+            .collect::<Vec<Entity>>();
+
+        // the NaN keys go last in the order they were found, the rest are sorted
+        let expected = vec![
+            entities[5],
+            entities[3],
+            entities[2],
+            entities[7],
+            entities[0],
+            entities[6],
+            entities[1],
+            entities[4],
+        ];
+        let sorted = engine.query_sorted::<(Depth,), _>(|_, (depth,)| depth.0);
+        assert_eq!(sorted, expected);
+
+        let mut sorted_query = SortedQuery::new();
+        let order = sorted_query.update::<(Depth,)>(&engine, |_, (depth,)| depth.0);
+        assert_eq!(order, &expected[..]);
+    }
+
+    #[test]
+    fn test_get_components_mut_pair() {
+        #[derive(Debug, PartialEq)]
+        struct Velocity(i32);
+        struct Ghost;
+
+        let mut engine = EntitiesAndComponents::new();
+        let first = engine.add_entity_with((Velocity(1),));
+        let second = engine.add_entity_with((Velocity(-2),));
+        let ghost = engine.add_entity_with((Ghost,));
+
+        let (a, b) = engine.get_components_mut_pair::<Velocity, Velocity>(first, second);
+        std::mem::swap(&mut a.0, &mut b.0);
+        assert_eq!(engine.get_components::<(Velocity,)>(first).0, &Velocity(-2));
+        assert_eq!(engine.get_components::<(Velocity,)>(second).0, &Velocity(1));
+
+        let (velocity, missing) =
+            engine.try_get_components_mut_pair::<Velocity, Velocity>(first, ghost);
+        assert!(velocity.is_some());
+        assert!(missing.is_none());
+
+        let same_entity = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            engine.get_components_mut_pair::<Velocity, Velocity>(first, first);
+        }));
+        assert!(same_entity.is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        struct Health;
+
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        let parent = engine.add_entity_with((Health,));
+        let child = engine.add_entity();
+        let grandchild = engine.add_entity();
+        engine.set_parent(child, parent);
+        engine.set_parent(grandchild, child);
+        assert!(world.validate().is_empty());
+
+        // corrupt the world the way a bad unsafe write could
+        let engine = &mut world.entities_and_components;
+        let gone = engine.add_entity();
+        engine.remove_entity(gone);
+        engine.get_components_mut::<(Parent,)>(child).0 .0 = gone;
+        engine.components[parent.entity_id].remove::<Health>();
+        let errors = world.validate();
+        assert!(errors.contains(&ValidationError::DanglingParent {
+            child,
+            parent: gone
+        }));
+        assert!(errors.contains(&ValidationError::MismatchedParent { parent, child }));
+        assert!(errors.contains(&ValidationError::MissingComponent {
+            entity: parent,
+            component: std::any::type_name::<Health>(),
+        }));
+        assert_eq!(errors.len(), 3);
+
+        let engine = &mut world.entities_and_components;
+        engine.get_components_mut::<(Parent,)>(child).0 .0 = grandchild;
+        let errors = world.validate();
+        assert!(errors.contains(&ValidationError::HierarchyCycle(child)));
+        assert!(errors.contains(&ValidationError::HierarchyCycle(grandchild)));
+    }
+
+    #[test]
+    fn test_depth_and_root() {
+        let mut engine = EntitiesAndComponents::new();
+        let root = engine.add_entity();
+        let child = engine.add_entity();
+        let grandchild = engine.add_entity();
+        let other_root = engine.add_entity();
+        engine.set_parent(grandchild, child);
+        assert_eq!(engine.get_depth(grandchild), 1);
+        assert_eq!(engine.get_root(grandchild), child);
+
+        // moving a subtree updates everything below it
+        engine.set_parent(child, root);
+        assert_eq!(engine.get_depth(root), 0);
+        assert_eq!(engine.get_root(root), root);
+        assert_eq!(engine.get_depth(child), 1);
+        assert_eq!(engine.get_depth(grandchild), 2);
+        assert_eq!(engine.get_root(grandchild), root);
+
+        engine.set_parent(root, other_root);
+        assert_eq!(engine.get_depth(grandchild), 3);
+        assert_eq!(engine.get_root(grandchild), other_root);
+
+        engine.remove_parent(child);
+        assert_eq!(engine.get_depth(child), 0);
+        assert_eq!(engine.get_depth(grandchild), 1);
+        assert_eq!(engine.get_root(grandchild), child);
+
+        let entity_map = engine.compact();
+        let grandchild = entity_map.get(grandchild).unwrap();
+        let child = entity_map.get(child).unwrap();
+        assert_eq!(engine.get_depth(grandchild), 1);
+        assert_eq!(engine.get_root(grandchild), child);
+    }
+
+    #[test]
+    fn test_visibility() {
+        struct Sprite;
+
+        let mut world = World::new();
+        world.add_visibility_system();
+        let engine = &mut world.entities_and_components;
+        let root = engine.add_entity_with((Visible(true), Sprite));
+        let group = engine.add_entity();
+        let child = engine.add_entity_with((Visible(true), Sprite));
+        let hidden_child = engine.add_entity_with((Visible(false), Sprite));
+        engine.set_parent(group, root);
+        engine.set_parent(child, group);
+        engine.set_parent(hidden_child, root);
+
+        let visible_sprites = |world: &World| {
+            let mut entities = world
+                .entities_and_components
+                .get_entities_matching::<VisibleOnly<(Sprite,)>>();
+            entities.sort();
+            entities
+        };
+
+        world.run();
+        let mut expected = vec![root, child];
+        expected.sort();
+        assert_eq!(visible_sprites(&world), expected);
+
+        // hiding the root hides everything below it, even through entities without Visible
+        world
+            .entities_and_components
+            .get_components_mut::<(Visible,)>(root)
+            .0
+             .0 = false;
+        world.run();
+        assert!(visible_sprites(&world).is_empty());
+        let (inherited,) = world
+            .entities_and_components
+            .get_components::<(InheritedVisibility,)>(child);
+        assert!(!inherited.is_visible());
+
+        // moving the child out from under the hidden root shows it again
+        world.entities_and_components.remove_parent(group);
+        world.run();
+        assert_eq!(visible_sprites(&world), vec![child]);
+    }
+
+    #[test]
+    fn test_ecs_cell() {
+        struct Enemy;
+        struct Score(i32);
+        struct ScoreBoard(Entity);
+        impl Resource for ScoreBoard {}
+
+        struct AddScore;
+        impl System for AddScore {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if single_entity.has_component::<Enemy>() {
+                    let board = single_entity.get_resource::<ScoreBoard>().0;
+                    let score = single_entity.get_cell::<Score>(board).unwrap();
+                    score.lock().0 += 1;
+                }
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let mut world = World::new();
+        world.register_cell_type::<Score>();
+        let engine = &mut world.entities_and_components;
+        let board = engine.add_entity_with((EcsCell::new(Score(0)),));
+        engine.add_resource(ScoreBoard(board));
+        for _ in 0..100 {
+            engine.add_entity_with((Enemy,));
+        }
+        world.add_system(AddScore);
+
+        world.run();
+        world.run();
+
+        let (score,) = world
+            .entities_and_components
+            .get_components::<(EcsCell<Score>,)>(board);
+        assert_eq!(score.lock().0, 200);
+
+        // stepping one entity on this thread reads the cell straight from the board
+        let enemy = world.entities_and_components.add_entity_with((Enemy,));
+        world.run_systems_on(enemy);
+        let (score,) = world
+            .entities_and_components
+            .get_components::<(EcsCell<Score>,)>(board);
+        assert_eq!(score.lock().0, 201);
+
+        // the cell types are registered per world
+        let mut other = World::new();
+        other.add_system(AddScore);
+        let engine = &mut other.entities_and_components;
+        let board = engine.add_entity_with((EcsCell::new(Score(0)),));
+        engine.add_resource(ScoreBoard(board));
+        let enemy = engine.add_entity_with((Enemy,));
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| other.run_systems_on(enemy)));
+        assert!(panic_message(&*result.unwrap_err()).contains("register_cell_type"));
+    }
+
+    #[test]
+    fn test_add_system_for() {
+        struct Player;
+        struct Enemy;
+        struct Steps(usize);
+
+        struct CountSteps;
+        impl System for CountSteps {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                assert!(single_entity.has_component::<Player>());
+                single_entity.get_component_mut::<Steps>().0 += 1;
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        let player = engine.add_entity_with((Player, Steps(0)));
+        let enemy = engine.add_entity_with((Enemy, Steps(0)));
+        world.add_system_for::<Player, _>(CountSteps);
+
+        world.run();
+        world.run();
+
+        let engine = &world.entities_and_components;
+        assert_eq!(engine.get_components::<(Steps,)>(player).0 .0, 2);
+        assert_eq!(engine.get_components::<(Steps,)>(enemy).0 .0, 0);
+    }
+
+    #[test]
+    fn test_pause_and_step() {
+        #[derive(Default)]
+        struct Frames(usize);
+        impl Resource for Frames {
+            fn update(&mut self) {
+                self.0 += 1;
+            }
+        }
+        #[derive(Default)]
+        struct Input(usize);
+        impl Resource for Input {
+            fn update(&mut self) {
+                self.0 += 1;
+            }
+        }
+        struct Ticks(usize);
+
+        struct Tick;
+        impl System for Tick {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                engine.get_resource_mut::<Ticks>().unwrap().0 += 1;
+            }
+        }
+        impl Resource for Ticks {}
+
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        engine.add_resource(Frames::default());
+        engine.add_resource(Input::default());
+        engine.add_resource(Ticks(0));
+        engine.set_resource_always_run::<Input>();
+        world.add_system(Tick);
+
+        let counts = |world: &World| {
+            let engine = &world.entities_and_components;
+            (
+                engine.get_resource::<Frames>().unwrap().0,
+                engine.get_resource::<Input>().unwrap().0,
+                engine.get_resource::<Ticks>().unwrap().0,
+            )
+        };
+
+        world.run();
+        assert_eq!(counts(&world), (1, 1, 1));
+
+        world.pause();
+        world.run();
+        world.run();
+        assert!(world.is_paused());
+        assert_eq!(counts(&world), (1, 3, 1));
+
+        world.step(2);
+        world.run();
+        world.run();
+        world.run();
+        assert_eq!(counts(&world), (3, 6, 3));
+
+        world.resume();
+        world.run();
+        assert_eq!(counts(&world), (4, 7, 4));
+    }
+
+    #[test]
+    fn test_frame_hooks() {
+        #[derive(Default)]
+        struct Stages(Vec<&'static str>);
+        impl Resource for Stages {
+            fn update(&mut self) {
+                self.0.push("resources");
+            }
+        }
+
+        struct Run;
+        impl System for Run {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                engine.get_resource_mut::<Stages>().unwrap().0.push("run");
+            }
+        }
+
+        let mut world = World::new();
+        world
+            .entities_and_components
+            .add_resource(Stages::default());
+        world.add_system(Run);
+        for (stage, name) in [
+            (FrameStage::End, "end"),
+            (FrameStage::AfterRun, "after run"),
+            (
+                FrameStage::AfterSingleEntityStep,
+                "after single entity step",
+            ),
+            (FrameStage::AfterPrestep, "after prestep"),
+            (FrameStage::Start, "start"),
+        ] {
+            world.on_frame(stage, move |engine| {
+                engine.get_resource_mut::<Stages>().unwrap().0.push(name);
+            });
+        }
+
+        world.run();
+        world.pause();
+        world.run();
+
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_resource::<Stages>()
+                .unwrap()
+                .0,
+            vec![
+                "start",
+                "resources",
+                "after prestep",
+                "after single entity step",
+                "run",
+                "after run",
+                "end",
+                "start",
+                "end",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_system_interval() {
+        struct Runs(Vec<usize>);
+        impl Resource for Runs {}
+
+        struct Expensive;
+        impl System for Expensive {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                let runs = engine.get_resource_mut::<Runs>().unwrap();
+                let frame = runs.0.len();
+                runs.0.push(frame);
+            }
+        }
+
+        struct Count(usize);
+        impl Resource for Count {}
+        struct EveryFrame;
+        impl System for EveryFrame {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                engine.get_resource_mut::<Count>().unwrap().0 += 1;
+            }
+        }
+
+        let mut world = World::new();
+        world.entities_and_components.add_resource(Runs(vec![]));
+        world.entities_and_components.add_resource(Count(0));
+        world.add_system_with_interval(Expensive, 3);
+        world.add_system(EveryFrame);
+
+        let mut ran_on = vec![];
+        for frame in 0..7 {
+            let before = world
+                .entities_and_components
+                .get_resource::<Runs>()
+                .unwrap()
+                .0
+                .len();
+            world.run();
+            let after = world
+                .entities_and_components
+                .get_resource::<Runs>()
+                .unwrap()
+                .0
+                .len();
+            if after > before {
+                ran_on.push(frame);
+            }
+        }
+
+        assert_eq!(ran_on, vec![0, 3, 6]);
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_resource::<Count>()
+                .unwrap()
+                .0,
+            7
+        );
+    }
+
+    #[test]
+    fn test_component_validators() {
+        struct Health(f32);
+        struct Position(f32);
+
+        struct Damage;
+        impl System for Damage {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if let Some(health) = single_entity.try_get_component_mut::<Health>() {
+                    health.0 -= 10.0;
+                }
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        struct Teleport;
+        impl System for Teleport {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine
+                    .get_entities_with_component::<Position>()
+                    .collect::<Vec<_>>()
+                {
+                    engine.get_components_mut::<(Position,)>(entity).0 .0 = f32::NAN;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        world.register_validator::<Health>(|health| health.0 >= 0.0);
+        world.register_validator::<Position>(|position| !position.0.is_nan());
+        let engine = &mut world.entities_and_components;
+        let healthy = engine.add_entity_with((Health(100.0),));
+        let dying = engine.add_entity_with((Health(5.0),));
+        let moving = engine.add_entity_with((Position(0.0),));
+        world.add_system(Damage);
+        world.add_system(Teleport);
+
+        world.run();
+
+        let failures = world.get_frame_report().failures();
+        assert_eq!(failures.len(), 2);
+        assert!(!failures
+            .iter()
+            .any(|failure| failure.entity == Some(healthy)));
+        let dying_failure = failures
+            .iter()
+            .find(|failure| failure.entity == Some(dying))
+            .unwrap();
+        assert!(dying_failure.system.ends_with("Damage"));
+        let moving_failure = failures
+            .iter()
+            .find(|failure| failure.entity == Some(moving))
+            .unwrap();
+        assert!(moving_failure.system.ends_with("Teleport"));
+    }
+
+    #[test]
+    fn test_previous_components() {
+        #[derive(Clone, PartialEq, Debug)]
+        struct Position(i32);
+
+        struct Move;
+        impl System for Move {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if let Some(position) = single_entity.try_get_component_mut::<Position>() {
+                    position.0 += 1;
+                }
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let mut world = World::new();
+        world.register_previous::<Position>();
+        world.add_system(Move);
+        let entity = world
+            .entities_and_components
+            .add_entity_with((Position(0),));
+
+        world.run();
+        let engine = &world.entities_and_components;
+        assert_eq!(
+            engine.get_components::<(Position, Prev<Position>)>(entity),
+            (&Position(1), &Prev(Position(0)))
+        );
+
+        world.run();
+        let engine = &world.entities_and_components;
+        assert_eq!(
+            engine.get_components::<(Position, Prev<Position>)>(entity),
+            (&Position(2), &Prev(Position(1)))
+        );
+
+        world
+            .entities_and_components
+            .remove_component_from::<Position>(entity);
+        world.run();
+        assert!(world
+            .entities_and_components
+            .try_get_component::<Prev<Position>>(entity)
+            .is_none());
+    }
+
+    #[test]
+    fn test_parents_before_children() {
+        struct Local(i32);
+        #[derive(Debug, PartialEq)]
+        struct Global(i32);
+
+        struct Propagate;
+        impl System for Propagate {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                let parent = single_entity
+                    .get_parent_component::<Global>()
+                    .map_or(0, |global| global.0);
+                let local = single_entity.get_component::<Local>().0;
+                single_entity.get_component_mut::<Global>().0 = parent + local;
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+
+            fn parents_before_children(&self) -> bool {
+                true
+            }
+        }
+
+        let mut world = World::new();
+        world.add_system(Propagate);
+        let engine = &mut world.entities_and_components;
+        let mut chains = vec![];
+        for _ in 0..20 {
+            let mut parent = engine.add_entity_with((Local(1), Global(0)));
+            let mut chain = vec![parent];
+            for _ in 0..10 {
+                let child = engine.add_entity_with((Local(1), Global(0)));
+                engine.set_parent(child, parent);
+                chain.push(child);
+                parent = child;
+            }
+            chains.push(chain);
+        }
+
+        world.run();
+
+        let engine = &world.entities_and_components;
+        for chain in chains {
+            for (depth, entity) in chain.into_iter().enumerate() {
+                assert_eq!(
+                    engine.get_components::<(Global,)>(entity).0,
+                    &Global(depth as i32 + 1)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_replay_migration() {
+        #[derive(Debug, PartialEq)]
+        struct Health {
+            current: i32,
+            max: i32,
+        }
+        impl ReplayComponent for Health {
+            const VERSION: u32 = 1;
+
+            fn save(&self) -> String {
+                format!("{} {}", self.current, self.max)
+            }
+            fn load(data: &str) -> Option<Self> {
+                let (current, max) = data.split_once(' ')?;
+                Some(Health {
+                    current: current.parse().ok()?,
+                    max: max.parse().ok()?,
+                })
+            }
+            // version 0 only saved the current health
+            fn migrate(old_version: u32, data: &str) -> Option<Self> {
+                match old_version {
+                    0 => Some(Health {
+                        current: data.parse().ok()?,
+                        max: 100,
+                    }),
+                    _ => None,
+                }
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "abc_ecs_replay_migration_{}.txt",
+            std::process::id()
+        ));
+        let name = std::any::type_name::<Health>();
+        fs::write(
+            &path,
+            format!("abc-ecs replay 1\nversion\t{name}\t0\nspawn\t1\nadd\t1\t{name}\t40\nframe\n"),
+        )
+        .unwrap();
+
+        let mut world = World::new();
+        world.register_replay_component::<Health>();
+        world.replay_from(&path).unwrap();
+        let entity = world.entities_and_components.get_entities()[0];
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_components::<(Health,)>(entity)
+                .0,
+            &Health {
+                current: 40,
+                max: 100
+            }
+        );
+
+        // files recorded now are saved with the current version and load without migrating
+        world.record_to(&path).unwrap();
+        world.finish_recording().unwrap();
+        let mut replayed = World::new();
+        replayed.register_replay_component::<Health>();
+        replayed.replay_from(&path).unwrap();
+        let entity = replayed.entities_and_components.get_entities()[0];
+        assert_eq!(
+            replayed
+                .entities_and_components
+                .get_components::<(Health,)>(entity)
+                .0
+                .max,
+            100
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_world_stats() {
+        struct Bullet;
+
+        struct Shoot;
+        impl System for Shoot {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for bullet in engine
+                    .get_entities_with_component::<Bullet>()
+                    .collect::<Vec<_>>()
+                {
+                    engine.remove_entity(bullet);
+                }
+                engine.add_entity_with((Bullet,));
+                engine.add_entity_with((Bullet,));
+            }
+        }
+
+        let mut world = World::new();
+        world.register_component::<Bullet>();
+        world.add_world_stats(true);
+        world.add_system(Shoot);
+
+        world.run();
+        world.run();
+
+        let stats = world
+            .entities_and_components
+            .get_resource::<WorldStats>()
+            .unwrap();
+        assert_eq!(stats.entity_count, 2);
+        assert_eq!((stats.spawned, stats.despawned), (2, 2));
+        assert_eq!(
+            stats.component_counts,
+            vec![(std::any::type_name::<Bullet>(), 2)]
+        );
+        assert_eq!(stats.system_timings.len(), 1);
+        assert!(stats.system_timings[0].0.ends_with("Shoot"));
+    }
+
+    #[test]
+    fn test_world_stats_pool_and_merge() {
+        #[derive(Clone)]
+        struct Bullet;
+
+        let mut world = World::new();
+        world.add_world_stats(false);
+        let mut pool = world.create_pool(2, || (Bullet,), |_, _| {});
+        world.run();
+        world.run();
+
+        let bullet = pool.acquire(&mut world.entities_and_components);
+        pool.release(&mut world.entities_and_components, bullet);
+        world.run();
+        let stats = world
+            .entities_and_components
+            .get_resource::<WorldStats>()
+            .unwrap();
+        assert_eq!((stats.spawned, stats.despawned), (1, 1));
+
+        let mut other = World::new();
+        other.entities_and_components.add_entity_with((Bullet,));
+        other.entities_and_components.add_entity_with((Bullet,));
+        world.merge(other, false);
+        world.compact();
+        world.run();
+        let stats = world
+            .entities_and_components
+            .get_resource::<WorldStats>()
+            .unwrap();
+        assert_eq!(
+            (stats.entity_count, stats.spawned, stats.despawned),
+            (2, 2, 0)
+        );
+
+        world.clear_entities();
+        world.run();
+        let stats = world
+            .entities_and_components
+            .get_resource::<WorldStats>()
+            .unwrap();
+        assert_eq!(
+            (stats.entity_count, stats.spawned, stats.despawned),
+            (0, 0, 2)
+        );
+    }
+
+    #[test]
+    fn test_resource_hooks() {
+        struct NetworkSession;
+        impl Resource for NetworkSession {}
+        struct Remote;
+        #[derive(Default)]
+        struct Log(Vec<&'static str>);
+        impl Resource for Log {}
+
+        struct SyncRemotes;
+        impl System for SyncRemotes {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if single_entity.try_get_resource::<NetworkSession>().is_none() {
+                    single_entity.remove_entity();
+                }
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let mut world = World::new();
+        world.add_system(SyncRemotes);
+        world.on_resource_added::<NetworkSession>(|engine| {
+            engine.add_entity_with((Remote,));
+            engine.init_resource::<Log>();
+            engine.get_resource_mut::<Log>().unwrap().0.push("added");
+        });
+        world.on_resource_removed::<NetworkSession>(|engine| {
+            engine.get_resource_mut::<Log>().unwrap().0.push("removed");
+        });
+
+        let engine = &mut world.entities_and_components;
+        engine.add_resource(NetworkSession);
+        // replacing the resource does not count as adding it
+        engine.add_resource(NetworkSession);
+        assert!(engine.has_resource::<NetworkSession>());
+        world.run();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_entity_count_with_component::<Remote>(),
+            1
+        );
+
+        world
+            .entities_and_components
+            .remove_resource::<NetworkSession>();
+        world.run();
+        let engine = &world.entities_and_components;
+        assert_eq!(engine.get_entity_count(), 0);
+        assert_eq!(
+            engine.get_resource::<Log>().unwrap().0,
+            vec!["added", "removed"]
+        );
+    }
+
+    #[test]
+    fn test_entity_bits() {
+        let mut engine = EntitiesAndComponents::new();
+        let entity = engine.add_entity();
+        let bits = entity.to_bits();
+        assert_eq!(Entity::from_bits(bits), entity);
+        assert_eq!(engine.entity_from_bits(bits), Some(entity));
+
+        engine.remove_entity(entity);
+        let reused = engine.add_entity();
+        assert_ne!(reused.to_bits(), bits);
+        assert_eq!(engine.entity_from_bits(bits), None);
+        assert_eq!(engine.entity_from_bits(reused.to_bits()), Some(reused));
+    }
+
+    #[test]
+    fn test_rng() {
+        struct RollSystem;
+
+        impl System for RollSystem {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                let roll = single_entity.rng().range(0..1000);
+                single_entity.get_component_mut::<Vec<u64>>().push(roll);
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        fn run_seeded(seed: u64) -> Vec<Vec<u64>> {
+            let mut world = World::new();
+            world
+                .entities_and_components
+                .add_resource(crate::Rng::new(seed));
+            world.add_system(RollSystem);
+            let entities: Vec<Entity> = (0..50)
+                .map(|_| {
+                    world
+                        .entities_and_components
+                        .add_entity_with((Vec::<u64>::new(),))
+                })
+                .collect();
+            for _ in 0..3 {
+                world.run();
+            }
+            entities
+                .iter()
+                .map(|entity| {
+                    world
+                        .entities_and_components
+                        .get_components::<(Vec<u64>,)>(*entity)
+                        .0
+                        .clone()
+                })
+                .collect()
+        }
+
+        let rolls = run_seeded(7);
+        assert_eq!(rolls, run_seeded(7));
+        assert_ne!(rolls, run_seeded(8));
+        // every frame and every entity gets a different stream
+        assert_ne!(rolls[0][0..1], rolls[0][1..2]);
+        assert_ne!(rolls[0], rolls[1]);
+
+        let mut rng = crate::Rng::new(3);
+        let value = rng.main().range_f32(-1.0..1.0);
+        assert!((-1.0..1.0).contains(&value));
+        assert_eq!(rng.stream(5).next_u64(), rng.stream(5).next_u64());
+        assert_ne!(rng.stream(5).next_u64(), rng.stream(6).next_u64());
+    }
+
+    #[test]
+    fn test_interpolated() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Transform {
+            position: [f32; 2],
+        }
+
+        impl Lerp for Transform {
+            fn lerp(&self, to: &Self, alpha: f32) -> Self {
+                Transform {
+                    position: self.position.lerp(&to.position, alpha),
+                }
+            }
+        }
+
+        struct MoveSystem;
+
+        impl System for MoveSystem {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine
+                    .get_entities_with_component::<Transform>()
+                    .collect::<Vec<_>>()
+                {
+                    engine.get_components_mut::<(Transform,)>(entity).0.position[0] += 10.0;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        world.register_previous::<Transform>();
+        world.add_system(MoveSystem);
+        let entity = world.entities_and_components.add_entity_with((Transform {
+            position: [0.0, 4.0],
+        },));
+        let other = world.entities_and_components.add_entity();
+
+        // without Prev the current value is used
+        assert_eq!(
+            world.interpolated::<Transform>(entity, 0.5),
+            Some(Transform {
+                position: [0.0, 4.0]
+            })
+        );
+        assert_eq!(world.interpolated::<Transform>(other, 0.5), None);
+
+        world.run();
+        world.run();
+        assert_eq!(
+            world.interpolated::<Transform>(entity, 0.25),
+            Some(Transform {
+                position: [12.5, 4.0]
+            })
+        );
+        assert_eq!(
+            world.interpolated::<Transform>(entity, 1.0),
+            Some(Transform {
+                position: [20.0, 4.0]
+            })
+        );
+    }
+
+    #[test]
+    fn test_query_str() {
+        struct Dead;
+
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        let alive = engine.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let dead = engine.add_entity_with((Position { x: 1.0, y: 0.0 }, Dead));
+        let moving = engine.add_entity_with((Velocity { x: 1.0, y: 0.0 },));
+
+        assert_eq!(
+            world.query_str("With(Position) & Without(Dead)"),
+            Ok(vec![alive])
+        );
+        let mut entities = world
+            .query_str("!With(Dead) & (With(Position) | With(Velocity))")
+            .unwrap();
+        entities.sort();
+        let mut expected = vec![alive, moving];
+        expected.sort();
+        assert_eq!(entities, expected);
+        assert_eq!(
+            world.query_str(&format!("With({})", std::any::type_name::<Dead>())),
+            Ok(vec![dead])
+        );
+
+        assert_eq!(
+            world.query_str("With(Health)"),
+            Err(QueryStrError::UnknownComponent("Health".to_string()))
+        );
+        assert!(matches!(
+            world.query_str("With(Position) &"),
+            Err(QueryStrError::Syntax { position: 16, .. })
+        ));
+        assert!(matches!(
+            world.query_str("With(Position) With(Dead)"),
+            Err(QueryStrError::Syntax { .. })
+        ));
+
+        // deep queries are rejected instead of overflowing the stack, long chains are fine
+        let nots = format!("{}With(Dead)", "!".repeat(200_000));
+        assert!(matches!(
+            world.query_str(&nots),
+            Err(QueryStrError::Syntax { position: 257, .. })
+        ));
+        let brackets = format!("{}With(Dead){}", "(".repeat(300), ")".repeat(300));
+        assert!(matches!(
+            world.query_str(&brackets),
+            Err(QueryStrError::Syntax { position: 257, .. })
+        ));
+        let chain = vec!["With(Dead)"; 100_000].join(" & ");
+        assert_eq!(world.query_str(&chain), Ok(vec![dead]));
+    }
+
+    #[test]
+    fn test_system_exclusions() {
+        struct Ai;
+
+        impl System for Ai {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                single_entity.get_component_mut::<Position>().x += 1.0;
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        fn x_of(world: &World, entity: Entity) -> f32 {
+            world
+                .entities_and_components
+                .get_components::<(Position,)>(entity)
+                .0
+                .x
+        }
+
+        let mut world = World::new();
+        let ai = world.add_system(Ai);
+        let actor = world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let skipping = world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, SkipSystem::<Ai>::new()));
+        let other = world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+
+        world.exclude_from_system(&ai, actor);
+        assert!(world.is_excluded_from_system(&ai, actor));
+        world.run();
+        world.run_systems_on(actor);
+        assert_eq!(x_of(&world, actor), 0.0);
+        assert_eq!(x_of(&world, skipping), 0.0);
+        assert_eq!(x_of(&world, other), 1.0);
+
+        world.include_in_system(&ai, actor);
+        world
+            .entities_and_components
+            .remove_component_from::<SkipSystem<Ai>>(skipping);
+        world.run();
+        assert_eq!(x_of(&world, actor), 1.0);
+        assert_eq!(x_of(&world, skipping), 1.0);
+        assert_eq!(x_of(&world, other), 2.0);
+    }
+
+    #[test]
+    fn test_frame_alloc() {
+        struct Mover;
+
+        impl System for Mover {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                let mut entities = engine.frame_alloc().vec::<Entity>();
+                entities.extend(engine.get_entities_with_component::<Position>());
+                for entity in entities.iter() {
+                    engine.get_components_mut::<(Position,)>(*entity).0.x += 1.0;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        world.add_system(Mover);
+        let entity = world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        world.run();
+        world.run();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_components::<(Position,)>(entity)
+                .0
+                .x,
+            2.0
+        );
+
+        let arena = world.frame_alloc();
+        assert_eq!(arena.free_buffers(), 1);
+        let capacity = {
+            let mut first = arena.vec::<u32>();
+            first.extend(0..100);
+            first.capacity()
+        };
+        // the memory of the dropped vec is reused, and it comes back empty
+        let reused = arena.vec::<u32>();
+        assert!(reused.is_empty());
+        assert_eq!(reused.capacity(), capacity);
+        drop(reused);
+
+        // buffers that were not used during a frame are freed at the end of it
+        world.remove_all_systems();
+        world.run();
+        world.run();
+        assert_eq!(arena.free_buffers(), 0);
+    }
+
+    #[test]
+    fn test_run_reuses_buffers() {
+        struct Mover;
+
+        impl System for Mover {
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                single_entity.get_component_mut::<Position>().x += 1.0;
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let mut world = World::new();
+        world.add_system(Mover);
+        world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        world.run();
+        // the markers, the level ends and the chunk results go back to the frame arena
+        assert_eq!(world.frame_alloc().free_buffers(), 3);
+
+        world.run();
+        assert_eq!(world.frame_alloc().free_buffers(), 3);
+    }
+
+    #[test]
+    fn test_query_trait() {
+        trait Drawable {
+            fn layer(&self) -> u32;
+        }
+
+        struct Sprite(u32);
+        struct Text;
+
+        impl Drawable for Sprite {
+            fn layer(&self) -> u32 {
+                self.0
+            }
+        }
+
+        impl Drawable for Text {
+            fn layer(&self) -> u32 {
+                10
+            }
+        }
+
+        let mut world = World::new();
+        world.register_trait_impl::<dyn Drawable, Sprite>(|sprite| sprite);
+        world.register_trait_impl::<dyn Drawable, Text>(|text| text);
+        world.register_trait_impl::<dyn Drawable, Text>(|text| text);
+
+        let engine = &mut world.entities_and_components;
+        let sprite = engine.add_entity_with((Sprite(1),));
+        let both = engine.add_entity_with((Sprite(2), Text));
+        engine.add_entity_with((Position { x: 0.0, y: 0.0 },));
+
+        let mut drawn = world
+            .query_trait::<dyn Drawable>()
+            .map(|(entity, drawable)| (entity, drawable.layer()))
+            .collect::<Vec<_>>();
+        drawn.sort();
+        let mut expected = vec![(sprite, 1), (both, 2), (both, 10)];
+        expected.sort();
+        assert_eq!(drawn, expected);
+
+        let mut layers = world
+            .entities_and_components
+            .get_trait_components::<dyn Drawable>(both)
+            .iter()
+            .map(|drawable| drawable.layer())
+            .collect::<Vec<_>>();
+        layers.sort();
+        assert_eq!(layers, vec![2, 10]);
+        assert_eq!(world.query_trait::<dyn std::fmt::Debug>().count(), 0);
+
+        // the thread safe view only gives out traits that are Sync
+        world.register_trait_impl::<dyn Drawable + Sync, Sprite>(|sprite| sprite);
+        let thread_safe = EntitiesAndComponentsThreadSafe::new(&mut world.entities_and_components);
+        assert_eq!(thread_safe.query_trait::<dyn Drawable + Sync>().count(), 2);
+    }
+
+    #[test]
+    fn test_scene() {
+        #[derive(Debug, PartialEq)]
+        struct Name(String);
+
+        impl ReplayComponent for Name {
+            fn save(&self) -> String {
+                self.0.clone()
+            }
+
+            fn load(data: &str) -> Option<Self> {
+                Some(Name(data.to_string()))
+            }
+        }
+
+        struct Point(i32, i32);
+
+        impl ReplayComponent for Point {
+            fn save(&self) -> String {
+                format!("{} {}", self.0, self.1)
+            }
+
+            fn load(data: &str) -> Option<Self> {
+                let (x, y) = data.split_once(' ')?;
+                Some(Point(x.parse().ok()?, y.parse().ok()?))
+            }
+        }
+
+        let mut world = World::new();
+        world.register_replay_component::<Name>();
+        world.register_replay_component::<Point>();
+        let engine = &mut world.entities_and_components;
+        let root = engine.add_entity_with((Name("level \"one\"".to_string()),));
+        let tree = engine.add_entity_with((Name("tree".to_string()), Point(1, 2)));
+        let leaf = engine.add_entity_with((Point(3, 4), Velocity { x: 0.0, y: 0.0 }));
+        let rock = engine.add_entity();
+        engine.set_parent(tree, root);
+        engine.set_parent(leaf, tree);
+        engine.set_parent(rock, root);
+
+        let scene = world.save_scene(root).unwrap();
+        assert!(scene.contains("Point\": \"3 4\""));
+        assert!(!scene.contains("Velocity"));
+
+        let mut loaded_world = World::new();
+        loaded_world.register_replay_component::<Name>();
+        loaded_world.register_replay_component::<Point>();
+        let loaded = loaded_world.load_scene(&scene).unwrap();
+        // the loaded entities have different ids
+        let without_ids = |scene: &str| {
+            scene
+                .lines()
+                .filter(|line| !line.trim_start().starts_with("id:"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        assert_eq!(
+            without_ids(&loaded_world.save_scene(loaded).unwrap()),
+            without_ids(&scene)
+        );
+        let engine = &loaded_world.entities_and_components;
+        assert_eq!(
+            engine.get_components::<(Name,)>(loaded).0 .0,
+            "level \"one\""
+        );
+        let children = engine.get_children(loaded);
+        assert_eq!(children.len(), 2);
+        assert_eq!(engine.get_children(children[0]).len(), 1);
+
+        // hand written scenes can have comments and leave out trailing commas
+        let written = "Scene(
+            versions: {}, // Name is version 0
+            root: Entity(
+                components: { \"ABC_ECS::tests::test_scene::Name\": \"hand\" },
+                children: [Entity(components: {}, children: [])]
+            )
+        )";
+        let hand = loaded_world.load_scene(written).unwrap();
+        assert_eq!(
+            loaded_world
+                .entities_and_components
+                .get_components::<(Name,)>(hand)
+                .0
+                 .0,
+            "hand"
+        );
+
+        let entity_count = loaded_world.entities_and_components.get_entity_count();
+        let error = loaded_world
+            .load_scene(&scene.replace("3 4", "three four"))
+            .unwrap_err();
+        assert!(error.message.contains("could not load"));
+        assert_eq!(
+            loaded_world.entities_and_components.get_entity_count(),
+            entity_count
+        );
+        assert_eq!(
+            loaded_world
+                .load_scene("Scene(\n  versions: [")
+                .unwrap_err()
+                .line,
+            2
+        );
+
+        // the error is on the line of the first entity nested more than 256 deep
+        let mut nested = String::from("Scene(versions: {}, root:\n");
+        for _ in 0..300 {
+            nested.push_str("Entity(components: {}, children: [\n");
+        }
+        let error = loaded_world.load_scene(&nested).unwrap_err();
+        assert!(error.message.contains("nested"));
+        assert_eq!(error.line, 259);
+
+        // saving stops at the same depth, so every saved scene can be loaded
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        let root = engine.add_entity();
+        let mut parent = root;
+        for _ in 0..256 {
+            let child = engine.add_entity();
+            engine.set_parent(child, parent);
+            parent = child;
+        }
+        let scene = world.save_scene(root).unwrap();
+        assert!(loaded_world.load_scene(&scene).is_ok());
+        let too_deep = world.entities_and_components.add_entity();
+        world.entities_and_components.set_parent(too_deep, parent);
+        let error = world.save_scene(root).unwrap_err();
+        assert!(error.message.contains("nested"));
+        // the line the entity would have started on, 4 lines below the start of its parent
+        let parent_line = scene
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.contains("Entity("))
+            .last()
+            .unwrap()
+            .0
+            + 1;
+        assert_eq!(error.line, parent_line + 4);
+    }
+
+    #[test]
+    fn test_scene_spawn_hooks() {
+        struct Name(String);
+
+        impl ReplayComponent for Name {
+            fn save(&self) -> String {
+                self.0.clone()
+            }
+
+            fn load(data: &str) -> Option<Self> {
+                (!data.is_empty()).then(|| Name(data.to_string()))
+            }
+        }
+
+        let spawned = Arc::new(Mutex::new(vec![]));
+        let despawned = Arc::new(Mutex::new(0));
+        let mut world = World::new();
+        world.register_replay_component::<Name>();
+        let spawned_by_hook = spawned.clone();
+        world.on_spawn(move |single_entity| {
+            let name = single_entity
+                .try_get_component::<Name>()
+                .map(|name| name.0.clone());
+            let has_parent = single_entity.get_parent_component::<Name>().is_some();
+            spawned_by_hook.lock().unwrap().push((name, has_parent));
+        });
+        let despawned_by_hook = despawned.clone();
+        world.on_despawn(move |_| *despawned_by_hook.lock().unwrap() += 1);
+
+        let name = std::any::type_name::<Name>();
+        let scene = format!(
+            "Scene(versions: {{}}, root: Entity(components: {{ \"{name}\": \"root\" }}, children: [
+                Entity(components: {{ \"{name}\": \"child\" }}, children: []),
+            ]))"
+        );
+        world.load_scene(&scene).unwrap();
+        // the hooks see the components and the parent of the loaded entities
+        assert_eq!(
+            *spawned.lock().unwrap(),
+            vec![
+                (Some("root".to_string()), false),
+                (Some("child".to_string()), true)
+            ]
+        );
+
+        // nothing is spawned or despawned when the scene can not be loaded
+        spawned.lock().unwrap().clear();
+        assert!(world.load_scene(&scene.replace("child", "")).is_err());
+        assert!(spawned.lock().unwrap().is_empty());
+        assert_eq!(*despawned.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_trace_capture() {
+        struct Traced;
+
+        impl System for Traced {
+            fn prestep(&mut self, _engine: &EntitiesAndComponentsThreadSafe) {}
+
+            fn implements_prestep(&self) -> bool {
+                true
+            }
+
+            fn single_entity_step(&self, _single_entity: &mut SingleMutEntity) {}
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+
+            fn run(&mut self, _engine: &mut EntitiesAndComponents) {}
+        }
+
+        let mut world = World::new();
+        world.add_system(Traced);
+        for _ in 0..12 {
+            world.entities_and_components.add_entity();
+        }
+        world.run();
+
+        world.start_capture();
+        assert!(world.is_capturing());
+        world.run();
+        world.run();
+        let path = std::env::temp_dir().join("abc_ecs_test_trace_capture.json");
+        world.stop_capture_to_json(&path).unwrap();
+        assert!(!world.is_capturing());
+
+        let json = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(json.starts_with("{\"traceEvents\":["));
+        let count = |category: &str| json.matches(&format!("\"cat\":\"{category}\"")).count();
+        assert_eq!(count("frame"), 2);
+        assert_eq!(count("prestep"), 2);
+        assert_eq!(count("run"), 2);
+        // 12 entities in chunks of 5 is 3 chunks a frame
+        assert_eq!(count("single_entity_step"), 6);
+        assert!(json.contains("\"args\":{\"entities\":2}"));
+        assert!(json.contains(std::any::type_name::<Traced>()));
+    }
+
+    #[test]
+    fn test_map_entities() {
+        #[derive(Debug, PartialEq)]
+        struct Target(Entity);
+
+        impl MapEntities for Target {
+            fn map_entities(&mut self, entity_map: &EntityMap) {
+                self.0 = entity_map.map(self.0);
+            }
+        }
+
+        impl ReplayComponent for Target {
+            fn save(&self) -> String {
+                self.0.to_bits().to_string()
+            }
+
+            fn load(data: &str) -> Option<Self> {
+                Some(Target(Entity::from_bits(data.parse().ok()?)))
+            }
+        }
+
+        fn target_of(world: &World, entity: Entity) -> Entity {
+            world
+                .entities_and_components
+                .get_components::<(Target,)>(entity)
+                .0
+                 .0
+        }
+
+        let mut world = World::new();
+        world.register_map_entities::<Target>();
+        world.register_replay_component::<Target>();
+        let engine = &mut world.entities_and_components;
+        let removed = engine.add_entity();
+        let root = engine.add_entity();
+        let turret = engine.add_entity();
+        engine.set_parent(turret, root);
+        engine.add_component_to(turret, Target(root));
+        engine.remove_entity(removed);
+
+        // scenes remap the saved ids to the loaded entities
+        let scene = world.save_scene(root).unwrap();
+        let loaded_root = world.load_scene(&scene).unwrap();
+        let loaded_turret = world.entities_and_components.get_children(loaded_root)[0];
+        assert_eq!(target_of(&world, loaded_turret), loaded_root);
+        assert_eq!(target_of(&world, turret), root);
+
+        let entity_map = world.compact();
+        let turret = entity_map.map(turret);
+        assert_eq!(target_of(&world, turret), entity_map.map(root));
+
+        let mut other = World::new();
+        other.entities_and_components.add_entity();
+        let entity_map = other.merge(std::mem::take(&mut world), false);
+        let parent = other
+            .entities_and_components
+            .get_parent(entity_map.map(turret));
+        assert_eq!(Some(target_of(&other, entity_map.map(turret))), parent);
+    }
+
+    #[test]
+    fn test_resource_snapshot() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Score(u32);
+        impl Resource for Score {}
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Bonus(u32);
+        impl Resource for Bonus {}
+
+        struct NotSaved(u32);
+        impl Resource for NotSaved {}
+
+        let mut world = World::new();
+        world.register_clone_resource::<Score>();
+        world.register_clone_resource::<Bonus>();
+        world.register_clone_resource::<crate::Rng>();
+        let engine = &mut world.entities_and_components;
+        engine.add_resource(Score(10));
+        engine.add_resource(NotSaved(1));
+        engine.add_resource(crate::Rng::new(4));
+        let rolls = engine
+            .get_resource_mut::<crate::Rng>()
+            .unwrap()
+            .main()
+            .next_u64();
+
+        let snapshot = world.snapshot_resources();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.get::<Score>(), Some(&Score(10)));
+
+        for _ in 0..2 {
+            let engine = &mut world.entities_and_components;
+            engine.get_resource_mut::<Score>().unwrap().0 = 50;
+            engine.get_resource_mut::<NotSaved>().unwrap().0 = 2;
+            engine.add_resource(Bonus(3));
+            let next_roll = engine
+                .get_resource_mut::<crate::Rng>()
+                .unwrap()
+                .main()
+                .next_u64();
+            assert_ne!(next_roll, rolls);
+
+            // the snapshot can be restored more than once
+            world.restore_resources(&snapshot);
+            let engine = &mut world.entities_and_components;
+            assert_eq!(engine.get_resource::<Score>(), Some(&Score(10)));
+            assert!(!engine.has_resource::<Bonus>());
+            assert_eq!(engine.get_resource::<NotSaved>().unwrap().0, 2);
+            assert_eq!(
+                engine
+                    .get_resource_mut::<crate::Rng>()
+                    .unwrap()
+                    .main()
+                    .next_u64(),
+                next_roll
+            );
+            world.restore_resources(&snapshot);
+        }
+    }
+
+    #[test]
+    fn test_warm_up() {
+        let mut world = World::new();
+        world.register_component::<Position>();
+        world.register_component::<Velocity>();
+        world.set_warm_up_capacity(500);
+        world.set_warm_up_component_capacity::<Velocity>(200);
+        world.warm_up();
+
+        let entities_and_components = &world.entities_and_components;
+        assert!(entities_and_components.entities.capacity() >= 500);
+        assert!(entities_and_components.components.capacity() >= 500);
+        // only the types given a capacity get room for entities
+        let capacity =
+            |type_id: TypeId| match &entities_and_components.entities_with_components[&type_id] {
+                ComponentEntities::Map(entities) => entities.capacity(),
+                ComponentEntities::Custom(_) => unreachable!(),
+            };
+        assert!(capacity(TypeId::of::<Velocity>()) >= 200);
+        assert!(capacity(TypeId::of::<Position>()) < 200);
+        assert!(world.entities_buffer.capacity() >= 500);
+        assert_eq!(world.entities_and_components.get_entity_count(), 0);
+
+        // warming up again with entities in the world keeps them
+        let entity = world
+            .entities_and_components
+            .add_entity_with((Position { x: 1.0, y: 2.0 },));
+        world.warm_up();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_entity_count_with_component::<Position>(),
+            1
+        );
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_components::<(Position,)>(entity)
+                .0
+                .x,
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_unique_system() {
+        struct MoveSystem {
+            speed: f32,
+        }
+
+        impl System for MoveSystem {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine
+                    .get_entities_with_component::<Position>()
+                    .collect::<Vec<Entity>>()
+                {
+                    engine.get_components_mut::<(Position,)>(entity).0.x += self.speed;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        let entity = world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        assert!(world.get_system_handle::<MoveSystem>().is_none());
+
+        let Ok(first) =
+            world.try_add_unique_system(MoveSystem { speed: 1.0 }, DuplicateSystemPolicy::Refuse)
+        else {
+            panic!("the system should have been added");
+        };
+        let Err(refused) =
+            world.try_add_unique_system(MoveSystem { speed: 5.0 }, DuplicateSystemPolicy::Refuse)
+        else {
+            panic!("the second MoveSystem should have been refused");
+        };
+        assert_eq!(refused.system_id, first.system_id);
+
+        world.run();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_components::<(Position,)>(entity)
+                .0
+                .x,
+            1.0
+        );
+
+        let Ok(replaced) =
+            world.try_add_unique_system(MoveSystem { speed: 10.0 }, DuplicateSystemPolicy::Replace)
+        else {
+            panic!("the system should have been added");
+        };
+        assert_eq!(replaced.system_id, first.system_id);
+        assert_eq!(world.systems.len(), 1);
+
+        world.run();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_components::<(Position,)>(entity)
+                .0
+                .x,
+            11.0
+        );
+    }
+
+    #[test]
+    fn test_add_component_if_absent() {
+        let mut world = World::new();
+        world
+            .entities_and_components
+            .add_index::<Position, i32>(|position| position.x as i32);
+        let entity = world
+            .entities_and_components
+            .add_entity_with((Position { x: 1.0, y: 0.0 },));
+
+        // the existing component is kept
+        assert!(!world
+            .entities_and_components
+            .add_component_if_absent(entity, Position { x: 5.0, y: 0.0 }));
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_components::<(Position,)>(entity)
+                .0
+                .x,
+            1.0
+        );
+
+        let velocity = world
+            .entities_and_components
+            .get_or_insert_component_with(entity, || Velocity { x: 2.0, y: 0.0 });
+        velocity.x += 1.0;
+        let velocity = world
+            .entities_and_components
+            .get_or_insert_component_with::<Velocity>(entity, || {
+                panic!("create should not be called")
+            });
+        assert_eq!(velocity.x, 3.0);
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_entity_count_with_component::<Velocity>(),
+            1
+        );
+
+        let other = world.entities_and_components.add_entity();
+        assert!(world
+            .entities_and_components
+            .add_component_if_absent(other, Position { x: 7.0, y: 0.0 }));
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_entities_by_key::<Position, i32>(&7),
+            &[other]
+        );
+    }
+
+    #[test]
+    fn test_read_only_system() {
+        struct CountPositions {
+            counted: Arc<Mutex<Vec<usize>>>,
+        }
+
+        impl ReadOnlySystem for CountPositions {
+            fn run(&self, engine: &EntitiesAndComponentsThreadSafe) {
+                let count = engine.get_entity_count_with_component::<Position>();
+                self.counted.lock().unwrap().push(count);
+            }
+        }
+
+        struct FailingCheck;
+
+        impl ReadOnlySystem for FailingCheck {
+            fn try_run(
+                &self,
+                _engine: &EntitiesAndComponentsThreadSafe,
+            ) -> Result<(), SystemError> {
+                Err(SystemError::new("check failed"))
+            }
+        }
+
+        let counted = Arc::new(Mutex::new(vec![]));
+        let mut world = World::new();
+        world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 0.0 }));
+        world.add_read_only_system(CountPositions {
+            counted: counted.clone(),
+        });
+        let handle = world.add_read_only_system(CountPositions {
+            counted: counted.clone(),
+        });
+        world.add_system(MovementSystem {});
+        world.add_read_only_system(FailingCheck);
+
+        world.run();
+        assert_eq!(*counted.lock().unwrap(), vec![1, 1]);
+        assert_eq!(world.get_frame_report().failures().len(), 1);
+
+        world.remove_system(handle);
+        world.run();
+        assert_eq!(*counted.lock().unwrap(), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn test_reserve_entities() {
+        struct SpawnBullets;
+
+        impl System for SpawnBullets {
+            fn prestep(&mut self, engine: &EntitiesAndComponentsThreadSafe) {
+                for entity in engine.reserve_entities(2).unwrap() {
+                    engine.queue_add_component_to(entity, Position { x: 0.0, y: 0.0 });
+                }
+            }
+
+            fn implements_prestep(&self) -> bool {
+                true
+            }
+
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if single_entity.has_component::<Velocity>() {
+                    let bullet = single_entity.reserve_entities(1).unwrap()[0];
+                    single_entity.queue_add_component_to(bullet, Position { x: 5.0, y: 0.0 });
+                }
+            }
+
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+        }
+
+        let spawned = Arc::new(Mutex::new(vec![]));
+        let mut world = World::new();
+        // nothing is ready before set_entity_reserve is called
+        let thread_safe = EntitiesAndComponentsThreadSafe::new(&mut world.entities_and_components);
+        assert!(thread_safe.reserve_entities(1).is_err());
+        let spawned_by_hook = spawned.clone();
+        world
+            .entities_and_components
+            .on_spawn(move |single_entity| {
+                // the components were added before the entity was spawned
+                let has_position = single_entity.has_component::<Position>();
+                spawned_by_hook.lock().unwrap().push(has_position);
+            });
+        world
+            .entities_and_components
+            .add_entity_with((Velocity { x: 1.0, y: 0.0 },));
+        spawned.lock().unwrap().clear();
+        world.set_entity_reserve(4);
+        world.add_system(SpawnBullets);
+
+        world.run();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_entity_count_with_component::<Position>(),
+            3
+        );
+        assert_eq!(*spawned.lock().unwrap(), vec![true, true, true]);
+        // the ready entity that was not handed out is kept for the next frame, but it is not listed until it is spawned
+        assert_eq!(world.entities_and_components.get_entity_count(), 1 + 3);
+        assert_eq!(world.entities_and_components.iter_entities().count(), 1 + 3);
+        // cleanup and inspect leave the ready entity alone too
+        assert_eq!(
+            world.entities_and_components.inspect().entities.len(),
+            1 + 3
+        );
+        let stats = world
+            .entities_and_components
+            .cleanup(CleanupPolicy::RemoveEmpty);
+        assert_eq!(stats.checked, 1 + 3);
+        assert_eq!(stats.empty, 0);
+        assert_eq!(stats.removed, 0);
+
+        world.run();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_entity_count_with_component::<Position>(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_reserve_entities_direct() {
+        let spawned = Arc::new(Mutex::new(vec![]));
+        let mut world = World::new();
+        let spawned_by_hook = spawned.clone();
+        world.on_spawn(move |single_entity| {
+            let has_position = single_entity.has_component::<Position>();
+            spawned_by_hook.lock().unwrap().push(has_position);
+        });
+
+        let engine = &mut world.entities_and_components;
+        let entities = engine.reserve_entities(2);
+        // the entities can be used right away, but they are not spawned yet
+        assert_eq!(engine.get_entity_count(), 2);
+        assert!(spawned.lock().unwrap().is_empty());
+        for entity in entities {
+            engine.add_component_to(entity, Position { x: 0.0, y: 0.0 });
+        }
+
+        world.run();
+        assert_eq!(*spawned.lock().unwrap(), vec![true, true]);
+        world.run();
+        assert_eq!(spawned.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_effective() {
+        #[derive(Debug, PartialEq)]
+        struct TeamColor(u32);
+
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        let team = engine.add_entity_with((TeamColor(1),));
+        let squad = engine.add_entity();
+        let soldier = engine.add_entity();
+        let loner = engine.add_entity();
+        engine.set_parent(squad, team);
+        engine.set_parent(soldier, squad);
+
+        assert_eq!(engine.effective::<TeamColor>(soldier), Some(&TeamColor(1)));
+        assert_eq!(engine.get_effective_owner::<TeamColor>(squad), Some(team));
+        assert_eq!(engine.effective::<TeamColor>(loner), None);
+
+        // a closer parent with the component takes over
+        engine.add_component_to(squad, TeamColor(2));
+        assert_eq!(engine.effective::<TeamColor>(soldier), Some(&TeamColor(2)));
+
+        engine.remove_component_from::<TeamColor>(squad);
+        assert_eq!(engine.effective::<TeamColor>(soldier), Some(&TeamColor(1)));
+
+        engine.set_parent(soldier, loner);
+        assert_eq!(engine.effective::<TeamColor>(soldier), None);
+        engine.add_component_to(soldier, TeamColor(3));
+        assert_eq!(world.effective::<TeamColor>(soldier), Some(&TeamColor(3)));
+    }
+
+    #[test]
+    fn test_iter_signatures() {
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        let moving = (0..3)
+            .map(|_| {
+                engine.add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 0.0 }))
+            })
+            .collect::<Vec<Entity>>();
+        let still = engine.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        engine.add_entity();
+
+        let signatures = world
+            .iter_signatures()
+            .map(|(type_ids, count, entities)| (type_ids, count, entities.collect::<Vec<Entity>>()))
+            .collect::<Vec<(Vec<TypeId>, usize, Vec<Entity>)>>();
+        assert_eq!(signatures.len(), 3);
+
+        let mut moving_types = vec![TypeId::of::<Position>(), TypeId::of::<Velocity>()];
+        moving_types.sort_unstable();
+        assert_eq!(signatures[0].0, moving_types);
+        assert_eq!(signatures[0].1, 3);
+        let mut moving_entities = signatures[0].2.clone();
+        moving_entities.sort_by_key(|entity| entity.to_bits());
+        assert_eq!(moving_entities, moving);
+
+        assert!(signatures.iter().any(|(type_ids, count, entities)| type_ids
+            == &vec![TypeId::of::<Position>()]
+            && *count == 1
+            && entities == &vec![still]));
+        assert!(signatures
+            .iter()
+            .any(|(type_ids, count, _)| type_ids.is_empty() && *count == 1));
+        assert!(world
+            .entities_and_components
+            .get_component_type_name(TypeId::of::<Velocity>())
+            .is_some_and(|name| name.ends_with("Velocity")));
+    }
+
+    #[test]
+    fn test_component_storage() {
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        let before = engine.add_entity_with((Position { x: 1.0, y: 0.0 },));
+        engine.set_component_storage::<Position, _>(DenseEntitySet::new());
+
+        let entities = (0..100)
+            .map(|i| {
+                engine.add_entity_with((Position {
+                    x: i as f32,
+                    y: 0.0,
+                },))
+            })
+            .collect::<Vec<Entity>>();
+        assert_eq!(engine.get_entity_count_with_component::<Position>(), 101);
+        assert!(engine
+            .get_entities_with_component::<Position>()
+            .any(|entity| entity == before));
+
+        for entity in entities.iter().step_by(2) {
+            engine.remove_entity(*entity);
+        }
+        engine.remove_component_from::<Position>(before);
+        let mut listed = engine
+            .get_entities_with_component::<Position>()
+            .collect::<Vec<Entity>>();
+        listed.sort_by_key(|entity| entity.index());
+        let mut expected = entities
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .copied()
+            .collect::<Vec<Entity>>();
+        expected.sort_by_key(|entity| entity.index());
+        assert_eq!(listed, expected);
+
+        // a new entity reusing a removed index is not mistaken for the removed one
+        let reused = engine.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        assert!(entities
+            .iter()
+            .any(|entity| entity.index() == reused.index()));
+        assert_eq!(engine.get_entity_count_with_component::<Position>(), 51);
+        assert!(engine.validate().is_empty());
+
+        engine.reset_component_storage::<Position>();
+        assert_eq!(engine.get_entity_count_with_component::<Position>(), 51);
+        assert!(engine.validate().is_empty());
+    }
+
+    #[test]
+    fn test_replay_encoder() {
+        #[derive(Debug, PartialEq)]
+        struct Position(f32, f32, f32);
+        impl ReplayComponent for Position {
+            fn save(&self) -> String {
+                format!("{} {} {}", self.0, self.1, self.2)
+            }
+            fn load(data: &str) -> Option<Self> {
+                let mut fields = data.split(' ').map(|field| field.parse::<f32>().ok());
+                Some(Position(fields.next()??, fields.next()??, fields.next()??))
+            }
+        }
+
+        struct Fall;
+        impl System for Fall {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine
+                    .get_entities_with_component::<Position>()
+                    .collect::<Vec<_>>()
+                {
+                    let (position,) = engine.get_components_mut::<(Position,)>(entity);
+                    position.1 -= 0.5;
+                }
+            }
+        }
+
+        assert_eq!(
+            TextDelta.encode("health 100", "health 90").unwrap(),
+            "7,1,9"
+        );
+        assert_eq!(
+            TextDelta.decode("health 100", "7,1,9").unwrap(),
+            "health 90"
+        );
+        assert_eq!(TextDelta.decode("short", "4,4,x"), None);
+        assert_eq!(
+            FieldDelta::new(' ').encode("1 2 3", "1 5 3").unwrap(),
+            " 5 "
+        );
+        assert_eq!(FieldDelta::new(' ').encode("1 2", "1 2 3"), None);
+
+        let path =
+            std::env::temp_dir().join(format!("abc_ecs_replay_encoder_{}.txt", std::process::id()));
+
+        let mut world = World::new();
+        world.register_replay_component::<Position>();
+        world.register_replay_encoder::<Position>(FieldDelta::new(' '));
+        world.add_system(Fall);
+        world
+            .entities_and_components
+            .add_entity_with((Position(1000.25, 20.5, -3000.75),));
+        world.record_to(&path).unwrap();
+        for _ in 0..3 {
+            world.run();
+        }
+        world.finish_recording().unwrap();
+
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            recorded
+                .lines()
+                .filter(|line| line.starts_with("delta"))
+                .count(),
+            3
+        );
+        assert!(recorded.contains("\t 19 \n"));
+
+        // the encoder has to be registered to read the deltas
+        let mut without_encoder = World::new();
+        without_encoder.register_replay_component::<Position>();
+        assert!(without_encoder.replay_from(&path).is_err());
+
+        let mut replayed = World::new();
+        replayed.register_replay_component::<Position>();
+        replayed.register_replay_encoder::<Position>(FieldDelta::new(' '));
+        replayed.replay_from(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let engine = &replayed.entities_and_components;
+        let entity = engine.get_entities()[0];
+        assert_eq!(
+            engine.get_components::<(Position,)>(entity).0,
+            &Position(1000.25, 19.0, -3000.75)
+        );
+    }
+
+    #[test]
+    fn test_despawn_soft() {
+        struct Health(i32);
+        struct Sword;
+
+        struct CountHealth;
+        impl System for CountHealth {
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if let Some(health) = single_entity.try_get_component_mut::<Health>() {
+                    health.0 += 1;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        world.set_soft_despawn_frames(2);
+        world.add_system(CountHealth);
+        let engine = &mut world.entities_and_components;
+        let player = engine.add_entity_with((Health(10),));
+        let sword = engine.add_entity_with((Sword,));
+        engine.set_parent(sword, player);
+        let goblin = engine.add_entity_with((Health(5),));
+
+        world.despawn_soft(player);
+        let engine = &mut world.entities_and_components;
+        assert!(engine.is_soft_despawned(player));
+        assert!(engine.is_soft_despawned(sword));
+        assert_eq!(engine.get_entity_count(), 1);
+        assert_eq!(engine.get_entities(), vec![goblin]);
+        assert_eq!(engine.get_entities_with_component::<Health>().count(), 1);
+        assert_eq!(engine.get_entities_with_component::<Sword>().count(), 0);
+        assert!(engine.validate().is_empty());
+        // the data is still there for code that reads it in the same frame
+        assert_eq!(engine.get_components::<(Health,)>(player).0 .0, 10);
+
+        world.run();
+        let engine = &mut world.entities_and_components;
+        assert_eq!(engine.get_components::<(Health,)>(player).0 .0, 10);
+        assert_eq!(engine.get_components::<(Health,)>(goblin).0 .0, 6);
+
+        assert!(world.resurrect(player));
+        assert!(!world.resurrect(player));
+        let engine = &mut world.entities_and_components;
+        assert!(!engine.is_soft_despawned(sword));
+        assert_eq!(engine.get_entity_count(), 3);
+        assert_eq!(engine.get_entities_with_component::<Sword>().count(), 1);
+        assert_eq!(engine.get_parent(sword), Some(player));
+        world.run();
+        assert_eq!(
+            world
+                .entities_and_components
+                .get_components::<(Health,)>(player)
+                .0
+                 .0,
+            11
+        );
+
+        // hidden entities are removed for good once their frames run out
+        world.despawn_soft(player);
+        for _ in 0..2 {
+            world.run();
+            assert!(world.entities_and_components.does_entity_exist(player));
+        }
+        world.run();
+        let engine = &world.entities_and_components;
+        assert!(!engine.does_entity_exist(player));
+        assert!(!engine.does_entity_exist(sword));
+        assert_eq!(engine.get_entity_count(), 1);
+        assert!(engine.validate().is_empty());
+    }
+
+    #[test]
+    fn test_merge_despawn_soft() {
+        struct Name(&'static str);
+
+        let mut other = World::new();
+        other.register_component::<Name>();
+        let engine = &mut other.entities_and_components;
+        let parent = engine.add_entity_with((Name("parent"),));
+        let child = engine.add_entity_with((Name("child"),));
+        engine.set_parent(child, parent);
+        other.despawn_soft(child);
+
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        let first = engine.add_entity_with((Name("first"),));
+        let second = engine.add_entity_with((Name("second"),));
+        // the hidden child has the same id as an entity in this world
+        assert_eq!(child, second);
+
+        let entity_map = world.merge(other, false);
+        assert!(entity_map.get(child).is_none());
+        let new_parent = entity_map.get(parent).unwrap();
+        let engine = &mut world.entities_and_components;
+        assert_eq!(engine.get_components::<(Name,)>(new_parent).0 .0, "parent");
+        assert!(engine.get_children(new_parent).is_empty());
+        assert!(engine.validate().is_empty());
+
+        engine.remove_entity(new_parent);
+        assert!(engine.does_entity_exist(first));
+        assert!(engine.does_entity_exist(second));
+        assert_eq!(engine.get_entity_count(), 2);
+    }
+
+    #[test]
+    fn test_replay_despawn_soft() {
+        #[derive(Debug, PartialEq)]
+        struct Health(i32);
+        impl ReplayComponent for Health {
+            fn save(&self) -> String {
+                self.0.to_string()
+            }
+            fn load(data: &str) -> Option<Self> {
+                data.parse().ok().map(Health)
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "abc_ecs_replay_despawn_soft_{}.txt",
+            std::process::id()
+        ));
+
+        let mut world = World::new();
+        world.register_replay_component::<Health>();
+        let engine = &mut world.entities_and_components;
+        let player = engine.add_entity_with((Health(10),));
+        let goblin = engine.add_entity_with((Health(5),));
+        world.despawn_soft(player);
+
+        // the player is hidden when recording starts and is brought back after
+        world.record_to(&path).unwrap();
+        world.resurrect(player);
+        world
+            .entities_and_components
+            .get_components_mut::<(Health,)>(player)
+            .0
+             .0 = 20;
+        world.run();
+        world.despawn_soft(goblin);
+        world.finish_recording().unwrap();
+
+        let mut replayed = World::new();
+        replayed.register_replay_component::<Health>();
+        replayed.replay_from(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let engine = &replayed.entities_and_components;
+        assert_eq!(engine.get_entity_count(), 1);
+        let player = engine.get_entities()[0];
+        assert_eq!(engine.get_components::<(Health,)>(player).0, &Health(20));
+        let hidden = engine.get_soft_despawned();
+        assert_eq!(hidden.len(), 1);
+        let goblin = hidden[0];
+        assert_eq!(engine.get_components::<(Health,)>(goblin).0, &Health(5));
+    }
+
+    #[test]
+    fn test_frame_budget() {
+        struct Thinks(usize);
+
+        struct Think;
+        impl System for Think {
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if let Some(thinks) = single_entity.try_get_component_mut::<Thinks>() {
+                    thinks.0 += 1;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        let handle = world.add_system(Think);
+        // the budget is used up after every entity, so one entity is stepped each frame
+        world.set_frame_budget(&handle, std::time::Duration::ZERO);
+        let engine = &mut world.entities_and_components;
+        let entities = (0..3)
+            .map(|_| engine.add_entity_with((Thinks(0),)))
+            .collect::<Vec<Entity>>();
+        let thinks = |world: &World| {
+            entities
+                .iter()
+                .map(|entity| {
+                    world
+                        .entities_and_components
+                        .get_components::<(Thinks,)>(*entity)
+                        .0
+                         .0
+                })
+                .collect::<Vec<usize>>()
+        };
+
+        world.run();
+        world.run();
+        assert_eq!(thinks(&world).iter().sum::<usize>(), 2);
+        assert!(thinks(&world).iter().all(|count| *count <= 1));
+        // every entity is stepped before any entity is stepped again
+        world.run();
+        assert_eq!(thinks(&world), vec![1, 1, 1]);
+        world.run();
+        assert_eq!(thinks(&world).iter().sum::<usize>(), 4);
+
+        world.remove_frame_budget(&handle);
+        world.run();
+        assert_eq!(thinks(&world).iter().sum::<usize>(), 7);
+    }
+
+    #[test]
+    fn test_frame_budget_despawn() {
+        struct Thinks(usize);
+
+        struct Think;
+        impl System for Think {
+            fn implements_single_entity_step(&self) -> bool {
+                true
+            }
+            fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+                if let Some(thinks) = single_entity.try_get_component_mut::<Thinks>() {
+                    thinks.0 += 1;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        let handle = world.add_system(Think);
+        world.set_frame_budget(&handle, std::time::Duration::ZERO);
+        let engine = &mut world.entities_and_components;
+        let entities = (0..4)
+            .map(|_| engine.add_entity_with((Thinks(0),)))
+            .collect::<Vec<Entity>>();
+
+        world.run();
+        world.run();
+        // removing an entity that was already stepped does not make the cycle skip the next one
+        world.entities_and_components.remove_entity(entities[0]);
+        world.run();
+        world.run();
+        let thinks = entities[1..]
+            .iter()
+            .map(|entity| {
+                world
+                    .entities_and_components
+                    .get_components::<(Thinks,)>(*entity)
+                    .0
+                     .0
+            })
+            .collect::<Vec<usize>>();
+        assert_eq!(thinks, vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn test_clone_for_simulation() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Position(i32);
+        #[derive(Clone)]
+        struct Target(Entity);
+        impl MapEntities for Target {
+            fn map_entities(&mut self, entity_map: &EntityMap) {
+                self.0 = entity_map.map(self.0);
+            }
+        }
+        struct NotCloned;
+        #[derive(Clone, Debug, PartialEq)]
+        struct Score(u32);
+        impl Resource for Score {}
+
+        struct Move;
+        impl System for Move {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine
+                    .get_entities_with_component::<Position>()
+                    .collect::<Vec<_>>()
+                {
+                    engine.get_components_mut::<(Position,)>(entity).0 .0 += 1;
+                }
+            }
+        }
+
+        let mut world = World::new();
+        world.register_clone_component::<Position>();
+        world.register_clone_component::<Target>();
+        world.register_clone_resource::<Score>();
+        world
+            .entities_and_components
+            .register_map_entities::<Target>();
+        world.add_system(Move);
+        let engine = &mut world.entities_and_components;
+        engine.add_resource(Score(3));
+        let player = engine.add_entity_with((Position(0), NotCloned));
+        let enemy = engine.add_entity_with((Position(10), Target(player)));
+        engine.set_parent(enemy, player);
+
+        let (mut simulation, entity_map) = world.clone_for_simulation();
+        simulation.add_system(Move);
+        simulation.run();
+        simulation.run();
+
+        let engine = &simulation.entities_and_components;
+        let (new_player, new_enemy) = (entity_map.map(player), entity_map.map(enemy));
+        assert_eq!(engine.get_entity_count(), 2);
+        assert_eq!(
+            engine.get_components::<(Position,)>(new_enemy).0,
+            &Position(12)
+        );
+        assert!(engine.try_get_component::<NotCloned>(new_player).is_none());
+        assert_eq!(
+            engine.get_components::<(Target,)>(new_enemy).0 .0,
+            new_player
+        );
+        assert_eq!(engine.get_parent(new_enemy), Some(new_player));
+        assert_eq!(engine.get_resource::<Score>(), Some(&Score(3)));
+        assert!(engine.validate().is_empty());
+
+        // the original world is untouched
+        let engine = &world.entities_and_components;
+        assert_eq!(engine.get_components::<(Position,)>(enemy).0, &Position(10));
+        assert!(engine.try_get_component::<NotCloned>(player).is_some());
+    }
+
+    #[test]
+    fn test_clone_for_simulation_registrations() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Team(u32);
+        #[derive(Clone, Debug, PartialEq, Default)]
+        struct Health(u32);
+        trait Named {
+            fn name(&self) -> &'static str;
+        }
+        impl Named for Team {
+            fn name(&self) -> &'static str {
+                "team"
+            }
+        }
+
+        let mut world = World::new();
+        world.register_clone_component::<Team>();
+        world.register_clone_component::<Health>();
+        world.register_clone_component::<EcsCell<u32>>();
+        let engine = &mut world.entities_and_components;
+        engine.add_index(|team: &Team| team.0);
+        engine.register_required_component::<Team, Health>();
+        engine.register_trait_impl::<dyn Named, Team>(|team| team);
+        let entity = engine.add_entity_with((Team(1), EcsCell::new(5u32)));
+
+        let (mut simulation, entity_map) = world.clone_for_simulation();
+        let engine = &mut simulation.entities_and_components;
+        let new_entity = entity_map.map(entity);
+        assert_eq!(engine.get_entities_by_key::<Team, u32>(&1), &[new_entity]);
+        assert_eq!(
+            engine
+                .query_trait::<dyn Named>()
+                .map(|(_, named)| named.name())
+                .collect::<Vec<_>>(),
+            vec!["team"]
+        );
+        let spawned = engine.add_entity_with((Team(2),));
+        assert_eq!(
+            engine.try_get_component::<Health>(spawned),
+            Some(&Health(0))
+        );
+        assert_eq!(engine.get_entities_by_key::<Team, u32>(&2), &[spawned]);
+
+        // the cell is copied, changing it in the copy does not change the original
+        *engine
+            .get_components::<(EcsCell<u32>,)>(new_entity)
+            .0
+            .lock() = 6;
+        let original = world
+            .entities_and_components
+            .get_components::<(EcsCell<u32>,)>(entity)
+            .0;
+        assert_eq!(*original.lock(), 5);
+    }
+
+    #[test]
+    fn test_clear_entities_bookkeeping() {
+        let mut world = World::new();
+        world.enable_leak_detection(3);
+        world.start_recording_ops(16);
+        let entities = [
+            world
+                .entities_and_components
+                .add_entity_with((Position { x: 0.0, y: 0.0 },)),
+            world
+                .entities_and_components
+                .add_entity_with((Position { x: 1.0, y: 0.0 },)),
+        ];
+
+        world.clear_entities();
+        world.run();
+
+        // every entity is despawned the same way remove_entity does it
+        let despawns = world
+            .recent_ops()
+            .iter()
+            .filter_map(|op| match op.op {
+                StructuralOp::Despawn(entity) => Some(entity),
+                _ => None,
+            })
+            .collect::<Vec<Entity>>();
+        assert_eq!(despawns.len(), 2);
+        assert!(entities.iter().all(|entity| despawns.contains(entity)));
+
+        let report = world.leak_report().unwrap();
+        let position = report
+            .components
+            .iter()
+            .find(|component| component.name.ends_with("Position"))
+            .unwrap();
+        assert_eq!((position.live, position.added, position.removed), (0, 2, 2));
+    }
+
+    #[test]
+    fn test_leak_report() {
+        struct Bullet([f32; 4]);
+        struct Enemy(u64);
+
+        struct Shoot;
+        impl System for Shoot {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                // the bullets are never removed
+                engine.add_entity_with((Bullet([0.0; 4]),));
+                for entity in engine
+                    .get_entities_with_component::<Enemy>()
+                    .collect::<Vec<_>>()
+                {
+                    engine.remove_entity(entity);
+                }
+                engine.add_entity_with((Enemy(1),));
+            }
+        }
+
+        let mut world = World::new();
+        assert!(world.leak_report().is_none());
+        world.add_system(Shoot);
+        world.enable_leak_detection(3);
+        for _ in 0..2 {
+            world.run();
+        }
+        let report = world.leak_report().unwrap();
+        assert_eq!(report.suspected_leaks().count(), 0);
+
+        world.run();
+        let report = world.leak_report().unwrap();
+        let leaks = report.suspected_leaks().collect::<Vec<_>>();
+        assert_eq!(leaks.len(), 1);
+        assert!(leaks[0].name.ends_with("Bullet"));
+        assert_eq!((leaks[0].live, leaks[0].added, leaks[0].removed), (3, 3, 0));
+        assert_eq!(leaks[0].size, std::mem::size_of::<Bullet>());
+        assert_eq!(leaks[0].live_bytes(), 3 * std::mem::size_of::<Bullet>());
+        // the bullets take the most memory so they are first
+        assert_eq!(&report.components[0], leaks[0]);
+
+        let enemy = report
+            .components
+            .iter()
+            .find(|component| component.name.ends_with("Enemy"))
+            .unwrap();
+        assert_eq!((enemy.live, enemy.added, enemy.removed), (1, 3, 2));
+        assert!(!enemy.suspected_leak);
+
+        // compacting only moves the components, they are not counted as added again
+        world.entities_and_components.compact();
+        world.run();
+        let report = world.leak_report().unwrap();
+        let bullet = report
+            .components
+            .iter()
+            .find(|component| component.name.ends_with("Bullet"))
+            .unwrap();
+        assert_eq!((bullet.live, bullet.added, bullet.removed), (4, 4, 0));
+
+        let engine = &world.entities_and_components;
+        for entity in engine.get_entities_with_component::<Bullet>() {
+            assert_eq!(engine.get_components::<(Bullet,)>(entity).0 .0, [0.0; 4]);
+        }
+        for entity in engine.get_entities_with_component::<Enemy>() {
+            assert_eq!(engine.get_components::<(Enemy,)>(entity).0 .0, 1);
+        }
+    }
+
+    #[test]
+    fn test_frame_maintenance() {
+        struct Health(i32);
+        struct Damaged;
+
+        // records what the systems saw, the last run function sees everything before maintenance
+        #[derive(Default)]
+        struct Seen(Vec<(usize, usize)>);
+        impl Resource for Seen {}
+
+        struct Attack;
+        impl System for Attack {
+            fn run(&mut self, engine: &mut EntitiesAndComponents) {
+                for entity in engine
+                    .get_entities_with_component::<Health>()
+                    .collect::<Vec<_>>()
+                {
+                    engine.add_component_to(entity, Damaged);
+                    let (health,) = engine.get_components_mut::<(Health,)>(entity);
+                    health.0 -= 10;
+                    if health.0 <= 0 {
+                        engine.queue_despawn(entity);
+                        // a later despawn of the same entity is fine
+                        engine.queue_despawn(entity);
+                    }
+                }
+                engine.defer(|engine| {
+                    engine.add_entity_with((Health(30),));
+                });
+                let seen = (
+                    engine.get_entity_count_with_component::<Health>(),
+                    engine.get_entity_count_with_component::<Damaged>(),
+                );
+                engine.get_resource_mut::<Seen>().unwrap().0.push(seen);
+            }
+        }
+
+        let mut world = World::new();
+        world.register_transient::<Damaged>();
+        world.add_system(Attack);
+        let engine = &mut world.entities_and_components;
+        engine.add_resource(Seen::default());
+        let knight = engine.add_entity_with((Health(20),));
+
+        world.run();
+        let engine = &world.entities_and_components;
+        // the deferred entity was added and Damaged was cleared after the systems ran
+        assert_eq!(engine.get_entity_count_with_component::<Health>(), 2);
+        assert_eq!(engine.get_entity_count_with_component::<Damaged>(), 0);
+
+        world.run();
+        let engine = &world.entities_and_components;
+        assert!(!engine.does_entity_exist(knight));
+        assert_eq!(engine.get_entity_count_with_component::<Health>(), 2);
+        assert_eq!(
+            engine.get_resource::<Seen>().unwrap().0,
+            vec![(1, 1), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_query_with_parent() {
+        struct Collider(f32);
+        struct RigidBody(f32);
+
+        let mut engine = EntitiesAndComponents::new();
+        let body = engine.add_entity_with((RigidBody(2.0),));
+        let attached = engine.add_entity_with((Collider(0.5),));
+        engine.set_parent(attached, body);
+        // a collider without a parent
+        engine.add_entity_with((Collider(1.0),));
+        let group = engine.add_entity();
+        let in_group = engine.add_entity_with((Collider(3.0),));
+        engine.set_parent(in_group, group);
+        // the parent has to match, not the grandparent
+        let nested = engine.add_entity_with((Collider(4.0),));
+        engine.set_parent(nested, in_group);
+
+        let pairs = engine
+            .query_with_parent::<(Collider,), (RigidBody,)>()
+            .map(|(child, (collider,), parent, (rigid_body,))| {
+                (child, collider.0, parent, rigid_body.0)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, vec![(attached, 0.5, body, 2.0)]);
+
+        let with_collider_parent = engine
+            .query_with_parent::<(Collider,), (Collider,)>()
+            .map(|(child, _, parent, _)| (child, parent))
+            .collect::<Vec<_>>();
+        assert_eq!(with_collider_parent, vec![(nested, in_group)]);
+    }
+
+    struct StaticStep;
+
+    impl System for StaticStep {
+        fn single_entity_step(&self, single_entity: &mut SingleMutEntity) {
+            single_entity.get_component_mut::<Position>().x += 1.0;
+        }
+
+        fn implements_single_entity_step(&self) -> bool {
+            true
+        }
+    }
+
+    struct StaticSum {
+        total: f32,
+    }
+
+    impl System for StaticSum {
+        fn run(&mut self, engine: &mut EntitiesAndComponents) {
+            self.total = engine
+                .get_entities_with_component::<Position>()
+                .map(|entity| engine.get_components::<(Position,)>(entity).0.x)
+                .sum();
+        }
+    }
+
+    #[test]
+    fn test_static_schedule() {
+        let mut world = World::new();
+        let skipped = world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        for _ in 0..3 {
+            world
+                .entities_and_components
+                .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        }
+        world
+            .entities_and_components
+            .add_component_to(skipped, SkipSystem::<StaticStep>::new());
+
+        let mut schedule = schedule!(StaticStep, StaticSum { total: 0.0 });
+        assert_eq!(schedule.len(), 2);
+        world.run_schedule(&mut schedule);
+        assert_eq!(schedule.systems().1.total, 3.0);
+        world.run_schedule(&mut schedule);
+        assert_eq!(schedule.systems().1.total, 6.0);
+
+        // the systems added to the world are not run by the schedule
+        world.add_system(StaticStep);
+        world.run_schedule(&mut schedule);
+        assert_eq!(schedule.systems().1.total, 9.0);
+        assert!(world.get_frame_report().is_ok());
+    }
+
+    #[test]
+    fn test_static_schedule_failures() {
+        let mut world = World::new();
+        world.set_panic_policy(PanicPolicy::LogAndSkip);
+        world.register_validator::<Position>(|position| position.x < 2.0);
+        // ParallelMovementSystem panics on this entity because it has no velocity
+        let missing_velocity = world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let moving = world
+            .entities_and_components
+            .add_entity_with((Position { x: 1.0, y: 0.0 }, Velocity { x: 1.0, y: 0.0 }));
+
+        let mut schedule = schedule!(ParallelMovementSystem {});
+        world.run_schedule(&mut schedule);
+
+        // the panic and the invalid position are reported like in run
+        let failures = world.get_frame_report().failures();
+        assert_eq!(failures.len(), 2);
+        let panicked = failures
+            .iter()
+            .find(|failure| failure.entity == Some(missing_velocity))
+            .unwrap();
+        assert!(panicked.error.message().contains("panicked"));
+        assert!(failures
+            .iter()
+            .any(|failure| failure.entity == Some(moving)));
+    }
+
+    #[test]
+    fn test_index_stats() {
+        let mut engine = EntitiesAndComponents::new();
+        for _ in 0..10 {
+            engine.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        }
+        let mut moving = vec![];
+        for _ in 0..2 {
+            moving.push(
+                engine.add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 })),
+            );
+        }
+
+        let index_stats = engine.index_stats();
+        assert_eq!(index_stats.len(), 2);
+        assert_eq!(index_stats[0].type_id, TypeId::of::<Position>());
+        assert_eq!(index_stats[0].entities, 12);
+        assert_eq!(index_stats[1].type_id, TypeId::of::<Velocity>());
+        assert_eq!(index_stats[1].entities, 2);
+
+        // the query goes through the 2 entities with Velocity instead of the 12 with Position
+        let driver = engine
+            .get_query_driver(
+                TypeId::of::<(Position, Velocity)>(),
+                &[TypeId::of::<Position>(), TypeId::of::<Velocity>()],
+            )
+            .unwrap();
+        assert_eq!(driver.len(), 2);
+        assert_eq!(
+            engine.get_entities_matching::<(Position, Velocity)>(),
+            moving
+        );
+
+        engine.pin_query_driver::<(Position, Velocity), Position>();
+        let driver = engine
+            .get_query_driver(
+                TypeId::of::<(Position, Velocity)>(),
+                &[TypeId::of::<Position>(), TypeId::of::<Velocity>()],
+            )
+            .unwrap();
+        assert_eq!(driver.len(), 12);
+        assert_eq!(
+            engine.get_entities_matching::<(Position, Velocity)>(),
+            moving
+        );
+
+        engine.unpin_query_driver::<(Position, Velocity)>();
+        assert!(engine
+            .get_query_driver(
+                TypeId::of::<(Position, String)>(),
+                &[TypeId::of::<Position>(), TypeId::of::<String>()],
+            )
+            .is_none());
+    }
+
+    #[cfg(feature = "test_utils")]
+    #[test]
+    fn test_world_test_harness() {
+        use crate::test_utils::*;
+
+        let mut harness =
+            WorldTestHarness::new().with_frame_time(std::time::Duration::from_millis(100));
+        let entity = harness.spawn((Position { x: 0.0, y: 0.0 },));
+        harness.add_system(StaticStep);
+        harness.run_frames(3);
+        harness.assert_component_eq(entity, &Position { x: 3.0, y: 0.0 });
+        harness.assert_no_component::<Velocity>(entity);
+        harness.assert_count::<Position>(1);
+        assert_eq!(harness.clock().frame, 3);
+        assert_eq!(
+            harness.clock().elapsed,
+            std::time::Duration::from_millis(300)
+        );
+
+        // only the given system runs
+        let sum = harness.run_single_system(StaticSum { total: 0.0 });
+        assert_eq!(sum.total, 3.0);
+        harness.assert_component_eq(entity, &Position { x: 3.0, y: 0.0 });
+
+        // the same seed gives the same numbers
+        let first = WorldTestHarness::new().with_seed(7);
+        let second = WorldTestHarness::new().with_seed(7);
+        let mut first_stream = first
+            .world
+            .entities_and_components
+            .get_resource::<crate::Rng>()
+            .unwrap()
+            .stream(1);
+        let mut second_stream = second
+            .world
+            .entities_and_components
+            .get_resource::<crate::Rng>()
+            .unwrap()
+            .stream(1);
+        assert_eq!(first_stream.next_u64(), second_stream.next_u64());
+    }
+
+    #[cfg(feature = "fuzz")]
+    #[test]
+    fn test_fuzz_world() {
+        use crate::fuzz::*;
+
+        for seed in 0..50 {
+            if let Err(failure) = fuzz_world(seed, 300) {
+                panic!("{failure}");
+            }
+        }
+
+        // a failing sequence can be run again on its own
+        let ops = [
+            FuzzOp::Spawn,
+            FuzzOp::Spawn,
+            FuzzOp::SetParent(0, 1),
+            FuzzOp::AddMarker(0),
+            FuzzOp::RunFrame(0b1111),
+            FuzzOp::Despawn(1),
+            FuzzOp::DespawnSoft(0),
+            FuzzOp::Acquire,
+            FuzzOp::Compact,
+            FuzzOp::Resurrect(0),
+            FuzzOp::Release(0),
+        ];
+        assert_eq!(run_ops(&ops), Ok(()));
+    }
+
+    #[test]
+    fn test_world_diff() {
+        let mut world = World::new();
+        world.register_clone_component::<Position>();
+        world.register_clone_component::<Velocity>();
+        world.register_diff_component::<Position>();
+        world.register_diff_component::<Velocity>();
+        let moving = world
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 2.0 }));
+        let resting = world
+            .entities_and_components
+            .add_entity_with((Position { x: 5.0, y: 5.0 },));
+
+        let (mut copy, entity_map) = world.clone_for_simulation();
+        assert!(diff_mapped(
+            &world.entities_and_components,
+            &copy.entities_and_components,
+            &entity_map
+        )
+        .is_empty());
+
+        copy.add_system(StaticStep);
+        copy.run();
+        let world_diff = diff_mapped(
+            &world.entities_and_components,
+            &copy.entities_and_components,
+            &entity_map,
+        );
+        assert!(world_diff.only_changes::<Position>());
+        assert_eq!(world_diff.changes_to::<Position>().count(), 2);
+        assert_eq!(world_diff.changes_to::<Velocity>().count(), 0);
+
+        let copied_resting = entity_map.get(resting).unwrap();
+        copy.entities_and_components.remove_entity(copied_resting);
+        copy.entities_and_components
+            .remove_component_from::<Velocity>(entity_map.get(moving).unwrap());
+        let spawned = copy
+            .entities_and_components
+            .add_entity_with((Velocity { x: 0.0, y: 0.0 },));
+        let world_diff = diff_mapped(
+            &world.entities_and_components,
+            &copy.entities_and_components,
+            &entity_map,
+        );
+        assert!(!world_diff.only_changes::<Position>());
+        assert_eq!(world_diff.spawned, vec![spawned]);
+        assert_eq!(world_diff.despawned, vec![resting]);
+        assert_eq!(
+            world_diff
+                .changes_to::<Velocity>()
+                .copied()
+                .collect::<Vec<ComponentChange>>(),
+            vec![ComponentChange {
+                entity: moving,
+                type_id: TypeId::of::<Velocity>(),
+                component: world
+                    .entities_and_components
+                    .get_type_name(TypeId::of::<Velocity>()),
+                kind: ChangeKind::Removed,
+            }]
+        );
+
+        // a world has no differences with itself
+        assert!(diff(
+            &world.entities_and_components,
+            &world.entities_and_components
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_component_storage_survives_clear() {
+        let mut world = World::new();
+        world
+            .entities_and_components
+            .set_component_storage::<Position, _>(DenseEntitySet::new());
+        world
+            .entities_and_components
+            .add_entity_with((Position { x: 1.0, y: 0.0 },));
+        world.clear_entities();
+
+        let engine = &mut world.entities_and_components;
+        assert!(matches!(
+            engine.entities_with_components[&TypeId::of::<Position>()],
+            ComponentEntities::Custom(_)
+        ));
+        assert_eq!(engine.get_entity_count_with_component::<Position>(), 0);
+        let entity = engine.add_entity_with((Position { x: 2.0, y: 0.0 },));
+        assert_eq!(
+            engine
+                .get_entities_with_component::<Position>()
+                .collect::<Vec<Entity>>(),
+            vec![entity]
+        );
+    }
+
+    #[test]
+    fn test_heavy() {
+        #[derive(Clone)]
+        struct Grid {
+            cells: [u8; 4096],
+        }
+
+        let mut other = World::new();
+        other.register_component::<Heavy<Grid>>();
+        let grid = other
+            .entities_and_components
+            .alloc_heavy(Grid { cells: [1; 4096] });
+        let address = &*grid as *const Grid;
+        let entity = other.entities_and_components.add_entity_with((grid,));
+
+        // moving the entity to another world only moves the handle
+        let mut engine = World::new();
+        engine.register_component::<Heavy<Grid>>();
+        let entity_map = engine.merge(other, false);
+        let entity = entity_map.get(entity).unwrap();
+
+        let (grid,) = engine
+            .entities_and_components
+            .get_components_mut::<(Heavy<Grid>,)>(entity);
+        assert_eq!(&**grid as *const Grid, address);
+        grid.cells[0] = 2;
+        assert_eq!(grid.cells[..2], [2, 1]);
+
+        // clones go in the same arena, removing a value gives its slot back
+        let arena = grid.arena();
+        let copy = grid.clone();
+        assert_eq!(copy.cells[..2], [2, 1]);
+        assert_eq!(arena.len(), 2);
+        let slot = grid.slot();
+        drop(copy);
+        assert_eq!(arena.len(), 1);
+        engine.entities_and_components.remove_entity(entity);
+        assert!(arena.is_empty());
+        // the slot freed last is used first, at the same address
+        let grid = arena.alloc(Grid { cells: [3; 4096] });
+        assert_eq!(grid.slot(), slot);
+        assert_eq!(&*grid as *const Grid, address);
+        assert_eq!(grid.cells[0], 3);
+    }
+
+    #[test]
+    fn test_system_sets() {
+        let mut engine = World::new();
+
+        let entity = engine
+            .entities_and_components
+            .add_entity_with((Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }));
+
+        engine.add_system_to_set(ParallelMovementSystem {}, "movement");
+
+        engine.configure_set("movement").disable();
+        engine.run();
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(entity);
+        assert_eq!(position.x, 0.0);
+
+        // only run while there is a single entity
+        engine
+            .configure_set("movement")
+            .enable()
+            .run_if(|entities_and_components| entities_and_components.get_entity_count() == 1);
+        engine.run();
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(entity);
+        assert_eq!(position.x, 1.0);
+
+        engine.entities_and_components.add_entity();
+        engine.run();
+        let (position,) = engine
+            .entities_and_components
+            .get_components::<(Position,)>(entity);
+        assert_eq!(position.x, 1.0);
+
+        engine.remove_set("movement");
+        assert_eq!(engine.systems.len(), 0);
+        assert!(!engine.set_configs.contains_key("movement"));
+    }
+
+    #[test]
+    fn test_entity_pool() {
+        let mut engine = World::new();
+
+        let mut pool = engine.create_pool(
+            2,
+            || (Position { x: 0.0, y: 0.0 }, Velocity { x: 1.0, y: 1.0 }),
+            |entity, entities_and_components| {
+                let (position,) = entities_and_components.get_components_mut::<(Position,)>(entity);
+                *position = Position { x: 0.0, y: 0.0 };
+            },
+        );
+
+        // pooled entities are not in the world
+        assert_eq!(engine.entities_and_components.get_entity_count(), 0);
+        assert_eq!(pool.free_count(), 2);
+
+        let entities_and_components = &mut engine.entities_and_components;
+        let bullet = pool.acquire(entities_and_components);
+        assert_eq!(pool.free_count(), 1);
+
+        let (position,) = entities_and_components.get_components_mut::<(Position,)>(bullet);
+        position.x = 10.0;
+
+        pool.release(entities_and_components, bullet);
+        assert!(!entities_and_components.does_entity_exist(bullet));
+        assert_eq!(
+            entities_and_components.get_entity_count_with_component::<Position>(),
+            0
+        );
+
+        let bullet = pool.acquire(entities_and_components);
+        let (position, velocity) =
+            entities_and_components.get_components::<(Position, Velocity)>(bullet);
+        assert_eq!(position.x, 0.0);
+        assert_eq!(velocity.x, 1.0);
+
+        // once the pool is empty new entities are made
+        pool.acquire(entities_and_components);
+        pool.acquire(entities_and_components);
+        assert_eq!(pool.free_count(), 0);
+        assert_eq!(entities_and_components.get_entity_count(), 3);
+    }
+
+    #[test]
+    fn test_query_in_subtree() {
+        let mut engine = World::new();
+        let entities_and_components = &mut engine.entities_and_components;
+
+        let root = entities_and_components.add_entity_with((Position { x: 0.0, y: 0.0 },));
+        let child = entities_and_components.add_entity_with((Position { x: 1.0, y: 0.0 },));
+        let grandchild = entities_and_components
+            .add_entity_with((Position { x: 2.0, y: 0.0 }, Velocity { x: 0.0, y: 0.0 }));
+        let unrelated = entities_and_components.add_entity_with((Position { x: 3.0, y: 0.0 },));
+
+        entities_and_components.set_parent(child, root);
+        entities_and_components.set_parent(grandchild, child);
+
+        let mut positions = entities_and_components.query_in_subtree::<(Position,)>(root);
+        positions.sort();
+        let mut expected = vec![child, grandchild];
+        expected.sort();
+        assert_eq!(positions, expected);
+
+        assert_eq!(
+            entities_and_components.query_in_subtree::<(Position, Velocity)>(root),
+            vec![grandchild]
+        );
+
+        // changing the hierarchy should not leave stale results behind
+        entities_and_components.set_parent(unrelated, grandchild);
+        entities_and_components.remove_parent(child);
+
+        assert_eq!(
+            entities_and_components.query_in_subtree::<(Position,)>(root),
+            vec![]
+        );
+        assert_eq!(
+            entities_and_components.query_in_subtree::<(Position,)>(grandchild),
+            vec![unrelated]
+        );
+    }
+
     #[test]
     fn bench_every_function() {
         let mut engine = World::new();
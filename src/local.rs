@@ -0,0 +1,95 @@
+use rustc_hash::FxHashMap;
+use std::any::{Any, TypeId};
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock};
+
+/// Storage that belongs to a single system and persists between frames
+/// It is managed by the world, so it can be reset through the SystemHandle of the system
+/// This lets single_entity_step (which only has &self) accumulate state while running in parallel
+#[derive(Default)]
+pub struct Local<T> {
+    value: Mutex<T>,
+}
+
+impl<T> Local<T> {
+    /// Locks the local storage and returns a guard to the value
+    /// Only one entity can hold the lock at a time, so keep the guard short lived
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.value.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Gets a mutable reference to the value, no locking is needed because the borrow is unique
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// All the locals that belong to one system, keyed by the type of the local
+#[derive(Default)]
+pub(crate) struct SystemLocals {
+    locals: RwLock<FxHashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+}
+
+impl SystemLocals {
+    /// Gets the local of type T, creating it with its default value if it doesn't exist yet
+    pub(crate) fn get_or_init<T: Default + Send + 'static>(&self) -> &Local<T> {
+        let type_id = TypeId::of::<Local<T>>();
+
+        let existing = self
+            .locals
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&type_id)
+            .map(|local| downcast_local::<T>(&**local) as *const Local<T>);
+
+        let pointer = match existing {
+            Some(pointer) => pointer,
+            None => {
+                let mut locals = self.locals.write().unwrap_or_else(PoisonError::into_inner);
+                let local = locals
+                    .entry(type_id)
+                    .or_insert_with(|| Box::new(Local::<T>::default()));
+                downcast_local::<T>(&**local) as *const Local<T>
+            }
+        };
+
+        // SAFETY: locals are boxed so they do not move when the map grows,
+        // and they are only ever removed through a mutable reference to the SystemLocals
+        unsafe { &*pointer }
+    }
+
+    /// Gets the local of type T mutably if it exists
+    pub(crate) fn get_mut<T: Send + 'static>(&mut self) -> Option<&mut T> {
+        self.locals
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(&TypeId::of::<Local<T>>())
+            .map(|local| {
+                local
+                    .downcast_mut::<Local<T>>()
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "Local of type {type:?} does not exist, was the type edited?",
+                            type = std::any::type_name::<T>()
+                        );
+                    })
+                    .get_mut()
+            })
+    }
+
+    /// Removes all the locals, they will be recreated with their default value the next time they are used
+    pub(crate) fn clear(&mut self) {
+        self.locals
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+fn downcast_local<T: Send + 'static>(local: &(dyn Any + Send + Sync)) -> &Local<T> {
+    local.downcast_ref::<Local<T>>().unwrap_or_else(|| {
+        panic!(
+            "Local of type {type:?} does not exist, was the type edited?",
+            type = std::any::type_name::<T>()
+        );
+    })
+}
@@ -1,4 +1,5 @@
 use crate::*;
+use std::marker::PhantomData;
 
 /// This trait is used to get a tuple of references to components
 /// it is automatically implemented for tuples of components
@@ -11,6 +12,16 @@ pub trait ComponentsRef<'a> {
         entities_and_components: &'a EntitiesAndComponents,
         entity: Entity,
     ) -> Self::Result;
+
+    /// Checks that the components are registered when strict components are turned on,
+    /// has to be called before get_from_map
+    #[doc(hidden)]
+    fn check_registered(entities_and_components: &EntitiesAndComponents);
+
+    /// Returns a tuple of references to the components in the component map of one entity
+    /// used when other entities are borrowed at the same time, like in query_child_pairs
+    #[doc(hidden)]
+    fn get_from_map(components: &'a Map<dyn Any + 'static>) -> Self::Result;
 }
 
 macro_rules! impl_components {
@@ -19,6 +30,8 @@ macro_rules! impl_components {
             type Result = ($(&'b $generic_name,)*);
 
             fn get_components(entities_and_components: &'b EntitiesAndComponents, entity: Entity) -> Self::Result {
+                Self::check_registered(entities_and_components);
+
                 let components = entities_and_components
                 .components
                 .get(entity.entity_id);
@@ -29,12 +42,20 @@ macro_rules! impl_components {
                     panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
                 }
 
-                let components = components.unwrap();
+                Self::get_from_map(components.unwrap())
+            }
+
+            fn check_registered(entities_and_components: &EntitiesAndComponents) {
+                $(
+                    entities_and_components.check_registered::<$generic_name>();
+                )*
+            }
 
+            fn get_from_map(components: &'b Map<dyn Any + 'static>) -> Self::Result {
                 (
                     $(
                         components
-                            .get::<Box<$generic_name>>()
+                            .get::<$generic_name>()
                             .unwrap_or_else(||{
                                 let type_name = std::any::type_name::<$generic_name>();
                                 panic!(
@@ -66,6 +87,10 @@ macro_rules! impl_try_components {
         impl<'b, $($generic_name: 'static),*> TryComponentsRef<'b> for ($($generic_name,)*) {
             type Result = ($(Option<&'b $generic_name>,)*);
             fn try_get_components(entities_and_components: &'b EntitiesAndComponents, entity: Entity) -> ($(Option<&'b $generic_name>,)*) {
+                $(
+                    entities_and_components.check_registered::<$generic_name>();
+                )*
+
                 let components = entities_and_components
                 .components
                 .get(entity.entity_id);
@@ -82,7 +107,7 @@ macro_rules! impl_try_components {
                 (
                     $(
                         components
-                            .get::<Box<$generic_name>>().map(|boxed_t1|{ &**boxed_t1}),
+                            .get::<$generic_name>(),
                     )*
                 )
             }
@@ -101,6 +126,16 @@ pub trait ComponentsMut<'a> {
         entities_and_components: &'a mut EntitiesAndComponents,
         entity: Entity,
     ) -> Self::Result;
+
+    /// Checks that the components can be borrowed and marks them as changed on an entity,
+    /// has to be called before get_from_map_mut
+    #[doc(hidden)]
+    fn prepare_mut(entities_and_components: &mut EntitiesAndComponents, entity: Entity);
+
+    /// Returns a tuple of mutable references to the components in the component map of one entity
+    /// used when other entities are borrowed at the same time, like in query_child_pairs
+    #[doc(hidden)]
+    fn get_from_map_mut(components: &'a mut Map<dyn Any + 'static>) -> Self::Result;
 }
 
 macro_rules! impl_components_mut {
@@ -109,6 +144,26 @@ macro_rules! impl_components_mut {
             type Result = ($(&'b mut $generic_name,)*);
 
             fn get_components_mut(entities_and_components: &'b mut EntitiesAndComponents, entity: Entity) -> Self::Result {
+                Self::prepare_mut(entities_and_components, entity);
+
+                if !entities_and_components.components.contains_key(entity.entity_id) {
+                    println!("//////////////////////////////////////////////////////////////");
+                    entities_and_components.tree(0);
+                    panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+                }
+
+                let components = entities_and_components
+                    .components
+                    .get_mut(entity.entity_id)
+                    .unwrap();
+
+                Self::get_from_map_mut(components)
+            }
+
+            fn prepare_mut(entities_and_components: &mut EntitiesAndComponents, entity: Entity) {
+                $(
+                    entities_and_components.check_registered::<$generic_name>();
+                )*
 
                 // make sure that the same component is not borrowed mutably more than once
                 let all_types = [
@@ -123,23 +178,17 @@ macro_rules! impl_components_mut {
                     }
                 }
 
-                let components = entities_and_components
-                .components
-                .get_mut(entity.entity_id);
-
-                if components.is_none() {
-                    println!("//////////////////////////////////////////////////////////////");
-                    entities_and_components.tree(0);
-                    panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
-                }
-
-                let components = components.unwrap();
+                $(
+                    entities_and_components.mark_changed(entity, std::any::TypeId::of::<$generic_name>());
+                )*
+            }
 
+            fn get_from_map_mut(components: &'b mut Map<dyn Any + 'static>) -> Self::Result {
                 (
                     $(
                         {
-                            let pointer: *mut $generic_name = &mut **components
-                                .get_mut::<Box<$generic_name>>()
+                            let pointer: *mut $generic_name = components
+                                .get_mut::<$generic_name>()
                                 .unwrap_or_else(||{
                                     let type_name = std::any::type_name::<$generic_name>();
                                     panic!(
@@ -180,6 +229,10 @@ macro_rules! impl_try_components_mut {
 
             fn try_get_components_mut(entities_and_components: &'b mut EntitiesAndComponents, entity: Entity) -> Self::Result {
 
+                $(
+                    entities_and_components.check_registered::<$generic_name>();
+                )*
+
                 // make sure that the same component is not borrowed mutably more than once
                 let all_types = [
                     $(
@@ -193,6 +246,10 @@ macro_rules! impl_try_components_mut {
                     }
                 }
 
+                $(
+                    entities_and_components.mark_changed(entity, std::any::TypeId::of::<$generic_name>());
+                )*
+
                 let components = entities_and_components
                     .components
                     .get_mut(entity.entity_id);
@@ -209,10 +266,10 @@ macro_rules! impl_try_components_mut {
                     $(
                         {
                             let original_reference = components
-                                .get_mut::<Box<$generic_name>>();
+                                .get_mut::<$generic_name>();
                             match original_reference {
                                 Some(reference) => {
-                                    let pointer: *mut $generic_name = &mut **reference;
+                                    let pointer: *mut $generic_name = reference;
                                     // SAFETY: We just checked that the component exists
                                     // and that the component is not borrowed mutably more than once
                                     // and lifetimes are checked at compile time to make sure that the component still exists
@@ -252,11 +309,12 @@ macro_rules! impl_owned_components {
                 entities_and_components: &mut EntitiesAndComponents,
                 components: Self::Input,
             ) -> Entity {
-                let entity = entities_and_components.add_entity();
+                let entity = entities_and_components.insert_entity();
 
                 $(
                     entities_and_components.add_component_to(entity, (components.$component_num));
                 )*
+                entities_and_components.run_spawn_hooks(entity);
 
                 entity
             }
@@ -264,6 +322,93 @@ macro_rules! impl_owned_components {
     };
 }
 
+/// This trait is used to check if an entity has every component in a tuple
+/// it is automatically implemented for tuples of components
+pub trait HasComponents {
+    /// Returns true if the entity exists and has all of the components
+    fn has_components(entities_and_components: &EntitiesAndComponents, entity: Entity) -> bool;
+
+    /// Returns every entity that has_components returns true for
+    fn get_matching_entities(entities_and_components: &EntitiesAndComponents) -> Vec<Entity> {
+        entities_and_components
+            .iter_entities()
+            .filter(|entity| Self::has_components(entities_and_components, *entity))
+            .collect()
+    }
+}
+
+/// A filter that matches entities with any of the components in T instead of all of them
+/// Use it anywhere a HasComponents filter is taken, like get_entities_matching::<Or<(Sprite, Text)>>()
+pub struct Or<T>(PhantomData<T>);
+
+// returns the entities that have the component with this type id
+fn entities_with_type(
+    entities_and_components: &EntitiesAndComponents,
+    type_id: TypeId,
+) -> Option<&ComponentEntities> {
+    entities_and_components
+        .entities_with_components
+        .get(&type_id)
+}
+
+macro_rules! impl_has_components {
+    ($first_name: ident $(, $generic_name: ident)*) => {
+        impl<$first_name: 'static, $($generic_name: 'static),*> HasComponents for ($first_name, $($generic_name,)*) {
+            fn has_components(entities_and_components: &EntitiesAndComponents, entity: Entity) -> bool {
+                match entities_and_components.components.get(entity.entity_id) {
+                    Some(components) => components.contains::<$first_name>() $(&& components.contains::<$generic_name>())*,
+                    None => false,
+                }
+            }
+
+            fn get_matching_entities(entities_and_components: &EntitiesAndComponents) -> Vec<Entity> {
+                // only the entities in the smallest set, or the pinned one, need to be checked
+                let type_ids = [TypeId::of::<$first_name>() $(, TypeId::of::<$generic_name>())*];
+                let entities = match entities_and_components.get_query_driver(TypeId::of::<Self>(), &type_ids) {
+                    Some(entities) => entities,
+                    None => return vec![],
+                };
+
+                // if every component is registered each entity can be checked with its mask
+                match entities_and_components.get_mask_of(&type_ids) {
+                    Some(mask) => entities
+                        .values()
+                        .copied()
+                        .filter(|entity| entities_and_components.entity_has_mask(*entity, mask))
+                        .collect(),
+                    None => entities
+                        .values()
+                        .copied()
+                        .filter(|entity| Self::has_components(entities_and_components, *entity))
+                        .collect(),
+                }
+            }
+        }
+
+        impl<$first_name: 'static, $($generic_name: 'static),*> HasComponents for Or<($first_name, $($generic_name,)*)> {
+            fn has_components(entities_and_components: &EntitiesAndComponents, entity: Entity) -> bool {
+                match entities_and_components.components.get(entity.entity_id) {
+                    Some(components) => components.contains::<$first_name>() $(|| components.contains::<$generic_name>())*,
+                    None => false,
+                }
+            }
+
+            fn get_matching_entities(entities_and_components: &EntitiesAndComponents) -> Vec<Entity> {
+                // union the entities of every component, the map removes duplicates
+                let mut matching_entities = SecondaryMap::new();
+                for type_id in [TypeId::of::<$first_name>() $(, TypeId::of::<$generic_name>())*] {
+                    if let Some(entities) = entities_with_type(entities_and_components, type_id) {
+                        for entity in entities.values() {
+                            matching_entities.insert(entity.entity_id, *entity);
+                        }
+                    }
+                }
+                matching_entities.values().copied().collect()
+            }
+        }
+    };
+}
+
 // it would be nice to have a macro that generates this code
 impl_components!(T1);
 impl_components!(T1, T2);
@@ -666,3 +811,77 @@ impl_owned_components!(
     12, T14, 13, T15, 14, T16, 15, T17, 16, T18, 17, T19, 18, T20, 19, T21, 20, T22, 21, T23, 22,
     T24, 23, T25, 24, T26, 25, T27, 26, T28, 27, T29, 28, T30, 29, T31, 30, T32, 31
 );
+
+impl_has_components!(T1);
+impl_has_components!(T1, T2);
+impl_has_components!(T1, T2, T3);
+impl_has_components!(T1, T2, T3, T4);
+impl_has_components!(T1, T2, T3, T4, T5);
+impl_has_components!(T1, T2, T3, T4, T5, T6);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16);
+impl_has_components!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24, T25
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24, T25, T26
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24, T25, T26, T27
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24, T25, T26, T27, T28
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24, T25, T26, T27, T28, T29
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24, T25, T26, T27, T28, T29, T30
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24, T25, T26, T27, T28, T29, T30, T31
+);
+impl_has_components!(
+    T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21,
+    T22, T23, T24, T25, T26, T27, T28, T29, T30, T31, T32
+);
@@ -0,0 +1,93 @@
+use crate::*;
+
+// removes a component registered with register_transient from every entity
+type ClearTransient = fn(&mut EntitiesAndComponents);
+
+// the structural changes that wait for the end of the run, see World::run
+#[derive(Default)]
+pub(crate) struct Maintenance {
+    commands: CommandQueue,
+    despawns: Vec<Entity>,
+    transient_components: FxHashMap<TypeId, ClearTransient>,
+}
+
+impl Maintenance {
+    // the registered transient components without the queued changes, used by World::clone_for_simulation
+    pub(crate) fn copy_registrations(&self) -> Self {
+        Maintenance {
+            transient_components: self.transient_components.clone(),
+            ..Maintenance::default()
+        }
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Queues a change to the world that is made at the end of the current run, in the order the changes were queued
+    /// Changes queued while the queued changes are being made wait for the next run, see World::run for the full order
+    pub fn defer(&mut self, command: impl FnOnce(&mut EntitiesAndComponents) + Send + 'static) {
+        self.maintenance.commands.push(command);
+    }
+
+    /// Queues an entity to be removed with its children at the end of the current run, after the deferred changes
+    /// Until then the entity can still be used, queueing it more than once is fine
+    pub fn queue_despawn(&mut self, entity: Entity) {
+        self.maintenance.despawns.push(entity);
+    }
+
+    /// Registers a component that is removed from every entity at the end of each run, like a Damaged or JustSpawned marker
+    /// Systems later in the run it was added in can still see it
+    pub fn register_transient<T: Component>(&mut self) {
+        self.maintenance.transient_components.insert(
+            TypeId::of::<T>(),
+            |entities_and_components| {
+                let entities = entities_and_components
+                    .get_entities_with_component::<T>()
+                    .collect::<Vec<Entity>>();
+                for entity in entities {
+                    entities_and_components.remove_component_from::<T>(entity);
+                }
+            },
+        );
+    }
+
+    // makes the changes that wait for the end of the run, in the order documented on World::run
+    pub(crate) fn run_maintenance(&mut self, paused: bool) {
+        std::mem::take(&mut self.maintenance.commands).apply(self);
+
+        for entity in std::mem::take(&mut self.maintenance.despawns) {
+            // children are removed with their parent, so they may already be gone
+            if self.does_entity_exist(entity) {
+                self.remove_entity(entity);
+            }
+        }
+
+        if !paused {
+            self.advance_soft_despawns();
+            let clear_transients = self
+                .maintenance
+                .transient_components
+                .values()
+                .copied()
+                .collect::<Vec<ClearTransient>>();
+            for clear_transient in clear_transients {
+                clear_transient(self);
+            }
+        }
+
+        self.sync_indexes();
+    }
+}
+
+impl World {
+    /// Registers a component that is removed from every entity at the end of each run, see EntitiesAndComponents::register_transient
+    pub fn register_transient<T: Component>(&mut self) {
+        self.entities_and_components.register_transient::<T>();
+    }
+
+    // the end of run and run_versioned, once every system has finished
+    pub(crate) fn run_maintenance(&mut self, paused: bool) {
+        self.entities_and_components.run_maintenance(paused);
+        self.update_world_stats();
+        self.entities_and_components.sample_leak_tracker();
+    }
+}
@@ -0,0 +1,78 @@
+use crate::*;
+
+/// A component that holds entities, like the target of a turret or the owner of an item
+/// Register it with EntitiesAndComponents::register_map_entities and its entities are updated
+/// when the entities they point at are moved by compact, merge, load_scene or replay_from
+pub trait MapEntities: Component {
+    /// Replaces every entity in the component with EntityMap::map
+    fn map_entities(&mut self, entity_map: &EntityMap);
+}
+
+// updates the entities in a type erased component
+pub(crate) type MapComponentEntities = fn(&mut dyn Any, &EntityMap);
+
+impl EntitiesAndComponents {
+    /// Registers a component that holds entities so they are remapped when entities are moved or loaded, see MapEntities
+    pub fn register_map_entities<T: MapEntities>(&mut self) {
+        self.entity_mappers
+            .insert(TypeId::of::<T>(), |component, entity_map| {
+                if let Some(component) = component.downcast_mut::<T>() {
+                    component.map_entities(entity_map);
+                }
+            });
+    }
+
+    // remaps the registered components of every entity that was moved
+    pub(crate) fn map_moved_entities(&mut self, entity_map: &EntityMap) {
+        self.map_entities_of(
+            entity_map.iter().map(|(_, new_entity)| new_entity),
+            entity_map,
+        );
+    }
+
+    // remaps the registered components of the entities
+    pub(crate) fn map_entities_of(
+        &mut self,
+        entities: impl IntoIterator<Item = Entity>,
+        entity_map: &EntityMap,
+    ) {
+        if self.entity_mappers.is_empty() {
+            return;
+        }
+
+        let type_ids = self.entity_mappers.keys().copied().collect::<Vec<TypeId>>();
+        for entity in entities {
+            for type_id in type_ids.iter() {
+                self.map_component_entities(entity, *type_id, entity_map);
+            }
+        }
+    }
+
+    // remaps one component if it is registered with register_map_entities
+    pub(crate) fn map_component_entities(
+        &mut self,
+        entity: Entity,
+        type_id: TypeId,
+        entity_map: &EntityMap,
+    ) {
+        let Some(map_entities) = self.entity_mappers.get(&type_id) else {
+            return;
+        };
+        let Some(components) = self.components.get_mut(entity.entity_id) else {
+            return;
+        };
+
+        // safe because the component is only changed in place, it stays under its own TypeId
+        let component = unsafe { components.as_raw_mut().get_mut(&type_id) };
+        if let Some(component) = component {
+            map_entities(&mut **component, entity_map);
+        }
+    }
+}
+
+impl World {
+    /// Registers a component that holds entities so they are remapped when entities are moved or loaded, see MapEntities
+    pub fn register_map_entities<T: MapEntities>(&mut self) {
+        self.entities_and_components.register_map_entities::<T>();
+    }
+}
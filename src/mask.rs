@@ -0,0 +1,83 @@
+use crate::*;
+
+// the most component types that can be given a bit, types registered after this use the slower lookups
+pub(crate) const MAX_MASKED_COMPONENTS: usize = 128;
+
+/// A bit for every registered component type an entity has
+/// Used to check if an entity has several components at once without a hash lookup for each one
+#[derive(Clone, Copy, PartialEq, Debug, Default, Eq)]
+pub(crate) struct ComponentMask(u128);
+
+impl ComponentMask {
+    pub(crate) fn set(&mut self, bit: usize) {
+        self.0 |= 1 << bit;
+    }
+
+    pub(crate) fn unset(&mut self, bit: usize) {
+        self.0 &= !(1 << bit);
+    }
+
+    pub(crate) fn contains_all(&self, other: ComponentMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl EntitiesAndComponents {
+    // gets the mask with a bit for every type, None if any of them do not have a bit
+    pub(crate) fn get_mask_of(&self, type_ids: &[TypeId]) -> Option<ComponentMask> {
+        let mut mask = ComponentMask::default();
+        for type_id in type_ids {
+            mask.set(*self.component_bits.get(type_id)?);
+        }
+        Some(mask)
+    }
+
+    // checks if an entity has every component in the mask, false if the entity does not exist
+    #[inline]
+    pub(crate) fn entity_has_mask(&self, entity: Entity, mask: ComponentMask) -> bool {
+        match self.component_masks.get(entity.entity_id) {
+            Some(entity_mask) => entity_mask.contains_all(mask),
+            None => false,
+        }
+    }
+
+    // gives a component type the next free bit and sets it for every entity that already has the component
+    pub(crate) fn assign_component_bit(&mut self, type_id: TypeId) {
+        if self.component_bits.contains_key(&type_id)
+            || self.component_bits.len() >= MAX_MASKED_COMPONENTS
+        {
+            return;
+        }
+
+        let bit = self.component_bits.len();
+        self.component_bits.insert(type_id, bit);
+
+        if let Some(entities) = self.entities_with_components.get(&type_id) {
+            for entity in entities.values() {
+                if let Some(mask) = self.component_masks.get_mut(entity.entity_id) {
+                    mask.set(bit);
+                }
+            }
+        }
+    }
+
+    // sets the bit for a component type on an entity if the type has one
+    #[inline]
+    pub(crate) fn set_component_bit(&mut self, entity: Entity, type_id: TypeId) {
+        if let Some(bit) = self.component_bits.get(&type_id) {
+            if let Some(mask) = self.component_masks.get_mut(entity.entity_id) {
+                mask.set(*bit);
+            }
+        }
+    }
+
+    // clears the bit for a component type on an entity if the type has one
+    #[inline]
+    pub(crate) fn unset_component_bit(&mut self, entity: Entity, type_id: TypeId) {
+        if let Some(bit) = self.component_bits.get(&type_id) {
+            if let Some(mask) = self.component_masks.get_mut(entity.entity_id) {
+                mask.unset(*bit);
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::*;
+
+/// A system that reads from one world and writes to another, see MultiWorld::add_cross_world_system
+/// Useful for copying the data a renderer needs out of the simulation world
+pub trait CrossWorldSystem: 'static {
+    /// Called once every MultiWorld::run, after the source world has run
+    fn run(&mut self, source: &EntitiesAndComponents, target: &mut EntitiesAndComponents);
+}
+
+/// Refers to a world in a MultiWorld
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub struct WorldId(usize);
+
+struct CrossWorldEntry {
+    system: Box<dyn CrossWorldSystem + Send + Sync>,
+    source: WorldId,
+    target: WorldId,
+}
+
+/// Holds several worlds, for example one for the simulation and one for the UI,
+/// and runs them together with the systems that move data between them
+#[derive(Default)]
+pub struct MultiWorld {
+    worlds: Vec<World>,
+    cross_world_systems: Vec<CrossWorldEntry>,
+}
+
+impl MultiWorld {
+    /// Creates a MultiWorld with no worlds
+    pub fn new() -> Self {
+        MultiWorld::default()
+    }
+
+    /// Adds a world and returns the id to get it with
+    pub fn add_world(&mut self, world: World) -> WorldId {
+        self.worlds.push(world);
+        WorldId(self.worlds.len() - 1)
+    }
+
+    /// Gets a reference to a world
+    /// panics if the world is not in this MultiWorld
+    pub fn get_world(&self, world: WorldId) -> &World {
+        self.worlds.get(world.0).unwrap_or_else(|| {
+            panic!("World ID {world:?} does not exist, was it made by a different MultiWorld?");
+        })
+    }
+
+    /// Gets a mutable reference to a world
+    /// panics if the world is not in this MultiWorld
+    pub fn get_world_mut(&mut self, world: WorldId) -> &mut World {
+        self.worlds.get_mut(world.0).unwrap_or_else(|| {
+            panic!("World ID {world:?} does not exist, was it made by a different MultiWorld?");
+        })
+    }
+
+    /// Adds a system that reads from the source world and writes to the target world
+    /// The target world runs after the source world so it sees the data the system wrote on the same run,
+    /// unless the worlds read from each other, then the world that was added first runs first
+    /// panics if the source and target are the same world or either one is not in this MultiWorld
+    pub fn add_cross_world_system<T: CrossWorldSystem + Send + Sync>(
+        &mut self,
+        system: T,
+        source: WorldId,
+        target: WorldId,
+    ) {
+        if source == target {
+            panic!("Cross world system reads and writes {source:?}, add it to the world with World::add_system instead");
+        }
+        self.get_world(source);
+        self.get_world(target);
+
+        self.cross_world_systems.push(CrossWorldEntry {
+            system: Box::new(system),
+            source,
+            target,
+        });
+    }
+
+    /// Runs every world with World::run
+    /// After a world runs, the cross world systems that read from it are run in the order they were added
+    pub fn run(&mut self) {
+        for world in self.get_run_order() {
+            self.worlds[world.0].run();
+
+            for entry in self.cross_world_systems.iter_mut() {
+                if entry.source != world {
+                    continue;
+                }
+
+                // the source and target are never the same, so this is two separate borrows
+                let (source, target) = if entry.source.0 < entry.target.0 {
+                    let (left, right) = self.worlds.split_at_mut(entry.target.0);
+                    (&left[entry.source.0], &mut right[0])
+                } else {
+                    let (left, right) = self.worlds.split_at_mut(entry.source.0);
+                    (&right[0], &mut left[entry.target.0])
+                };
+
+                entry.system.run(
+                    &source.entities_and_components,
+                    &mut target.entities_and_components,
+                );
+            }
+        }
+    }
+
+    // every world runs after the worlds it reads from, if there is a cycle the world added first goes first
+    fn get_run_order(&self) -> Vec<WorldId> {
+        let mut order = Vec::with_capacity(self.worlds.len());
+        let mut has_run = vec![false; self.worlds.len()];
+
+        while order.len() < self.worlds.len() {
+            let is_ready = |world: usize| {
+                !has_run[world]
+                    && self
+                        .cross_world_systems
+                        .iter()
+                        .all(|entry| entry.target.0 != world || has_run[entry.source.0])
+            };
+
+            let next = (0..self.worlds.len())
+                .find(|world| is_ready(*world))
+                .or_else(|| (0..self.worlds.len()).find(|world| !has_run[*world]));
+
+            if let Some(next) = next {
+                has_run[next] = true;
+                order.push(WorldId(next));
+            }
+        }
+
+        order
+    }
+}
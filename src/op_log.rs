@@ -0,0 +1,157 @@
+use crate::*;
+
+/// A change to the structure of the world, see World::start_recording_ops
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub enum StructuralOp {
+    /// An entity was added
+    Spawn(Entity),
+    /// An entity was removed, or moved out of the world
+    Despawn(Entity),
+    /// A component was added to an entity, or overwritten
+    AddComponent {
+        /// The entity the component was added to
+        entity: Entity,
+        /// The type name of the component
+        component: &'static str,
+    },
+    /// A component was removed from an entity
+    RemoveComponent {
+        /// The entity the component was removed from
+        entity: Entity,
+        /// The type name of the component
+        component: &'static str,
+    },
+    /// An entity was made the child of another entity
+    SetParent {
+        /// The entity that was given a parent
+        child: Entity,
+        /// The new parent of the entity
+        parent: Entity,
+    },
+    /// An entity was removed from its parent
+    RemoveParent(Entity),
+}
+
+/// A structural change and where it came from
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub struct RecordedOp {
+    /// What changed
+    pub op: StructuralOp,
+    /// The type name of the system that made the change, None if it was made outside of a system
+    pub system: Option<&'static str>,
+    /// How many times the systems had been run when the change was made
+    pub frame: u64,
+}
+
+// the last capacity structural changes, only kept while recording
+pub(crate) struct OpLog {
+    ops: VecDeque<RecordedOp>,
+    capacity: usize,
+    // the system that is running, set by World::run_systems and by queued commands
+    system: Option<&'static str>,
+    frame: u64,
+}
+
+impl OpLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        OpLog {
+            ops: VecDeque::with_capacity(capacity),
+            capacity,
+            system: None,
+            frame: 0,
+        }
+    }
+
+    fn push(&mut self, op: StructuralOp) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.ops.len() == self.capacity {
+            self.ops.pop_front();
+        }
+        self.ops.push_back(RecordedOp {
+            op,
+            system: self.system,
+            frame: self.frame,
+        });
+    }
+}
+
+impl EntitiesAndComponents {
+    // records a structural change if recording is on
+    pub(crate) fn record_op(&mut self, op: StructuralOp) {
+        self.frame_counts.count(&op);
+        self.record_op_to_file(&op);
+        if let Some(op_log) = &mut self.op_log {
+            op_log.push(op);
+        }
+    }
+
+    // records that a component was added or removed, internal components like Children are left out
+    pub(crate) fn record_component_op(&mut self, entity: Entity, type_id: TypeId, added: bool) {
+        if inspect::is_internal_component(type_id) {
+            return;
+        }
+
+        self.record_component_to_file(entity, type_id, added);
+        if self.op_log.is_none() {
+            return;
+        }
+
+        let component = self.get_type_name(type_id);
+        self.record_op(match added {
+            true => StructuralOp::AddComponent { entity, component },
+            false => StructuralOp::RemoveComponent { entity, component },
+        });
+    }
+
+    // sets the system that the next changes are recorded for
+    pub(crate) fn set_recording_system(&mut self, system: Option<&'static str>) {
+        if let Some(op_log) = &mut self.op_log {
+            op_log.system = system;
+        }
+    }
+
+    // the system the changes are being recorded for
+    pub(crate) fn get_recording_system(&self) -> Option<&'static str> {
+        self.op_log.as_ref().and_then(|op_log| op_log.system)
+    }
+
+    // starts a new frame in the log
+    pub(crate) fn next_recording_frame(&mut self) {
+        self.record_frame_to_file();
+        if let Some(op_log) = &mut self.op_log {
+            op_log.frame += 1;
+        }
+    }
+}
+
+impl World {
+    /// Starts recording every spawn, despawn, component add or remove and parent change
+    /// along with the system that did it and the frame it happened in
+    /// Only the last capacity changes are kept, get them with recent_ops
+    /// Calling this while recording clears the log
+    pub fn start_recording_ops(&mut self, capacity: usize) {
+        self.entities_and_components.op_log = Some(OpLog::new(capacity));
+    }
+
+    /// Stops recording and throws away the recorded changes
+    pub fn stop_recording_ops(&mut self) {
+        self.entities_and_components.op_log = None;
+    }
+
+    /// Checks if structural changes are being recorded
+    pub fn is_recording_ops(&self) -> bool {
+        self.entities_and_components.op_log.is_some()
+    }
+
+    /// Gets the recorded structural changes from oldest to newest
+    /// Empty if start_recording_ops has not been called
+    pub fn recent_ops(&self) -> Vec<RecordedOp> {
+        match &self.entities_and_components.op_log {
+            Some(op_log) => op_log.ops.iter().copied().collect(),
+            None => vec![],
+        }
+    }
+}
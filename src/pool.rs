@@ -0,0 +1,62 @@
+use crate::*;
+
+type ResetEntity = Box<dyn Fn(Entity, &mut EntitiesAndComponents)>;
+
+/// A pool of entities that are reused instead of being removed and added again
+/// Released entities are taken out of the world, so systems will not see them,
+/// but their components are kept so they don't need to be allocated again when the entity is acquired
+/// Useful for things like bullets and particles that are constantly being created and destroyed
+pub struct EntityPool<T> {
+    free: Vec<Map<dyn Any + 'static>>,
+    create: Box<dyn Fn() -> T>,
+    reset: ResetEntity,
+}
+
+impl<T: OwnedComponents<Input = T>> EntityPool<T> {
+    pub(crate) fn new(
+        entities_and_components: &mut EntitiesAndComponents,
+        capacity: usize,
+        create: Box<dyn Fn() -> T>,
+        reset: ResetEntity,
+    ) -> Self {
+        let mut free = Vec::with_capacity(capacity);
+        for _ in 0..capacity {
+            let entity = entities_and_components.add_entity_with(create());
+            free.push(entities_and_components.detach_entity(entity));
+        }
+
+        EntityPool {
+            free,
+            create,
+            reset,
+        }
+    }
+
+    /// Gets an entity from the pool and adds it back into the world
+    /// If the pool is empty a new entity is made
+    pub fn acquire(&mut self, entities_and_components: &mut EntitiesAndComponents) -> Entity {
+        match self.free.pop() {
+            Some(components) => {
+                let entity = entities_and_components.add_entity_with_component_map(components);
+                (self.reset)(entity, entities_and_components);
+                entities_and_components.run_spawn_hooks(entity);
+                entity
+            }
+            None => entities_and_components.add_entity_with((self.create)()),
+        }
+    }
+
+    /// Takes an entity out of the world and returns it to the pool
+    /// The entity is unlinked from its parent and children, the children are not released
+    /// The entity handle is no longer valid after this, acquire will give out a new one
+    /// panics if the entity does not exist
+    pub fn release(&mut self, entities_and_components: &mut EntitiesAndComponents, entity: Entity) {
+        self.free
+            .push(entities_and_components.detach_entity(entity));
+    }
+
+    /// Gets the number of entities waiting in the pool
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+}
@@ -0,0 +1,109 @@
+use crate::*;
+
+/// The value a component had at the start of the last frame, see World::register_previous
+/// Useful for interpolating between the last two fixed updates when rendering
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Prev<T>(pub T);
+
+/// A value that can be blended with another one, used by EntitiesAndComponents::interpolated
+pub trait Lerp {
+    /// Gets the value alpha of the way from self to to, 0.0 gives self and 1.0 gives to
+    fn lerp(&self, to: &Self, alpha: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, alpha: f32) -> Self {
+        self + (to - self) * alpha
+    }
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, to: &Self, alpha: f32) -> Self {
+        self + (to - self) * alpha as f64
+    }
+}
+
+impl<T: Lerp, const N: usize> Lerp for [T; N] {
+    fn lerp(&self, to: &Self, alpha: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(&to[i], alpha))
+    }
+}
+
+// copies T into Prev<T> on every entity with T, and removes Prev<T> from the entities that lost T
+fn store_previous<T: Component + Clone>(entities_and_components: &mut EntitiesAndComponents) {
+    let mut entities = entities_and_components.frame_alloc().vec::<Entity>();
+    entities.extend(entities_and_components.get_entities_with_component::<T>());
+    for entity in entities.drain(..) {
+        let value = entities_and_components
+            .try_get_component::<T>(entity)
+            .cloned()
+            .unwrap_or_else(|| {
+                panic!("Entity ID {entity:?} is listed with a component it does not have, was the Entity ID edited?")
+            });
+        match entities_and_components.try_get_component_mut::<Prev<T>>(entity) {
+            Some(prev) => prev.0 = value,
+            None => entities_and_components.add_component_to(entity, Prev(value)),
+        }
+    }
+
+    entities.extend(
+        entities_and_components
+            .get_entities_with_component::<Prev<T>>()
+            .filter(|entity| {
+                entities_and_components
+                    .try_get_component::<T>(*entity)
+                    .is_none()
+            }),
+    );
+    // the entities that lost T
+    for entity in entities.drain(..) {
+        entities_and_components.remove_component_from::<Prev<T>>(entity);
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Gets the component T blended between its value in Prev<T> and its current value
+    /// alpha is how far the render is between the last fixed update and the next one, from 0.0 to 1.0
+    /// If the entity has no Prev<T> yet the current value is used, T has to be registered with World::register_previous
+    /// Returns None if the entity does not have T
+    pub fn interpolated<T: Component + Lerp>(&self, entity: Entity, alpha: f32) -> Option<T> {
+        let current = self.try_get_component::<T>(entity)?;
+        let previous = self
+            .try_get_component::<Prev<T>>(entity)
+            .map_or(current, |prev| &prev.0);
+        Some(previous.lerp(current, alpha))
+    }
+}
+
+impl World {
+    /// Keeps the last frame's value of the component T in Prev<T> on every entity with T
+    /// The values are copied at the start of every run before anything else changes them,
+    /// so during a frame Prev<T> holds what T was at the end of the last frame
+    /// Entities get Prev<T> on the first run after they get T, and lose it on the first run after they lose T
+    /// Nothing is copied while the world is paused
+    pub fn register_previous<T: Component + Clone>(&mut self) {
+        if self
+            .entities_and_components
+            .registered_components
+            .contains_key(&TypeId::of::<T>())
+        {
+            // strict components would otherwise panic on Prev<T>
+            self.entities_and_components.register_component::<Prev<T>>();
+        }
+        self.previous_components
+            .insert(TypeId::of::<T>(), store_previous::<T>);
+    }
+
+    /// Gets the component T blended between its value in Prev<T> and its current value, see EntitiesAndComponents::interpolated
+    pub fn interpolated<T: Component + Lerp>(&self, entity: Entity, alpha: f32) -> Option<T> {
+        self.entities_and_components
+            .interpolated::<T>(entity, alpha)
+    }
+
+    // copies every component registered with register_previous into its Prev
+    pub(crate) fn store_previous_components(&mut self) {
+        for store in self.previous_components.values() {
+            store(&mut self.entities_and_components);
+        }
+    }
+}
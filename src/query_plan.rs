@@ -0,0 +1,84 @@
+use crate::*;
+
+/// The size of the set of entities kept for one component type, see EntitiesAndComponents::index_stats
+#[derive(Clone, PartialEq, Debug)]
+pub struct IndexStats {
+    /// The type name of the component
+    pub name: &'static str,
+    /// The type of the component
+    pub type_id: TypeId,
+    /// The number of entities with the component, not counting entities hidden with despawn_soft
+    pub entities: usize,
+}
+
+impl EntitiesAndComponents {
+    /// Gets the number of entities with each component type that has ever been added, the largest sets first
+    /// Queries over several components go through the smallest of their sets, so this shows how fast they are
+    pub fn index_stats(&self) -> Vec<IndexStats> {
+        let mut index_stats = self
+            .entities_with_components
+            .iter()
+            .map(|(type_id, entities)| IndexStats {
+                name: self.get_type_name(*type_id),
+                type_id: *type_id,
+                entities: entities.len(),
+            })
+            .collect::<Vec<IndexStats>>();
+        index_stats.sort_by(|a, b| b.entities.cmp(&a.entities).then_with(|| a.name.cmp(b.name)));
+        index_stats
+    }
+
+    /// Makes get_entities_matching::<Q>() always go through the entities with D instead of the smallest set
+    /// Useful when the smallest set is usually the wrong one to start from, like a marker that almost every entity
+    /// with the other components has, D is ignored if it is not one of the components of Q
+    pub fn pin_query_driver<Q: HasComponents + 'static, D: Component>(&mut self) {
+        self.query_drivers
+            .insert(TypeId::of::<Q>(), TypeId::of::<D>());
+    }
+
+    /// Lets get_entities_matching::<Q>() pick the smallest set again, see pin_query_driver
+    pub fn unpin_query_driver<Q: HasComponents + 'static>(&mut self) {
+        self.query_drivers.remove(&TypeId::of::<Q>());
+    }
+
+    // the entities a query for every one of type_ids goes through, the pinned component or the smallest set
+    // returns None if no entity has one of the components, so nothing can match
+    pub(crate) fn get_query_driver(
+        &self,
+        query: TypeId,
+        type_ids: &[TypeId],
+    ) -> Option<&ComponentEntities> {
+        if let Some(driver) = self.query_drivers.get(&query) {
+            if type_ids.contains(driver) {
+                return self.entities_with_components.get(driver);
+            }
+        }
+
+        let mut smallest: Option<&ComponentEntities> = None;
+        for type_id in type_ids {
+            let entities = self.entities_with_components.get(type_id)?;
+            // the first of the smallest sets is used so the order does not change when the sizes are equal
+            if smallest.is_none_or(|smallest| entities.len() < smallest.len()) {
+                smallest = Some(entities);
+            }
+        }
+        smallest
+    }
+}
+
+impl World {
+    /// Gets the number of entities with each component type, see EntitiesAndComponents::index_stats
+    pub fn index_stats(&self) -> Vec<IndexStats> {
+        self.entities_and_components.index_stats()
+    }
+
+    /// Makes a query always go through the entities with D, see EntitiesAndComponents::pin_query_driver
+    pub fn pin_query_driver<Q: HasComponents + 'static, D: Component>(&mut self) {
+        self.entities_and_components.pin_query_driver::<Q, D>();
+    }
+
+    /// Lets a query pick the smallest set again, see EntitiesAndComponents::unpin_query_driver
+    pub fn unpin_query_driver<Q: HasComponents + 'static>(&mut self) {
+        self.entities_and_components.unpin_query_driver::<Q>();
+    }
+}
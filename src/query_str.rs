@@ -0,0 +1,233 @@
+use crate::*;
+
+/// Why a query passed to World::query_str could not be run
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub enum QueryStrError {
+    /// The query is not written correctly
+    Syntax {
+        /// The byte in the query where the problem was found
+        position: usize,
+        /// What was expected there
+        message: String,
+    },
+    /// No component with this name has been added or registered
+    UnknownComponent(String),
+    /// More than one component type has this name, the full path has to be used
+    AmbiguousComponent {
+        /// The name in the query
+        name: String,
+        /// The full type names it could mean
+        matches: Vec<&'static str>,
+    },
+}
+
+impl std::fmt::Display for QueryStrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryStrError::Syntax { position, message } => {
+                write!(f, "expected {message} at position {position}")
+            }
+            QueryStrError::UnknownComponent(name) => {
+                write!(f, "there is no component named {name}")
+            }
+            QueryStrError::AmbiguousComponent { name, matches } => {
+                write!(f, "{name} could be any of {}", matches.join(", "))
+            }
+        }
+    }
+}
+
+// a parsed query with the component names already turned into types
+enum QueryExpr {
+    With(TypeId),
+    Without(TypeId),
+    Not(Box<QueryExpr>),
+    // a chain of & or | is one node, so long chains do not make the query deeper
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+}
+
+impl QueryExpr {
+    fn matches(&self, entities_and_components: &EntitiesAndComponents, entity: Entity) -> bool {
+        match self {
+            QueryExpr::With(type_id) => {
+                entities_and_components.has_component_type(entity, *type_id)
+            }
+            QueryExpr::Without(type_id) => {
+                !entities_and_components.has_component_type(entity, *type_id)
+            }
+            QueryExpr::Not(expr) => !expr.matches(entities_and_components, entity),
+            QueryExpr::And(exprs) => exprs
+                .iter()
+                .all(|expr| expr.matches(entities_and_components, entity)),
+            QueryExpr::Or(exprs) => exprs
+                .iter()
+                .any(|expr| expr.matches(entities_and_components, entity)),
+        }
+    }
+}
+
+// how deep '!' and brackets can be nested in a query, deeper queries are rejected instead of overflowing the stack
+const MAX_QUERY_DEPTH: usize = 256;
+
+// parses queries like "With(Position) & !(With(Dead) | Without(Health))"
+// & binds tighter than |
+struct QueryParser<'a> {
+    query: &'a str,
+    position: usize,
+    entities_and_components: &'a EntitiesAndComponents,
+}
+
+impl QueryParser<'_> {
+    fn error<T>(&self, message: &str) -> Result<T, QueryStrError> {
+        Err(QueryStrError::Syntax {
+            position: self.position,
+            message: message.to_string(),
+        })
+    }
+
+    fn rest(&self) -> &str {
+        &self.query[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    // skips past token if it is next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    // depth is how many '!' and brackets the expression is inside of
+    fn parse_or(&mut self, depth: usize) -> Result<QueryExpr, QueryStrError> {
+        let mut exprs = vec![self.parse_and(depth)?];
+        while self.eat("|") {
+            exprs.push(self.parse_and(depth)?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => QueryExpr::Or(exprs),
+        })
+    }
+
+    fn parse_and(&mut self, depth: usize) -> Result<QueryExpr, QueryStrError> {
+        let mut exprs = vec![self.parse_term(depth)?];
+        while self.eat("&") {
+            exprs.push(self.parse_term(depth)?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => QueryExpr::And(exprs),
+        })
+    }
+
+    fn parse_term(&mut self, depth: usize) -> Result<QueryExpr, QueryStrError> {
+        self.skip_whitespace();
+        if depth > MAX_QUERY_DEPTH {
+            return self.error(&format!(
+                "at most {MAX_QUERY_DEPTH} nested '!' and brackets"
+            ));
+        }
+        if self.eat("!") {
+            return Ok(QueryExpr::Not(Box::new(self.parse_term(depth + 1)?)));
+        }
+        if self.eat("(") {
+            let expr = self.parse_or(depth + 1)?;
+            if !self.eat(")") {
+                return self.error("')'");
+            }
+            return Ok(expr);
+        }
+        // Without has to be checked first since With is the start of it
+        if self.eat("Without(") {
+            return Ok(QueryExpr::Without(self.parse_component()?));
+        }
+        if self.eat("With(") {
+            return Ok(QueryExpr::With(self.parse_component()?));
+        }
+        self.error("With(..), Without(..), '!' or '('")
+    }
+
+    // reads a component name up to the closing bracket
+    fn parse_component(&mut self) -> Result<TypeId, QueryStrError> {
+        let Some(length) = self.rest().find(')') else {
+            return self.error("')'");
+        };
+        let name = self.rest()[..length].trim();
+        if name.is_empty() {
+            return self.error("a component name");
+        }
+        let type_id = resolve_component(self.entities_and_components, name)?;
+        self.position += length + 1;
+        Ok(type_id)
+    }
+}
+
+// finds the component with a type name, either the full path or just the name of the type
+fn resolve_component(
+    entities_and_components: &EntitiesAndComponents,
+    name: &str,
+) -> Result<TypeId, QueryStrError> {
+    let mut matches: Vec<(TypeId, &'static str)> = entities_and_components
+        .component_names
+        .iter()
+        .chain(entities_and_components.registered_components.iter())
+        .filter(|(_, type_name)| {
+            **type_name == name
+                || type_name
+                    .strip_suffix(name)
+                    .is_some_and(|path| path.ends_with("::"))
+        })
+        .map(|(type_id, type_name)| (*type_id, *type_name))
+        .collect();
+    matches.sort_unstable_by_key(|(_, type_name)| *type_name);
+    matches.dedup_by_key(|(type_id, _)| *type_id);
+
+    match matches.as_slice() {
+        [] => Err(QueryStrError::UnknownComponent(name.to_string())),
+        [(type_id, _)] => Ok(*type_id),
+        _ => Err(QueryStrError::AmbiguousComponent {
+            name: name.to_string(),
+            matches: matches.iter().map(|(_, type_name)| *type_name).collect(),
+        }),
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Finds the entities matching a query written as a string, for consoles and editors
+    /// like "With(Position) & Without(Dead)", queries can use &, |, ! and brackets
+    /// Components are found by their type name, which can be the full path or just the name of the type,
+    /// only components that have been added to an entity or registered can be found
+    pub fn query_str(&self, query: &str) -> Result<Vec<Entity>, QueryStrError> {
+        let mut parser = QueryParser {
+            query,
+            position: 0,
+            entities_and_components: self,
+        };
+        let expr = parser.parse_or(0)?;
+        parser.skip_whitespace();
+        if parser.position != query.len() {
+            return parser.error("'&', '|' or the end of the query");
+        }
+
+        Ok(self
+            .iter_entities()
+            .filter(|entity| expr.matches(self, *entity))
+            .collect())
+    }
+}
+
+impl World {
+    /// Finds the entities matching a query written as a string, see EntitiesAndComponents::query_str
+    pub fn query_str(&self, query: &str) -> Result<Vec<Entity>, QueryStrError> {
+        self.entities_and_components.query_str(query)
+    }
+}
@@ -0,0 +1,124 @@
+use crate::*;
+use rayon::iter::IntoParallelRefIterator;
+use std::time::{Duration, Instant};
+
+/// A system that can only read the world, its run function is given the same view of the world as System::prestep
+/// Read only systems whose run functions are called one after another,
+/// like the ones in the same set, run at the same time on different threads,
+/// so only components and resources that are Send + Sync can be read
+/// Add it with World::add_read_only_system
+pub trait ReadOnlySystem: Send + Sync + 'static {
+    /// This function is called once every frame, at the same point as System::run
+    fn run(&self, _engine: &EntitiesAndComponentsThreadSafe) {}
+    /// Like run but can return an error, the error is added to the FrameReport
+    /// By default this calls run, implement one or the other
+    fn try_run(&self, engine: &EntitiesAndComponentsThreadSafe) -> Result<(), SystemError> {
+        self.run(engine);
+        Ok(())
+    }
+    /// Declares the components the system reads, see System::component_access
+    fn component_access(&self, _access: &mut ComponentAccess) {}
+}
+
+// stores a ReadOnlySystem with the other systems, so sets, groups and intervals work the same way for it
+struct ReadOnly<T: ReadOnlySystem>(T);
+
+impl<T: ReadOnlySystem> System for ReadOnly<T> {
+    fn try_run(&mut self, engine: &mut EntitiesAndComponents) -> Result<(), SystemError> {
+        let (result, resource_commands) = {
+            let thread_safe_engine = EntitiesAndComponentsThreadSafe::new(engine);
+            let result = self.0.try_run(&thread_safe_engine);
+            (result, thread_safe_engine.take_resource_commands())
+        };
+        resource_commands.apply(engine);
+        result
+    }
+
+    fn component_access(&self, access: &mut ComponentAccess) {
+        self.0.component_access(access);
+    }
+}
+
+// runs a read only system from the type erased system it is stored as
+#[derive(Clone, Copy)]
+pub(crate) struct ReadOnlyRunner {
+    run: fn(&dyn Any, &EntitiesAndComponentsThreadSafe) -> Result<(), SystemError>,
+    name: &'static str,
+}
+
+impl World {
+    /// Adds a system that only reads the world, see ReadOnlySystem
+    /// It can be removed and put in sets like any other system
+    pub fn add_read_only_system<T: ReadOnlySystem>(&mut self, system: T) -> SystemHandle {
+        let system_handle = self.add_system(ReadOnly(system));
+        self.read_only_systems.insert(
+            system_handle.system_id,
+            ReadOnlyRunner {
+                run: |system, engine| match system.downcast_ref::<ReadOnly<T>>() {
+                    Some(system) => system.0.try_run(engine),
+                    None => Ok(()),
+                },
+                name: std::any::type_name::<T>(),
+            },
+        );
+
+        system_handle
+    }
+
+    // checks if a system was added with add_read_only_system
+    pub(crate) fn is_read_only_system(&self, system_id: DefaultKey) -> bool {
+        self.read_only_systems.contains_key(system_id)
+    }
+
+    // runs the run functions of read only systems at the same time
+    pub(crate) fn run_read_only_systems(
+        &mut self,
+        system_ids: &[DefaultKey],
+        frame_failures: &mut Vec<SystemFailure>,
+    ) {
+        if system_ids.is_empty() {
+            return;
+        }
+
+        let trace_capture = self.trace_capture.as_ref();
+        let thread_safe_engine =
+            EntitiesAndComponentsThreadSafe::new(&mut self.entities_and_components);
+        let systems = system_ids
+            .iter()
+            .map(|system_id| {
+                (
+                    &self.systems[*system_id],
+                    self.read_only_systems[*system_id],
+                )
+            })
+            .collect::<Vec<(&Box<dyn SystemWrapper + Send + Sync>, ReadOnlyRunner)>>();
+
+        let results = systems
+            .par_iter()
+            .map(|(system, runner)| {
+                let start = Instant::now();
+                let result = (runner.run)(system.as_any(), &thread_safe_engine);
+                if let Some(trace_capture) = trace_capture {
+                    trace_capture.record(runner.name, "run", start, None);
+                }
+                (runner.name, start.elapsed(), result)
+            })
+            .collect::<Vec<(&'static str, Duration, Result<(), SystemError>)>>();
+        let resource_commands = thread_safe_engine.take_resource_commands();
+        resource_commands.apply(&mut self.entities_and_components);
+        self.entities_and_components.spawn_taken_entities();
+
+        for (system_name, elapsed, result) in results {
+            if self.profile_systems {
+                self.system_timings.push((system_name, elapsed));
+            }
+            if let Err(error) = result {
+                frame_failures.push(SystemFailure {
+                    system: system_name,
+                    entity: None,
+                    error,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use crate::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+const REPLAY_HEADER: &str = "abc-ecs replay 1";
+
+/// A component that can be written to a replay file, see World::record_to
+/// Register it with World::register_replay_component, components that are not registered are left out of the replay
+pub trait ReplayComponent: Component + Sized {
+    /// Writes the component as text, the text can be anything as long as load can read it back
+    fn save(&self) -> String;
+
+    /// Reads a component from text made by save, returns None if the text is not valid
+    fn load(data: &str) -> Option<Self>;
+
+    /// The version of the text made by save, increase it whenever the text changes shape
+    /// Replay files remember the version each component was saved with so old files can still be loaded with migrate
+    const VERSION: u32 = 0;
+
+    /// Reads a component from text made by save when the component had an older VERSION
+    /// Returns None if the text is not valid or the version can not be migrated, which is the default
+    fn migrate(old_version: u32, data: &str) -> Option<Self> {
+        let _ = (old_version, data);
+        None
+    }
+}
+
+type SaveComponent = fn(&dyn Any) -> Option<String>;
+type LoadComponent = fn(&mut EntitiesAndComponents, Entity, u32, &str) -> bool;
+type RemoveComponent = fn(&mut EntitiesAndComponents, Entity);
+
+// the functions to save, load and remove a registered replay component without knowing its type
+#[derive(Clone, Copy)]
+pub(crate) struct ReplayCodec {
+    pub(crate) name: &'static str,
+    pub(crate) version: u32,
+    pub(crate) save: SaveComponent,
+    pub(crate) load: LoadComponent,
+    remove: RemoveComponent,
+}
+
+impl ReplayCodec {
+    fn new<T: ReplayComponent>() -> Self {
+        ReplayCodec {
+            name: std::any::type_name::<T>(),
+            version: T::VERSION,
+            save: |component| component.downcast_ref::<T>().map(T::save),
+            load: |entities_and_components, entity, version, data| {
+                let component = if version == T::VERSION {
+                    T::load(data)
+                } else {
+                    T::migrate(version, data)
+                };
+                match component {
+                    Some(component) => {
+                        entities_and_components.add_component_to(entity, component);
+                        true
+                    }
+                    None => false,
+                }
+            },
+            remove: |entities_and_components, entity| {
+                entities_and_components.remove_component_from::<T>(entity);
+            },
+        }
+    }
+}
+
+// writes the recorded operations of a world to a file, see World::record_to
+pub(crate) struct Recorder {
+    writer: BufWriter<File>,
+    // the last value written for each replay component, used to find the components that changed
+    saved: SecondaryMap<DefaultKey, FxHashMap<TypeId, String>>,
+    // the first error, writes are skipped after it and it is returned by finish_recording
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    fn write_line(&mut self, line: std::fmt::Arguments) {
+        if self.error.is_none() {
+            if let Err(error) = writeln!(self.writer, "{line}") {
+                self.error = Some(error);
+            }
+        }
+    }
+
+    fn write_op(&mut self, op: &StructuralOp) {
+        match *op {
+            StructuralOp::Spawn(entity) => {
+                self.write_line(format_args!("spawn\t{}", entity_id(entity)));
+            }
+            StructuralOp::Despawn(entity) => {
+                self.saved.remove(entity.entity_id);
+                self.write_line(format_args!("despawn\t{}", entity_id(entity)));
+            }
+            StructuralOp::SetParent { child, parent } => {
+                self.write_line(format_args!(
+                    "parent\t{}\t{}",
+                    entity_id(child),
+                    entity_id(parent)
+                ));
+            }
+            StructuralOp::RemoveParent(child) => {
+                self.write_line(format_args!("unparent\t{}", entity_id(child)));
+            }
+            // components are written by record_component_to_file since their value is needed
+            StructuralOp::AddComponent { .. } | StructuralOp::RemoveComponent { .. } => {}
+        }
+    }
+
+    // writes a component if it changed since it was last written, command is add or set
+    // a set is written as a delta from the last value when the component has an encoder and that is shorter
+    fn write_component(
+        &mut self,
+        command: &str,
+        entity: Entity,
+        type_id: TypeId,
+        name: &str,
+        data: String,
+        encoder: Option<&dyn ReplayEncoder>,
+    ) {
+        if !self.saved.contains_key(entity.entity_id) {
+            self.saved.insert(entity.entity_id, FxHashMap::default());
+        }
+        let saved = &mut self.saved[entity.entity_id];
+        if command == "set" && saved.get(&type_id) == Some(&data) {
+            return;
+        }
+
+        let delta = match (command, saved.get(&type_id), encoder) {
+            ("set", Some(previous), Some(encoder)) => encoder
+                .encode(previous, &data)
+                .filter(|encoded| encoded.len() < data.len()),
+            _ => None,
+        };
+        let line = match delta {
+            Some(encoded) => format!("delta\t{}\t{name}\t{}", entity_id(entity), escape(&encoded)),
+            None => format!(
+                "{command}\t{}\t{name}\t{}",
+                entity_id(entity),
+                escape(&data)
+            ),
+        };
+        saved.insert(type_id, data);
+        self.write_line(format_args!("{line}"));
+    }
+}
+
+// the id an entity is written with, it is only used to match up entities within one file
+fn entity_id(entity: Entity) -> u64 {
+    entity.to_bits()
+}
+
+// values are written on one line with tabs between the fields, so newlines and tabs are escaped
+fn escape(data: &str) -> String {
+    data.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+fn unescape(data: &str) -> String {
+    let mut unescaped = String::with_capacity(data.len());
+    let mut chars = data.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn invalid_data(line_number: usize, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {line_number} of the replay: {message}"),
+    )
+}
+
+impl EntitiesAndComponents {
+    // writes a component that was added or removed to the replay file, if the component is registered for replays
+    pub(crate) fn record_component_to_file(
+        &mut self,
+        entity: Entity,
+        type_id: TypeId,
+        added: bool,
+    ) {
+        let (Some(recorder), Some(codec)) = (&mut self.recorder, self.replay_codecs.get(&type_id))
+        else {
+            return;
+        };
+
+        if !added {
+            if let Some(saved) = recorder.saved.get_mut(entity.entity_id) {
+                saved.remove(&type_id);
+            }
+            recorder.write_line(format_args!(
+                "remove\t{}\t{}",
+                entity_id(entity),
+                codec.name
+            ));
+            return;
+        }
+
+        let data = self
+            .components
+            .get(entity.entity_id)
+            .and_then(|components| components.as_raw().get(&type_id))
+            .and_then(|component| (codec.save)(&**component));
+        if let Some(data) = data {
+            recorder.write_component("add", entity, type_id, codec.name, data, None);
+        }
+    }
+
+    // writes every replay component that changed since it was last written, then marks the end of a frame
+    pub(crate) fn record_frame_to_file(&mut self) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+
+        for (type_id, codec) in self.replay_codecs.iter() {
+            let Some(entities) = self.entities_with_components.get(type_id) else {
+                continue;
+            };
+            let encoder = self.replay_encoders.get(type_id).map(|encoder| &**encoder);
+
+            for entity in entities.values() {
+                let data = self
+                    .components
+                    .get(entity.entity_id)
+                    .and_then(|components| components.as_raw().get(type_id))
+                    .and_then(|component| (codec.save)(&**component));
+                if let Some(data) = data {
+                    recorder.write_component("set", *entity, *type_id, codec.name, data, encoder);
+                }
+            }
+        }
+
+        recorder.write_line(format_args!("frame"));
+        if recorder.error.is_none() {
+            if let Err(error) = recorder.writer.flush() {
+                recorder.error = Some(error);
+            }
+        }
+    }
+
+    // writes the new ids of the entities moved by compact, the lines after it use the new ids
+    pub(crate) fn record_remap_to_file(&mut self, entity_map: &EntityMap) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+
+        // taken out first, a new id can be in the same slot as an old id that was not moved yet
+        let saved = entity_map
+            .iter()
+            .filter_map(|(old_entity, new_entity)| {
+                recorder
+                    .saved
+                    .remove(old_entity.entity_id)
+                    .map(|saved| (new_entity, saved))
+            })
+            .collect::<Vec<_>>();
+        for (new_entity, saved) in saved {
+            recorder.saved.insert(new_entity.entity_id, saved);
+        }
+        for (old_entity, new_entity) in entity_map.iter() {
+            recorder.write_line(format_args!(
+                "remap\t{}\t{}",
+                entity_id(old_entity),
+                entity_id(new_entity)
+            ));
+        }
+    }
+
+    // writes that an entity was hidden with despawn_soft or brought back with resurrect
+    // one line is written for each entity, so children added to a hidden entity are not hidden by the replay
+    pub(crate) fn record_soft_despawn_to_file(&mut self, entity: Entity, hidden: bool) {
+        if let Some(recorder) = &mut self.recorder {
+            let command = if hidden { "hide" } else { "resurrect" };
+            recorder.write_line(format_args!("{command}\t{}", entity_id(entity)));
+        }
+    }
+
+    // writes the spawn, despawn and parent changes to the replay file
+    pub(crate) fn record_op_to_file(&mut self, op: &StructuralOp) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.write_op(op);
+        }
+    }
+}
+
+impl World {
+    /// Registers a component so it is written to replay files, see record_to
+    /// The component has to be registered in the world that records and in the world that replays
+    pub fn register_replay_component<T: ReplayComponent>(&mut self) {
+        self.entities_and_components
+            .replay_codecs
+            .insert(TypeId::of::<T>(), ReplayCodec::new::<T>());
+    }
+
+    /// Starts writing the world to a file so it can be rebuilt later with replay_from
+    /// The file starts with every entity, its replay components and the hierarchy,
+    /// then every spawn, despawn, component add or remove, parent change, despawn_soft and resurrect is written as it happens,
+    /// as are the new ids given to entities by compact and the hierarchy of entities moved in by merge,
+    /// and at the start of every run the replay components that changed are written
+    /// Only components registered with register_replay_component are written, tags, groups and resources are not
+    /// Components registered with register_replay_encoder are written with their encoder when they change
+    /// Entities stored inside components are only remapped when the replay is loaded if the component is registered with register_map_entities
+    /// Stop with finish_recording, which also returns any error from writing the file
+    pub fn record_to(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{REPLAY_HEADER}")?;
+        for codec in self.entities_and_components.replay_codecs.values() {
+            writeln!(writer, "version\t{}\t{}", codec.name, codec.version)?;
+        }
+
+        let entities_and_components = &mut self.entities_and_components;
+        entities_and_components.recorder = Some(Recorder {
+            writer,
+            saved: SecondaryMap::new(),
+            error: None,
+        });
+
+        // hidden entities are written as well, so they can be brought back with resurrect
+        let hidden = entities_and_components.get_soft_despawned();
+        let mut entities = entities_and_components.get_entities();
+        entities.extend(hidden.iter().copied());
+        for entity in entities.iter() {
+            entities_and_components.record_op_to_file(&StructuralOp::Spawn(*entity));
+        }
+        for entity in entities.iter() {
+            let type_ids = entities_and_components.components[entity.entity_id]
+                .as_raw()
+                .keys()
+                .copied()
+                .collect::<Vec<TypeId>>();
+            for type_id in type_ids {
+                entities_and_components.record_component_to_file(*entity, type_id, true);
+            }
+        }
+        for entity in entities.iter() {
+            if let Some(parent) = entities_and_components.get_parent(*entity) {
+                entities_and_components.record_op_to_file(&StructuralOp::SetParent {
+                    child: *entity,
+                    parent,
+                });
+            }
+        }
+        for entity in hidden {
+            entities_and_components.record_soft_despawn_to_file(entity, true);
+        }
+        entities_and_components.record_frame_to_file();
+
+        match entities_and_components
+            .recorder
+            .as_mut()
+            .and_then(|recorder| recorder.error.take())
+        {
+            Some(error) => {
+                entities_and_components.recorder = None;
+                Err(error)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the changes since the last run and stops writing to the replay file started with record_to
+    /// Returns the first error from writing the file, if there was one
+    pub fn finish_recording(&mut self) -> io::Result<()> {
+        // the changes made since the last run
+        self.entities_and_components.record_frame_to_file();
+        let Some(mut recorder) = self.entities_and_components.recorder.take() else {
+            return Ok(());
+        };
+
+        match recorder.error.take() {
+            Some(error) => Err(error),
+            None => recorder.writer.flush(),
+        }
+    }
+
+    /// Rebuilds a world from a file written with record_to by adding its entities to this world
+    /// The replay components have to be registered with register_replay_component first
+    /// Components saved with an older ReplayComponent::VERSION are loaded with ReplayComponent::migrate
+    /// Components written with an encoder need the same encoder registered with register_replay_encoder
+    /// Returns an error if the file can not be read, was not written by record_to, or has a component that is not registered
+    pub fn replay_from(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let reader = BufReader::new(File::open(path)?);
+        let codecs = self
+            .entities_and_components
+            .replay_codecs
+            .iter()
+            .map(|(type_id, codec)| (codec.name, (*type_id, *codec)))
+            .collect::<FxHashMap<&'static str, (TypeId, ReplayCodec)>>();
+        let mut entities = FxHashMap::<u64, Entity>::default();
+        // the same as entities, for remapping the entities inside components
+        let mut entity_map = EntityMap::new();
+        // the version each component was saved with, files from before versions were written are version 0
+        let mut versions = FxHashMap::<String, u32>::default();
+        let encoders = self.entities_and_components.replay_encoders.clone();
+        // the last text loaded for each component of each entity, which delta lines are decoded from
+        let mut loaded = FxHashMap::<(u64, TypeId), String>::default();
+
+        let mut lines = reader.lines().enumerate();
+        match lines.next() {
+            Some((_, Ok(header))) if header == REPLAY_HEADER => {}
+            Some((_, Err(error))) => return Err(error),
+            _ => return Err(invalid_data(1, "it was not written by record_to")),
+        }
+
+        for (line_index, line) in lines {
+            let line = line?;
+            let line_number = line_index + 1;
+            let fields = line.split('\t').collect::<Vec<&str>>();
+
+            // an entity that was despawned, or removed with its parent, is unknown as well
+            let get_entity = |engine: &EntitiesAndComponents, field: usize| {
+                fields
+                    .get(field)
+                    .and_then(|id| id.parse::<u64>().ok())
+                    .and_then(|id| entities.get(&id).copied())
+                    .filter(|entity| engine.does_entity_exist(*entity))
+                    .ok_or_else(|| invalid_data(line_number, "unknown entity"))
+            };
+            let get_codec = || {
+                let name = fields.get(2).copied().unwrap_or_default();
+                codecs.get(name).ok_or_else(|| {
+                    invalid_data(
+                        line_number,
+                        format!("{name} was not registered with register_replay_component"),
+                    )
+                })
+            };
+
+            let engine = &mut self.entities_and_components;
+            match fields[0] {
+                "spawn" => {
+                    let id = fields
+                        .get(1)
+                        .and_then(|id| id.parse::<u64>().ok())
+                        .ok_or_else(|| invalid_data(line_number, "missing entity"))?;
+                    let entity = engine.add_entity();
+                    entities.insert(id, entity);
+                    entity_map.insert(Entity::from_bits(id), entity);
+                }
+                "despawn" => {
+                    let entity = fields
+                        .get(1)
+                        .and_then(|id| id.parse::<u64>().ok())
+                        .and_then(|id| entities.remove(&id))
+                        .ok_or_else(|| invalid_data(line_number, "unknown entity"))?;
+                    // children are removed with their parent, so they may already be gone
+                    if engine.does_entity_exist(entity) {
+                        engine.remove_entity(entity);
+                    }
+                }
+                "version" => {
+                    let (name, version) = (fields.get(1), fields.get(2));
+                    let version = version
+                        .and_then(|version| version.parse::<u32>().ok())
+                        .ok_or_else(|| invalid_data(line_number, "missing version"))?;
+                    versions.insert(name.copied().unwrap_or_default().to_string(), version);
+                }
+                "add" | "set" | "delta" => {
+                    let (entity, (type_id, codec)) = (get_entity(engine, 1)?, get_codec()?);
+                    let mut data = unescape(fields.get(3).copied().unwrap_or_default());
+                    let key = (entity_id(entity), *type_id);
+                    if fields[0] == "delta" {
+                        let encoder = encoders.get(type_id).ok_or_else(|| {
+                            invalid_data(
+                                line_number,
+                                format!(
+                                    "{} was not registered with register_replay_encoder",
+                                    codec.name
+                                ),
+                            )
+                        })?;
+                        data = loaded
+                            .get(&key)
+                            .and_then(|previous| encoder.decode(previous, &data))
+                            .ok_or_else(|| {
+                                invalid_data(
+                                    line_number,
+                                    format!("{} could not decode {data:?}", codec.name),
+                                )
+                            })?;
+                    }
+                    let version = versions.get(codec.name).copied().unwrap_or(0);
+                    if !(codec.load)(engine, entity, version, &data) {
+                        return Err(invalid_data(
+                            line_number,
+                            format!(
+                                "{} could not load {data:?} saved with version {version}",
+                                codec.name
+                            ),
+                        ));
+                    }
+                    engine.map_component_entities(entity, *type_id, &entity_map);
+                    loaded.insert(key, data);
+                }
+                "remove" => {
+                    let (entity, (_, codec)) = (get_entity(engine, 1)?, get_codec()?);
+                    (codec.remove)(engine, entity);
+                }
+                "hide" => {
+                    let entity = get_entity(engine, 1)?;
+                    if !engine.is_soft_despawned(entity) {
+                        engine.hide_entity(entity);
+                    }
+                }
+                "resurrect" => {
+                    let entity = get_entity(engine, 1)?;
+                    if engine.is_soft_despawned(entity) {
+                        engine.show_entity(entity);
+                    }
+                }
+                "parent" => {
+                    let (child, parent) = (get_entity(engine, 1)?, get_entity(engine, 2)?);
+                    engine.set_parent(child, parent);
+                }
+                "unparent" => {
+                    engine.remove_parent(get_entity(engine, 1)?);
+                }
+                "remap" => {
+                    let entity = fields
+                        .get(1)
+                        .and_then(|id| id.parse::<u64>().ok())
+                        .and_then(|id| entities.remove(&id))
+                        .ok_or_else(|| invalid_data(line_number, "unknown entity"))?;
+                    let id = fields
+                        .get(2)
+                        .and_then(|id| id.parse::<u64>().ok())
+                        .ok_or_else(|| invalid_data(line_number, "missing entity"))?;
+                    entities.insert(id, entity);
+                    entity_map.insert(Entity::from_bits(id), entity);
+                }
+                "frame" | "" => {}
+                other => {
+                    return Err(invalid_data(line_number, format!("unknown line {other:?}")));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
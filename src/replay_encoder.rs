@@ -0,0 +1,126 @@
+use crate::*;
+
+/// Shrinks the text a replay component is written with each frame, see World::register_replay_encoder
+/// Only a component that changed and was already written for the same entity is encoded,
+/// so encode and decode are always given the text that was written last time
+pub trait ReplayEncoder: Send + Sync + 'static {
+    /// Makes the text to write for data, returns None to write data as it is
+    fn encode(&self, previous: &str, data: &str) -> Option<String>;
+
+    /// Rebuilds the text made by ReplayComponent::save from the text made by encode, returns None if it is not valid
+    fn decode(&self, previous: &str, encoded: &str) -> Option<String>;
+}
+
+/// A ReplayEncoder that only writes the part of the text between what is the same at the start and at the end
+/// Suits components where one part changes at a time, like a name or a counter at the end of the text
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TextDelta;
+
+impl ReplayEncoder for TextDelta {
+    fn encode(&self, previous: &str, data: &str) -> Option<String> {
+        let mut prefix = previous
+            .bytes()
+            .zip(data.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !data.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        // the suffix can not overlap the prefix in either text
+        let max_suffix = previous.len().min(data.len()) - prefix;
+        let mut suffix = previous
+            .bytes()
+            .rev()
+            .zip(data.bytes().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !data.is_char_boundary(data.len() - suffix) {
+            suffix -= 1;
+        }
+
+        Some(format!(
+            "{prefix},{suffix},{}",
+            &data[prefix..data.len() - suffix]
+        ))
+    }
+
+    fn decode(&self, previous: &str, encoded: &str) -> Option<String> {
+        let mut fields = encoded.splitn(3, ',');
+        let prefix = fields.next()?.parse::<usize>().ok()?;
+        let suffix = fields.next()?.parse::<usize>().ok()?;
+        let middle = fields.next()?;
+        if prefix + suffix > previous.len() {
+            return None;
+        }
+        let start = previous.get(..prefix)?;
+        let end = previous.get(previous.len() - suffix..)?;
+        Some(format!("{start}{middle}{end}"))
+    }
+}
+
+/// A ReplayEncoder for text made of fields split by a separator, like "1.5 2 0.25" for a position
+/// Fields that did not change are written empty, so only the changed numbers take space
+/// Data is written as it is when the number of fields changed or a field became empty
+#[derive(Clone, Copy, Debug)]
+pub struct FieldDelta {
+    /// The character between the fields
+    pub separator: char,
+}
+
+impl FieldDelta {
+    /// Creates a FieldDelta for fields split by separator
+    pub fn new(separator: char) -> Self {
+        FieldDelta { separator }
+    }
+}
+
+impl Default for FieldDelta {
+    fn default() -> Self {
+        FieldDelta::new(' ')
+    }
+}
+
+impl ReplayEncoder for FieldDelta {
+    fn encode(&self, previous: &str, data: &str) -> Option<String> {
+        let previous = previous.split(self.separator).collect::<Vec<&str>>();
+        let data = data.split(self.separator).collect::<Vec<&str>>();
+        if previous.len() != data.len() || data.iter().any(|field| field.is_empty()) {
+            return None;
+        }
+
+        let fields = previous
+            .iter()
+            .zip(data.iter())
+            .map(|(previous, field)| if previous == field { "" } else { *field })
+            .collect::<Vec<&str>>();
+        Some(fields.join(&self.separator.to_string()))
+    }
+
+    fn decode(&self, previous: &str, encoded: &str) -> Option<String> {
+        let previous = previous.split(self.separator).collect::<Vec<&str>>();
+        let encoded = encoded.split(self.separator).collect::<Vec<&str>>();
+        if previous.len() != encoded.len() {
+            return None;
+        }
+
+        let fields = previous
+            .iter()
+            .zip(encoded.iter())
+            .map(|(previous, field)| if field.is_empty() { *previous } else { *field })
+            .collect::<Vec<&str>>();
+        Some(fields.join(&self.separator.to_string()))
+    }
+}
+
+impl World {
+    /// Writes the replay component T with an encoder when it changes, like register_replay_encoder::<Position>(FieldDelta::new(' '))
+    /// This makes replay files smaller for components that change a little every frame
+    /// The encoder is only used when the component is written again after it changed, adding it is always written in full
+    /// The same encoder has to be registered in the world that replays the file
+    pub fn register_replay_encoder<T: ReplayComponent>(&mut self, encoder: impl ReplayEncoder) {
+        self.entities_and_components
+            .replay_encoders
+            .insert(TypeId::of::<T>(), Arc::new(encoder));
+    }
+}
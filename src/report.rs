@@ -0,0 +1,88 @@
+use crate::*;
+
+/// An error returned by a system from try_run or try_single_entity_step
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub struct SystemError {
+    message: String,
+}
+
+impl SystemError {
+    /// Creates an error with a message describing what went wrong
+    pub fn new(message: impl Into<String>) -> Self {
+        SystemError {
+            message: message.into(),
+        }
+    }
+
+    /// Gets the message describing what went wrong
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for SystemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SystemError {}
+
+impl From<&str> for SystemError {
+    fn from(message: &str) -> Self {
+        SystemError::new(message)
+    }
+}
+
+impl From<String> for SystemError {
+    fn from(message: String) -> Self {
+        SystemError::new(message)
+    }
+}
+
+/// A system that returned an error
+#[derive(Clone, PartialEq, Debug)]
+pub struct SystemFailure {
+    /// The type name of the system
+    pub system: &'static str,
+    /// The entity the system was running on, None if the error came from try_run
+    pub entity: Option<Entity>,
+    /// The error the system returned
+    pub error: SystemError,
+}
+
+impl std::fmt::Display for SystemFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.entity {
+            Some(entity) => write!(f, "{} failed on {:?}: {}", self.system, entity, self.error),
+            None => write!(f, "{} failed: {}", self.system, self.error),
+        }
+    }
+}
+
+/// Every system failure from the last time the world was run
+/// Get it with World::get_frame_report
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct FrameReport {
+    failures: Vec<SystemFailure>,
+}
+
+impl FrameReport {
+    /// Gets every system failure in the order they were reported
+    pub fn failures(&self) -> &[SystemFailure] {
+        &self.failures
+    }
+
+    /// Checks if every system ran without an error
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, failure: SystemFailure) {
+        self.failures.push(failure);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.failures.clear();
+    }
+}
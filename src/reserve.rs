@@ -0,0 +1,168 @@
+use crate::*;
+
+// entities made ahead of time so systems running in parallel can get new entities, see set_entity_reserve
+#[derive(Default)]
+pub(crate) struct EntityReserve {
+    // the number of entities made ready before the systems run
+    size: usize,
+    // entities that have not been handed out yet, they exist but have no components
+    ready: Vec<Entity>,
+    // entities that were handed out and have not had the on_spawn functions called yet
+    taken: Vec<Entity>,
+}
+
+impl EntityReserve {
+    // points the reserved entities at the entities that replaced them, the ones that were not moved are dropped
+    pub(crate) fn remap(&mut self, entity_map: &EntityMap) {
+        for entities in [&mut self.ready, &mut self.taken] {
+            entities.retain_mut(|entity| match entity_map.get(*entity) {
+                Some(new_entity) => {
+                    *entity = new_entity;
+                    true
+                }
+                None => false,
+            });
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.ready.clear();
+        self.taken.clear();
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Adds entities with no components and returns them, so components can be added to them later
+    /// The entities can be used right away, but like the ones from EntitiesAndComponentsThreadSafe::reserve_entities
+    /// the on_spawn functions are only called for them during the next run, once prestep has finished,
+    /// so they see the components that were added in between
+    pub fn reserve_entities(&mut self, count: usize) -> Vec<Entity> {
+        let entities = (0..count)
+            .map(|_| self.insert_entity())
+            .collect::<Vec<Entity>>();
+        self.entity_reserve
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .taken
+            .extend_from_slice(&entities);
+        entities
+    }
+
+    /// Sets how many entities are made ready before the systems run each frame
+    /// prestep and single_entity_step can only get new entities from EntitiesAndComponentsThreadSafe::reserve_entities
+    /// and SingleMutEntity::reserve_entities, which hand out these ready entities
+    /// The ready entities already exist with no components, but they are left out of iter_entities, get_entity_count
+    /// and single_entity_step until they are handed out and spawned
+    pub fn set_entity_reserve(&mut self, count: usize) {
+        self.entity_reserve
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .size = count;
+    }
+
+    // makes sure there are as many ready entities as set with set_entity_reserve
+    pub(crate) fn refill_entity_reserve(&mut self) {
+        let reserve = self
+            .entity_reserve
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        let missing = reserve.size.saturating_sub(reserve.ready.len());
+        for _ in 0..missing {
+            // the on_spawn functions are called once the entity is handed out and has its components
+            let entity = self.insert_entity();
+            self.reserved_entities.insert(entity.entity_id, ());
+            self.entity_reserve
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .ready
+                .push(entity);
+        }
+    }
+
+    // hands out ready entities while the systems run in parallel
+    pub(crate) fn take_reserved_entities(&self, count: usize) -> Result<Vec<Entity>, SystemError> {
+        let mut reserve = self
+            .entity_reserve
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if count > reserve.ready.len() {
+            return Err(SystemError::new(format!(
+                "{count} entities were reserved but only {ready} are ready, was the number raised with set_entity_reserve?",
+                ready = reserve.ready.len()
+            )));
+        }
+
+        let first = reserve.ready.len() - count;
+        let taken = reserve.ready.split_off(first);
+        reserve.taken.extend_from_slice(&taken);
+        Ok(taken)
+    }
+
+    // calls the on_spawn functions for the entities that were handed out, once the commands have added their components
+    // the entities from EntitiesAndComponents::reserve_entities were never hidden, so removing them from reserved_entities does nothing
+    pub(crate) fn spawn_taken_entities(&mut self) {
+        let taken = std::mem::take(
+            &mut self
+                .entity_reserve
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .taken,
+        );
+        for entity in taken {
+            self.reserved_entities.remove(entity.entity_id);
+            if self.does_entity_exist(entity) {
+                self.run_spawn_hooks(entity);
+            }
+        }
+    }
+}
+
+impl EntitiesAndComponentsThreadSafe<'_> {
+    /// Gets entities with no components, their components can be added with queue_add_component_to
+    /// The entities come from the ones made ready before the systems ran, see EntitiesAndComponents::set_entity_reserve
+    /// The on_spawn functions are called for them once every prestep has finished and the queued components were added,
+    /// until then they are left out of iter_entities
+    /// Returns an error if there are not enough ready entities, like when set_entity_reserve was never called
+    pub fn reserve_entities(&self, count: usize) -> Result<Vec<Entity>, SystemError> {
+        self.entities_and_components.take_reserved_entities(count)
+    }
+
+    /// Queues a component to be added to an entity, overwriting the old one if it exists
+    /// The component is added once every prestep has finished, if the entity still exists
+    pub fn queue_add_component_to<T: Component + Send>(&self, entity: Entity, component: T) {
+        self.resource_commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(move |entities_and_components| {
+                if entities_and_components.does_entity_exist(entity) {
+                    entities_and_components.add_component_to(entity, component);
+                }
+            });
+    }
+}
+
+impl SingleMutEntity<'_> {
+    /// Gets entities with no components, their components can be added with queue_add_component_to
+    /// The on_spawn functions are called for them once every single_entity_step has finished, see EntitiesAndComponentsThreadSafe::reserve_entities
+    /// Returns an error if there are not enough ready entities, like when set_entity_reserve was never called
+    pub fn reserve_entities(&self, count: usize) -> Result<Vec<Entity>, SystemError> {
+        self.entities_and_components.take_reserved_entities(count)
+    }
+
+    /// Queues a component to be added to another entity, like one from reserve_entities
+    /// The component is added after every single_entity_step has finished, if the entity still exists
+    pub fn queue_add_component_to<T: Component + Send>(&mut self, entity: Entity, component: T) {
+        self.commands.push(move |entities_and_components| {
+            if entities_and_components.does_entity_exist(entity) {
+                entities_and_components.add_component_to(entity, component);
+            }
+        });
+    }
+}
+
+impl World {
+    /// Sets how many entities are made ready before the systems run each frame, see EntitiesAndComponents::set_entity_reserve
+    pub fn set_entity_reserve(&mut self, count: usize) {
+        self.entities_and_components.set_entity_reserve(count);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::*;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::Range;
+
+/// A random number resource that can be used from systems running in parallel
+/// Instead of one generator that every thread fights over, it hands out RngStreams made from the seed,
+/// the frame and a key, so the same seed always gives the same numbers no matter which thread runs what
+/// Add it with add_resource(Rng::new(seed)), then use SingleMutEntity::rng or stream in prestep
+#[derive(Clone)]
+pub struct Rng {
+    seed: u64,
+    frame: u64,
+    main: RngStream,
+}
+
+impl Rng {
+    /// Makes a generator that gives the same numbers every time it is made with the same seed
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            seed,
+            frame: 0,
+            main: RngStream::new(mix(seed)),
+        }
+    }
+
+    /// Makes a generator with a different seed every time, for when the numbers don't have to be repeatable
+    pub fn from_entropy() -> Self {
+        Rng::new(RandomState::new().build_hasher().finish())
+    }
+
+    /// Gets the seed the generator was made with, save it to play the same numbers back
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Gets a stream of numbers for a key, like an entity's to_bits, that is the same for the whole frame
+    /// Different keys give unrelated streams so they can be used on different threads at once
+    pub fn stream(&self, key: u64) -> RngStream {
+        RngStream::new(mix(self.seed ^ mix(self.frame ^ mix(key))))
+    }
+
+    /// Gets the stream used outside of parallel steps, like in System::run
+    pub fn main(&mut self) -> &mut RngStream {
+        &mut self.main
+    }
+}
+
+impl Resource for Rng {
+    fn update(&mut self) {
+        // every frame gets new streams
+        self.frame += 1;
+    }
+}
+
+/// A stream of random numbers, see Rng
+#[derive(Clone, Debug)]
+pub struct RngStream {
+    state: u64,
+}
+
+impl RngStream {
+    /// Makes a stream from a seed, the same seed always gives the same numbers
+    pub fn new(seed: u64) -> Self {
+        RngStream { state: seed }
+    }
+
+    /// Gets a random u64
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        mix(self.state)
+    }
+
+    /// Gets a random f32 from 0 up to but not including 1
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Gets a random number in the range, panics if the range is empty
+    pub fn range(&mut self, range: Range<u64>) -> u64 {
+        assert!(
+            !range.is_empty(),
+            "Cannot pick a number from an empty range"
+        );
+        range.start + self.next_u64() % (range.end - range.start)
+    }
+
+    /// Gets a random f32 in the range, panics if the range is empty
+    pub fn range_f32(&mut self, range: Range<f32>) -> f32 {
+        assert!(
+            !range.is_empty(),
+            "Cannot pick a number from an empty range"
+        );
+        range.start + (range.end - range.start) * self.next_f32()
+    }
+
+    /// Returns true with the chance given, 0.0 is never and 1.0 is always
+    pub fn chance(&mut self, chance: f32) -> bool {
+        self.next_f32() < chance
+    }
+}
+
+// splitmix64, spreads every bit of the input over the output
+fn mix(value: u64) -> u64 {
+    let mut value = value;
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+// a hash of the system name that is the same on every run, unlike the std hashers
+fn hash_name(name: &str) -> u64 {
+    name.bytes()
+        .fold(0xCBF2_9CE4_8422_2325, |hash, byte| mix(hash ^ byte as u64))
+}
+
+impl SingleMutEntity<'_> {
+    /// Gets a stream of random numbers for this entity and system, made from the Rng resource
+    /// The stream is the same every time this is called in the same step, so keep it instead of calling this again
+    /// panics if there is no Rng resource
+    pub fn rng(&self) -> RngStream {
+        let system = self.commands.get_system().map_or(0, hash_name);
+        self.get_resource::<Rng>()
+            .stream(self.entity.to_bits() ^ mix(system))
+    }
+}
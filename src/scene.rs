@@ -0,0 +1,472 @@
+use crate::*;
+use replay::ReplayCodec;
+
+/// Why a scene could not be saved with World::save_scene or loaded with World::load_scene
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub struct SceneError {
+    /// The line of the scene the problem was found on, starting at 1
+    pub line: usize,
+    /// What went wrong
+    pub message: String,
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} of the scene: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// an entity read from a scene before anything is added to the world
+struct SceneEntity {
+    // the entity it was saved from, used to remap the entities in components
+    id: Option<u64>,
+    // the line, type name and text of each component
+    components: Vec<(usize, String, String)>,
+    children: Vec<SceneEntity>,
+}
+
+// writes a string the way rust and ron write string literals
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("    ");
+    }
+}
+
+// how deep entities can be nested in a scene, deeper scenes are rejected instead of overflowing the stack
+const MAX_SCENE_DEPTH: usize = 256;
+
+// what save_scene writes next, see World::write_scene_entities
+enum SceneWrite {
+    Entity {
+        entity: Entity,
+        indent: usize,
+        // how many entities this one is inside of
+        depth: usize,
+    },
+    // the start of the line a child is written on
+    Child {
+        indent: usize,
+    },
+    // after a child
+    Comma,
+    // closes the children and the entity
+    End {
+        indent: usize,
+        has_children: bool,
+    },
+}
+
+// reads scenes written by save_scene, see World::load_scene for the format
+struct SceneParser<'a> {
+    scene: &'a str,
+    position: usize,
+    // the line of position, counted as the parser moves forward
+    line: usize,
+}
+
+impl<'a> SceneParser<'a> {
+    fn new(scene: &'a str) -> Self {
+        SceneParser {
+            scene,
+            position: 0,
+            line: 1,
+        }
+    }
+
+    fn line(&self) -> usize {
+        self.line
+    }
+
+    // moves position forward by length bytes
+    fn advance(&mut self, length: usize) {
+        let skipped = &self.scene[self.position..self.position + length];
+        self.line += skipped.matches('\n').count();
+        self.position += length;
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, SceneError> {
+        Err(SceneError {
+            line: self.line(),
+            message: message.into(),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.scene[self.position..]
+    }
+
+    // skips whitespace and // comments
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.advance(rest.len() - trimmed.len());
+            if !trimmed.starts_with("//") {
+                return;
+            }
+            self.advance(trimmed.find('\n').unwrap_or(trimmed.len()));
+        }
+    }
+
+    // skips past token if it is next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.advance(token.len());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), SceneError> {
+        match self.eat(token) {
+            true => Ok(()),
+            false => self.error(format!("expected {token}")),
+        }
+    }
+
+    // a field name followed by a colon, like components:
+    fn expect_field(&mut self, name: &str) -> Result<(), SceneError> {
+        self.expect(name)?;
+        self.expect(":")
+    }
+
+    fn parse_string(&mut self) -> Result<String, SceneError> {
+        self.expect("\"")?;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.advance(index + 1);
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 'r')) => value.push('\r'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, other)) => value.push(other),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        self.error("the string is never closed")
+    }
+
+    fn parse_number<T: std::str::FromStr>(&mut self) -> Result<T, SceneError> {
+        self.skip_whitespace();
+        let length = self
+            .rest()
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest().len());
+        match self.rest()[..length].parse() {
+            Ok(number) => {
+                self.advance(length);
+                Ok(number)
+            }
+            Err(_) => self.error("expected a number"),
+        }
+    }
+
+    // Scene(versions: { "name": 0, }, root: Entity(..))
+    fn parse_scene(&mut self) -> Result<(FxHashMap<String, u32>, SceneEntity), SceneError> {
+        self.expect("Scene")?;
+        self.expect("(")?;
+        self.expect_field("versions")?;
+        self.expect("{")?;
+        let mut versions = FxHashMap::default();
+        while !self.eat("}") {
+            let name = self.parse_string()?;
+            self.expect(":")?;
+            versions.insert(name, self.parse_number()?);
+            if !self.eat(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        self.eat(",");
+        self.expect_field("root")?;
+        let root = self.parse_entity(0)?;
+        self.eat(",");
+        self.expect(")")?;
+
+        self.skip_whitespace();
+        if !self.rest().is_empty() {
+            return self.error("expected the end of the scene");
+        }
+        Ok((versions, root))
+    }
+
+    // Entity(id: 0, components: { "name": "data", }, children: [ Entity(..), ])
+    // depth is how many entities this one is inside of
+    fn parse_entity(&mut self, depth: usize) -> Result<SceneEntity, SceneError> {
+        self.skip_whitespace();
+        if depth > MAX_SCENE_DEPTH {
+            return self.error(format!(
+                "entities are nested more than {MAX_SCENE_DEPTH} deep"
+            ));
+        }
+        self.expect("Entity")?;
+        self.expect("(")?;
+        let mut id = None;
+        if self.eat("id") {
+            self.expect(":")?;
+            id = Some(self.parse_number()?);
+            self.eat(",");
+        }
+        self.expect_field("components")?;
+        self.expect("{")?;
+        let mut components = vec![];
+        while !self.eat("}") {
+            self.skip_whitespace();
+            let line = self.line();
+            let name = self.parse_string()?;
+            self.expect(":")?;
+            components.push((line, name, self.parse_string()?));
+            if !self.eat(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        self.eat(",");
+        self.expect_field("children")?;
+        self.expect("[")?;
+        let mut children = vec![];
+        while !self.eat("]") {
+            children.push(self.parse_entity(depth + 1)?);
+            if !self.eat(",") {
+                self.expect("]")?;
+                break;
+            }
+        }
+        self.eat(",");
+        self.expect(")")?;
+        Ok(SceneEntity {
+            id,
+            components,
+            children,
+        })
+    }
+}
+
+impl World {
+    /// Writes an entity and everything below it as text that can be read back with load_scene
+    /// Only components registered with register_replay_component are written, using ReplayComponent::save
+    /// Children are written inside their parent, so the text can be edited by hand and diffed in version control
+    /// Each entity is written with its id so components registered with register_map_entities can be remapped when loading
+    /// Returns an error if entities are nested more than 256 deep, since load_scene could not read them back
+    /// panics if the entity does not exist
+    pub fn save_scene(&self, root: Entity) -> Result<String, SceneError> {
+        if !self.entities_and_components.does_entity_exist(root) {
+            panic!("Entity ID {root:?} does not exist, was the Entity ID edited?");
+        }
+
+        let mut codecs = self
+            .entities_and_components
+            .replay_codecs
+            .iter()
+            .collect::<Vec<(&TypeId, &ReplayCodec)>>();
+        codecs.sort_by_key(|(_, codec)| codec.name);
+
+        let mut out = String::from("Scene(\n    versions: {\n");
+        for (_, codec) in codecs.iter() {
+            write_indent(&mut out, 2);
+            write_string(&mut out, codec.name);
+            out.push_str(&format!(": {},\n", codec.version));
+        }
+        out.push_str("    },\n    root: ");
+        self.write_scene_entities(&mut out, root, &codecs)?;
+        out.push_str(",\n)\n");
+        Ok(out)
+    }
+
+    // writes the entity and everything below it, without recursing so deep hierarchies can not overflow the stack
+    fn write_scene_entities(
+        &self,
+        out: &mut String,
+        root: Entity,
+        codecs: &[(&TypeId, &ReplayCodec)],
+    ) -> Result<(), SceneError> {
+        let entities_and_components = &self.entities_and_components;
+        let mut to_write = vec![SceneWrite::Entity {
+            entity: root,
+            indent: 1,
+            depth: 0,
+        }];
+        while let Some(step) = to_write.pop() {
+            let (entity, indent, depth) = match step {
+                SceneWrite::Entity {
+                    entity,
+                    indent,
+                    depth,
+                } => (entity, indent, depth),
+                SceneWrite::Child { indent } => {
+                    out.push('\n');
+                    write_indent(out, indent);
+                    continue;
+                }
+                SceneWrite::Comma => {
+                    out.push(',');
+                    continue;
+                }
+                SceneWrite::End {
+                    indent,
+                    has_children,
+                } => {
+                    if has_children {
+                        out.push('\n');
+                        write_indent(out, indent + 1);
+                    }
+                    out.push_str("],\n");
+                    write_indent(out, indent);
+                    out.push(')');
+                    continue;
+                }
+            };
+
+            // load_scene would reject it, so it is not written
+            if depth > MAX_SCENE_DEPTH {
+                return Err(SceneError {
+                    line: out.matches('\n').count() + 1,
+                    message: format!("entities are nested more than {MAX_SCENE_DEPTH} deep"),
+                });
+            }
+
+            out.push_str("Entity(\n");
+            write_indent(out, indent + 1);
+            out.push_str(&format!("id: {},\n", entity.to_bits()));
+            write_indent(out, indent + 1);
+            out.push_str("components: {");
+            let mut has_components = false;
+            for (type_id, codec) in codecs {
+                let data = entities_and_components
+                    .components
+                    .get(entity.entity_id)
+                    .and_then(|components| components.as_raw().get(*type_id))
+                    .and_then(|component| (codec.save)(&**component));
+                if let Some(data) = data {
+                    out.push('\n');
+                    write_indent(out, indent + 2);
+                    write_string(out, codec.name);
+                    out.push_str(": ");
+                    write_string(out, &data);
+                    out.push(',');
+                    has_components = true;
+                }
+            }
+            if has_components {
+                out.push('\n');
+                write_indent(out, indent + 1);
+            }
+            out.push_str("},\n");
+
+            write_indent(out, indent + 1);
+            out.push_str("children: [");
+            // pushed in reverse so the children are written in order and the end comes last
+            let children = entities_and_components.get_children(entity);
+            to_write.push(SceneWrite::End {
+                indent,
+                has_children: !children.is_empty(),
+            });
+            for child in children.iter().rev() {
+                to_write.push(SceneWrite::Comma);
+                to_write.push(SceneWrite::Entity {
+                    entity: *child,
+                    indent: indent + 2,
+                    depth: depth + 1,
+                });
+                to_write.push(SceneWrite::Child { indent: indent + 2 });
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the entities in a scene written by save_scene to the world and returns the root
+    /// The scene looks like Scene(versions: { "type": 0 }, root: Entity(id: 0, components: { "type": "data" }, children: [ Entity(..) ]))
+    /// with the fields in that order, id can be left out, trailing commas and // comments are allowed
+    /// Components registered with register_map_entities are remapped from the saved ids to the loaded entities
+    /// The components have to be registered with register_replay_component,
+    /// components saved with an older ReplayComponent::VERSION are loaded with ReplayComponent::migrate
+    /// Nothing is added if the scene can not be loaded, or if its entities are nested more than 256 deep
+    pub fn load_scene(&mut self, scene: &str) -> Result<Entity, SceneError> {
+        let mut parser = SceneParser::new(scene);
+        let (versions, root) = parser.parse_scene()?;
+
+        let codecs = self
+            .entities_and_components
+            .replay_codecs
+            .values()
+            .map(|codec| (codec.name, *codec))
+            .collect::<FxHashMap<&'static str, ReplayCodec>>();
+
+        // the on_spawn functions are called once every entity has its components and parent
+        let root_entity = self.entities_and_components.insert_entity();
+        let mut loaded = vec![];
+        let mut entity_map = EntityMap::new();
+        let mut to_load = vec![(root_entity, &root)];
+        while let Some((entity, scene_entity)) = to_load.pop() {
+            loaded.push(entity);
+            if let Some(id) = scene_entity.id {
+                entity_map.insert(Entity::from_bits(id), entity);
+            }
+            for (line, name, data) in scene_entity.components.iter() {
+                let error = match codecs.get(name.as_str()) {
+                    Some(codec) => {
+                        let version = versions.get(name).copied().unwrap_or(0);
+                        match (codec.load)(&mut self.entities_and_components, entity, version, data)
+                        {
+                            true => continue,
+                            false => {
+                                format!(
+                                    "{name} could not load {data:?} saved with version {version}"
+                                )
+                            }
+                        }
+                    }
+                    None => format!("{name} was not registered with register_replay_component"),
+                };
+
+                // the children were added under the root, so this removes everything that was loaded
+                self.entities_and_components
+                    .remove_unspawned_entity(root_entity);
+                return Err(SceneError {
+                    line: *line,
+                    message: error,
+                });
+            }
+
+            for child in scene_entity.children.iter() {
+                let child_entity = self.entities_and_components.insert_entity();
+                self.entities_and_components
+                    .set_parent(child_entity, entity);
+                to_load.push((child_entity, child));
+            }
+        }
+
+        self.entities_and_components
+            .map_entities_of(loaded.iter().copied(), &entity_map);
+        for entity in loaded {
+            self.entities_and_components.run_spawn_hooks(entity);
+        }
+        Ok(root_entity)
+    }
+}
@@ -0,0 +1,188 @@
+use crate::*;
+use std::fmt::Write;
+
+/// The components a system reads and writes, declared in System::component_access
+/// It is only used to describe the schedule, systems can still access any component
+#[derive(Clone, PartialEq, Debug, Default, Eq)]
+pub struct ComponentAccess {
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+}
+
+impl ComponentAccess {
+    /// Declares that the system reads a component
+    pub fn read<T: Component>(&mut self) -> &mut Self {
+        self.reads.push(std::any::type_name::<T>());
+        self
+    }
+
+    /// Declares that the system writes a component
+    pub fn write<T: Component>(&mut self) -> &mut Self {
+        self.writes.push(std::any::type_name::<T>());
+        self
+    }
+
+    /// Gets the type names of the components the system reads
+    pub fn get_reads(&self) -> &[&'static str] {
+        &self.reads
+    }
+
+    /// Gets the type names of the components the system writes
+    pub fn get_writes(&self) -> &[&'static str] {
+        &self.writes
+    }
+}
+
+// one system in an exported schedule
+pub(crate) struct ScheduleNode {
+    pub(crate) name: &'static str,
+    pub(crate) set: Option<&'static str>,
+    pub(crate) group: Option<GroupId>,
+    pub(crate) order: i32,
+    pub(crate) stages: Vec<&'static str>,
+    pub(crate) access: ComponentAccess,
+}
+
+// the systems of a world in the order their run functions are called,
+// with an edge from every system to the systems in the next order
+pub(crate) struct Schedule {
+    nodes: Vec<ScheduleNode>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Schedule {
+    pub(crate) fn new(mut nodes: Vec<ScheduleNode>) -> Self {
+        nodes.sort_by_key(|node| node.order);
+
+        let mut edges = vec![];
+        for (from, from_node) in nodes.iter().enumerate() {
+            // the first order after this one, every system in it waits for this system
+            let next_order = nodes
+                .iter()
+                .map(|node| node.order)
+                .find(|order| *order > from_node.order);
+
+            if let Some(next_order) = next_order {
+                for (to, to_node) in nodes.iter().enumerate() {
+                    if to_node.order == next_order {
+                        edges.push((from, to));
+                    }
+                }
+            }
+        }
+
+        Schedule { nodes, edges }
+    }
+
+    pub(crate) fn to_graphviz(&self) -> String {
+        let mut output =
+            String::from("digraph schedule {\n    rankdir=LR;\n    node [shape=box];\n");
+
+        // systems in a set are grouped together
+        let mut sets = self
+            .nodes
+            .iter()
+            .filter_map(|node| node.set)
+            .collect::<Vec<&'static str>>();
+        sets.sort_unstable();
+        sets.dedup();
+
+        for (index, set) in sets.iter().enumerate() {
+            let _ = writeln!(output, "    subgraph cluster_{index} {{");
+            let _ = writeln!(output, "        label=\"{}\";", escape(set));
+            for (node_index, node) in self.nodes.iter().enumerate() {
+                if node.set == Some(*set) {
+                    let _ = writeln!(output, "        {};", self.graphviz_node(node_index));
+                }
+            }
+            output.push_str("    }\n");
+        }
+
+        for (node_index, node) in self.nodes.iter().enumerate() {
+            if node.set.is_none() {
+                let _ = writeln!(output, "    {};", self.graphviz_node(node_index));
+            }
+        }
+
+        for (from, to) in &self.edges {
+            let _ = writeln!(output, "    system_{from} -> system_{to};");
+        }
+
+        output.push_str("}\n");
+        output
+    }
+
+    fn graphviz_node(&self, node_index: usize) -> String {
+        let node = &self.nodes[node_index];
+
+        // graphviz uses \l to end a left aligned line
+        let mut label = format!("{}\\l", escape(node.name));
+        let _ = write!(label, "order: {}\\l", node.order);
+        if let Some(group) = node.group {
+            let _ = write!(label, "group: {}\\l", group.0);
+        }
+        let _ = write!(label, "stages: {}\\l", node.stages.join(", "));
+        for read in node.access.get_reads() {
+            let _ = write!(label, "reads {}\\l", escape(read));
+        }
+        for write in node.access.get_writes() {
+            let _ = write!(label, "writes {}\\l", escape(write));
+        }
+
+        format!("system_{node_index} [label=\"{label}\"]")
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        let systems = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                format!(
+                    "{{\"id\":{index},\"name\":{name},\"set\":{set},\"group\":{group},\"order\":{order},\"stages\":{stages},\"reads\":{reads},\"writes\":{writes}}}",
+                    name = json_string(node.name),
+                    set = node.set.map_or(String::from("null"), json_string),
+                    group = node.group.map_or(String::from("null"), |group| group.0.to_string()),
+                    order = node.order,
+                    stages = json_strings(&node.stages),
+                    reads = json_strings(node.access.get_reads()),
+                    writes = json_strings(node.access.get_writes()),
+                )
+            })
+            .collect::<Vec<String>>();
+
+        let edges = self
+            .edges
+            .iter()
+            .map(|(from, to)| format!("{{\"from\":{from},\"to\":{to}}}"))
+            .collect::<Vec<String>>();
+
+        format!(
+            "{{\"systems\":[{}],\"edges\":[{}]}}",
+            systems.join(","),
+            edges.join(",")
+        )
+    }
+}
+
+fn json_strings(strings: &[&'static str]) -> String {
+    let strings = strings
+        .iter()
+        .map(|string| json_string(string))
+        .collect::<Vec<String>>();
+    format!("[{}]", strings.join(","))
+}
+
+// escapes a string so it can go between quotes in graphviz
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for character in string.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
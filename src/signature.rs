@@ -0,0 +1,55 @@
+use crate::*;
+
+impl EntitiesAndComponents {
+    /// Groups the entities by the exact set of component types they have
+    /// Yields the sorted component types, the number of entities and the entities of each group, largest group first
+    /// Useful for tools that show which combinations of components are common and for finding combinations that should not exist
+    /// The Parent and Children components of the hierarchy are part of the signature
+    /// This checks every entity, it is too slow to call every frame
+    pub fn iter_signatures(
+        &self,
+    ) -> impl Iterator<Item = (Vec<TypeId>, usize, impl Iterator<Item = Entity>)> {
+        let mut signatures: FxHashMap<Vec<TypeId>, Vec<Entity>> = FxHashMap::default();
+        for (entity_id, components) in self.components.iter() {
+            if self.soft_despawns.remaining.contains_key(entity_id) {
+                continue;
+            }
+            let mut type_ids = components.as_raw().keys().copied().collect::<Vec<TypeId>>();
+            type_ids.sort_unstable();
+            signatures
+                .entry(type_ids)
+                .or_default()
+                .push(Entity { entity_id });
+        }
+
+        let mut signatures = signatures
+            .into_iter()
+            .collect::<Vec<(Vec<TypeId>, Vec<Entity>)>>();
+        signatures.sort_unstable_by(|(a_types, a_entities), (b_types, b_entities)| {
+            b_entities
+                .len()
+                .cmp(&a_entities.len())
+                .then_with(|| a_types.cmp(b_types))
+        });
+        signatures
+            .into_iter()
+            .map(|(type_ids, entities)| (type_ids, entities.len(), entities.into_iter()))
+    }
+
+    /// Gets the type name of a component type that has been added to an entity or registered, for showing signatures
+    pub fn get_component_type_name(&self, type_id: TypeId) -> Option<&'static str> {
+        self.component_names
+            .get(&type_id)
+            .or_else(|| self.registered_components.get(&type_id))
+            .copied()
+    }
+}
+
+impl World {
+    /// Groups the entities by the exact set of component types they have, see EntitiesAndComponents::iter_signatures
+    pub fn iter_signatures(
+        &self,
+    ) -> impl Iterator<Item = (Vec<TypeId>, usize, impl Iterator<Item = Entity>)> {
+        self.entities_and_components.iter_signatures()
+    }
+}
@@ -0,0 +1,82 @@
+use std::ops::{Deref, DerefMut};
+
+/// A list that keeps up to N items inline and only allocates once it grows past that
+/// Used for Children since most entities only have a few
+pub(crate) enum SmallVec<T: Copy, const N: usize> {
+    // the items after len are copies of the first item that was pushed and are never read
+    Inline { len: usize, items: [T; N] },
+    Heap(Vec<T>),
+}
+
+impl<T: Copy, const N: usize> SmallVec<T, N> {
+    pub(crate) fn new() -> Self {
+        // an empty Vec does not allocate
+        SmallVec::Heap(Vec::new())
+    }
+
+    pub(crate) fn from_slice(items: &[T]) -> Self {
+        let mut small_vec = SmallVec::new();
+        for item in items {
+            small_vec.push(*item);
+        }
+        small_vec
+    }
+
+    pub(crate) fn push(&mut self, item: T) {
+        match self {
+            SmallVec::Heap(heap) if heap.capacity() == 0 && N > 0 => {
+                *self = SmallVec::Inline {
+                    len: 1,
+                    items: [item; N],
+                };
+            }
+            SmallVec::Inline { len, items } if *len < N => {
+                items[*len] = item;
+                *len += 1;
+            }
+            SmallVec::Inline { items, .. } => {
+                let mut heap = Vec::with_capacity(N * 2);
+                heap.extend_from_slice(items);
+                heap.push(item);
+                *self = SmallVec::Heap(heap);
+            }
+            SmallVec::Heap(heap) => heap.push(item),
+        }
+    }
+
+    pub(crate) fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        match self {
+            SmallVec::Inline { len, items } => {
+                let mut kept = 0;
+                for i in 0..*len {
+                    if keep(&items[i]) {
+                        items[kept] = items[i];
+                        kept += 1;
+                    }
+                }
+                *len = kept;
+            }
+            SmallVec::Heap(heap) => heap.retain(keep),
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> Deref for SmallVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            SmallVec::Inline { len, items } => &items[..*len],
+            SmallVec::Heap(heap) => heap,
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> DerefMut for SmallVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match self {
+            SmallVec::Inline { len, items } => &mut items[..*len],
+            SmallVec::Heap(heap) => heap,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::*;
+
+type SnapshotResource = fn(&EntitiesAndComponents) -> Option<Box<dyn Any + Send + Sync>>;
+// puts the saved copy back, or removes the resource if it did not exist when the snapshot was taken
+type RestoreResource = fn(&mut EntitiesAndComponents, Option<&(dyn Any + Send + Sync)>);
+
+// copies and restores a resource registered with register_clone_resource
+#[derive(Clone, Copy)]
+pub(crate) struct CloneResource {
+    snapshot: SnapshotResource,
+    restore: RestoreResource,
+}
+
+impl CloneResource {
+    fn new<T: Resource + Clone + Send + Sync>() -> Self {
+        CloneResource {
+            snapshot: |entities_and_components| {
+                entities_and_components
+                    .get_resource::<T>()
+                    .map(|resource| Box::new(resource.clone()) as Box<dyn Any + Send + Sync>)
+            },
+            restore: |entities_and_components, saved| match saved
+                .and_then(|saved| saved.downcast_ref::<T>())
+            {
+                Some(saved) => match entities_and_components.get_resource_mut::<T>() {
+                    Some(resource) => resource.clone_from(saved),
+                    None => entities_and_components.add_resource(saved.clone()),
+                },
+                None => entities_and_components.remove_resource::<T>(),
+            },
+        }
+    }
+}
+
+// copies a component registered with register_clone_component, None if it is not the registered type
+pub(crate) type CloneComponent = fn(&dyn Any) -> Option<Box<dyn Any>>;
+
+/// A copy of the resources registered with register_clone_resource, see EntitiesAndComponents::snapshot_resources
+/// It can be restored any number of times, like every time a rollback goes back to the same frame
+pub struct ResourceSnapshot {
+    resources: FxHashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl ResourceSnapshot {
+    /// Gets the saved copy of a resource, None if it was not registered or did not exist when the snapshot was taken
+    pub fn get<T: Resource>(&self) -> Option<&T> {
+        self.resources
+            .get(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_ref::<T>())
+    }
+
+    /// Gets the number of resources that were saved
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    /// Checks if no resources were saved
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Registers a resource to be copied by snapshot_resources, like a score, the Rng or a game clock
+    /// Resources that are not registered are left alone by snapshots
+    pub fn register_clone_resource<T: Resource + Clone + Send + Sync>(&mut self) {
+        self.clone_resources
+            .insert(TypeId::of::<T>(), CloneResource::new::<T>());
+    }
+
+    /// Registers a component to be copied by World::clone_for_simulation
+    /// Components that are not registered are left out of the copy
+    pub fn register_clone_component<T: Component + Clone>(&mut self) {
+        self.clone_components
+            .insert(TypeId::of::<T>(), |component| {
+                component
+                    .downcast_ref::<T>()
+                    .map(|component| Box::new(component.clone()) as Box<dyn Any>)
+            });
+    }
+
+    /// Copies every resource registered with register_clone_resource so they can be put back with restore_resources
+    pub fn snapshot_resources(&self) -> ResourceSnapshot {
+        let resources = self
+            .clone_resources
+            .iter()
+            .filter_map(|(type_id, clone_resource)| {
+                (clone_resource.snapshot)(self).map(|resource| (*type_id, resource))
+            })
+            .collect();
+        ResourceSnapshot { resources }
+    }
+
+    /// Puts the registered resources back to how they were when the snapshot was taken
+    /// Registered resources that did not exist then are removed, the added and removed hooks run as usual
+    pub fn restore_resources(&mut self, snapshot: &ResourceSnapshot) {
+        let clone_resources = self
+            .clone_resources
+            .iter()
+            .map(|(type_id, clone_resource)| (*type_id, *clone_resource))
+            .collect::<Vec<(TypeId, CloneResource)>>();
+        for (type_id, clone_resource) in clone_resources {
+            let saved = snapshot.resources.get(&type_id).map(|saved| &**saved);
+            (clone_resource.restore)(self, saved);
+        }
+    }
+}
+
+impl World {
+    /// Registers a resource to be copied by snapshot_resources, see EntitiesAndComponents::register_clone_resource
+    pub fn register_clone_resource<T: Resource + Clone + Send + Sync>(&mut self) {
+        self.entities_and_components.register_clone_resource::<T>();
+    }
+
+    /// Registers a component to be copied by clone_for_simulation, see EntitiesAndComponents::register_clone_component
+    pub fn register_clone_component<T: Component + Clone>(&mut self) {
+        self.entities_and_components.register_clone_component::<T>();
+    }
+
+    /// Copies the entities, the hierarchy and the components and resources registered as cloneable into a new world without systems
+    /// Useful for trying out what would happen, like AI looking ahead or a server predicting a player, without touching this world
+    /// The copy has the same registrations, so it can be cloned again, and it can be given systems and run like any world:
+    /// registered components, required components, indexes, trait implementations, view components, cell types,
+    /// transient components, map_entities, clone, diff and replay registrations, query drivers,
+    /// on_spawn, on_despawn and resource hooks and the resource update settings
+    /// Storages set with set_component_storage, recording and leak detection are not copied,
+    /// the copy keeps which entities have each component in the default storage
+    /// Returns the copy and a map from the entities in this world to the ones in the copy,
+    /// components registered with register_map_entities are updated to point at the copied entities
+    /// Entities hidden with despawn_soft are left out
+    pub fn clone_for_simulation(&self) -> (World, EntityMap) {
+        let source = &self.entities_and_components;
+        let mut world = World::new();
+        let engine = &mut world.entities_and_components;
+        engine.component_names = source.component_names.clone();
+        engine.registered_components = source.registered_components.clone();
+        engine.strict_components = source.strict_components;
+        engine.component_bits = source.component_bits.clone();
+        engine.required_components = source.required_components.clone();
+        engine.view_columns = source.view_columns.clone();
+        engine.cell_types = source.cell_types.clone();
+        engine.value_indexes = source.copy_value_indexes();
+        engine.replay_codecs = source.replay_codecs.clone();
+        engine.replay_encoders = source.replay_encoders.clone();
+        engine.trait_impls = source
+            .trait_impls
+            .iter()
+            .map(|(type_id, trait_impls)| (*type_id, trait_impls.copy()))
+            .collect();
+        engine.entity_mappers = source.entity_mappers.clone();
+        engine.clone_components = source.clone_components.clone();
+        engine.diff_components = source.diff_components.clone();
+        engine.clone_resources = source.clone_resources.clone();
+        engine.query_drivers = source.query_drivers.clone();
+        engine.independent_resources = source.independent_resources.clone();
+        engine.resource_orders = source.resource_orders.clone();
+        engine.always_run_resources = source.always_run_resources.clone();
+        engine.maintenance = source.maintenance.copy_registrations();
+
+        let mut entity_map = EntityMap::new();
+        for entity in source.iter_entities() {
+            let mut components = Map::new();
+            for (type_id, component) in source.components[entity.entity_id].as_raw().iter() {
+                let copy = source
+                    .clone_components
+                    .get(type_id)
+                    .and_then(|clone_component| clone_component(&**component));
+                if let Some(copy) = copy {
+                    // safe because the copy has the same type as the component it was made from
+                    unsafe {
+                        components.as_raw_mut().insert(*type_id, copy);
+                    }
+                }
+            }
+            entity_map.insert(entity, engine.add_entity_with_component_map(components));
+        }
+
+        // the children are added in the same order as in this world
+        for (parent, new_parent) in entity_map.iter() {
+            for child in source.get_children(parent) {
+                if let Some(new_child) = entity_map.get(child) {
+                    engine.set_parent(new_child, new_parent);
+                }
+            }
+        }
+        engine.map_moved_entities(&entity_map);
+        // the hooks are copied last so they are not called for the copied entities and resources
+        engine.restore_resources(&source.snapshot_resources());
+        engine.entity_hooks = source.entity_hooks.clone();
+        engine.resource_hooks = source.resource_hooks.clone();
+
+        (world, entity_map)
+    }
+
+    /// Copies the registered resources, see EntitiesAndComponents::snapshot_resources
+    pub fn snapshot_resources(&self) -> ResourceSnapshot {
+        self.entities_and_components.snapshot_resources()
+    }
+
+    /// Puts the registered resources back to how they were in the snapshot, see EntitiesAndComponents::restore_resources
+    pub fn restore_resources(&mut self, snapshot: &ResourceSnapshot) {
+        self.entities_and_components.restore_resources(snapshot);
+    }
+}
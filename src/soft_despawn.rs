@@ -0,0 +1,190 @@
+use crate::*;
+
+// the entities hidden by despawn_soft, see EntitiesAndComponents::despawn_soft
+pub(crate) struct SoftDespawns {
+    // the number of runs a hidden entity is kept for
+    frames: usize,
+    // the runs left before each hidden entity is removed
+    pub(crate) remaining: SecondaryMap<DefaultKey, usize>,
+}
+
+impl Default for SoftDespawns {
+    fn default() -> Self {
+        SoftDespawns {
+            frames: 60,
+            remaining: SecondaryMap::new(),
+        }
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Hides an entity and its children from every query and system without removing them
+    /// The entities keep their components and can be brought back with resurrect,
+    /// they are removed for good after the number of runs set with set_soft_despawn_frames, 60 by default
+    /// Useful for kill cams and undo, and for code later in the same frame that still reads the entity
+    /// Their components can still be read and written directly and does_entity_exist returns true until they are removed,
+    /// use is_soft_despawned to tell them apart
+    /// panics if the entity does not exist
+    pub fn despawn_soft(&mut self, entity: Entity) {
+        if self.is_soft_despawned(entity) {
+            return;
+        }
+        if !self.does_entity_exist(entity) {
+            panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+        }
+
+        for entity in self.get_soft_despawn_subtree(entity) {
+            if !self.is_soft_despawned(entity) {
+                self.hide_entity(entity);
+            }
+        }
+    }
+
+    /// Brings back an entity hidden with despawn_soft and the children that were hidden with it
+    /// Returns false if the entity is not hidden, like when it was already removed for good
+    pub fn resurrect(&mut self, entity: Entity) -> bool {
+        if !self.is_soft_despawned(entity) {
+            return false;
+        }
+
+        for entity in self.get_soft_despawn_subtree(entity) {
+            if self.is_soft_despawned(entity) {
+                self.show_entity(entity);
+            }
+        }
+        true
+    }
+
+    // hides one entity that is not hidden yet, its children are left as they are
+    pub(crate) fn hide_entity(&mut self, entity: Entity) {
+        self.unindex_tags(entity);
+        for type_id in self.get_component_type_ids(entity) {
+            if let Some(entities) = self.entities_with_components.get_mut(&type_id) {
+                entities.remove(entity);
+            }
+            self.unset_component_bit(entity, type_id);
+            self.remove_from_value_indexes(entity, type_id);
+        }
+        let frames = self.soft_despawns.frames;
+        self.soft_despawns
+            .remaining
+            .insert(entity.entity_id, frames);
+        self.record_soft_despawn_to_file(entity, true);
+    }
+
+    // brings back one hidden entity, its children are left as they are
+    pub(crate) fn show_entity(&mut self, entity: Entity) {
+        self.soft_despawns.remaining.remove(entity.entity_id);
+        for type_id in self.get_component_type_ids(entity) {
+            self.entities_with_components
+                .entry(type_id)
+                .or_default()
+                .insert(entity);
+            self.set_component_bit(entity, type_id);
+            self.update_value_indexes(entity, type_id);
+            self.invalidate_inherited_owners(type_id);
+        }
+        self.index_tags(entity);
+        self.record_soft_despawn_to_file(entity, false);
+    }
+
+    /// Checks if an entity was hidden with despawn_soft and has not been removed or resurrected
+    pub fn is_soft_despawned(&self, entity: Entity) -> bool {
+        self.soft_despawns.remaining.contains_key(entity.entity_id)
+            && self.does_entity_exist(entity)
+    }
+
+    /// Sets the number of runs an entity hidden with despawn_soft is kept for before it is removed
+    /// Entities that are already hidden keep the number they were hidden with
+    pub fn set_soft_despawn_frames(&mut self, frames: usize) {
+        self.soft_despawns.frames = frames;
+    }
+
+    // the entity and everything below it, parents first
+    fn get_soft_despawn_subtree(&self, entity: Entity) -> Vec<Entity> {
+        let mut subtree = vec![entity];
+        let mut index = 0;
+        while let Some(current) = subtree.get(index).copied() {
+            subtree.extend(self.get_children(current));
+            index += 1;
+        }
+        subtree
+    }
+
+    fn get_component_type_ids(&self, entity: Entity) -> Vec<TypeId> {
+        self.components[entity.entity_id]
+            .as_raw()
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    // counts down the runs left for the hidden entities and removes the ones that have none left
+    pub(crate) fn advance_soft_despawns(&mut self) {
+        let mut expired = vec![];
+        for (entity_id, remaining) in self.soft_despawns.remaining.iter_mut() {
+            match remaining.checked_sub(1) {
+                Some(left) => *remaining = left,
+                None => expired.push(Entity { entity_id }),
+            }
+        }
+
+        for entity in expired {
+            self.soft_despawns.remaining.remove(entity.entity_id);
+            // children are removed with their parent, so they may already be gone
+            if self.does_entity_exist(entity) {
+                self.remove_entity(entity);
+            }
+        }
+    }
+
+    // every entity that is hidden right now
+    pub(crate) fn get_soft_despawned(&self) -> Vec<Entity> {
+        self.soft_despawns
+            .remaining
+            .keys()
+            .map(|entity_id| Entity { entity_id })
+            .collect()
+    }
+
+    // removes every hidden entity now, used before the entities are moved
+    pub(crate) fn remove_soft_despawned(&mut self) {
+        let hidden = self.get_soft_despawned();
+        self.soft_despawns.remaining.clear();
+        for entity in hidden {
+            if self.does_entity_exist(entity) {
+                self.remove_entity(entity);
+            }
+        }
+    }
+}
+
+impl SingleMutEntity<'_> {
+    /// Hides the entity from every query and system, see EntitiesAndComponents::despawn_soft
+    /// The entity is hidden after every single_entity_step has finished
+    pub fn despawn_soft(&mut self) {
+        let entity = self.entity;
+        self.commands.push(move |entities_and_components| {
+            if entities_and_components.does_entity_exist(entity) {
+                entities_and_components.despawn_soft(entity);
+            }
+        });
+    }
+}
+
+impl World {
+    /// Hides an entity and its children from every query and system, see EntitiesAndComponents::despawn_soft
+    pub fn despawn_soft(&mut self, entity: Entity) {
+        self.entities_and_components.despawn_soft(entity);
+    }
+
+    /// Brings back an entity hidden with despawn_soft, see EntitiesAndComponents::resurrect
+    pub fn resurrect(&mut self, entity: Entity) -> bool {
+        self.entities_and_components.resurrect(entity)
+    }
+
+    /// Sets the number of runs an entity hidden with despawn_soft is kept for, see EntitiesAndComponents::set_soft_despawn_frames
+    pub fn set_soft_despawn_frames(&mut self, frames: usize) {
+        self.entities_and_components.set_soft_despawn_frames(frames);
+    }
+}
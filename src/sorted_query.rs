@@ -0,0 +1,103 @@
+use crate::*;
+use std::cmp::Ordering;
+
+// sorts by key and keeps the order of entities with equal keys
+// keys that can not be compared with themselves like NaN go last so the rest is sorted with a total order
+fn sort_by_key<K: PartialOrd>(keyed: &mut [(K, Entity)]) {
+    keyed.sort_by(|(a, _), (b, _)| {
+        let a_comparable = a.partial_cmp(a).is_some();
+        let b_comparable = b.partial_cmp(b).is_some();
+        match (a_comparable, b_comparable) {
+            (true, true) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => Ordering::Equal,
+        }
+    });
+}
+
+impl EntitiesAndComponents {
+    /// Gets every entity with all of the components in T sorted by a key made from the entity and its components
+    /// Useful for drawing in z order or running AI in order of priority
+    /// key is called once per entity, entities with equal keys are kept in the order they were found
+    /// Entities whose key can not be compared, like a NaN float, go after every other entity
+    /// Use a SortedQuery instead to keep the order between frames so that sorting is faster when few keys change
+    pub fn query_sorted<'a, T: ComponentsRef<'a> + HasComponents + 'static, K: PartialOrd>(
+        &'a self,
+        key: impl Fn(Entity, T::Result) -> K,
+    ) -> Vec<Entity> {
+        let mut keyed = T::get_matching_entities(self)
+            .into_iter()
+            .map(|entity| (key(entity, T::get_components(self, entity)), entity))
+            .collect::<Vec<(K, Entity)>>();
+        sort_by_key(&mut keyed);
+
+        keyed.into_iter().map(|(_, entity)| entity).collect()
+    }
+}
+
+/// A sorted query that remembers the order from the last time it was updated
+/// Keep it in a system or a Local and call update every frame,
+/// since the order is usually almost the same as last frame the sort has very little to do
+pub struct SortedQuery<K> {
+    keyed: Vec<(K, Entity)>,
+    entities: Vec<Entity>,
+}
+
+impl<K: PartialOrd> SortedQuery<K> {
+    /// Creates an empty sorted query
+    pub fn new() -> Self {
+        SortedQuery {
+            keyed: vec![],
+            entities: vec![],
+        }
+    }
+
+    /// Sorts every entity with all of the components in T by the key, see EntitiesAndComponents::query_sorted
+    /// Entities that were in the last update keep their place when their keys are equal, new entities go after them
+    pub fn update<'a, T: ComponentsRef<'a> + HasComponents + 'static>(
+        &mut self,
+        entities_and_components: &'a EntitiesAndComponents,
+        key: impl Fn(Entity, T::Result) -> K,
+    ) -> &[Entity] {
+        let matching = T::get_matching_entities(entities_and_components);
+        let mut is_new = matching
+            .iter()
+            .map(|entity| entity.entity_id)
+            .collect::<FxHashSet<DefaultKey>>();
+
+        // the entities from last time that still match, in their old order, then the new ones
+        self.entities
+            .retain(|entity| is_new.remove(&entity.entity_id));
+        self.entities.extend(
+            matching
+                .into_iter()
+                .filter(|entity| is_new.contains(&entity.entity_id)),
+        );
+
+        self.keyed.clear();
+        self.keyed.extend(self.entities.iter().map(|entity| {
+            (
+                key(*entity, T::get_components(entities_and_components, *entity)),
+                *entity,
+            )
+        }));
+        sort_by_key(&mut self.keyed);
+
+        self.entities.clear();
+        self.entities
+            .extend(self.keyed.iter().map(|(_, entity)| *entity));
+        &self.entities
+    }
+
+    /// Gets the entities in the order from the last update
+    pub fn get_entities(&self) -> &[Entity] {
+        &self.entities
+    }
+}
+
+impl<K: PartialOrd> Default for SortedQuery<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,173 @@
+use crate::*;
+use rustc_hash::FxHashSet;
+use std::marker::PhantomData;
+
+/// A component that has a position that can be put in a SpatialGrid
+/// Implement this for your position component, then call World::add_spatial_grid
+pub trait SpatialPosition: Component {
+    /// Gets the x and y position used by the grid
+    fn spatial_position(&self) -> (f32, f32);
+}
+
+/// A resource that splits the world into square cells so entities can be found by area quickly
+/// It holds every entity with the component P and is kept up to date by a system added with World::add_spatial_grid
+/// The positions in the grid are from the last time the system ran
+pub struct SpatialGrid<P> {
+    cell_size: f32,
+    cells: FxHashMap<(i32, i32), Vec<Entity>>,
+    // the position and cell of every entity in the grid
+    // keyed by the full entity id so that removed entities are never mixed up with new ones in the same slot
+    entries: FxHashMap<DefaultKey, SpatialEntry>,
+    marker: PhantomData<fn() -> P>,
+}
+
+struct SpatialEntry {
+    position: (f32, f32),
+    cell: (i32, i32),
+}
+
+impl<P: SpatialPosition> Resource for SpatialGrid<P> {}
+
+impl<P: SpatialPosition> SpatialGrid<P> {
+    /// Creates an empty grid, the cell size should be around the size of the area that is usually queried
+    pub fn new(cell_size: f32) -> Self {
+        assert!(
+            cell_size > 0.0,
+            "The cell size of a SpatialGrid must be positive"
+        );
+
+        SpatialGrid {
+            cell_size,
+            cells: FxHashMap::default(),
+            entries: FxHashMap::default(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Gets the size of the cells in the grid
+    pub fn get_cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Gets the number of entities in the grid
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if there are no entities in the grid
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets all the entities with a position inside of the box, including the edges
+    pub fn entities_in_aabb(&self, min: (f32, f32), max: (f32, f32)) -> Vec<Entity> {
+        let min_cell = self.cell_of(min);
+        let max_cell = self.cell_of(max);
+
+        let mut entities = vec![];
+        for x in min_cell.0..=max_cell.0 {
+            for y in min_cell.1..=max_cell.1 {
+                let Some(cell) = self.cells.get(&(x, y)) else {
+                    continue;
+                };
+                for entity in cell {
+                    let (position_x, position_y) = self.entries[&entity.entity_id].position;
+                    if position_x >= min.0
+                        && position_x <= max.0
+                        && position_y >= min.1
+                        && position_y <= max.1
+                    {
+                        entities.push(*entity);
+                    }
+                }
+            }
+        }
+
+        entities
+    }
+
+    fn cell_of(&self, position: (f32, f32)) -> (i32, i32) {
+        (
+            (position.0 / self.cell_size).floor() as i32,
+            (position.1 / self.cell_size).floor() as i32,
+        )
+    }
+
+    // moves the entities that changed cells and removes the ones that are gone,
+    // entities that stay in the same cell only have their position updated
+    fn update_positions(&mut self, positions: Vec<(Entity, (f32, f32))>) {
+        let mut seen = FxHashSet::default();
+
+        for (entity, position) in positions {
+            let cell = self.cell_of(position);
+            seen.insert(entity.entity_id);
+
+            match self.entries.get_mut(&entity.entity_id) {
+                Some(entry) => {
+                    entry.position = position;
+                    if entry.cell != cell {
+                        let old_cell = entry.cell;
+                        entry.cell = cell;
+                        self.remove_from_cell(entity, old_cell);
+                        self.cells.entry(cell).or_default().push(entity);
+                    }
+                }
+                None => {
+                    self.entries
+                        .insert(entity.entity_id, SpatialEntry { position, cell });
+                    self.cells.entry(cell).or_default().push(entity);
+                }
+            }
+        }
+
+        let removed = self
+            .entries
+            .keys()
+            .filter(|entity_id| !seen.contains(*entity_id))
+            .copied()
+            .collect::<Vec<DefaultKey>>();
+        for entity_id in removed {
+            if let Some(entry) = self.entries.remove(&entity_id) {
+                self.remove_from_cell(Entity { entity_id }, entry.cell);
+            }
+        }
+    }
+
+    fn remove_from_cell(&mut self, entity: Entity, cell: (i32, i32)) {
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.retain(|cell_entity| *cell_entity != entity);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+}
+
+// keeps the SpatialGrid<P> resource up to date with the positions of the entities
+pub(crate) struct SpatialGridSystem<P> {
+    marker: PhantomData<fn() -> P>,
+}
+
+impl<P> SpatialGridSystem<P> {
+    pub(crate) fn new() -> Self {
+        SpatialGridSystem {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<P: SpatialPosition> System for SpatialGridSystem<P> {
+    fn run(&mut self, engine: &mut EntitiesAndComponents) {
+        let positions = engine
+            .get_entities_with_component::<P>()
+            .map(|entity| {
+                let (component,) = engine.get_components::<(P,)>(entity);
+                (entity, component.spatial_position())
+            })
+            .collect::<Vec<(Entity, (f32, f32))>>();
+
+        if let Some(grid) = engine.get_resource_mut::<SpatialGrid<P>>() {
+            grid.update_positions(positions);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use crate::*;
+
+/// A fixed list of systems whose types are known at compile time, usually made with the schedule! macro
+/// Running it calls the systems directly instead of through a Box<dyn System>, so small systems can be inlined
+/// Each system keeps its own Locals for as long as the schedule lives, see World::run_schedule
+pub struct StaticSchedule<T: SystemTuple> {
+    systems: T,
+    // one for each system in the tuple, in the same order
+    locals: Vec<SystemLocals>,
+}
+
+impl<T: SystemTuple> StaticSchedule<T> {
+    /// Creates a schedule from a tuple of systems, they run in the order of the tuple
+    pub fn new(systems: T) -> Self {
+        StaticSchedule {
+            systems,
+            locals: (0..T::LEN).map(|_| SystemLocals::default()).collect(),
+        }
+    }
+
+    /// Gets the tuple of systems
+    pub fn systems(&self) -> &T {
+        &self.systems
+    }
+
+    /// Gets the tuple of systems mutably, for changing the settings of a system between runs
+    pub fn systems_mut(&mut self) -> &mut T {
+        &mut self.systems
+    }
+
+    /// Takes the tuple of systems out of the schedule, their Locals are dropped
+    pub fn into_systems(self) -> T {
+        self.systems
+    }
+
+    /// Gets the number of systems in the schedule
+    pub fn len(&self) -> usize {
+        T::LEN
+    }
+
+    /// Checks if there are no systems in the schedule
+    pub fn is_empty(&self) -> bool {
+        T::LEN == 0
+    }
+}
+
+/// Makes a StaticSchedule from systems, they run in the order they are listed
+/// ```
+/// use ABC_ECS::{schedule, System, World};
+///
+/// struct Movement;
+/// impl System for Movement {}
+///
+/// struct Render;
+/// impl System for Render {}
+///
+/// let mut world = World::new();
+/// let mut schedule = schedule!(Movement, Render);
+/// world.run_schedule(&mut schedule);
+/// ```
+#[macro_export]
+macro_rules! schedule {
+    ($($systems: expr),* $(,)?) => {
+        $crate::StaticSchedule::new(($($systems,)*))
+    };
+}
+
+/// Called with every system of a StaticSchedule in order, with the type of each system known at compile time
+#[doc(hidden)]
+pub trait SystemVisitor {
+    /// Called with one system and its position in the schedule
+    fn visit<S: System>(&mut self, index: usize, system: &mut S);
+}
+
+/// A tuple of systems that can be put in a StaticSchedule, it is automatically implemented for tuples of up to 16 systems
+pub trait SystemTuple: 'static {
+    /// The number of systems in the tuple
+    const LEN: usize;
+
+    /// Calls the visitor with every system in the order of the tuple
+    #[doc(hidden)]
+    fn visit_systems<V: SystemVisitor>(&mut self, visitor: &mut V);
+}
+
+impl SystemTuple for () {
+    const LEN: usize = 0;
+
+    fn visit_systems<V: SystemVisitor>(&mut self, _visitor: &mut V) {}
+}
+
+macro_rules! impl_system_tuple {
+    ($len: expr; $($generic_name: ident $index: tt),*) => {
+        impl<$($generic_name: System),*> SystemTuple for ($($generic_name,)*) {
+            const LEN: usize = $len;
+
+            #[inline]
+            fn visit_systems<V: SystemVisitor>(&mut self, visitor: &mut V) {
+                $(
+                    visitor.visit($index, &mut self.$index);
+                )*
+            }
+        }
+    };
+}
+
+impl_system_tuple!(1; S0 0);
+impl_system_tuple!(2; S0 0, S1 1);
+impl_system_tuple!(3; S0 0, S1 1, S2 2);
+impl_system_tuple!(4; S0 0, S1 1, S2 2, S3 3);
+impl_system_tuple!(5; S0 0, S1 1, S2 2, S3 3, S4 4);
+impl_system_tuple!(6; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5);
+impl_system_tuple!(7; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6);
+impl_system_tuple!(8; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7);
+impl_system_tuple!(9; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7, S8 8);
+impl_system_tuple!(10; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7, S8 8, S9 9);
+impl_system_tuple!(11; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7, S8 8, S9 9, S10 10);
+impl_system_tuple!(12; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7, S8 8, S9 9, S10 10, S11 11);
+impl_system_tuple!(13; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7, S8 8, S9 9, S10 10, S11 11, S12 12);
+impl_system_tuple!(14; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7, S8 8, S9 9, S10 10, S11 11, S12 12, S13 13);
+impl_system_tuple!(15; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7, S8 8, S9 9, S10 10, S11 11, S12 12, S13 13, S14 14);
+impl_system_tuple!(16; S0 0, S1 1, S2 2, S3 3, S4 4, S5 5, S6 6, S7 7, S8 8, S9 9, S10 10, S11 11, S12 12, S13 13, S14 14, S15 15);
+
+// finds out what the systems of a schedule need before anything runs
+#[derive(Default)]
+struct ScheduleNeeds {
+    prestep: bool,
+    single_entity_step: bool,
+    parents_before_children: bool,
+}
+
+impl SystemVisitor for ScheduleNeeds {
+    fn visit<S: System>(&mut self, _index: usize, system: &mut S) {
+        self.prestep |= system.implements_prestep();
+        if system.implements_single_entity_step() {
+            self.single_entity_step = true;
+            self.parents_before_children |= system.parents_before_children();
+        }
+    }
+}
+
+struct PrestepVisitor<'a, 'b> {
+    engine: &'a EntitiesAndComponentsThreadSafe<'b>,
+}
+
+impl SystemVisitor for PrestepVisitor<'_, '_> {
+    fn visit<S: System>(&mut self, _index: usize, system: &mut S) {
+        if system.implements_prestep() {
+            system.prestep(self.engine);
+        }
+    }
+}
+
+// runs single_entity_step of every system on one entity with the same runner as single_entity_steps
+struct SingleEntityStepVisitor<'a> {
+    entity: Entity,
+    entities_and_components: &'a mut EntitiesAndComponents,
+    locals: &'a [SystemLocals],
+    commands: &'a mut CommandQueue,
+    failures: &'a mut Vec<SystemFailure>,
+    settings: StepSettings<'a>,
+}
+
+impl SystemVisitor for SingleEntityStepVisitor<'_> {
+    #[inline]
+    fn visit<S: System>(&mut self, index: usize, system: &mut S) {
+        if !system.implements_single_entity_step() {
+            return;
+        }
+        if self
+            .entities_and_components
+            .has_component_type(self.entity, TypeId::of::<SkipSystem<S>>())
+        {
+            return;
+        }
+
+        single_entity_step(
+            &*system,
+            &self.locals[index],
+            self.entities_and_components,
+            self.entity,
+            self.commands,
+            self.failures,
+            self.settings,
+        );
+    }
+}
+
+struct RunVisitor<'a> {
+    entities_and_components: &'a mut EntitiesAndComponents,
+    failures: &'a mut Vec<SystemFailure>,
+    validators: Option<&'a ComponentValidators>,
+}
+
+impl SystemVisitor for RunVisitor<'_> {
+    fn visit<S: System>(&mut self, _index: usize, system: &mut S) {
+        let type_name = std::any::type_name::<S>();
+        self.entities_and_components
+            .set_recording_system(Some(type_name));
+        let result = system.try_run(self.entities_and_components);
+        self.entities_and_components.set_recording_system(None);
+        if let Err(error) = result {
+            self.failures.push(SystemFailure {
+                system: type_name,
+                entity: None,
+                error,
+            });
+        }
+        if let Some(validators) = self.validators {
+            validators.check_all(self.entities_and_components, type_name, self.failures);
+        }
+    }
+}
+
+impl World {
+    /// Runs the world like run, but with the systems of the schedule instead of the systems added to the world
+    /// The systems are called directly, one after the other on this thread, which is faster for many small systems
+    /// Sets, groups, intervals, frame budgets, exclude_from_system and timings only apply to added systems,
+    /// SkipSystem<S>, the panic policy, the component validators, the error handler and the frame hooks work the same as in run
+    pub fn run_schedule<T: SystemTuple>(&mut self, schedule: &mut StaticSchedule<T>) {
+        self.frame_report.clear();
+        let paused = !self.take_frame();
+        let frame_start = self.start_span();
+        self.run_frame_hooks(FrameStage::Start);
+        if !paused {
+            self.store_previous_components();
+        }
+        self.update_resources_and_tasks(paused);
+        if !paused {
+            self.run_static_systems(schedule);
+        }
+        self.run_maintenance(paused);
+        self.extract();
+        self.run_frame_hooks(FrameStage::End);
+        self.finish_span("frame", "frame", frame_start);
+        self.entities_and_components.frame_arena.reset();
+    }
+
+    // the systems part of run_schedule, in the same stages as run_systems
+    fn run_static_systems<T: SystemTuple>(&mut self, schedule: &mut StaticSchedule<T>) {
+        self.entities_and_components.next_recording_frame();
+        self.entities_and_components.advance_change_tick();
+        self.entities_and_components.refill_entity_reserve();
+        let mut frame_failures = vec![];
+
+        let mut needs = ScheduleNeeds::default();
+        schedule.systems.visit_systems(&mut needs);
+
+        if needs.prestep {
+            let resource_commands = {
+                let thread_safe_entities_and_components =
+                    EntitiesAndComponentsThreadSafe::new(&mut self.entities_and_components);
+                schedule.systems.visit_systems(&mut PrestepVisitor {
+                    engine: &thread_safe_entities_and_components,
+                });
+                thread_safe_entities_and_components
+                    .resource_commands
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner)
+            };
+            resource_commands.apply(&mut self.entities_and_components);
+            self.entities_and_components.spawn_taken_entities();
+        }
+        self.run_frame_hooks(FrameStage::AfterPrestep);
+
+        if needs.single_entity_step {
+            self.entities_buffer.clear();
+            self.entities_buffer
+                .extend(self.entities_and_components.iter_entities());
+            if needs.parents_before_children {
+                let entities_and_components = &self.entities_and_components;
+                self.entities_buffer
+                    .sort_by_cached_key(|entity| entities_and_components.get_depth(*entity));
+            }
+
+            let span_start = self.start_span();
+            let settings = StepSettings {
+                panic_policy: self.panic_policy,
+                validators: self.component_validators.get_active(),
+                cells: None,
+            };
+            let mut commands = CommandQueue::default();
+            for entity in &self.entities_buffer {
+                schedule
+                    .systems
+                    .visit_systems(&mut SingleEntityStepVisitor {
+                        entity: *entity,
+                        entities_and_components: &mut self.entities_and_components,
+                        locals: &schedule.locals,
+                        commands: &mut commands,
+                        failures: &mut frame_failures,
+                        settings,
+                    });
+            }
+            self.finish_span("single_entity_step", "single_entity_step", span_start);
+            commands.apply(&mut self.entities_and_components);
+            self.entities_and_components.spawn_taken_entities();
+        }
+        self.run_frame_hooks(FrameStage::AfterSingleEntityStep);
+
+        schedule.systems.visit_systems(&mut RunVisitor {
+            entities_and_components: &mut self.entities_and_components,
+            failures: &mut frame_failures,
+            validators: self.component_validators.get_active(),
+        });
+        self.run_frame_hooks(FrameStage::AfterRun);
+
+        self.report_failures(frame_failures);
+    }
+}
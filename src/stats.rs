@@ -0,0 +1,94 @@
+use crate::*;
+use std::time::{Duration, Instant};
+
+/// Numbers about the world for debug overlays, added with World::add_world_stats and updated at the end of every run
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct WorldStats {
+    /// The number of entities at the end of the last run
+    pub entity_count: usize,
+    /// The number of entities spawned since the run before the last one
+    /// Entities moved in by World::merge or taken from an EntityPool count as spawned, entities moved by compact do not
+    pub spawned: usize,
+    /// The number of entities removed since the run before the last one
+    /// Entities released to an EntityPool or removed by clear_entities count as removed
+    pub despawned: usize,
+    /// The number of entities with each component registered with register_component, sorted by name
+    pub component_counts: Vec<(&'static str, usize)>,
+    /// How long the run function of each system took in the last run, in the order they ran
+    /// Only filled if profiling was turned on with add_world_stats
+    pub system_timings: Vec<(&'static str, Duration)>,
+}
+
+impl Resource for WorldStats {}
+
+// the spawns and despawns since the stats were last updated
+#[derive(Default)]
+pub(crate) struct FrameCounts {
+    spawned: usize,
+    despawned: usize,
+}
+
+impl FrameCounts {
+    pub(crate) fn count(&mut self, op: &StructuralOp) {
+        match op {
+            StructuralOp::Spawn(_) => self.spawned += 1,
+            StructuralOp::Despawn(_) => self.despawned += 1,
+            _ => {}
+        }
+    }
+}
+
+impl World {
+    /// Adds the WorldStats resource, which is updated at the end of every run
+    /// If profile_systems is true the run function of every system is timed, which has a small cost
+    pub fn add_world_stats(&mut self, profile_systems: bool) {
+        self.entities_and_components
+            .add_resource(WorldStats::default());
+        self.profile_systems = profile_systems;
+    }
+
+    // times the run function of a system if profiling is on
+    pub(crate) fn start_timing(&self) -> Option<Instant> {
+        self.profile_systems.then(Instant::now)
+    }
+
+    pub(crate) fn finish_timing(&mut self, system: &'static str, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.system_timings.push((system, start.elapsed()));
+        }
+    }
+
+    // fills in the WorldStats resource if it was added
+    pub(crate) fn update_world_stats(&mut self) {
+        let engine = &mut self.entities_and_components;
+        let frame_counts = std::mem::take(&mut engine.frame_counts);
+        let system_timings = std::mem::take(&mut self.system_timings);
+        if engine.get_resource::<WorldStats>().is_none() {
+            return;
+        }
+
+        let mut component_counts = engine
+            .registered_components
+            .iter()
+            .map(|(type_id, name)| {
+                let count = engine
+                    .entities_with_components
+                    .get(type_id)
+                    .map_or(0, |entities| entities.len());
+                (*name, count)
+            })
+            .collect::<Vec<(&'static str, usize)>>();
+        component_counts.sort();
+
+        let entity_count = engine.get_entity_count();
+        if let Some(stats) = engine.get_resource_mut::<WorldStats>() {
+            *stats = WorldStats {
+                entity_count,
+                spawned: frame_counts.spawned,
+                despawned: frame_counts.despawned,
+                component_counts,
+                system_timings,
+            };
+        }
+    }
+}
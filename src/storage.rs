@@ -0,0 +1,238 @@
+use crate::*;
+
+/// Stores which entities have a component type, see EntitiesAndComponents::set_component_storage
+/// By default each component type uses a SecondaryMap, implement this to store the entities another way
+pub trait EntityStorage: Send + Sync {
+    /// Adds an entity, does nothing if it is already stored
+    fn insert(&mut self, entity: Entity);
+    /// Removes an entity, returns true if it was stored
+    fn remove(&mut self, entity: Entity) -> bool;
+    /// Checks if an entity is stored, an old entity with the same index as a stored one is not
+    fn contains(&self, entity: Entity) -> bool;
+    /// Gets every stored entity, in any order
+    fn entities(&self) -> &[Entity];
+    /// Removes every entity
+    fn clear(&mut self);
+    /// Makes room for entities with an Entity::index below capacity
+    fn set_capacity(&mut self, _capacity: usize) {}
+}
+
+/// An EntityStorage with one bit for every entity index and a packed list of the entities
+/// Checking if an entity is stored only reads its bit, which is faster than a SecondaryMap when there are many checks,
+/// but the bits take memory for every entity index up to the highest one stored, so it suits worlds under about 100k entities
+#[derive(Default)]
+pub struct DenseEntitySet {
+    // one bit for each entity index, set if an entity with that index is stored
+    bits: Vec<u64>,
+    // where each stored index is in entities
+    positions: Vec<u32>,
+    entities: Vec<Entity>,
+}
+
+impl DenseEntitySet {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        DenseEntitySet::default()
+    }
+
+    fn has_index(&self, index: usize) -> bool {
+        self.bits
+            .get(index / 64)
+            .is_some_and(|bits| bits & (1 << (index % 64)) != 0)
+    }
+}
+
+impl EntityStorage for DenseEntitySet {
+    fn insert(&mut self, entity: Entity) {
+        let index = entity.index();
+        if self.has_index(index) {
+            // a removed entity's index can be reused by a new entity
+            self.entities[self.positions[index] as usize] = entity;
+            return;
+        }
+
+        if index / 64 >= self.bits.len() {
+            self.bits.resize(index / 64 + 1, 0);
+        }
+        if index >= self.positions.len() {
+            self.positions.resize(index + 1, 0);
+        }
+        self.bits[index / 64] |= 1 << (index % 64);
+        self.positions[index] = self.entities.len() as u32;
+        self.entities.push(entity);
+    }
+
+    fn remove(&mut self, entity: Entity) -> bool {
+        if !self.contains(entity) {
+            return false;
+        }
+
+        let index = entity.index();
+        self.bits[index / 64] &= !(1 << (index % 64));
+        let position = self.positions[index] as usize;
+        self.entities.swap_remove(position);
+        if let Some(moved) = self.entities.get(position) {
+            self.positions[moved.index()] = position as u32;
+        }
+        true
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        let index = entity.index();
+        self.has_index(index) && self.entities[self.positions[index] as usize] == entity
+    }
+
+    fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    fn clear(&mut self) {
+        self.bits.clear();
+        self.entities.clear();
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.bits
+            .reserve(capacity.div_ceil(64).saturating_sub(self.bits.len()));
+        self.positions
+            .reserve(capacity.saturating_sub(self.positions.len()));
+        self.entities
+            .reserve(capacity.saturating_sub(self.entities.len()));
+    }
+}
+
+// the entities with one component type
+pub(crate) enum ComponentEntities {
+    Map(SecondaryMap<DefaultKey, Entity>),
+    Custom(Box<dyn EntityStorage>),
+}
+
+impl Default for ComponentEntities {
+    fn default() -> Self {
+        ComponentEntities::Map(SecondaryMap::new())
+    }
+}
+
+// iterates over the entities with a component type
+pub(crate) enum ComponentEntitiesIter<'a> {
+    Map(slotmap::secondary::Values<'a, DefaultKey, Entity>),
+    Custom(std::slice::Iter<'a, Entity>),
+}
+
+impl<'a> Iterator for ComponentEntitiesIter<'a> {
+    type Item = &'a Entity;
+
+    fn next(&mut self) -> Option<&'a Entity> {
+        match self {
+            ComponentEntitiesIter::Map(entities) => entities.next(),
+            ComponentEntitiesIter::Custom(entities) => entities.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            ComponentEntitiesIter::Map(entities) => entities.size_hint(),
+            ComponentEntitiesIter::Custom(entities) => entities.size_hint(),
+        }
+    }
+}
+
+impl ComponentEntities {
+    pub(crate) fn insert(&mut self, entity: Entity) {
+        match self {
+            ComponentEntities::Map(entities) => {
+                entities.insert(entity.entity_id, entity);
+            }
+            ComponentEntities::Custom(storage) => storage.insert(entity),
+        }
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) {
+        match self {
+            ComponentEntities::Map(entities) => {
+                entities.remove(entity.entity_id);
+            }
+            ComponentEntities::Custom(storage) => {
+                storage.remove(entity);
+            }
+        }
+    }
+
+    pub(crate) fn contains(&self, entity: Entity) -> bool {
+        match self {
+            ComponentEntities::Map(entities) => entities.get(entity.entity_id) == Some(&entity),
+            ComponentEntities::Custom(storage) => storage.contains(entity),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            ComponentEntities::Map(entities) => entities.len(),
+            ComponentEntities::Custom(storage) => storage.entities().len(),
+        }
+    }
+
+    pub(crate) fn values(&self) -> ComponentEntitiesIter<'_> {
+        match self {
+            ComponentEntities::Map(entities) => ComponentEntitiesIter::Map(entities.values()),
+            ComponentEntities::Custom(storage) => {
+                ComponentEntitiesIter::Custom(storage.entities().iter())
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            ComponentEntities::Map(entities) => entities.clear(),
+            ComponentEntities::Custom(storage) => storage.clear(),
+        }
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        match self {
+            ComponentEntities::Map(entities) => entities.set_capacity(capacity),
+            ComponentEntities::Custom(storage) => storage.set_capacity(capacity),
+        }
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Changes how the entities with the component T are stored, like set_component_storage::<Enemy, _>(DenseEntitySet::new())
+    /// This decides how fast checking if an entity has T and listing the entities with T is, see DenseEntitySet
+    /// The entities that already have T are moved into the new storage
+    pub fn set_component_storage<T: Component, S: EntityStorage + 'static>(
+        &mut self,
+        mut storage: S,
+    ) {
+        storage.clear();
+        let entities = self
+            .entities_with_components
+            .entry(TypeId::of::<T>())
+            .or_default();
+        for entity in entities.values() {
+            storage.insert(*entity);
+        }
+        *entities = ComponentEntities::Custom(Box::new(storage));
+    }
+
+    /// Goes back to storing the entities with the component T in a SecondaryMap, see set_component_storage
+    pub fn reset_component_storage<T: Component>(&mut self) {
+        let entities = self
+            .entities_with_components
+            .entry(TypeId::of::<T>())
+            .or_default();
+        let mut map = SecondaryMap::new();
+        for entity in entities.values() {
+            map.insert(entity.entity_id, *entity);
+        }
+        *entities = ComponentEntities::Map(map);
+    }
+}
+
+impl World {
+    /// Changes how the entities with the component T are stored, see EntitiesAndComponents::set_component_storage
+    pub fn set_component_storage<T: Component, S: EntityStorage + 'static>(&mut self, storage: S) {
+        self.entities_and_components
+            .set_component_storage::<T, S>(storage);
+    }
+}
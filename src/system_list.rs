@@ -0,0 +1,107 @@
+use crate::*;
+
+/// A list of systems that can be declared next to the systems and added to a world all at once
+/// Usually made with the systems! macro, see World::with_registered_systems
+#[derive(Default)]
+pub struct SystemList {
+    systems: Vec<Box<dyn SystemWrapper + Send + Sync>>,
+}
+
+impl SystemList {
+    /// Creates an empty list
+    pub fn new() -> Self {
+        SystemList::default()
+    }
+
+    /// Adds a system, or every system of another list, to the end of the list
+    pub fn add(&mut self, systems: impl IntoSystemList) {
+        self.systems.extend(systems.into_system_list().systems);
+    }
+
+    /// Adds a system, or every system of another list, to the end of the list and returns the list
+    pub fn with(mut self, systems: impl IntoSystemList) -> Self {
+        self.add(systems);
+        self
+    }
+
+    /// Gets the number of systems in the list
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    /// Checks if there are no systems in the list
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+}
+
+/// Something that can be added to a SystemList, either a system or another SystemList
+pub trait IntoSystemList {
+    /// Turns this into a list
+    fn into_system_list(self) -> SystemList;
+}
+
+impl<T: System + Send + Sync + 'static> IntoSystemList for T {
+    fn into_system_list(self) -> SystemList {
+        SystemList {
+            systems: vec![Box::new(self)],
+        }
+    }
+}
+
+impl IntoSystemList for SystemList {
+    fn into_system_list(self) -> SystemList {
+        self
+    }
+}
+
+/// Makes a SystemList from systems and other SystemLists, they run in the order they are listed
+/// ```
+/// use ABC_ECS::{systems, System, SystemList, World};
+///
+/// struct Gravity;
+/// impl System for Gravity {}
+///
+/// struct Collisions;
+/// impl System for Collisions {}
+///
+/// // each module can declare its own systems
+/// fn physics_systems() -> SystemList {
+///     systems![Gravity, Collisions]
+/// }
+///
+/// struct Pathfinding;
+/// impl System for Pathfinding {}
+///
+/// let world = World::with_registered_systems(systems![physics_systems(), Pathfinding]);
+/// ```
+#[macro_export]
+macro_rules! systems {
+    ($($systems: expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut system_list = $crate::SystemList::new();
+        $(
+            system_list.add($systems);
+        )*
+        system_list
+    }};
+}
+
+impl World {
+    /// Creates a new world with every system in the list, see the systems! macro
+    pub fn with_registered_systems(systems: SystemList) -> Self {
+        let mut world = World::new();
+        world.add_systems(systems);
+        world
+    }
+
+    /// Adds every system in the list in order, see the systems! macro
+    /// Returns a handle for each system
+    pub fn add_systems(&mut self, systems: SystemList) -> Vec<SystemHandle> {
+        systems
+            .systems
+            .into_iter()
+            .map(|system| self.add_boxed_system(system))
+            .collect()
+    }
+}
@@ -0,0 +1,68 @@
+use crate::*;
+
+type RunCondition = Box<dyn Fn(&EntitiesAndComponents) -> bool + Send + Sync>;
+
+/// The settings shared by every system in a set
+/// Get one with World::configure_set, systems are added to a set with World::add_system_to_set
+pub struct SystemSet {
+    enabled: bool,
+    order: i32,
+    run_conditions: Vec<RunCondition>,
+}
+
+impl SystemSet {
+    pub(crate) fn new() -> Self {
+        SystemSet {
+            enabled: true,
+            order: 0,
+            run_conditions: vec![],
+        }
+    }
+
+    /// Only runs the systems in the set when the condition returns true
+    /// If there are multiple conditions they all have to return true
+    pub fn run_if<F: Fn(&EntitiesAndComponents) -> bool + Send + Sync + 'static>(
+        &mut self,
+        condition: F,
+    ) -> &mut Self {
+        self.run_conditions.push(Box::new(condition));
+        self
+    }
+
+    /// Sets when the run function of the systems in the set is called compared to other sets
+    /// Lower orders run first, systems that are not in a set have an order of 0
+    pub fn order(&mut self, order: i32) -> &mut Self {
+        self.order = order;
+        self
+    }
+
+    /// Lets the systems in the set run again after disable was called
+    pub fn enable(&mut self) -> &mut Self {
+        self.enabled = true;
+        self
+    }
+
+    /// Stops all the systems in the set from running until enable is called
+    pub fn disable(&mut self) -> &mut Self {
+        self.enabled = false;
+        self
+    }
+
+    /// Checks if the set is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn get_order(&self) -> i32 {
+        self.order
+    }
+
+    // checks if the systems in the set should run this frame
+    pub(crate) fn should_run(&self, entities_and_components: &EntitiesAndComponents) -> bool {
+        self.enabled
+            && self
+                .run_conditions
+                .iter()
+                .all(|condition| condition(entities_and_components))
+    }
+}
@@ -0,0 +1,157 @@
+use crate::*;
+use std::sync::OnceLock;
+
+// the id of an interned tag, the same tag has the same id in every world
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
+pub(crate) struct TagId(u32);
+
+// every tag that has been used, tags are never removed so their names can be &'static str
+#[derive(Default)]
+struct TagInterner {
+    ids: FxHashMap<&'static str, TagId>,
+    names: Vec<&'static str>,
+}
+
+fn interner() -> &'static Mutex<TagInterner> {
+    static INTERNER: OnceLock<Mutex<TagInterner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+// gets the id of a tag, interning it if it has not been used before
+fn intern(tag: &str) -> TagId {
+    let mut interner = interner().lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(id) = interner.ids.get(tag) {
+        return *id;
+    }
+
+    let name: &'static str = Box::leak(tag.to_owned().into_boxed_str());
+    let id = TagId(interner.names.len() as u32);
+    interner.names.push(name);
+    interner.ids.insert(name, id);
+    id
+}
+
+// gets the id of a tag without interning it, None if it has never been used
+fn find(tag: &str) -> Option<TagId> {
+    interner()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .ids
+        .get(tag)
+        .copied()
+}
+
+fn name_of(id: TagId) -> &'static str {
+    interner()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .names[id.0 as usize]
+}
+
+// the tags on an entity, stored as a component so they move with the entity
+pub(crate) struct Tags {
+    tags: SmallVec<TagId, 4>,
+}
+
+impl EntitiesAndComponents {
+    /// Adds a tag to an entity, tags are a quick way to mark entities without making a marker type
+    /// Adding a tag the entity already has does nothing
+    /// panics if the entity does not exist
+    pub fn add_tag(&mut self, entity: Entity, tag: &str) {
+        let id = intern(tag);
+
+        match self.try_get_component_mut::<Tags>(entity) {
+            Some(tags) if tags.tags.contains(&id) => return,
+            Some(tags) => tags.tags.push(id),
+            None => self.add_component_to(
+                entity,
+                Tags {
+                    tags: SmallVec::from_slice(&[id]),
+                },
+            ),
+        }
+
+        self.tagged_entities
+            .entry(id)
+            .or_default()
+            .insert(entity.entity_id, entity);
+    }
+
+    /// Removes a tag from an entity
+    /// If the entity does not have the tag, it will do nothing
+    /// panics if the entity does not exist
+    pub fn remove_tag(&mut self, entity: Entity, tag: &str) {
+        if !self.does_entity_exist(entity) {
+            panic!("Entity ID {entity:?} does not exist, was the Entity ID edited?");
+        }
+
+        let Some(id) = find(tag) else {
+            return;
+        };
+
+        if let Some(tags) = self.try_get_component_mut::<Tags>(entity) {
+            tags.tags.retain(|tag_id| *tag_id != id);
+            if tags.tags.is_empty() {
+                self.remove_component_from::<Tags>(entity);
+            }
+        }
+
+        if let Some(entities) = self.tagged_entities.get_mut(&id) {
+            entities.remove(entity.entity_id);
+        }
+    }
+
+    /// Checks if an entity has a tag
+    pub fn has_tag(&self, entity: Entity, tag: &str) -> bool {
+        match find(tag) {
+            Some(id) => self
+                .tagged_entities
+                .get(&id)
+                .is_some_and(|entities| entities.contains_key(entity.entity_id)),
+            None => false,
+        }
+    }
+
+    /// Gets every entity with a tag
+    pub fn get_entities_with_tag(&self, tag: &str) -> Vec<Entity> {
+        find(tag)
+            .and_then(|id| self.tagged_entities.get(&id))
+            .map_or(vec![], |entities| entities.values().copied().collect())
+    }
+
+    /// Gets every tag on an entity in the order they were added
+    /// panics if the entity does not exist
+    pub fn get_tags(&self, entity: Entity) -> Vec<&'static str> {
+        match self.try_get_component::<Tags>(entity) {
+            Some(tags) => tags.tags.iter().map(|id| name_of(*id)).collect(),
+            None => vec![],
+        }
+    }
+
+    // adds an entity that was moved into this world to the lists of entities with its tags
+    pub(crate) fn index_tags(&mut self, entity: Entity) {
+        for id in self.get_tag_ids(entity) {
+            self.tagged_entities
+                .entry(id)
+                .or_default()
+                .insert(entity.entity_id, entity);
+        }
+    }
+
+    // removes an entity that is being removed or moved out of this world from the lists of entities with its tags
+    pub(crate) fn unindex_tags(&mut self, entity: Entity) {
+        for id in self.get_tag_ids(entity) {
+            if let Some(entities) = self.tagged_entities.get_mut(&id) {
+                entities.remove(entity.entity_id);
+            }
+        }
+    }
+
+    // the ids of the tags on an entity, empty if the entity does not exist
+    fn get_tag_ids(&self, entity: Entity) -> Vec<TagId> {
+        self.components
+            .get(entity.entity_id)
+            .and_then(|components| components.get::<Tags>())
+            .map_or(vec![], |tags| tags.tags.to_vec())
+    }
+}
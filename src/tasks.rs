@@ -0,0 +1,99 @@
+use crate::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Wake, Waker};
+
+// what to do with the world once a task has finished
+type Completion = Box<dyn FnOnce(&mut EntitiesAndComponents) + Send>;
+type Task = Pin<Box<dyn Future<Output = Completion> + Send>>;
+
+/// A resource that runs futures like asset loading or network requests alongside the world
+/// Every pending task is polled once at the start of World::run,
+/// when a task finishes its result is given back to the world on the same run
+/// Add it with init_resource::<AsyncTasks>(), tasks can then be spawned from any system
+#[derive(Default)]
+pub struct AsyncTasks {
+    tasks: Mutex<Vec<Task>>,
+}
+
+impl Resource for AsyncTasks {}
+
+impl AsyncTasks {
+    /// Spawns a task, on_complete is called with its output during the World::run after it finishes
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        future: impl Future<Output = T> + Send + 'static,
+        on_complete: impl FnOnce(T, &mut EntitiesAndComponents) + Send + 'static,
+    ) {
+        let task = async move {
+            let output = future.await;
+            Box::new(move |entities_and_components: &mut EntitiesAndComponents| {
+                on_complete(output, entities_and_components)
+            }) as Completion
+        };
+
+        self.tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Box::pin(task));
+    }
+
+    /// Spawns a task that adds its output to an entity as a component once it finishes
+    /// If the entity was removed before the task finished, the output is dropped
+    pub fn spawn_component<T: Component + Send>(
+        &self,
+        entity: Entity,
+        future: impl Future<Output = T> + Send + 'static,
+    ) {
+        self.spawn(future, move |component, entities_and_components| {
+            if entities_and_components.does_entity_exist(entity) {
+                entities_and_components.add_component_to(entity, component);
+            }
+        });
+    }
+
+    /// Gets the number of tasks that have not finished yet
+    pub fn len(&self) -> usize {
+        self.tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Checks if every task has finished
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // polls every task once and returns the completions of the ones that finished
+    pub(crate) fn poll(&self) -> Vec<Completion> {
+        let mut tasks =
+            std::mem::take(&mut *self.tasks.lock().unwrap_or_else(PoisonError::into_inner));
+
+        let waker = Waker::from(Arc::new(PollEveryRun));
+        let mut context = Context::from_waker(&waker);
+
+        let mut completions = vec![];
+        tasks.retain_mut(|task| match task.as_mut().poll(&mut context) {
+            Poll::Ready(completion) => {
+                completions.push(completion);
+                false
+            }
+            Poll::Pending => true,
+        });
+
+        // keep the unfinished tasks in front of any that were spawned while polling
+        let mut pending = self.tasks.lock().unwrap_or_else(PoisonError::into_inner);
+        tasks.append(&mut pending);
+        *pending = tasks;
+
+        completions
+    }
+}
+
+// tasks are polled every run no matter what, so being woken up does nothing
+struct PollEveryRun;
+
+impl Wake for PollEveryRun {
+    fn wake(self: Arc<Self>) {}
+}
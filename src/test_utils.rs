@@ -0,0 +1,237 @@
+//! Helpers for testing systems, turned on with the test_utils feature
+//! ```ignore
+//! let mut harness = WorldTestHarness::new();
+//! let entity = harness.spawn((Position { x: 0.0 }, Velocity { x: 1.0 }));
+//! harness.add_system(Movement);
+//! harness.run_frames(3);
+//! harness.assert_component_eq(entity, &Position { x: 3.0 });
+//! ```
+//! Every harness starts with an Rng resource seeded with 0 and a TestClock that moves a fixed time every frame,
+//! so the same test gives the same result every time it is run
+
+use crate::*;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// A clock resource that moves forward by delta every frame instead of following the real time
+/// Systems that read it see the same times in every run of a test
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TestClock {
+    /// The time each frame takes, 1/60th of a second by default
+    pub delta: Duration,
+    /// The time since the clock was made, it includes the current frame
+    pub elapsed: Duration,
+    /// The number of frames since the clock was made, it includes the current frame
+    pub frame: u64,
+}
+
+impl TestClock {
+    /// Creates a clock at frame 0 that moves delta every frame
+    pub fn new(delta: Duration) -> Self {
+        TestClock {
+            delta,
+            elapsed: Duration::ZERO,
+            frame: 0,
+        }
+    }
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        TestClock::new(Duration::from_secs(1) / 60)
+    }
+}
+
+impl Resource for TestClock {
+    fn update(&mut self) {
+        self.elapsed += self.delta;
+        self.frame += 1;
+    }
+}
+
+/// A world with a fixed seed and clock, and helpers for running it and checking its components
+pub struct WorldTestHarness {
+    /// The world being tested, it can be used directly for anything the harness does not cover
+    pub world: World,
+    // true if run_frames panics when a system reports an error
+    fail_on_errors: bool,
+}
+
+impl WorldTestHarness {
+    /// Creates a harness with an empty world, an Rng seeded with 0 and a TestClock running at 60 frames a second
+    pub fn new() -> Self {
+        WorldTestHarness::with_world(World::new())
+    }
+
+    /// Creates a harness around a world, the Rng and TestClock are only added if the world does not have them
+    pub fn with_world(mut world: World) -> Self {
+        let engine = &mut world.entities_and_components;
+        if engine.get_resource::<Rng>().is_none() {
+            engine.add_resource(Rng::new(0));
+        }
+        if engine.get_resource::<TestClock>().is_none() {
+            engine.add_resource(TestClock::default());
+        }
+        WorldTestHarness {
+            world,
+            fail_on_errors: true,
+        }
+    }
+
+    /// Replaces the Rng with one made from seed and returns the harness
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.world
+            .entities_and_components
+            .add_resource(Rng::new(seed));
+        self
+    }
+
+    /// Sets the time each frame takes on the TestClock and returns the harness
+    pub fn with_frame_time(mut self, delta: Duration) -> Self {
+        self.clock_mut().delta = delta;
+        self
+    }
+
+    /// Lets run_frames keep going when a system reports an error, they can be read with World::get_frame_report
+    pub fn allow_errors(mut self) -> Self {
+        self.fail_on_errors = false;
+        self
+    }
+
+    /// Gets the TestClock of the world
+    pub fn clock(&self) -> &TestClock {
+        self.world
+            .entities_and_components
+            .get_resource::<TestClock>()
+            .unwrap_or_else(|| panic!("TestClock does not exist, was it removed from the world?"))
+    }
+
+    /// Gets the TestClock of the world mutably, for changing the time between frames
+    pub fn clock_mut(&mut self) -> &mut TestClock {
+        self.world
+            .entities_and_components
+            .get_resource_mut::<TestClock>()
+            .unwrap_or_else(|| panic!("TestClock does not exist, was it removed from the world?"))
+    }
+
+    /// Adds an entity with components to the world
+    pub fn spawn<T: OwnedComponents<Input = T>>(&mut self, components: T) -> Entity {
+        self.world
+            .entities_and_components
+            .add_entity_with(components)
+    }
+
+    /// Adds a system to the world, it runs every frame from now on
+    pub fn add_system<T: System + Send + Sync>(&mut self, system: T) -> SystemHandle {
+        self.world.add_system(system)
+    }
+
+    /// Runs the world frames times
+    /// panics with the failures as soon as a system reports an error, unless allow_errors was called
+    #[track_caller]
+    pub fn run_frames(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.world.run();
+            if self.fail_on_errors && !self.world.get_frame_report().is_ok() {
+                let failures = self
+                    .world
+                    .get_frame_report()
+                    .failures()
+                    .iter()
+                    .map(|failure| failure.to_string())
+                    .collect::<Vec<String>>();
+                let frame = self.clock().frame;
+                panic!("systems failed on frame {frame}: {}", failures.join(", "));
+            }
+        }
+    }
+
+    /// Runs one frame with only this system, the systems added to the world do not run
+    /// Returns the system so the state it collected can be checked
+    #[track_caller]
+    pub fn run_single_system<S: System>(&mut self, system: S) -> S {
+        let mut schedule = StaticSchedule::new((system,));
+        self.world.run_schedule(&mut schedule);
+        if self.fail_on_errors && !self.world.get_frame_report().is_ok() {
+            let failures = self
+                .world
+                .get_frame_report()
+                .failures()
+                .iter()
+                .map(|failure| failure.to_string())
+                .collect::<Vec<String>>();
+            panic!("the system failed: {}", failures.join(", "));
+        }
+        let (system,) = schedule.into_systems();
+        system
+    }
+
+    /// panics if the entity does not have a component equal to expected
+    #[track_caller]
+    pub fn assert_component_eq<T: Component + PartialEq + Debug>(
+        &self,
+        entity: Entity,
+        expected: &T,
+    ) {
+        assert_component_eq(&self.world.entities_and_components, entity, expected);
+    }
+
+    /// panics if the entity has the component T
+    #[track_caller]
+    pub fn assert_no_component<T: Component>(&self, entity: Entity) {
+        assert_no_component::<T>(&self.world.entities_and_components, entity);
+    }
+
+    /// panics if the number of entities with the component T is not expected
+    #[track_caller]
+    pub fn assert_count<T: Component>(&self, expected: usize) {
+        let count = self
+            .world
+            .entities_and_components
+            .get_entity_count_with_component::<T>();
+        assert_eq!(
+            count,
+            expected,
+            "expected {expected} entities with {}, found {count}",
+            std::any::type_name::<T>()
+        );
+    }
+}
+
+impl Default for WorldTestHarness {
+    fn default() -> Self {
+        WorldTestHarness::new()
+    }
+}
+
+/// panics if the entity does not have a component equal to expected
+#[track_caller]
+pub fn assert_component_eq<T: Component + PartialEq + Debug>(
+    entities_and_components: &EntitiesAndComponents,
+    entity: Entity,
+    expected: &T,
+) {
+    let type_name = std::any::type_name::<T>();
+    match entities_and_components.try_get_component::<T>(entity) {
+        Some(component) => assert_eq!(
+            component, expected,
+            "Component {type_name} on {entity:?} is not the expected value"
+        ),
+        None => panic!("Component {type_name} does not exist on {entity:?}"),
+    }
+}
+
+/// panics if the entity has the component T
+#[track_caller]
+pub fn assert_no_component<T: Component>(
+    entities_and_components: &EntitiesAndComponents,
+    entity: Entity,
+) {
+    if entities_and_components
+        .try_get_component::<T>(entity)
+        .is_some()
+    {
+        let type_name = std::any::type_name::<T>();
+        panic!("Component {type_name} exists on {entity:?} but was not expected");
+    }
+}
@@ -0,0 +1,132 @@
+use crate::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::thread::ThreadId;
+use std::time::Instant;
+
+// one span on the timeline
+struct TraceSpan {
+    name: &'static str,
+    // the phase of the frame, prestep, single_entity_step, run or frame
+    category: &'static str,
+    thread: usize,
+    start: Instant,
+    end: Instant,
+    entities: Option<usize>,
+}
+
+// the spans recorded since World::start_capture, spans can be recorded from any thread
+pub(crate) struct TraceCapture {
+    start: Instant,
+    spans: Mutex<Vec<TraceSpan>>,
+    // chrome://tracing wants a number for each thread, threads are numbered in the order they first record a span
+    threads: Mutex<FxHashMap<ThreadId, usize>>,
+}
+
+impl TraceCapture {
+    fn new() -> Self {
+        TraceCapture {
+            start: Instant::now(),
+            spans: Mutex::new(vec![]),
+            threads: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    fn thread_number(&self) -> usize {
+        let mut threads = self.threads.lock().unwrap_or_else(PoisonError::into_inner);
+        let next = threads.len();
+        *threads.entry(std::thread::current().id()).or_insert(next)
+    }
+
+    // records a span from start until now on the current thread
+    pub(crate) fn record(
+        &self,
+        name: &'static str,
+        category: &'static str,
+        start: Instant,
+        entities: Option<usize>,
+    ) {
+        let span = TraceSpan {
+            name,
+            category,
+            thread: self.thread_number(),
+            start,
+            end: Instant::now(),
+            entities,
+        };
+        self.spans
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(span);
+    }
+
+    fn write_json(self, writer: &mut impl Write) -> io::Result<()> {
+        let spans = self
+            .spans
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        let micros = |instant: Instant| {
+            instant.saturating_duration_since(self.start).as_secs_f64() * 1_000_000.0
+        };
+
+        writeln!(writer, "{{\"traceEvents\":[")?;
+        for (index, span) in spans.iter().enumerate() {
+            let args = match span.entities {
+                Some(entities) => format!(",\"args\":{{\"entities\":{entities}}}"),
+                None => String::new(),
+            };
+            writeln!(
+                writer,
+                "{{\"name\":{},\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":{}{args}}}{}",
+                json_string(span.name),
+                span.category,
+                micros(span.start),
+                micros(span.end) - micros(span.start),
+                span.thread,
+                if index + 1 < spans.len() { "," } else { "" }
+            )?;
+        }
+        writeln!(writer, "],\"displayTimeUnit\":\"ms\"}}")
+    }
+}
+
+impl World {
+    /// Starts recording when each system's prestep, single_entity_step and run functions start and stop, and on which thread
+    /// single_entity_step is recorded once for each chunk of entities, so the spans show how well the work was spread over the threads
+    /// Calling this while capturing throws away what was captured so far
+    /// Write the capture with stop_capture_to_json
+    pub fn start_capture(&mut self) {
+        self.trace_capture = Some(TraceCapture::new());
+    }
+
+    /// Checks if start_capture has been called without stop_capture_to_json
+    pub fn is_capturing(&self) -> bool {
+        self.trace_capture.is_some()
+    }
+
+    /// Stops capturing and writes the capture to a file that can be opened in chrome://tracing or ui.perfetto.dev
+    /// Writes an empty timeline if start_capture was not called
+    pub fn stop_capture_to_json(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let trace_capture = self.trace_capture.take().unwrap_or_else(TraceCapture::new);
+        let mut writer = BufWriter::new(File::create(path)?);
+        trace_capture.write_json(&mut writer)?;
+        writer.flush()
+    }
+
+    // the time a span started, None if nothing is being captured
+    pub(crate) fn start_span(&self) -> Option<Instant> {
+        self.trace_capture.as_ref().map(|_| Instant::now())
+    }
+
+    pub(crate) fn finish_span(
+        &self,
+        name: &'static str,
+        category: &'static str,
+        start: Option<Instant>,
+    ) {
+        if let (Some(trace_capture), Some(start)) = (&self.trace_capture, start) {
+            trace_capture.record(name, category, start, None);
+        }
+    }
+}
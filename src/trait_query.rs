@@ -0,0 +1,127 @@
+use crate::*;
+
+type TraitCast<T> = Arc<dyn for<'a> Fn(&'a dyn Any) -> Option<&'a T> + Send + Sync>;
+
+// the components registered as implementing the trait T, see register_trait_impl
+struct TraitImpls<T: ?Sized + 'static> {
+    impls: Vec<(TypeId, TraitCast<T>)>,
+}
+
+// the TraitImpls of any trait, so they can be stored together and copied without knowing the trait
+pub(crate) trait AnyTraitImpls: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn copy(&self) -> Box<dyn AnyTraitImpls>;
+}
+
+impl<T: ?Sized + 'static> AnyTraitImpls for TraitImpls<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn copy(&self) -> Box<dyn AnyTraitImpls> {
+        Box::new(TraitImpls::<T> {
+            impls: self.impls.clone(),
+        })
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Registers the component C as implementing the trait T so it can be found with query_trait::<dyn T>()
+    /// cast turns the component into the trait object, it is almost always |component| component
+    /// like register_trait_impl::<dyn Drawable, Sprite>(|sprite| sprite)
+    /// Registering the same component twice for a trait does nothing
+    pub fn register_trait_impl<T: ?Sized + 'static, C: Component>(&mut self, cast: fn(&C) -> &T) {
+        let trait_impls = self
+            .trait_impls
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(TraitImpls::<T> { impls: vec![] }))
+            .as_any_mut()
+            .downcast_mut::<TraitImpls<T>>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "The implementations of {type:?} are stored as the wrong type, was the TypeId edited?",
+                    type = std::any::type_name::<T>()
+                )
+            });
+
+        if trait_impls
+            .impls
+            .iter()
+            .any(|(type_id, _)| *type_id == TypeId::of::<C>())
+        {
+            return;
+        }
+        trait_impls.impls.push((
+            TypeId::of::<C>(),
+            Arc::new(move |component| component.downcast_ref::<C>().map(cast)),
+        ));
+    }
+
+    /// Gets every component registered with register_trait_impl::<T, _> as a T, along with its entity
+    /// An entity with more than one of the components is returned once for each of them
+    pub fn query_trait<T: ?Sized + 'static>(&self) -> impl Iterator<Item = (Entity, &T)> + '_ {
+        self.get_trait_impls::<T>()
+            .into_iter()
+            .flat_map(|trait_impls| trait_impls.impls.iter())
+            .flat_map(move |(type_id, cast)| {
+                self.entities_with_components
+                    .get(type_id)
+                    .into_iter()
+                    .flat_map(|entities| entities.values())
+                    .filter_map(move |entity| {
+                        let component = self
+                            .components
+                            .get(entity.entity_id)?
+                            .as_raw()
+                            .get(type_id)?;
+                        Some((*entity, cast(&**component)?))
+                    })
+            })
+    }
+
+    /// Gets the components on an entity that were registered with register_trait_impl::<T, _> as T
+    pub fn get_trait_components<T: ?Sized + 'static>(&self, entity: Entity) -> Vec<&T> {
+        let Some(components) = self.components.get(entity.entity_id) else {
+            return vec![];
+        };
+        self.get_trait_impls::<T>()
+            .into_iter()
+            .flat_map(|trait_impls| trait_impls.impls.iter())
+            .filter_map(|(type_id, cast)| cast(&**components.as_raw().get(type_id)?))
+            .collect()
+    }
+
+    fn get_trait_impls<T: ?Sized + 'static>(&self) -> Option<&TraitImpls<T>> {
+        self.trait_impls
+            .get(&TypeId::of::<T>())
+            .and_then(|trait_impls| trait_impls.as_any().downcast_ref::<TraitImpls<T>>())
+    }
+}
+
+impl EntitiesAndComponentsThreadSafe<'_> {
+    /// Gets every component registered as implementing T, see EntitiesAndComponents::query_trait
+    /// T has to be Sync since other threads can read the same components, so register and query dyn Trait + Sync
+    pub fn query_trait<T: ?Sized + Sync + 'static>(
+        &self,
+    ) -> impl Iterator<Item = (Entity, &T)> + '_ {
+        self.entities_and_components.query_trait::<T>()
+    }
+}
+
+impl World {
+    /// Registers the component C as implementing the trait T, see EntitiesAndComponents::register_trait_impl
+    pub fn register_trait_impl<T: ?Sized + 'static, C: Component>(&mut self, cast: fn(&C) -> &T) {
+        self.entities_and_components
+            .register_trait_impl::<T, C>(cast);
+    }
+
+    /// Gets every component registered as implementing T, see EntitiesAndComponents::query_trait
+    pub fn query_trait<T: ?Sized + 'static>(&self) -> impl Iterator<Item = (Entity, &T)> + '_ {
+        self.entities_and_components.query_trait::<T>()
+    }
+}
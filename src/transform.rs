@@ -0,0 +1,12 @@
+use crate::*;
+
+/// A transform component that is relative to the parent of the entity, like a position, rotation and scale
+/// Implement it for your transform to use EntitiesAndComponents::get_world_transform
+/// and EntitiesAndComponents::set_parent_keep_world_transform
+pub trait HierarchyTransform: Component + Clone {
+    /// Gets the world transform of a child with the local transform child, when self is the world transform of its parent
+    fn combine(&self, child: &Self) -> Self;
+
+    /// Gets the transform that undoes this one, so that self.inverse().combine(&self) does nothing
+    fn inverse(&self) -> Self;
+}
@@ -0,0 +1,87 @@
+use crate::*;
+use std::marker::PhantomData;
+
+/// A marker is a component that says which other components an entity has
+/// Entities spawned with add_typed_entity get a TypedEntity handle,
+/// which can get the marker's components without having to name them again
+pub trait Marker: Component {
+    /// The components every entity with this marker has
+    type Components: for<'a> ComponentsRef<'a>
+        + for<'a> ComponentsMut<'a>
+        + OwnedComponents<Input = Self::Components>
+        + HasComponents;
+}
+
+/// An entity that is known to have the marker M and all of the marker's components
+/// The components are only guaranteed to be there if they are not removed manually
+pub struct TypedEntity<M> {
+    entity: Entity,
+    marker: PhantomData<fn() -> M>,
+}
+
+impl<M: Marker> TypedEntity<M> {
+    pub(crate) fn new(entity: Entity) -> Self {
+        TypedEntity {
+            entity,
+            marker: PhantomData,
+        }
+    }
+
+    /// Gets the untyped entity
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Gets a tuple of references to the marker's components
+    /// panics if the entity does not exist
+    pub fn get<'a>(
+        &self,
+        entities_and_components: &'a EntitiesAndComponents,
+    ) -> <M::Components as ComponentsRef<'a>>::Result {
+        <M::Components as ComponentsRef<'a>>::get_components(entities_and_components, self.entity)
+    }
+
+    /// Gets a tuple of mutable references to the marker's components
+    /// panics if the entity does not exist
+    pub fn get_mut<'a>(
+        &self,
+        entities_and_components: &'a mut EntitiesAndComponents,
+    ) -> <M::Components as ComponentsMut<'a>>::Result {
+        <M::Components as ComponentsMut<'a>>::get_components_mut(
+            entities_and_components,
+            self.entity,
+        )
+    }
+}
+
+// implemented by hand so that M doesn't need to implement these traits
+impl<M> Clone for TypedEntity<M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M> Copy for TypedEntity<M> {}
+
+impl<M> PartialEq for TypedEntity<M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entity == other.entity
+    }
+}
+
+impl<M> Eq for TypedEntity<M> {}
+
+impl<M> std::fmt::Debug for TypedEntity<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedEntity")
+            .field("marker", &std::any::type_name::<M>())
+            .field("entity", &self.entity)
+            .finish()
+    }
+}
+
+impl<M> From<TypedEntity<M>> for Entity {
+    fn from(typed_entity: TypedEntity<M>) -> Self {
+        typed_entity.entity
+    }
+}
@@ -0,0 +1,46 @@
+use crate::*;
+
+/// What try_add_unique_system does when a system of the same type was already added
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Default)]
+pub enum DuplicateSystemPolicy {
+    /// The new system is not added, this is the default
+    #[default]
+    Refuse,
+    /// The new system takes the place of the old one
+    /// It keeps the old system's handle, group, set, interval and exclusions, its Locals are reset
+    Replace,
+}
+
+impl World {
+    /// Adds a system unless a system of the same type is already in the world,
+    /// so adding MovementSystem twice does not make everything move twice
+    /// Returns the handle of the new system, or Err with the handle of the system that was already added if it was refused
+    /// With DuplicateSystemPolicy::Replace the old system is swapped out and its handle is returned as Ok
+    pub fn try_add_unique_system<T: System + Send + Sync + 'static>(
+        &mut self,
+        system: T,
+        policy: DuplicateSystemPolicy,
+    ) -> Result<SystemHandle, SystemHandle> {
+        let Some(system_handle) = self.get_system_handle::<T>() else {
+            return Ok(self.add_system(system));
+        };
+
+        match policy {
+            DuplicateSystemPolicy::Refuse => Err(system_handle),
+            DuplicateSystemPolicy::Replace => {
+                self.systems[system_handle.system_id] = Box::new(system);
+                self.reset_locals(&system_handle);
+                Ok(system_handle)
+            }
+        }
+    }
+
+    /// Gets the handle of a system of the type T, None if there is none
+    /// If more than one was added with add_system any of them may be returned
+    pub fn get_system_handle<T: System + Send + Sync + 'static>(&self) -> Option<SystemHandle> {
+        self.systems
+            .iter()
+            .find(|(_, system)| system.as_any().is::<T>())
+            .map(|(system_id, _)| SystemHandle { system_id })
+    }
+}
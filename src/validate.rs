@@ -0,0 +1,182 @@
+use crate::*;
+
+/// Something wrong with the world found by World::validate
+/// These should never happen, finding one means there is a bug, most likely in unsafe code
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub enum ValidationError {
+    /// An entity has a parent that does not exist
+    DanglingParent {
+        /// The entity with the parent
+        child: Entity,
+        /// The parent that does not exist
+        parent: Entity,
+    },
+    /// An entity's children has an entity that does not exist
+    DanglingChild {
+        /// The entity with the children
+        parent: Entity,
+        /// The child that does not exist
+        child: Entity,
+    },
+    /// An entity is in the children of an entity that is not its parent
+    MismatchedParent {
+        /// The entity whose children has the child
+        parent: Entity,
+        /// The child, which has a different parent or no parent
+        child: Entity,
+    },
+    /// An entity is its own ancestor
+    HierarchyCycle(Entity),
+    /// An entity is in the list of entities with a component but does not have the component
+    MissingComponent {
+        /// The entity that is missing the component
+        entity: Entity,
+        /// The type name of the component
+        component: &'static str,
+    },
+    /// An entity has a component but is not in the list of entities with the component
+    UnlistedComponent {
+        /// The entity with the component
+        entity: Entity,
+        /// The type name of the component
+        component: &'static str,
+    },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::DanglingParent { child, parent } => {
+                write!(
+                    f,
+                    "{child:?} has the parent {parent:?} which does not exist"
+                )
+            }
+            ValidationError::DanglingChild { parent, child } => {
+                write!(f, "{parent:?} has the child {child:?} which does not exist")
+            }
+            ValidationError::MismatchedParent { parent, child } => {
+                write!(
+                    f,
+                    "{parent:?} has the child {child:?} but is not its parent"
+                )
+            }
+            ValidationError::HierarchyCycle(entity) => {
+                write!(f, "{entity:?} is its own ancestor")
+            }
+            ValidationError::MissingComponent { entity, component } => {
+                write!(
+                    f,
+                    "{entity:?} is listed with {component} but does not have it"
+                )
+            }
+            ValidationError::UnlistedComponent { entity, component } => {
+                write!(f, "{entity:?} has {component} but is not listed with it")
+            }
+        }
+    }
+}
+
+impl EntitiesAndComponents {
+    /// Checks that the hierarchy and the lists of entities with each component are consistent
+    /// Returns every problem found, this is slow so it is meant for tests and debug builds
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+
+        for entity in self.iter_entities() {
+            let Some(components) = self.components.get(entity.entity_id) else {
+                continue;
+            };
+
+            if let Some(parent) = components.get::<Parent>() {
+                if !self.does_entity_exist(parent.0) {
+                    errors.push(ValidationError::DanglingParent {
+                        child: entity,
+                        parent: parent.0,
+                    });
+                } else if self.has_hierarchy_cycle(entity) {
+                    errors.push(ValidationError::HierarchyCycle(entity));
+                }
+            }
+
+            if let Some(children) = components.get::<Children>() {
+                for child in children.children.iter() {
+                    if !self.does_entity_exist(*child) {
+                        errors.push(ValidationError::DanglingChild {
+                            parent: entity,
+                            child: *child,
+                        });
+                    } else if self.get_parent(*child) != Some(entity) {
+                        errors.push(ValidationError::MismatchedParent {
+                            parent: entity,
+                            child: *child,
+                        });
+                    }
+                }
+            }
+
+            for type_id in components.as_raw().keys() {
+                let listed = self
+                    .entities_with_components
+                    .get(type_id)
+                    .is_some_and(|entities| entities.contains(entity));
+                if !listed {
+                    errors.push(ValidationError::UnlistedComponent {
+                        entity,
+                        component: self.get_type_name(*type_id),
+                    });
+                }
+            }
+        }
+
+        for (type_id, entities) in self.entities_with_components.iter() {
+            for entity in entities.values() {
+                let has_component = self
+                    .components
+                    .get(entity.entity_id)
+                    .is_some_and(|components| components.as_raw().contains_key(type_id));
+                if !has_component {
+                    errors.push(ValidationError::MissingComponent {
+                        entity: *entity,
+                        component: self.get_type_name(*type_id),
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    // walks up the parents of an entity, there is a cycle if it comes back to the entity
+    fn has_hierarchy_cycle(&self, entity: Entity) -> bool {
+        let mut current = entity;
+        // a chain longer than the number of entities has to loop
+        for _ in 0..=self.get_entity_count() {
+            match self
+                .components
+                .get(current.entity_id)
+                .and_then(|components| components.get::<Parent>())
+            {
+                Some(parent) if parent.0 == entity => return true,
+                Some(parent) => current = parent.0,
+                None => return false,
+            }
+        }
+        // the parents loop above this entity, the entities in the loop are reported on their own
+        false
+    }
+
+    // the type name of a component for error messages
+    pub(crate) fn get_type_name(&self, type_id: TypeId) -> &'static str {
+        self.get_component_type_name(type_id)
+            .unwrap_or("unknown component")
+    }
+}
+
+impl World {
+    /// Checks that the hierarchy and the lists of entities with each component are consistent
+    /// see EntitiesAndComponents::validate
+    pub fn validate(&self) -> Vec<ValidationError> {
+        self.entities_and_components.validate()
+    }
+}
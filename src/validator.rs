@@ -0,0 +1,118 @@
+use crate::*;
+
+type ValidatorCheck = Box<dyn Fn(&EntitiesAndComponents, Entity) -> bool + Send + Sync>;
+
+// a function that checks that the data in a component makes sense, see World::register_validator
+struct ComponentValidator {
+    type_id: TypeId,
+    component: &'static str,
+    // returns false if the entity has the component and it is invalid
+    is_valid: ValidatorCheck,
+}
+
+// every validator registered with World::register_validator
+#[derive(Default)]
+pub(crate) struct ComponentValidators {
+    validators: Vec<ComponentValidator>,
+    // the components that are already invalid, keyed by the index of the validator,
+    // they are only reported by the system that made them invalid and not again by every system after it
+    invalid: Mutex<FxHashSet<(usize, DefaultKey)>>,
+}
+
+impl ComponentValidators {
+    // validators only run in debug builds so they never slow down a release build
+    pub(crate) fn is_active(&self) -> bool {
+        cfg!(debug_assertions) && !self.validators.is_empty()
+    }
+
+    // the validators to run after each single_entity_step, None in release builds or if there are none
+    pub(crate) fn get_active(&self) -> Option<&Self> {
+        if self.is_active() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    // checks the components of one entity, used after each single_entity_step
+    pub(crate) fn check_entity(
+        &self,
+        entities_and_components: &EntitiesAndComponents,
+        entity: Entity,
+        system: &'static str,
+        failures: &mut Vec<SystemFailure>,
+    ) {
+        let mut invalid = self.invalid.lock().unwrap_or_else(PoisonError::into_inner);
+        for (index, validator) in self.validators.iter().enumerate() {
+            if (validator.is_valid)(entities_and_components, entity) {
+                invalid.remove(&(index, entity.entity_id));
+            } else if invalid.insert((index, entity.entity_id)) {
+                failures.push(validator.failure(system, entity));
+            }
+        }
+    }
+
+    // checks the components of every entity, used after each run function
+    pub(crate) fn check_all(
+        &self,
+        entities_and_components: &EntitiesAndComponents,
+        system: &'static str,
+        failures: &mut Vec<SystemFailure>,
+    ) {
+        let mut invalid = self.invalid.lock().unwrap_or_else(PoisonError::into_inner);
+        // rebuilt from scratch so removed entities and components are forgotten
+        let mut still_invalid = FxHashSet::default();
+        for (index, validator) in self.validators.iter().enumerate() {
+            let Some(entities) = entities_and_components
+                .entities_with_components
+                .get(&validator.type_id)
+            else {
+                continue;
+            };
+            for entity in entities.values() {
+                if !(validator.is_valid)(entities_and_components, *entity) {
+                    if !invalid.contains(&(index, entity.entity_id)) {
+                        failures.push(validator.failure(system, *entity));
+                    }
+                    still_invalid.insert((index, entity.entity_id));
+                }
+            }
+        }
+        *invalid = still_invalid;
+    }
+}
+
+impl ComponentValidator {
+    fn failure(&self, system: &'static str, entity: Entity) -> SystemFailure {
+        SystemFailure {
+            system,
+            entity: Some(entity),
+            error: SystemError::new(format!("{} failed its validator", self.component)),
+        }
+    }
+}
+
+impl World {
+    /// Registers a function that checks that a component holds valid data, like a position that is not NaN
+    /// In debug builds every validator is run after each single_entity_step and each run function,
+    /// a component that becomes invalid is reported once as a failure of the system that just ran, see get_frame_report and set_error_handler
+    /// Validators do nothing in release builds
+    pub fn register_validator<T: Component>(
+        &mut self,
+        is_valid: impl Fn(&T) -> bool + Send + Sync + 'static,
+    ) {
+        self.component_validators
+            .validators
+            .push(ComponentValidator {
+                type_id: TypeId::of::<T>(),
+                component: std::any::type_name::<T>(),
+                is_valid: Box::new(move |entities_and_components, entity| {
+                    entities_and_components
+                        .components
+                        .get(entity.entity_id)
+                        .and_then(|components| components.get::<T>())
+                        .is_none_or(&is_valid)
+                }),
+            });
+    }
+}
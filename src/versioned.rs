@@ -0,0 +1,252 @@
+use crate::*;
+
+type MergeFunction<T> = Box<dyn Fn(&T, &T, &T) -> T + Send + Sync>;
+
+/// How the versions of a component that different systems made are merged by World::run_versioned
+pub enum MergePolicy<T> {
+    /// The change from the system that runs last is kept
+    LastWrite,
+    /// The change from the system that runs first is kept
+    FirstWrite,
+    /// Called with the original component, the merged component so far and the version of a system that changed it,
+    /// returns the new merged component
+    /// If the component was added or removed by a system the change is kept like LastWrite
+    Custom(MergeFunction<T>),
+}
+
+// copies a versioned component before a system changes it for the first time
+pub(crate) type CopyComponent = fn(&dyn Any) -> Box<dyn Any + Send + Sync>;
+
+// the value each changed component had before the system changed it, None if the entity did not have it
+pub(crate) type Originals = SecondaryMap<DefaultKey, Option<Box<dyn Any + Send + Sync>>>;
+
+// the versioned components the running system has changed, only exists while run_versioned calls the run functions
+pub(crate) struct VersionedChanges {
+    copies: FxHashMap<TypeId, CopyComponent>,
+    originals: FxHashMap<TypeId, Originals>,
+}
+
+// a type erased versioned component, see World::register_versioned_component
+pub(crate) trait VersionedColumn: Send + Sync {
+    // the function that copies the component before a system changes it
+    fn copy_function(&self) -> CopyComponent;
+    // forgets the changes from the last run
+    fn begin(&mut self);
+    // merges the changes of the system that just ran and puts the original components back for the next system
+    fn collect(
+        &mut self,
+        entities_and_components: &mut EntitiesAndComponents,
+        originals: Originals,
+    );
+    // writes the merged components into the world
+    fn finish(&mut self, entities_and_components: &mut EntitiesAndComponents);
+}
+
+pub(crate) fn copy_component<T: Component + Clone + Send + Sync>(
+    component: &dyn Any,
+) -> Box<dyn Any + Send + Sync> {
+    let component = component.downcast_ref::<T>().unwrap_or_else(|| {
+        panic!(
+            "Component of type {type:?} was stored under the wrong type",
+            type = std::any::type_name::<T>()
+        );
+    });
+    Box::new(component.clone())
+}
+
+impl EntitiesAndComponents {
+    // starts keeping the original of every versioned component a system changes
+    pub(crate) fn start_versioned_changes(&mut self, copies: FxHashMap<TypeId, CopyComponent>) {
+        self.versioned_changes = Some(VersionedChanges {
+            copies,
+            originals: FxHashMap::default(),
+        });
+    }
+
+    // gets the originals of the components the last system changed
+    pub(crate) fn take_versioned_changes(&mut self) -> FxHashMap<TypeId, Originals> {
+        match &mut self.versioned_changes {
+            Some(changes) => std::mem::take(&mut changes.originals),
+            None => FxHashMap::default(),
+        }
+    }
+
+    pub(crate) fn stop_versioned_changes(&mut self) {
+        self.versioned_changes = None;
+    }
+
+    // keeps a copy of a versioned component the first time the running system could change it, see mark_changed
+    pub(crate) fn copy_versioned(&mut self, entity: Entity, type_id: TypeId) {
+        let Some(changes) = &mut self.versioned_changes else {
+            return;
+        };
+        let Some(copy) = changes.copies.get(&type_id) else {
+            return;
+        };
+        let Some(components) = self.components.get(entity.entity_id) else {
+            return;
+        };
+        let originals = changes.originals.entry(type_id).or_default();
+        if !originals.contains_key(entity.entity_id) {
+            let original = components
+                .as_raw()
+                .get(&type_id)
+                .map(|component| copy(&**component));
+            originals.insert(entity.entity_id, original);
+        }
+    }
+
+    // keeps a copy of every versioned component on an entity, for when all of its components are borrowed at once
+    pub(crate) fn copy_all_versioned(&mut self, entity: Entity) {
+        let Some(changes) = &self.versioned_changes else {
+            return;
+        };
+        let type_ids = changes.copies.keys().copied().collect::<Vec<TypeId>>();
+        for type_id in type_ids {
+            self.copy_versioned(entity, type_id);
+        }
+    }
+
+    // puts a component back the way it was before a system changed it
+    // nothing that happens for a real change happens here, no hooks, required components or op log entries,
+    // only the lists, bits and value indexes are kept in sync with the storage
+    fn restore_component<T: Component>(&mut self, entity: Entity, original: Option<T>) {
+        let type_id = TypeId::of::<T>();
+        let Some(components) = self.components.get_mut(entity.entity_id) else {
+            return;
+        };
+        let hidden = self.soft_despawns.remaining.contains_key(entity.entity_id);
+        match original {
+            Some(original) => {
+                let had_component = components.insert(original).is_some();
+                if !had_component {
+                    self.count_component_added(type_id);
+                    if !hidden {
+                        self.entities_with_components
+                            .entry(type_id)
+                            .or_default()
+                            .insert(entity);
+                        self.set_component_bit(entity, type_id);
+                    }
+                }
+                if !hidden {
+                    self.update_value_indexes(entity, type_id);
+                }
+            }
+            None => {
+                if components.remove::<T>().is_some() {
+                    self.count_component_removed(type_id);
+                    if let Some(entities) = self.entities_with_components.get_mut(&type_id) {
+                        entities.remove(entity);
+                    }
+                    self.unset_component_bit(entity, type_id);
+                    self.remove_from_value_indexes(entity, type_id);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct Versioned<T> {
+    policy: MergePolicy<T>,
+    // the components before any system ran, only for the entities a system changed
+    base: SecondaryMap<DefaultKey, T>,
+    // the entities that a system changed the component on, None if it was removed
+    merged: SecondaryMap<DefaultKey, Option<T>>,
+}
+
+impl<T> Versioned<T> {
+    pub(crate) fn new(policy: MergePolicy<T>) -> Self {
+        Versioned {
+            policy,
+            base: SecondaryMap::new(),
+            merged: SecondaryMap::new(),
+        }
+    }
+}
+
+impl<T: Component + Clone + PartialEq + Send + Sync> Versioned<T> {
+    fn merge(&mut self, entity: Entity, version: Option<T>) {
+        let merged = match (&self.policy, self.merged.get(entity.entity_id)) {
+            (MergePolicy::FirstWrite, Some(merged)) => merged.clone(),
+            (MergePolicy::Custom(merge), merged) => {
+                let base = self.base.get(entity.entity_id);
+                let merged = match merged {
+                    Some(merged) => merged.as_ref(),
+                    None => base,
+                };
+                match (base, merged, &version) {
+                    (Some(base), Some(merged), Some(version)) => Some(merge(base, merged, version)),
+                    _ => version,
+                }
+            }
+            _ => version,
+        };
+
+        self.merged.insert(entity.entity_id, merged);
+    }
+}
+
+impl<T: Component + Clone + PartialEq + Send + Sync> VersionedColumn for Versioned<T> {
+    fn copy_function(&self) -> CopyComponent {
+        copy_component::<T>
+    }
+
+    fn begin(&mut self) {
+        self.base.clear();
+        self.merged.clear();
+    }
+
+    fn collect(
+        &mut self,
+        entities_and_components: &mut EntitiesAndComponents,
+        mut originals: Originals,
+    ) {
+        for (entity_id, original) in originals.drain() {
+            let entity = Entity { entity_id };
+            // removing entities is not versioned
+            if !entities_and_components.does_entity_exist(entity) {
+                continue;
+            }
+            let original = original.map(|original| {
+                *original.downcast::<T>().unwrap_or_else(|_| {
+                    panic!(
+                        "Component of type {type:?} was copied as the wrong type",
+                        type = std::any::type_name::<T>()
+                    );
+                })
+            });
+            // every system starts from the same components so the first original is the base
+            if let Some(original) = &original {
+                if !self.base.contains_key(entity_id) {
+                    self.base.insert(entity_id, original.clone());
+                }
+            }
+
+            let version = entities_and_components.try_get_component::<T>(entity);
+            if version != original.as_ref() {
+                let version = version.cloned();
+                self.merge(entity, version);
+            }
+
+            // the next system sees the components from before any system ran
+            entities_and_components.restore_component(entity, original);
+        }
+    }
+
+    fn finish(&mut self, entities_and_components: &mut EntitiesAndComponents) {
+        for (entity_id, component) in self.merged.drain() {
+            let entity = Entity { entity_id };
+            // the entity could have been removed by a later system
+            if !entities_and_components.does_entity_exist(entity) {
+                continue;
+            }
+
+            match component {
+                Some(component) => entities_and_components.add_component_to(entity, component),
+                None => entities_and_components.remove_component_from::<T>(entity),
+            }
+        }
+        self.base.clear();
+    }
+}
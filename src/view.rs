@@ -0,0 +1,77 @@
+use crate::*;
+
+// copies every instance of one registered component type into a column for a WorldView
+pub(crate) type CopyViewColumn = fn(&EntitiesAndComponents) -> Arc<dyn Any + Send + Sync>;
+
+pub(crate) fn copy_view_column<T: Component + Clone + Send + Sync>(
+    entities_and_components: &EntitiesAndComponents,
+) -> Arc<dyn Any + Send + Sync> {
+    let mut column: SecondaryMap<DefaultKey, T> = SecondaryMap::new();
+    for (entity, component) in entities_and_components.export_column::<T>() {
+        column.insert(entity.entity_id, component);
+    }
+    Arc::new(column)
+}
+
+/// A read only copy of the world that can be sent to other threads
+/// It only holds the components registered with EntitiesAndComponents::register_view_component
+/// Cloning a view is cheap, all of the data is shared
+#[derive(Clone)]
+pub struct WorldView {
+    entities: Arc<SecondaryMap<DefaultKey, Entity>>,
+    columns: Arc<FxHashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl WorldView {
+    pub(crate) fn new(
+        entities: SecondaryMap<DefaultKey, Entity>,
+        columns: FxHashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+    ) -> Self {
+        WorldView {
+            entities: Arc::new(entities),
+            columns: Arc::new(columns),
+        }
+    }
+
+    /// Returns an iterator over all the entities that existed when the view was made
+    pub fn iter_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.values().copied()
+    }
+
+    /// Gets the number of entities that existed when the view was made
+    pub fn get_entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Checks if an entity existed when the view was made
+    pub fn does_entity_exist(&self, entity: Entity) -> bool {
+        self.entities.contains_key(entity.entity_id)
+    }
+
+    /// Gets a reference to a component on an entity
+    /// If the component does not exist on the entity, it will return None
+    /// panics if the component was not registered with register_view_component
+    pub fn try_get_component<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.get_column::<T>().get(entity.entity_id)
+    }
+
+    /// Gets every entity with a certain component and a reference to the component
+    /// panics if the component was not registered with register_view_component
+    pub fn iter_component<T: Component>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.get_column::<T>()
+            .iter()
+            .map(|(entity_id, component)| (Entity { entity_id }, component))
+    }
+
+    fn get_column<T: Component>(&self) -> &SecondaryMap<DefaultKey, T> {
+        self.columns
+            .get(&TypeId::of::<T>())
+            .and_then(|column| column.downcast_ref::<SecondaryMap<DefaultKey, T>>())
+            .unwrap_or_else(|| {
+                panic!(
+                    "Component of type {type:?} is not in the view, was it registered with register_view_component?",
+                    type = std::any::type_name::<T>()
+                );
+            })
+    }
+}
@@ -0,0 +1,197 @@
+use crate::*;
+use std::marker::PhantomData;
+
+/// Whether an entity should be drawn, children are hidden with their parent
+/// The visibility after the parents are taken into account is in InheritedVisibility, see World::add_visibility_system
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub struct Visible(pub bool);
+
+impl Default for Visible {
+    fn default() -> Self {
+        Visible(true)
+    }
+}
+
+/// Whether an entity is visible once its parents are taken into account
+/// It is added with Visible and kept up to date by the system added with World::add_visibility_system
+/// Entities without Visible do not hide their children, but a hidden entity above them still does
+#[derive(Clone, Copy, PartialEq, Debug, Eq)]
+pub struct InheritedVisibility(bool);
+
+impl InheritedVisibility {
+    /// Checks if the entity and every parent with Visible is visible
+    pub fn is_visible(&self) -> bool {
+        self.0
+    }
+}
+
+impl Default for InheritedVisibility {
+    fn default() -> Self {
+        InheritedVisibility(true)
+    }
+}
+
+/// A filter that matches the entities that match T and are visible, like get_entities_matching::<VisibleOnly<(Sprite,)>>()
+/// Entities without InheritedVisibility are not matched
+pub struct VisibleOnly<T>(PhantomData<T>);
+
+impl<T: HasComponents> HasComponents for VisibleOnly<T> {
+    fn has_components(entities_and_components: &EntitiesAndComponents, entity: Entity) -> bool {
+        is_visible(entities_and_components, entity)
+            && T::has_components(entities_and_components, entity)
+    }
+
+    fn get_matching_entities(entities_and_components: &EntitiesAndComponents) -> Vec<Entity> {
+        let mut matching_entities = T::get_matching_entities(entities_and_components);
+        matching_entities.retain(|entity| is_visible(entities_and_components, *entity));
+        matching_entities
+    }
+}
+
+fn is_visible(entities_and_components: &EntitiesAndComponents, entity: Entity) -> bool {
+    entities_and_components
+        .components
+        .get(entity.entity_id)
+        .and_then(|components| components.get::<InheritedVisibility>())
+        .is_some_and(InheritedVisibility::is_visible)
+}
+
+// what the visibility of an entity depends on, when this changes the entity and everything below it are updated
+#[derive(Clone, Copy, PartialEq)]
+struct VisibilityInputs {
+    visible: Option<bool>,
+    parent: Option<Entity>,
+}
+
+// keeps InheritedVisibility up to date, only the entities whose Visible or parent changed
+// since the last run are updated, along with everything below them
+pub(crate) struct VisibilitySystem {
+    last_inputs: SecondaryMap<DefaultKey, VisibilityInputs>,
+}
+
+impl VisibilitySystem {
+    pub(crate) fn new() -> Self {
+        VisibilitySystem {
+            last_inputs: SecondaryMap::new(),
+        }
+    }
+
+    // finds the entities whose inputs changed, the ones that are gone are forgotten
+    fn get_changed_entities(&mut self, engine: &EntitiesAndComponents) -> Vec<Entity> {
+        let mut tracked = SecondaryMap::new();
+        for entity in engine
+            .get_entities_with_component::<Visible>()
+            .chain(engine.get_entities_with_parent())
+        {
+            tracked.insert(entity.entity_id, entity);
+        }
+
+        // an entity that lost both Visible and its parent still has to update its children
+        let mut changed = vec![];
+        self.last_inputs.retain(|entity_id, _| {
+            let keep = tracked.contains_key(entity_id);
+            if !keep {
+                let entity = Entity { entity_id };
+                if engine.does_entity_exist(entity) {
+                    changed.push(entity);
+                }
+            }
+            keep
+        });
+
+        for entity in tracked.values() {
+            let inputs = VisibilityInputs {
+                visible: engine
+                    .try_get_component::<Visible>(*entity)
+                    .map(|visible| visible.0),
+                parent: engine.get_parent(*entity),
+            };
+            if self.last_inputs.get(entity.entity_id) != Some(&inputs) {
+                self.last_inputs.insert(entity.entity_id, inputs);
+                changed.push(*entity);
+            }
+        }
+        changed
+    }
+}
+
+// the visibility an entity passes down to its children
+fn get_passed_visibility(engine: &EntitiesAndComponents, entity: Entity) -> bool {
+    let mut current = entity;
+    loop {
+        if let Some(inherited) = engine.try_get_component::<InheritedVisibility>(current) {
+            return inherited.0;
+        }
+        if let Some(visible) = engine.try_get_component::<Visible>(current) {
+            // the entity was just given Visible and its InheritedVisibility has not been added yet
+            if !visible.0 {
+                return false;
+            }
+        }
+        match engine.get_parent(current) {
+            Some(parent) => current = parent,
+            None => return true,
+        }
+    }
+}
+
+impl System for VisibilitySystem {
+    fn run(&mut self, engine: &mut EntitiesAndComponents) {
+        let mut changed = self.get_changed_entities(engine);
+        if changed.is_empty() {
+            return;
+        }
+
+        // entities higher up are updated first so their children see the new visibility
+        changed.sort_by_key(|entity| engine.get_depth(*entity));
+        let mut updated = SecondaryMap::new();
+
+        for entity in changed {
+            if updated.contains_key(entity.entity_id) {
+                continue;
+            }
+
+            let parent_visibility = engine
+                .get_parent(entity)
+                .is_none_or(|parent| get_passed_visibility(engine, parent));
+            let mut to_visit = vec![(entity, parent_visibility)];
+            while let Some((entity, parent_visibility)) = to_visit.pop() {
+                updated.insert(entity.entity_id, ());
+
+                let visibility = match engine.try_get_component::<Visible>(entity) {
+                    Some(visible) => {
+                        let visibility = parent_visibility && visible.0;
+                        match engine.try_get_component_mut::<InheritedVisibility>(entity) {
+                            Some(inherited) => inherited.0 = visibility,
+                            None => {
+                                engine.add_component_to(entity, InheritedVisibility(visibility))
+                            }
+                        }
+                        visibility
+                    }
+                    None => {
+                        // Visible was removed, so the old InheritedVisibility should not be passed down anymore
+                        engine.remove_component_from::<InheritedVisibility>(entity);
+                        parent_visibility
+                    }
+                };
+
+                for child in engine.get_children(entity) {
+                    to_visit.push((child, visibility));
+                }
+            }
+        }
+    }
+}
+
+impl World {
+    /// Adds a system that keeps the InheritedVisibility of every entity with Visible up to date
+    /// An entity is visible if it and every parent with Visible is visible
+    /// Only entities whose Visible or parent changed since the last run are updated, along with everything below them
+    /// Systems added before this one will see the visibility from the last frame
+    pub fn add_visibility_system(&mut self) -> SystemHandle {
+        self.entities_and_components
+            .register_required_component::<Visible, InheritedVisibility>();
+        self.add_system(VisibilitySystem::new())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::*;
+
+impl EntitiesAndComponents {
+    /// Sets how many entities warm_up makes room for, like the most entities a level will have at once
+    /// Nothing is allocated until warm_up is called
+    pub fn set_warm_up_capacity(&mut self, entities: usize) {
+        self.warm_up_capacity = entities;
+    }
+
+    /// Gets the number of entities warm_up makes room for, see set_warm_up_capacity
+    pub fn get_warm_up_capacity(&self) -> usize {
+        self.warm_up_capacity
+    }
+
+    /// Sets how many entities with the component T warm_up makes room for
+    /// Only the component types set here get room, so rare components do not take memory for every entity
+    pub fn set_warm_up_component_capacity<T: Component>(&mut self, entities: usize) {
+        self.warm_up_components.insert(TypeId::of::<T>(), entities);
+    }
+
+    /// Does the work that would otherwise happen the first time entities and components are added,
+    /// so the first frame of a level does not hitch
+    /// Grows the entity and component storage to the warm up capacity,
+    /// creates the lists of entities for every registered or previously used component type,
+    /// grows the lists of the types set with set_warm_up_component_capacity,
+    /// and brings the indexes added with add_index up to date
+    /// Calling it again does nothing unless the capacity was raised or more components were registered
+    pub fn warm_up(&mut self) {
+        let capacity = self.warm_up_capacity;
+        self.entities
+            .reserve(capacity.saturating_sub(self.entities.len()));
+        self.components
+            .reserve(capacity.saturating_sub(self.components.len()));
+        self.hierarchy_levels.set_capacity(capacity);
+        self.component_masks.set_capacity(capacity);
+
+        let type_ids = self
+            .registered_components
+            .keys()
+            .chain(self.component_names.keys())
+            .copied()
+            .collect::<Vec<TypeId>>();
+        for type_id in type_ids {
+            self.entities_with_components.entry(type_id).or_default();
+        }
+        for (type_id, entities) in &self.warm_up_components {
+            self.entities_with_components
+                .entry(*type_id)
+                .or_default()
+                .set_capacity(*entities);
+        }
+
+        self.sync_indexes();
+    }
+}
+
+impl World {
+    /// Sets how many entities warm_up makes room for, see EntitiesAndComponents::set_warm_up_capacity
+    pub fn set_warm_up_capacity(&mut self, entities: usize) {
+        self.entities_and_components.set_warm_up_capacity(entities);
+    }
+
+    /// Sets how many entities with the component T warm_up makes room for,
+    /// see EntitiesAndComponents::set_warm_up_component_capacity
+    pub fn set_warm_up_component_capacity<T: Component>(&mut self, entities: usize) {
+        self.entities_and_components
+            .set_warm_up_component_capacity::<T>(entities);
+    }
+
+    /// Gets ready for the first frame of a level, see EntitiesAndComponents::warm_up
+    /// Also grows the buffer single_entity_step uses to the warm up capacity
+    /// and starts the threads systems run on, which otherwise start on the first run
+    pub fn warm_up(&mut self) {
+        self.entities_and_components.warm_up();
+
+        let capacity = self.entities_and_components.warm_up_capacity;
+        self.entities_buffer
+            .reserve(capacity.saturating_sub(self.entities_buffer.len()));
+
+        rayon::broadcast(|_| {});
+    }
+}